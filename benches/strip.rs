@@ -0,0 +1,75 @@
+//! Benchmarks of stripping typical inputs, run with `cargo bench`.
+//!
+//! Each case strips its input repeatedly for about a second and prints the
+//! mean time per call. Pass a substring of case names to run only those,
+//! e.g. `cargo bench -- chat`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use strip_codeblocks::{
+    StripOptions, strip_codeblocks, strip_codeblocks_cow, strip_codeblocks_with,
+};
+
+/// How long each case runs for.
+const BUDGET: Duration = Duration::from_secs(1);
+
+/// A short chat reply without code.
+const CHAT_PROSE: &str = "Sure! The error means the borrow checker found two mutable \
+    references to the same value. Move the second use after the first one ends, or clone \
+    the value if both need to live at once.";
+
+/// A short chat reply with one code block.
+const CHAT_CODE: &str = "Sure! Try this:\n\n```rust\nfn main() {\n    let mut v = vec![1, 2];\n    \
+    v.push(3);\n    println!(\"{v:?}\");\n}\n```\n\nThe `push` only needs the borrow while it runs.";
+
+/// Runs `f` repeatedly within [`BUDGET`] and prints the mean time per call.
+fn bench(name: &str, filter: Option<&str>, mut f: impl FnMut()) {
+    if filter.is_some_and(|filter| !name.contains(filter)) {
+        return;
+    }
+
+    // Warm up, and size batches so the clock is read rarely
+    let start = Instant::now();
+    let mut batch = 1;
+    while start.elapsed() < BUDGET / 10 {
+        for _ in 0..batch {
+            f();
+        }
+        batch *= 2;
+    }
+
+    let start = Instant::now();
+    let mut calls = 0u64;
+    while start.elapsed() < BUDGET {
+        for _ in 0..batch {
+            f();
+        }
+        calls += batch;
+    }
+    let mean = start.elapsed().as_nanos() / u128::from(calls);
+    println!("{name:<32} {mean:>10} ns/iter");
+}
+
+fn main() {
+    let filter = std::env::args().skip(1).find(|arg| !arg.starts_with('-'));
+    let filter = filter.as_deref();
+    let options = StripOptions::new();
+    let document = CHAT_CODE.repeat(200);
+
+    for (name, input) in [
+        ("chat_prose", CHAT_PROSE),
+        ("chat_code", CHAT_CODE),
+        ("document", document.as_str()),
+    ] {
+        bench(&format!("strip_codeblocks/{name}"), filter, || {
+            black_box(strip_codeblocks(black_box(input)));
+        });
+        bench(&format!("strip_codeblocks_with/{name}"), filter, || {
+            black_box(strip_codeblocks_with(black_box(input), &options));
+        });
+        bench(&format!("strip_codeblocks_cow/{name}"), filter, || {
+            black_box(strip_codeblocks_cow(black_box(input), &options));
+        });
+    }
+}
@@ -0,0 +1,218 @@
+//! Reading and writing `.gz` and `.zst` files, through the `gzip` and `zstd`
+//! programs.
+
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
+
+/// A compression format, known from the extension of a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Codec {
+    Gzip,
+    Zstd,
+}
+
+impl Codec {
+    /// Returns the format of the file at `path`, if it is compressed.
+    fn of(path: &Path) -> Option<Codec> {
+        match path.extension()?.to_str()? {
+            "gz" => Some(Codec::Gzip),
+            "zst" => Some(Codec::Zstd),
+            _ => None,
+        }
+    }
+
+    /// Returns a command running the program of this format, quietly and
+    /// writing to standard output.
+    fn command(self) -> Command {
+        let mut command = Command::new(self.program());
+        command.args(["-c", "-q"]);
+        command
+    }
+
+    fn program(self) -> &'static str {
+        match self {
+            Codec::Gzip => "gzip",
+            Codec::Zstd => "zstd",
+        }
+    }
+
+    /// Returns the error for `output` when the program failed.
+    fn check(self, output: Output) -> io::Result<Output> {
+        if output.status.success() {
+            return Ok(output);
+        }
+        let message = String::from_utf8_lossy(&output.stderr);
+        Err(io::Error::other(format!(
+            "{} failed: {}",
+            self.program(),
+            message.trim()
+        )))
+    }
+
+    /// Adds the name of the program to `err`, from starting it.
+    fn spawn_error(self, err: io::Error) -> io::Error {
+        io::Error::new(err.kind(), format!("cannot run {}: {err}", self.program()))
+    }
+}
+
+/// Reads the file at `path`, decompressing it when its extension is `.gz` or
+/// `.zst`.
+pub fn read_to_string(path: &Path) -> io::Result<String> {
+    let Some(codec) = Codec::of(path) else {
+        return fs::read_to_string(path);
+    };
+    let output = codec
+        .command()
+        .arg("-d")
+        .arg("--")
+        .arg(path)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|err| codec.spawn_error(err))?;
+    let output = codec.check(output)?;
+    String::from_utf8(output.stdout).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+/// Writes `text` to the file at `path`, compressing it when its extension is
+/// `.gz` or `.zst`.
+///
+/// Compressed text is written to a temporary file beside `path`, which only
+/// replaces `path` once the program succeeded, so a missing or failing program
+/// leaves an existing file as it was.
+pub fn write(path: &Path, text: &str) -> io::Result<()> {
+    let Some(codec) = Codec::of(path) else {
+        return fs::write(path, text);
+    };
+    replace(path, |file| {
+        let mut child = codec
+            .command()
+            .stdin(Stdio::piped())
+            .stdout(file)
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|err| codec.spawn_error(err))?;
+        let written = child
+            .stdin
+            .take()
+            .expect("standard input is piped")
+            .write_all(text.as_bytes());
+        codec.check(child.wait_with_output()?)?;
+        written
+    })
+}
+
+/// Replaces the file at `path` with a temporary file written by `fill`,
+/// keeping the permissions of the file it replaces. The temporary file is
+/// removed when `fill` or the replacement fails.
+fn replace(path: &Path, fill: impl FnOnce(File) -> io::Result<()>) -> io::Result<()> {
+    let mut name = std::ffi::OsString::from(".");
+    name.push(path.file_name().unwrap_or_default());
+    name.push(format!(".{}.tmp", std::process::id()));
+    let temporary = path.with_file_name(name);
+
+    let result = File::create(&temporary).and_then(|file| {
+        if let Ok(metadata) = fs::metadata(path) {
+            file.set_permissions(metadata.permissions())?;
+        }
+        fill(file)?;
+        fs::rename(&temporary, path)
+    });
+    if result.is_err() {
+        let _ = fs::remove_file(&temporary);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codec_of() {
+        assert_eq!(Codec::of(Path::new("chats.md.gz")), Some(Codec::Gzip));
+        assert_eq!(Codec::of(Path::new("chats.md.zst")), Some(Codec::Zstd));
+        assert_eq!(Codec::of(Path::new("chats.md")), None);
+        assert_eq!(Codec::of(Path::new("gz")), None);
+    }
+
+    /// Returns a fresh directory for the files of the test `name`.
+    fn directory(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("strip-{name}-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Returns whether the program of `codec` can be run, so tests of a
+    /// format are skipped on machines without it.
+    fn installed(codec: Codec) -> bool {
+        let installed = Command::new(codec.program())
+            .arg("--version")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok();
+        if !installed {
+            eprintln!("skipped: {} is not installed", codec.program());
+        }
+        installed
+    }
+
+    /// Writes and reads back a compressed file named `name`, whose content
+    /// starts with `magic`, and checks that a corrupt file is an error.
+    fn round_trip(codec: Codec, name: &str, magic: &[u8]) {
+        if !installed(codec) {
+            return;
+        }
+        let dir = directory(codec.program());
+        let path = dir.join(name);
+
+        let text = "Run:\n```sh\nls\n```\n".repeat(100);
+        write(&path, &text).unwrap();
+        assert!(fs::read(&path).unwrap().starts_with(magic));
+        assert_eq!(read_to_string(&path).unwrap(), text);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        fs::write(&path, "not compressed").unwrap();
+        let err = read_to_string(&path).unwrap_err().to_string();
+        assert!(
+            err.starts_with(&format!("{} failed", codec.program())),
+            "{err}"
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_gzip_round_trip() {
+        round_trip(Codec::Gzip, "answer.md.gz", &[0x1f, 0x8b]);
+    }
+
+    #[test]
+    fn test_zstd_round_trip() {
+        round_trip(Codec::Zstd, "answer.md.zst", &[0x28, 0xb5, 0x2f, 0xfd]);
+    }
+
+    #[test]
+    fn test_failed_write_keeps_file() {
+        let dir = directory("replace");
+        let path = dir.join("answer.md.gz");
+        fs::write(&path, "original").unwrap();
+
+        let err = replace(&path, |mut file| {
+            file.write_all(b"partial")?;
+            Err(io::Error::other("cannot run gzip"))
+        })
+        .unwrap_err();
+        assert_eq!(err.to_string(), "cannot run gzip");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "original");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        replace(&path, |mut file| file.write_all(b"replaced")).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "replaced");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,303 @@
+//! Stripping settings, merged from `.stripcodeblocksrc` files and command-line flags.
+
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use clap::{Args, ValueEnum};
+use serde::Deserialize;
+use strip_codeblocks::{Action, BlockMatcher, StripOptions, env_language_list, read_language_list};
+
+/// File name of the configuration file, looked up from the working directory upward.
+pub const CONFIG_FILE_NAME: &str = ".stripcodeblocksrc";
+
+/// Environment variable holding languages to strip, overriding the configuration file.
+pub const LANGUAGES_VAR: &str = "STRIP_CODEBLOCKS_LANGUAGES";
+
+/// Environment variable holding languages never to strip, overriding the configuration file.
+pub const DENY_LANGUAGES_VAR: &str = "STRIP_CODEBLOCKS_DENY_LANGUAGES";
+
+/// Environment variable holding a selection expression, overriding the configuration file.
+pub const SELECT_VAR: &str = "STRIP_CODEBLOCKS_SELECT";
+
+/// Placeholder used by `action = "placeholder"` when none is configured.
+const DEFAULT_PLACEHOLDER: &str = "[code omitted]";
+
+/// What happens to the selected code blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ActionKind {
+    /// Remove the fences but keep the content
+    Unwrap,
+    /// Leave the blocks untouched
+    Keep,
+    /// Remove the blocks entirely
+    Remove,
+    /// Replace the blocks with the placeholder text
+    Placeholder,
+}
+
+/// Stripping settings. Every setting is optional so that config files and
+/// flags can each override only part of them.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Args)]
+#[serde(deny_unknown_fields)]
+pub struct Settings {
+    /// Only strip blocks in these languages (comma-separated)
+    #[arg(short, long = "language", value_delimiter = ',', value_name = "LANG")]
+    pub languages: Option<Vec<String>>,
+
+    /// File listing more languages to strip, one per line
+    #[arg(long, value_name = "PATH")]
+    pub languages_file: Option<PathBuf>,
+
+    /// Never strip blocks in these languages (comma-separated), whatever the other settings
+    #[arg(long = "deny-language", value_delimiter = ',', value_name = "LANG")]
+    pub deny_languages: Option<Vec<String>>,
+
+    /// File listing more languages never to strip, one per line
+    #[arg(long, value_name = "PATH")]
+    pub deny_languages_file: Option<PathBuf>,
+
+    /// Only strip blocks matching this expression, such as `lang:rust & lines>10`
+    #[arg(long, value_name = "EXPR")]
+    pub select: Option<String>,
+
+    /// What to do with the selected blocks
+    #[arg(short, long, value_enum)]
+    pub action: Option<ActionKind>,
+
+    /// Text replacing blocks with `--action placeholder`
+    #[arg(long, value_name = "TEXT")]
+    pub placeholder: Option<String>,
+
+    /// Remove `$ ` prompts from console session blocks
+    #[arg(long, num_args = 0, default_missing_value = "true")]
+    pub strip_prompts: Option<bool>,
+
+    /// Drop output lines from console session blocks
+    #[arg(long, num_args = 0, default_missing_value = "true")]
+    pub drop_output: Option<bool>,
+
+    /// Remove comments from unwrapped code, by language
+    #[arg(long, num_args = 0, default_missing_value = "true")]
+    pub strip_comments: Option<bool>,
+}
+
+impl Settings {
+    /// Parses settings from the contents of a configuration file.
+    pub fn from_toml(source: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(source)
+    }
+
+    /// Reads settings from the configuration file at `path`.
+    ///
+    /// The paths of language lists are relative to the directory of the file.
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let source =
+            fs::read_to_string(path).map_err(|err| format!("{}: {err}", path.display()))?;
+        let mut settings =
+            Self::from_toml(&source).map_err(|err| format!("{}: {err}", path.display()))?;
+
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let lists = [
+            &mut settings.languages_file,
+            &mut settings.deny_languages_file,
+        ];
+        for list in lists.into_iter().flatten() {
+            *list = dir.join(&*list);
+        }
+        Ok(settings)
+    }
+
+    /// Reads the language lists set in the environment.
+    pub fn from_env() -> Self {
+        Settings {
+            languages: env_language_list(LANGUAGES_VAR),
+            deny_languages: env_language_list(DENY_LANGUAGES_VAR),
+            select: env::var(SELECT_VAR)
+                .ok()
+                .filter(|select| !select.trim().is_empty()),
+            ..Settings::default()
+        }
+    }
+
+    /// Returns these settings overridden by the ones set in `other`.
+    pub fn merge(self, other: Settings) -> Settings {
+        Settings {
+            languages: other.languages.or(self.languages),
+            languages_file: other.languages_file.or(self.languages_file),
+            deny_languages: other.deny_languages.or(self.deny_languages),
+            deny_languages_file: other.deny_languages_file.or(self.deny_languages_file),
+            select: other.select.or(self.select),
+            action: other.action.or(self.action),
+            placeholder: other.placeholder.or(self.placeholder),
+            strip_prompts: other.strip_prompts.or(self.strip_prompts),
+            drop_output: other.drop_output.or(self.drop_output),
+            strip_comments: other.strip_comments.or(self.strip_comments),
+        }
+    }
+
+    /// Builds the library options these settings describe, reading the
+    /// language lists they refer to.
+    pub fn to_options(&self) -> Result<StripOptions, Box<dyn Error>> {
+        let action = match self.action.unwrap_or(ActionKind::Unwrap) {
+            ActionKind::Unwrap => Action::Unwrap,
+            ActionKind::Keep => Action::Keep,
+            ActionKind::Remove => Action::Remove,
+            ActionKind::Placeholder => Action::Placeholder(
+                self.placeholder
+                    .clone()
+                    .unwrap_or_else(|| DEFAULT_PLACEHOLDER.to_string()),
+            ),
+        };
+
+        let mut options = StripOptions::new()
+            .action(action)
+            .strip_prompts(self.strip_prompts.unwrap_or(false))
+            .drop_output(self.drop_output.unwrap_or(false))
+            .strip_comments(self.strip_comments.unwrap_or(false));
+        if let Some(languages) = list(&self.languages, &self.languages_file)? {
+            options = options.languages(languages);
+        }
+        if let Some(denied) = list(&self.deny_languages, &self.deny_languages_file)? {
+            options = options.deny_languages(denied);
+        }
+        if let Some(select) = &self.select {
+            let matcher: BlockMatcher = select
+                .parse()
+                .map_err(|err| format!("invalid selection `{select}`: {err}"))?;
+            options = options.select(matcher);
+        }
+        Ok(options)
+    }
+}
+
+/// Returns the languages of `languages` and of the `file` listing more, if
+/// either is set.
+fn list(
+    languages: &Option<Vec<String>>,
+    file: &Option<PathBuf>,
+) -> Result<Option<Vec<String>>, Box<dyn Error>> {
+    let mut list = languages.clone();
+    if let Some(file) = file {
+        let listed =
+            read_language_list(file).map_err(|err| format!("{}: {err}", file.display()))?;
+        list.get_or_insert_default().extend(listed);
+    }
+    Ok(list)
+}
+
+/// Finds the configuration file closest to `start`, looking in `start` and its ancestors.
+pub fn find_config(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .map(|dir| dir.join(CONFIG_FILE_NAME))
+        .find(|path| path.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let settings = Settings::from_toml(
+            "languages = [\"rust\", \"sh\"]\naction = \"placeholder\"\nplaceholder = \"[snip]\"\nstrip_prompts = true\n",
+        )
+        .unwrap();
+
+        assert_eq!(settings.languages, Some(vec!["rust".into(), "sh".into()]));
+        assert_eq!(settings.action, Some(ActionKind::Placeholder));
+        assert_eq!(
+            settings.to_options().unwrap(),
+            StripOptions::new()
+                .action(Action::Placeholder("[snip]".into()))
+                .languages(["rust", "sh"])
+                .strip_prompts(true)
+        );
+    }
+
+    #[test]
+    fn test_select() {
+        let settings = Settings::from_toml("select = \"lang:sh | lines>=20\"").unwrap();
+        assert_eq!(
+            settings.to_options().unwrap(),
+            StripOptions::new().select(BlockMatcher::language("sh") | BlockMatcher::lines(20..))
+        );
+
+        let invalid = Settings {
+            select: Some("lang:sh |".into()),
+            ..Settings::default()
+        };
+        let error = invalid.to_options().unwrap_err().to_string();
+        assert!(
+            error.starts_with("invalid selection `lang:sh |`: column 10"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn test_unknown_keys_are_rejected() {
+        assert!(Settings::from_toml("langauges = [\"rust\"]").is_err());
+        assert!(Settings::from_toml("action = \"delete\"").is_err());
+    }
+
+    #[test]
+    fn test_flags_override_config() {
+        let config = Settings {
+            action: Some(ActionKind::Remove),
+            strip_prompts: Some(true),
+            ..Settings::default()
+        };
+        let flags = Settings {
+            action: Some(ActionKind::Keep),
+            ..Settings::default()
+        };
+
+        let merged = config.merge(flags);
+        assert_eq!(merged.action, Some(ActionKind::Keep));
+        assert_eq!(merged.strip_prompts, Some(true));
+    }
+
+    #[test]
+    fn test_language_lists() {
+        let root =
+            std::env::temp_dir().join(format!("strip-codeblocks-cli-lists-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("deny.txt"), "# policy\nsql\n").unwrap();
+        fs::write(
+            root.join(CONFIG_FILE_NAME),
+            "deny_languages = [\"hcl\"]\ndeny_languages_file = \"deny.txt\"",
+        )
+        .unwrap();
+
+        let settings = Settings::load(&root.join(CONFIG_FILE_NAME)).unwrap();
+        assert_eq!(settings.deny_languages_file, Some(root.join("deny.txt")));
+        assert_eq!(
+            settings.to_options().unwrap(),
+            StripOptions::new().deny_languages(["hcl", "sql"])
+        );
+
+        let missing = Settings {
+            languages_file: Some(root.join("missing.txt")),
+            ..Settings::default()
+        };
+        assert!(missing.to_options().is_err());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_find_config_in_ancestors() {
+        let root =
+            std::env::temp_dir().join(format!("strip-codeblocks-cli-{}", std::process::id()));
+        let nested = root.join("a").join("b");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(root.join(CONFIG_FILE_NAME), "action = \"remove\"").unwrap();
+
+        assert_eq!(find_config(&nested), Some(root.join(CONFIG_FILE_NAME)));
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
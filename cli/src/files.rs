@@ -0,0 +1,112 @@
+//! Expanding input patterns and processing many files in parallel.
+
+use std::error::Error;
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+/// Expands the glob patterns among `inputs`; other inputs are kept as paths.
+///
+/// Matches of each pattern are sorted, and a pattern matching no file is an
+/// error, so a typo doesn't silently process nothing.
+pub fn expand_inputs(inputs: &[String]) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let mut paths = Vec::new();
+
+    for input in inputs {
+        if !input.contains(['*', '?', '[']) {
+            paths.push(PathBuf::from(input));
+            continue;
+        }
+
+        let mut matches = glob::glob(input)?
+            .filter(|entry| entry.as_ref().map_or(true, |path| path.is_file()))
+            .collect::<Result<Vec<_>, _>>()?;
+        if matches.is_empty() {
+            return Err(format!("{input}: no files match this pattern").into());
+        }
+        matches.sort();
+        paths.extend(matches);
+    }
+
+    Ok(paths)
+}
+
+/// Applies `process` to every item on up to `jobs` threads, returning the
+/// results in the order of `items`.
+pub fn parallel_map<T, R, F>(items: &[T], jobs: usize, process: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let jobs = jobs.clamp(1, items.len().max(1));
+    if jobs == 1 {
+        return items.iter().map(process).collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<R>>> = Mutex::new(items.iter().map(|_| None).collect());
+
+    thread::scope(|scope| {
+        for _ in 0..jobs {
+            scope.spawn(|| {
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(item) = items.get(index) else { break };
+                    let result = process(item);
+                    results.lock().unwrap()[index] = Some(result);
+                }
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|result| result.expect("every item is processed"))
+        .collect()
+}
+
+/// Returns the number of threads to use by default.
+pub fn default_jobs() -> usize {
+    thread::available_parallelism().map_or(1, NonZeroUsize::get)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_parallel_map_keeps_order() {
+        let items: Vec<usize> = (0..100).collect();
+        let results = parallel_map(&items, 8, |item| item * 2);
+        assert_eq!(results, (0..100).map(|item| item * 2).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_expand_inputs() {
+        let dir =
+            std::env::temp_dir().join(format!("strip-codeblocks-glob-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for name in ["b.md", "a.md", "c.txt"] {
+            fs::write(dir.join(name), "").unwrap();
+        }
+
+        let pattern = dir.join("*.md").display().to_string();
+        let literal = dir.join("c.txt").display().to_string();
+        let paths = expand_inputs(&[pattern, literal]).unwrap();
+        assert_eq!(
+            paths,
+            [dir.join("a.md"), dir.join("b.md"), dir.join("c.txt")]
+        );
+
+        let missing = dir.join("*.rst").display().to_string();
+        assert!(expand_inputs(&[missing]).is_err());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
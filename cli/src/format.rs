@@ -0,0 +1,223 @@
+//! Writing the stripped text in the format chosen with `--format`.
+
+use clap::ValueEnum;
+use serde_json::json;
+use strip_codeblocks::{FlatSegment, Report, SegmentKind, StrippedRef};
+
+use crate::verify;
+
+/// How the stripped text is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum Format {
+    /// The stripped text as it is, still markdown
+    #[default]
+    Markdown,
+    /// The stripped text without the markup of headings, quotes, emphasis, inline code and links
+    Plain,
+    /// A JSON object with the stripped text and the blocks found
+    Json,
+    /// Minimal HTML, with prose in `<p>` and what code blocks were stripped to in `<pre>`
+    Html,
+}
+
+impl Format {
+    /// Writes `output`, which `input` was stripped to as the runs of
+    /// `stripped`, finding the blocks of `report`, in this format.
+    pub fn render(
+        self,
+        input: &str,
+        stripped: &StrippedRef,
+        output: String,
+        report: &Report,
+    ) -> String {
+        let push_segment = match self {
+            Format::Markdown => return output,
+            Format::Json => return to_json(output, report),
+            Format::Plain => push_plain_segment,
+            Format::Html => push_html_segment,
+        };
+        let mut rendered = String::with_capacity(output.len());
+        for segment in &stripped.segments {
+            push_segment(&mut rendered, stripped.text(input, segment), segment);
+        }
+        rendered
+    }
+}
+
+/// Returns the stripped text and the blocks of `report` as a line of JSON.
+fn to_json(output: String, report: &Report) -> String {
+    let blocks: Vec<_> = report
+        .blocks
+        .iter()
+        .map(|block| {
+            json!({
+                "language": block.language,
+                "lines": [block.lines.start(), block.lines.end()],
+                "span": [block.span.start, block.span.end],
+                "action": verify::describe(&block.action).0,
+            })
+        })
+        .collect();
+    let mut json = json!({ "text": output, "blocks": blocks }).to_string();
+    json.push('\n');
+    json
+}
+
+/// Writes a run of plain text: prose without its markup, code as it is.
+fn push_plain_segment(output: &mut String, text: &str, segment: &FlatSegment) {
+    match segment.segment_kind() {
+        SegmentKind::Prose => push_plain(output, text),
+        _ => output.push_str(text),
+    }
+}
+
+/// Writes a run of HTML: paragraphs of prose, or a block in `<pre>`.
+fn push_html_segment(output: &mut String, text: &str, segment: &FlatSegment) {
+    match segment.segment_kind() {
+        SegmentKind::Prose => push_paragraphs(output, text),
+        _ => push_pre(output, text, segment),
+    }
+}
+
+/// Writes `prose` without the most common markdown markup, line by line.
+///
+/// Single `*` and `_` are left alone, as they are as often part of the text,
+/// like in `snake_case`, as emphasis.
+fn push_plain(output: &mut String, prose: &str) {
+    for line in prose.split_inclusive('\n') {
+        let mut line = line;
+        while let Some(rest) = line.strip_prefix('>') {
+            line = rest.strip_prefix(' ').unwrap_or(rest);
+        }
+        let hashes = line.len() - line.trim_start_matches('#').len();
+        if (1..=6).contains(&hashes) && line[hashes..].starts_with(' ') {
+            line = &line[hashes + 1..];
+        }
+        push_plain_inline(output, line);
+    }
+}
+
+/// Writes `line` without emphasis, inline code delimiters and link targets.
+fn push_plain_inline(output: &mut String, line: &str) {
+    let mut rest = line;
+    while let Some(start) = rest.find(['*', '_', '`', '[', '!']) {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if let Some(after) = rest.strip_prefix("**").or_else(|| rest.strip_prefix("__")) {
+            rest = after;
+        } else if rest.starts_with('`') {
+            rest = rest.trim_start_matches('`');
+        } else if let Some((text, after)) = link(rest.strip_prefix('!').unwrap_or(rest)) {
+            output.push_str(text);
+            rest = after;
+        } else {
+            output.push_str(&rest[..1]);
+            rest = &rest[1..];
+        }
+    }
+    output.push_str(rest);
+}
+
+/// Splits `[text](target)` at the start of `text` into the text of the link
+/// and what follows it.
+fn link(text: &str) -> Option<(&str, &str)> {
+    let inner = text.strip_prefix('[')?;
+    let close = inner.find(']')?;
+    let target = inner[close..].strip_prefix("](")?;
+    let end = target.find(')')?;
+    Some((&inner[..close], &target[end + 1..]))
+}
+
+/// Writes each paragraph of `prose` in a `<p>` element.
+fn push_paragraphs(output: &mut String, prose: &str) {
+    let mut paragraph = String::new();
+    for line in prose.split_inclusive('\n') {
+        if line.trim().is_empty() {
+            push_paragraph(output, &paragraph);
+            paragraph.clear();
+        } else {
+            paragraph.push_str(line);
+        }
+    }
+    push_paragraph(output, &paragraph);
+}
+
+fn push_paragraph(output: &mut String, paragraph: &str) {
+    let paragraph = paragraph.trim();
+    if !paragraph.is_empty() {
+        output.push_str("<p>");
+        push_escaped(output, paragraph);
+        output.push_str("</p>\n");
+    }
+}
+
+/// Writes what a block was stripped to in a `<pre>` element, with a `<code>`
+/// element unless it is a placeholder stripping generated.
+fn push_pre(output: &mut String, code: &str, segment: &FlatSegment) {
+    let code = code.strip_suffix('\n').unwrap_or(code);
+    let code = code.strip_suffix('\r').unwrap_or(code);
+    if code.trim().is_empty() {
+        return;
+    }
+    let (open, close) = if segment.is_generated() {
+        ("<pre>", "</pre>\n")
+    } else {
+        ("<pre><code>", "</code></pre>\n")
+    };
+    output.push_str(open);
+    push_escaped(output, code);
+    output.push_str(close);
+}
+
+/// Writes `text` with the characters special to HTML escaped.
+fn push_escaped(output: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '"' => output.push_str("&quot;"),
+            c => output.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use strip_codeblocks::{Action, StripOptions};
+
+    const INPUT: &str = "# Setup\n\n> Run **this** with `sh`:\n```sh\nls <dir>\n```\nSee [the docs](https://example.com).\n";
+
+    fn render(format: Format, options: &StripOptions) -> String {
+        let (stripped, report) =
+            strip_codeblocks::strip_codeblocks_ref_with_report(INPUT, options).unwrap();
+        format.render(INPUT, &stripped, stripped.rebuild(INPUT), &report)
+    }
+
+    #[test]
+    fn test_plain() {
+        assert_eq!(
+            render(Format::Plain, &StripOptions::new()),
+            "Setup\n\nRun this with sh:\nls <dir>\n\nSee the docs.\n"
+        );
+    }
+
+    #[test]
+    fn test_html() {
+        assert_eq!(
+            render(Format::Html, &StripOptions::new()),
+            "<p># Setup</p>\n<p>&gt; Run **this** with `sh`:</p>\n<pre><code>ls &lt;dir&gt;</code></pre>\n<p>See [the docs](https://example.com).</p>\n"
+        );
+        let options = StripOptions::new().action(Action::Placeholder("[code]".into()));
+        assert!(render(Format::Html, &options).contains("<pre>[code]</pre>\n"));
+    }
+
+    #[test]
+    fn test_json() {
+        assert_eq!(
+            render(Format::Json, &StripOptions::new()),
+            "{\"text\":\"# Setup\\n\\n> Run **this** with `sh`:\\nls <dir>\\n\\nSee [the docs](https://example.com).\\n\",\"blocks\":[{\"language\":\"sh\",\"lines\":[4,6],\"span\":[35,53],\"action\":\"unwrap\"}]}\n"
+        );
+    }
+}
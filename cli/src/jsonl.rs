@@ -0,0 +1,111 @@
+//! Stripping a text field of every record of a JSON Lines dataset.
+
+use serde_json::Value;
+use strip_codeblocks::StripOptions;
+
+use crate::stats::Stats;
+
+/// Strips the string at `field` in each JSON record of `input`, one record per line.
+///
+/// `field` is either a top-level key or a JSON Pointer such as
+/// `/messages/0/content`. Records where the field is missing or isn't a string
+/// are written back unchanged, as are blank lines. Records are re-serialized
+/// compactly, with their keys in their original order. The blocks of every
+/// stripped field are recorded in `stats`.
+pub fn strip_jsonl(
+    input: &str,
+    field: &str,
+    options: &StripOptions,
+    stats: &mut Stats,
+) -> Result<String, String> {
+    let mut output = String::with_capacity(input.len());
+
+    for (number, line) in input.split_inclusive('\n').enumerate() {
+        let record = line.trim_end_matches(['\n', '\r']);
+        if record.trim().is_empty() {
+            output.push_str(line);
+            continue;
+        }
+
+        let mut value: Value =
+            serde_json::from_str(record).map_err(|err| format!("line {}: {err}", number + 1))?;
+        let target = if field.starts_with('/') {
+            value.pointer_mut(field)
+        } else {
+            value.get_mut(field)
+        };
+        if let Some(Value::String(text)) = target {
+            let (output, report) = strip_codeblocks::strip_codeblocks_with_report(text, options);
+            stats.record(&report, text, &output);
+            *text = output;
+        }
+
+        output.push_str(&value.to_string());
+        output.push_str(&line[record.len()..]);
+    }
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_jsonl_field() {
+        let input = "{\"id\":1,\"text\":\"```sh\\nls\\n```\"}\n\n{\"id\":2,\"text\":\"plain\"}\n";
+        let output =
+            strip_jsonl(input, "text", &StripOptions::new(), &mut Stats::default()).unwrap();
+        assert_eq!(
+            output,
+            "{\"id\":1,\"text\":\"ls\\n\"}\n\n{\"id\":2,\"text\":\"plain\"}\n"
+        );
+    }
+
+    #[test]
+    fn test_strip_jsonl_pointer() {
+        let input = "{\"messages\":[{\"role\":\"assistant\",\"content\":\"```\\nx\\n```\"}]}";
+        let output = strip_jsonl(
+            input,
+            "/messages/0/content",
+            &StripOptions::new(),
+            &mut Stats::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            output,
+            "{\"messages\":[{\"role\":\"assistant\",\"content\":\"x\\n\"}]}"
+        );
+    }
+
+    #[test]
+    fn test_strip_jsonl_records_stats() {
+        let input = "{\"text\":\"```sh\\nls\\n```\"}\n{\"text\":\"```\\nx\\n```\"}\n";
+        let mut stats = Stats::default();
+        strip_jsonl(input, "text", &StripOptions::new(), &mut stats).unwrap();
+        assert_eq!(stats.blocks_found, 2);
+        assert_eq!(stats.languages["sh"], 1);
+        assert_eq!(stats.bytes_removed, 16);
+    }
+
+    #[test]
+    fn test_strip_jsonl_leaves_other_records() {
+        let input = "{\"text\":42}\n{\"other\":\"```\\nx\\n```\"}\n";
+        assert_eq!(
+            strip_jsonl(input, "text", &StripOptions::new(), &mut Stats::default()).unwrap(),
+            input
+        );
+    }
+
+    #[test]
+    fn test_strip_jsonl_reports_invalid_lines() {
+        let err = strip_jsonl(
+            "{}\n{oops}\n",
+            "text",
+            &StripOptions::new(),
+            &mut Stats::default(),
+        )
+        .unwrap_err();
+        assert!(err.starts_with("line 2: "), "{err}");
+    }
+}
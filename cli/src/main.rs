@@ -0,0 +1,280 @@
+//! Command-line interface to strip markdown fenced code blocks from text.
+//!
+//! Reads documents from files (or glob patterns) or standard input and writes
+//! the stripped text to standard output, a file, or back to the input files.
+//! Files ending in `.gz` or `.zst` are decompressed and compressed with the
+//! `gzip` and `zstd` programs.
+//! Settings come from the closest `.stripcodeblocksrc` TOML file found from the
+//! working directory upward, overridden by environment variables, then by
+//! command-line flags.
+
+mod compress;
+mod config;
+mod files;
+mod format;
+mod jsonl;
+mod stats;
+mod verify;
+
+use std::error::Error;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::Parser;
+use similar::TextDiff;
+use strip_codeblocks::StripOptions;
+
+use crate::config::{Settings, find_config};
+use crate::format::Format;
+use crate::stats::Stats;
+use crate::verify::ColorChoice;
+
+/// Strip markdown fenced code blocks from text, preserving only the inner content.
+#[derive(Debug, Parser)]
+#[command(version)]
+struct Cli {
+    /// Input files or glob patterns (reads standard input when omitted or `-`); `.gz` and `.zst` files are decompressed
+    inputs: Vec<String>,
+
+    /// Output file (writes to standard output when omitted), compressed when it ends in `.gz` or `.zst`
+    #[arg(short, long, conflicts_with_all = ["in_place", "dry_run"])]
+    output: Option<PathBuf>,
+
+    /// Rewrite the input files with their stripped text
+    #[arg(short, long)]
+    in_place: bool,
+
+    /// Keep a copy of each rewritten file, with a `.bak` extension appended
+    #[arg(long, requires = "in_place")]
+    backup: bool,
+
+    /// Print a diff of the changes `--in-place` would make, without writing anything
+    #[arg(long)]
+    dry_run: bool,
+
+    /// List the detected blocks with their line ranges and the action that would be taken, without stripping
+    #[arg(long, visible_alias = "list", conflicts_with_all = ["output", "in_place", "dry_run", "jsonl"])]
+    verify: bool,
+
+    /// When to color the `--verify` listing
+    #[arg(long, value_enum, default_value_t = ColorChoice::Auto, value_name = "WHEN")]
+    color: ColorChoice,
+
+    /// Treat the input as JSON Lines and strip a text field of each record
+    #[arg(long)]
+    jsonl: bool,
+
+    /// Field stripped with `--jsonl`: a top-level key or a JSON Pointer like `/messages/0/content`
+    #[arg(long, default_value = "text", requires = "jsonl")]
+    field: String,
+
+    /// Treat the input as a unified diff and strip the markdown on each side of its hunks
+    #[arg(long, conflicts_with_all = ["jsonl", "verify", "stats", "stats_out"])]
+    diff: bool,
+
+    /// How to write the stripped text
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = Format::Markdown,
+        conflicts_with_all = ["in_place", "dry_run", "verify", "jsonl", "diff"]
+    )]
+    format: Format,
+
+    /// Print a JSON summary of the blocks found, per language, and the bytes removed to standard error
+    #[arg(long, conflicts_with = "verify")]
+    stats: bool,
+
+    /// Write the `--stats` summary to this file instead
+    #[arg(long, value_name = "PATH", conflicts_with = "verify")]
+    stats_out: Option<PathBuf>,
+
+    /// Number of files processed in parallel (defaults to the number of CPUs)
+    #[arg(short, long, value_name = "N")]
+    jobs: Option<usize>,
+
+    /// Configuration file to use instead of the discovered `.stripcodeblocksrc`
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Ignore configuration files
+    #[arg(long, conflicts_with = "config")]
+    no_config: bool,
+
+    #[command(flatten)]
+    settings: Settings,
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Runs the command, returning whether every input was processed successfully.
+fn run(cli: Cli) -> Result<bool, Box<dyn Error>> {
+    let config_path = match &cli.config {
+        Some(path) => Some(path.clone()),
+        None if cli.no_config => None,
+        None => find_config(&std::env::current_dir()?),
+    };
+    let config = match config_path {
+        Some(path) => Settings::load(&path)?,
+        None => Settings::default(),
+    };
+    let options = config
+        .merge(Settings::from_env())
+        .merge(cli.settings.clone())
+        .to_options()?;
+
+    let inputs: Vec<_> = cli
+        .inputs
+        .iter()
+        .filter(|input| *input != "-")
+        .cloned()
+        .collect();
+    if inputs.is_empty() {
+        if cli.in_place || cli.dry_run {
+            return Err("--in-place and --dry-run need input files".into());
+        }
+        let mut input = String::new();
+        io::stdin().read_to_string(&mut input)?;
+        if cli.verify {
+            let listing = verify::list_blocks("<stdin>", &input, &options, cli.color.enabled());
+            io::stdout().write_all(listing.as_bytes())?;
+            return Ok(true);
+        }
+        let (output, stats) = strip(&cli, &options, &input)?;
+        write_output(&cli, &output)?;
+        write_stats(&cli, &stats)?;
+        return Ok(true);
+    }
+
+    let paths = files::expand_inputs(&inputs)?;
+    if cli.output.is_some() && paths.len() > 1 {
+        return Err("--output needs a single input file".into());
+    }
+
+    let jobs = cli.jobs.unwrap_or_else(files::default_jobs);
+    let results = files::parallel_map(&paths, jobs, |path| process_file(&cli, &options, path));
+
+    let mut success = true;
+    let mut total = Stats::default();
+    let mut stdout = io::stdout().lock();
+    for (path, result) in paths.iter().zip(results) {
+        match result {
+            Ok((output, stats)) => {
+                total.add(stats);
+                match output {
+                    Some(output) if cli.in_place || cli.dry_run || cli.verify => {
+                        stdout.write_all(output.as_bytes())?
+                    }
+                    Some(output) => write_output(&cli, &output)?,
+                    None => {}
+                }
+            }
+            Err(err) => {
+                eprintln!("error: {}: {err}", path.display());
+                success = false;
+            }
+        }
+    }
+    write_stats(&cli, &total)?;
+    Ok(success)
+}
+
+/// Strips one file.
+///
+/// Returns the text to print: the stripped text when writing to the output,
+/// the diff of the changes with `--dry-run`, the block listing with
+/// `--verify`, and nothing with `--in-place`, with the stats of the file.
+fn process_file(
+    cli: &Cli,
+    options: &StripOptions,
+    path: &Path,
+) -> io::Result<(Option<String>, Stats)> {
+    let input = compress::read_to_string(path)?;
+    if cli.verify {
+        let name = path.display().to_string();
+        let listing = verify::list_blocks(&name, &input, options, cli.color.enabled());
+        return Ok((Some(listing), Stats::default()));
+    }
+
+    let (output, stats) = strip(cli, options, &input).map_err(io::Error::other)?;
+
+    if cli.dry_run {
+        let name = path.display().to_string();
+        let diff = TextDiff::from_lines(&input, &output)
+            .unified_diff()
+            .header(&name, &name)
+            .to_string();
+        return Ok((Some(diff), stats));
+    }
+    if !cli.in_place {
+        return Ok((Some(output), stats));
+    }
+
+    if output != input {
+        if cli.backup {
+            let mut backup = path.as_os_str().to_owned();
+            backup.push(".bak");
+            fs::copy(path, backup)?;
+        }
+        compress::write(path, &output)?;
+    }
+    Ok((None, stats))
+}
+
+/// Strips one document, each record of a dataset with `--jsonl`, or each
+/// side of a patch with `--diff`, with the stats of the document, written in
+/// the `--format` chosen.
+fn strip(cli: &Cli, options: &StripOptions, input: &str) -> Result<(String, Stats), String> {
+    let mut stats = Stats::default();
+    let output = if cli.jsonl {
+        jsonl::strip_jsonl(input, &cli.field, options, &mut stats)?
+    } else if cli.diff {
+        // Hunks are stripped piecewise, without a report of their blocks, so
+        // `--stats` is refused with `--diff`
+        strip_codeblocks::strip_patch_with(input, options)
+    } else {
+        let (stripped, report) = strip_codeblocks::strip_codeblocks_ref_with_report(input, options)
+            .map_err(|err| err.to_string())?;
+        let output = stripped.rebuild(input);
+        stats.record(&report, input, &output);
+        stats.files_changed = usize::from(output != input);
+        // The format only changes how the stripped text is written
+        return Ok((cli.format.render(input, &stripped, output, &report), stats));
+    };
+    stats.files_changed = usize::from(output != input);
+    Ok((output, stats))
+}
+
+/// Writes `stats` to the `--stats-out` file, or standard error with `--stats`.
+fn write_stats(cli: &Cli, stats: &Stats) -> Result<(), Box<dyn Error>> {
+    match &cli.stats_out {
+        Some(path) => {
+            fs::write(path, stats.to_json()).map_err(|err| format!("{}: {err}", path.display()))?
+        }
+        None if cli.stats => io::stderr().write_all(stats.to_json().as_bytes())?,
+        None => {}
+    }
+    Ok(())
+}
+
+/// Writes stripped text to the `--output` file, or standard output.
+fn write_output(cli: &Cli, output: &str) -> Result<(), Box<dyn Error>> {
+    match &cli.output {
+        Some(path) => {
+            compress::write(path, output).map_err(|err| format!("{}: {err}", path.display()))?
+        }
+        None => io::stdout().write_all(output.as_bytes())?,
+    }
+    Ok(())
+}
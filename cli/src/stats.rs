@@ -0,0 +1,81 @@
+//! Summarizing what stripping did, for `--stats`.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+use strip_codeblocks::Report;
+
+/// A machine-readable summary of a run, written as JSON.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Stats {
+    /// The number of code blocks found, whatever was done with them.
+    pub blocks_found: usize,
+    /// The number of blocks found per language, `""` for blocks without one.
+    pub languages: BTreeMap<String, usize>,
+    /// The number of bytes removed, less those added by placeholders.
+    pub bytes_removed: i64,
+    /// The number of documents whose stripped text differs from the input.
+    pub files_changed: usize,
+}
+
+impl Stats {
+    /// Records the blocks of `report`, found while stripping `input` to `output`.
+    ///
+    /// Doesn't count the document as changed, which is up to the caller, as a
+    /// dataset has many stripped texts per file.
+    pub fn record(&mut self, report: &Report, input: &str, output: &str) {
+        self.blocks_found += report.blocks.len();
+        for block in &report.blocks {
+            let language = block.language.as_deref().unwrap_or_default();
+            *self.languages.entry(language.to_string()).or_default() += 1;
+        }
+        self.bytes_removed += input.len() as i64 - output.len() as i64;
+    }
+
+    /// Adds the counts of `other` to these.
+    pub fn add(&mut self, other: Stats) {
+        self.blocks_found += other.blocks_found;
+        for (language, blocks) in other.languages {
+            *self.languages.entry(language).or_default() += blocks;
+        }
+        self.bytes_removed += other.bytes_removed;
+        self.files_changed += other.files_changed;
+    }
+
+    /// Returns the summary as a line of JSON.
+    pub fn to_json(&self) -> String {
+        let mut json = serde_json::to_string(self).expect("stats are serializable");
+        json.push('\n');
+        json
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use strip_codeblocks::{Action, StripOptions, strip_codeblocks_with_report};
+
+    #[test]
+    fn test_record_and_add() {
+        let input = "```sh\nls\n```\n```\nx\n```\n```sh\npwd\n```";
+        let options = StripOptions::new().action(Action::Remove);
+        let (output, report) = strip_codeblocks_with_report(input, &options);
+
+        let mut stats = Stats::default();
+        stats.record(&report, input, &output);
+        stats.add(Stats {
+            blocks_found: 1,
+            languages: BTreeMap::from([("sh".to_string(), 1)]),
+            bytes_removed: -3,
+            files_changed: 1,
+        });
+
+        assert_eq!(
+            stats.to_json(),
+            format!(
+                "{{\"blocks_found\":4,\"languages\":{{\"\":1,\"sh\":3}},\"bytes_removed\":{},\"files_changed\":1}}\n",
+                input.len() - output.len() - 3
+            )
+        );
+    }
+}
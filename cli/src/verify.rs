@@ -0,0 +1,113 @@
+//! Listing the blocks detected in a document, for `--verify`.
+
+use std::fmt::Write;
+
+use clap::ValueEnum;
+use strip_codeblocks::{Action, StripOptions};
+
+/// When to color the listing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    /// Color when writing to a terminal
+    Auto,
+    /// Always color
+    Always,
+    /// Never color
+    Never,
+}
+
+impl ColorChoice {
+    /// Whether to color output written to standard output.
+    pub fn enabled(self) -> bool {
+        use std::io::IsTerminal;
+
+        match self {
+            ColorChoice::Auto => std::io::stdout().is_terminal(),
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+}
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const CYAN: &str = "\x1b[36m";
+
+/// Lists the blocks of `input` with their language, line range and the action
+/// stripping with `options` would take, one block per line.
+pub fn list_blocks(name: &str, input: &str, options: &StripOptions, color: bool) -> String {
+    let (_, report) = strip_codeblocks::strip_codeblocks_with_report(input, options);
+    let paint = |code: &'static str| if color { code } else { "" };
+
+    let mut listing = String::new();
+    for block in &report.blocks {
+        let first_line = line_number(input, block.span.start);
+        let last_line = line_number(
+            input,
+            block.span.end.saturating_sub(1).max(block.span.start),
+        );
+        let (action, action_color) = describe(&block.action);
+
+        let _ = writeln!(
+            listing,
+            "{bold}{name}:{first_line}-{last_line}{reset}\t{cyan}{language}{reset}\t{action_color}{action}{reset}",
+            bold = paint(BOLD),
+            cyan = paint(CYAN),
+            reset = paint(RESET),
+            language = block.language.as_deref().unwrap_or("-"),
+            action_color = paint(action_color),
+        );
+    }
+    listing
+}
+
+/// Returns the 1-based line number of the byte at `offset` in `text`.
+fn line_number(text: &str, offset: usize) -> usize {
+    text.as_bytes()[..offset]
+        .iter()
+        .filter(|&&byte| byte == b'\n')
+        .count()
+        + 1
+}
+
+/// Returns the name and color of an action.
+pub fn describe(action: &Action) -> (&'static str, &'static str) {
+    match action {
+        Action::Unwrap => ("unwrap", "\x1b[32m"),
+        Action::Keep => ("keep", "\x1b[2m"),
+        Action::Remove => ("remove", "\x1b[31m"),
+        Action::Placeholder(_) => ("placeholder", "\x1b[33m"),
+        Action::FirstLine(_) => ("first line", "\x1b[36m"),
+        _ => ("other", ""),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_list_blocks() {
+        let input = "Intro\n```rust\nfn a() {}\n```\ntext\n```\nplain\n```";
+        let options = StripOptions::new().languages(["rust"]);
+
+        assert_eq!(
+            list_blocks("a.md", input, &options, false),
+            "a.md:2-4\trust\tunwrap\na.md:6-8\t-\tkeep\n"
+        );
+    }
+
+    #[test]
+    fn test_list_blocks_colored() {
+        let listing = list_blocks(
+            "-",
+            "```\nx\n```",
+            &StripOptions::new().action(Action::Remove),
+            true,
+        );
+        assert_eq!(
+            listing,
+            "\x1b[1m-:1-3\x1b[0m\t\x1b[36m-\x1b[0m\t\x1b[31mremove\x1b[0m\n"
+        );
+    }
+}
@@ -0,0 +1,11 @@
+//! Checks the guarantees of `strip_codeblocks_hardened` on arbitrary text.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use strip_codeblocks::strip_codeblocks_hardened;
+
+fuzz_target!(|text: &str| {
+    let output = strip_codeblocks_hardened(text);
+    assert!(output.len() <= text.len());
+});
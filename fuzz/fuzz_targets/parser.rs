@@ -0,0 +1,38 @@
+//! Finds and strips blocks of arbitrary text with every engine and action,
+//! checking that nothing panics and that blocks are well-formed.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use strip_codeblocks::experimental::Engine;
+use strip_codeblocks::{
+    Action, StripOptions, strip_codeblocks_with, strip_codeblocks_with_report,
+    try_strip_codeblocks_with, verify_prose_preserved,
+};
+
+fuzz_target!(|text: &str| {
+    for engine in [Engine::Regex, Engine::Parser] {
+        for action in [
+            Action::Unwrap,
+            Action::Remove,
+            Action::FirstLine("…".into()),
+        ] {
+            let options = StripOptions::new().engine(engine).action(action);
+            strip_codeblocks_with(text, &options);
+            let _ = try_strip_codeblocks_with(text, &options.clone().strict(true));
+
+            let preserved = strip_codeblocks_with(text, &options.preserve_whitespace(true));
+            if engine == Engine::Regex {
+                assert!(verify_prose_preserved(text, &preserved).is_ok());
+            }
+        }
+
+        let (_, report) = strip_codeblocks_with_report(text, &StripOptions::new().engine(engine));
+        let mut last = 0;
+        for block in &report.blocks {
+            assert!(last <= block.span.start && block.span.start < block.span.end);
+            assert!(block.span.end <= text.len());
+            last = block.span.end;
+        }
+    }
+});
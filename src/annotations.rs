@@ -0,0 +1,108 @@
+//! Typed metadata attached to code blocks by [`Pipeline`](crate::Pipeline) stages.
+
+use std::any::{Any, TypeId};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+/// Values of any type attached to a code block, at most one of each type.
+///
+/// [`Pipeline::annotate`](crate::Pipeline::annotate) stages attach them, and
+/// later stages and the [report](crate::Pipeline::run_with_report) read them
+/// back by type, so multi-stage pipelines don't need side tables keyed by
+/// block index. Annotations are opaque to comparisons: they always compare
+/// equal and hash alike, so reports compare on what stripping did.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::Annotations;
+///
+/// struct Reviewed(bool);
+///
+/// let mut annotations = Annotations::new();
+/// annotations.insert(Reviewed(true));
+/// annotations.insert(42_u32);
+///
+/// assert!(annotations.get::<Reviewed>().unwrap().0);
+/// assert_eq!(annotations.get::<u32>(), Some(&42));
+/// assert_eq!(annotations.get::<String>(), None);
+/// ```
+#[derive(Clone, Default)]
+pub struct Annotations {
+    values: Vec<(TypeId, Arc<dyn Any + Send + Sync>)>,
+}
+
+impl Annotations {
+    /// Creates an empty set of annotations.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Attaches `value`, replacing any value of the same type.
+    pub fn insert<T: Any + Send + Sync>(&mut self, value: T) {
+        let value = Arc::new(value);
+        match self
+            .values
+            .iter_mut()
+            .find(|(id, _)| *id == TypeId::of::<T>())
+        {
+            Some((_, slot)) => *slot = value,
+            None => self.values.push((TypeId::of::<T>(), value)),
+        }
+    }
+
+    /// Returns the value of type `T`, if one is attached.
+    pub fn get<T: Any>(&self) -> Option<&T> {
+        self.values
+            .iter()
+            .find(|(id, _)| *id == TypeId::of::<T>())
+            .and_then(|(_, value)| value.downcast_ref())
+    }
+
+    /// Returns how many values are attached.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns whether no value is attached.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+impl fmt::Debug for Annotations {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Annotations")
+            .field("len", &self.values.len())
+            .finish()
+    }
+}
+
+impl PartialEq for Annotations {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for Annotations {}
+
+impl Hash for Annotations {
+    fn hash<H: Hasher>(&self, _: &mut H) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_replaces_same_type() {
+        let mut annotations = Annotations::new();
+        assert!(annotations.is_empty());
+        annotations.insert("first");
+        annotations.insert("second");
+        assert_eq!(annotations.len(), 1);
+        assert_eq!(annotations.get::<&str>(), Some(&"second"));
+        assert_eq!(annotations, Annotations::new());
+    }
+}
@@ -0,0 +1,314 @@
+//! Masking code blocks with stable ids, keeping a table to restore them.
+
+use std::collections::BTreeMap;
+
+use crate::hash::sha256_hex;
+use crate::region::Regions;
+use crate::scan::codeblocks_for;
+use crate::{Action, StripOptions};
+
+/// The mask of [`anonymize`].
+const DEFAULT_MASK: &str = "[block:{id}]";
+
+/// The number of hexadecimal digits of mask ids.
+const ID_LEN: usize = 16;
+
+/// The blocks masked by [`anonymize_with`], by id, to restore them later.
+///
+/// Ids are the first 16 hexadecimal digits of the SHA-256 digest of the
+/// masked block, fences included, so the same block gets the same id in
+/// every document and every run, and is held once however often it appears.
+/// Where documents are shared without their code, the table can be kept
+/// apart, or exported as JSON with `to_json` (`json` feature) and encrypted
+/// with the tools of your choice, for an authorized process to restore the
+/// blocks.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MaskTable {
+    /// The mask, with `{id}` standing for the id of the block.
+    mask: String,
+    blocks: BTreeMap<String, String>,
+}
+
+impl MaskTable {
+    /// Returns the mask of the blocks, with `{id}` standing for their id.
+    pub fn mask(&self) -> &str {
+        &self.mask
+    }
+
+    /// Returns the block masked with `id`, fences included, if there is one.
+    pub fn get(&self, id: &str) -> Option<&str> {
+        self.blocks.get(id).map(String::as_str)
+    }
+
+    /// Returns an iterator over the ids and blocks of the table, by id.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.blocks
+            .iter()
+            .map(|(id, block)| (id.as_str(), block.as_str()))
+    }
+
+    /// Returns the number of distinct blocks masked.
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Returns whether no block was masked.
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    /// Returns `text` with the masks of the blocks of the table replaced by
+    /// the blocks, undoing [`anonymize_with`].
+    ///
+    /// Masks of ids the table doesn't hold are left as they are, so a table
+    /// restores the documents of every run it was built from.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strip_codeblocks::anonymize;
+    ///
+    /// let text = "Key:\n```\nsk-123\n```\nDone";
+    /// let (masked, table) = anonymize(text);
+    ///
+    /// assert_eq!(table.restore(&masked), text);
+    /// ```
+    pub fn restore(&self, text: &str) -> String {
+        let mut found: Vec<(usize, usize, &str)> = Vec::new();
+        for (id, block) in &self.blocks {
+            let mask = self.mask.replace("{id}", id);
+            found.extend(
+                text.match_indices(&mask)
+                    .map(|(start, mask)| (start, start + mask.len(), block.as_str())),
+            );
+        }
+        found.sort_unstable_by_key(|&(start, ..)| start);
+
+        let mut output = String::with_capacity(text.len());
+        let mut last = 0;
+        for (start, end, block) in found {
+            // Masks can't overlap, unless the text happens to join two
+            if start < last {
+                continue;
+            }
+            output.push_str(&text[last..start]);
+            output.push_str(block);
+            last = end;
+        }
+        output.push_str(&text[last..]);
+        output
+    }
+}
+
+#[cfg(feature = "json")]
+impl MaskTable {
+    /// Returns the table as a JSON object, with the mask under `mask` and
+    /// the blocks by id under `blocks`, for a sidecar file.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strip_codeblocks::anonymize;
+    ///
+    /// let (_, table) = anonymize("```\nsk-123\n```");
+    ///
+    /// assert_eq!(
+    ///     table.to_json(),
+    ///     r#"{"mask":"[block:{id}]","blocks":{"d3f6e9cba1af3c30":"```\nsk-123\n```"}}"#
+    /// );
+    /// ```
+    pub fn to_json(&self) -> String {
+        let blocks = self
+            .blocks
+            .iter()
+            .map(|(id, block)| (id.clone(), serde_json::Value::from(block.as_str())))
+            .collect::<serde_json::Map<_, _>>();
+        serde_json::json!({ "mask": self.mask, "blocks": blocks }).to_string()
+    }
+
+    /// Reads a table written by [`to_json`](Self::to_json).
+    ///
+    /// # Arguments
+    ///
+    /// * `json` - The JSON object of the table
+    ///
+    /// # Returns
+    ///
+    /// The table, or an error if `json` isn't valid JSON or lacks the mask
+    /// or the blocks.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strip_codeblocks::{JsonError, MaskTable, anonymize};
+    ///
+    /// let (masked, table) = anonymize("```\nsk-123\n```");
+    /// let restored = MaskTable::from_json(&table.to_json())?;
+    /// assert_eq!(restored, table);
+    ///
+    /// let error = MaskTable::from_json(r#"{"mask": "[{id}]", "blocks": {"a": 1}}"#).unwrap_err();
+    /// assert_eq!(error, JsonError::NotAString("/blocks/a".into()));
+    /// # Ok::<(), JsonError>(())
+    /// ```
+    pub fn from_json(json: &str) -> Result<Self, crate::JsonError> {
+        use crate::JsonError;
+        use serde_json::Value;
+
+        let document: Value = serde_json::from_str(json)?;
+        let mask = match document.get("mask") {
+            Some(Value::String(mask)) => mask.clone(),
+            Some(_) => return Err(JsonError::NotAString("/mask".into())),
+            None => return Err(JsonError::Missing("/mask".into())),
+        };
+        let Some(Value::Object(entries)) = document.get("blocks") else {
+            return Err(JsonError::Missing("/blocks".into()));
+        };
+        let mut blocks = BTreeMap::new();
+        for (id, block) in entries {
+            let Value::String(block) = block else {
+                let pointer = id.replace('~', "~0").replace('/', "~1");
+                return Err(JsonError::NotAString(format!("/blocks/{pointer}")));
+            };
+            blocks.insert(id.clone(), block.clone());
+        }
+        Ok(Self { mask, blocks })
+    }
+}
+
+/// Replaces every fenced code block with a mask holding a stable id, and
+/// returns the table of the masked blocks.
+///
+/// This is [`anonymize_with`] with the default options and the mask
+/// `[block:{id}]`.
+///
+/// # Arguments
+///
+/// * `text` - The markdown text containing code blocks to mask
+///
+/// # Returns
+///
+/// The masked text, and the table restoring it.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::anonymize;
+///
+/// let text = "Key:\n```\nsk-123\n```\nAgain:\n```\nsk-123\n```";
+/// let (masked, table) = anonymize(text);
+///
+/// assert_eq!(masked, "Key:\n[block:d3f6e9cba1af3c30]\nAgain:\n[block:d3f6e9cba1af3c30]");
+/// assert_eq!(table.get("d3f6e9cba1af3c30"), Some("```\nsk-123\n```"));
+/// assert_eq!(table.len(), 1);
+/// ```
+pub fn anonymize(text: &str) -> (String, MaskTable) {
+    anonymize_with(text, &StripOptions::new(), DEFAULT_MASK)
+}
+
+/// Replaces the fenced code blocks `options` select with `mask`, `{id}`
+/// standing for a stable id of the block, and returns the table of the
+/// masked blocks, for sharing a corpus without its code.
+///
+/// Blocks are masked whole, fences included, so [`MaskTable::restore`] gives
+/// back the exact text. Only which blocks are masked is read from
+/// `options`: the blocks masked are those it doesn't
+/// [keep](Action::Keep), as set by the [languages](StripOptions::languages)
+/// filters, the [selection](StripOptions::select) and the
+/// [region markers](StripOptions::region_markers). The rest of the text is
+/// copied as it is. Ids only depend on the block, so the same block gets the
+/// same mask in every document of a corpus.
+///
+/// # Arguments
+///
+/// * `text` - The markdown text containing code blocks to mask
+/// * `options` - Which blocks are masked
+/// * `mask` - The text replacing blocks, with `{id}` standing for their id
+///
+/// # Returns
+///
+/// The masked text, and the table restoring it.
+///
+/// # Panics
+///
+/// Panics if `mask` doesn't contain `{id}`, as the blocks couldn't be restored.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::{StripOptions, anonymize_with};
+///
+/// let options = StripOptions::new().languages(["env"]);
+///
+/// let text = "```env\nTOKEN=abc\n```\n```sh\nls\n```";
+/// let (masked, table) = anonymize_with(text, &options, "<!-- masked {id} -->");
+///
+/// assert_eq!(masked, "<!-- masked ab8e61b51976726b -->\n```sh\nls\n```");
+/// assert_eq!(table.restore(&masked), text);
+/// ```
+pub fn anonymize_with(text: &str, options: &StripOptions, mask: &str) -> (String, MaskTable) {
+    assert!(mask.contains("{id}"), "the mask must contain `{{id}}`");
+
+    let regions = Regions::new(text, options);
+    let mut table = MaskTable {
+        mask: mask.to_string(),
+        blocks: BTreeMap::new(),
+    };
+    let mut output = String::with_capacity(text.len());
+    let mut last = 0;
+    for block in codeblocks_for(text, options) {
+        if *regions.action_for(&block, options) == Action::Keep {
+            continue;
+        }
+        let original = &text[block.span.clone()];
+        let id = &sha256_hex(original.as_bytes())[..ID_LEN];
+        output.push_str(&text[last..block.span.start]);
+        output.push_str(&mask.replace("{id}", id));
+        last = block.span.end;
+        table
+            .blocks
+            .entry(id.to_string())
+            .or_insert_with(|| original.to_string());
+    }
+    output.push_str(&text[last..]);
+    (output, table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::experimental::Engine;
+
+    #[test]
+    fn test_round_trip() {
+        let options = StripOptions::new()
+            .engine(Engine::Parser)
+            .region_markers(true);
+        let text = "~~~py\nx = 1\n~~~\n<!-- strip-codeblocks: off -->\n```\nkept\n```\n";
+        let (masked, table) = anonymize_with(text, &options, "{id}");
+
+        assert_eq!(table.len(), 1);
+        let (id, block) = table.iter().next().unwrap();
+        assert_eq!(block, "~~~py\nx = 1\n~~~");
+        assert_eq!(
+            masked,
+            format!("{id}\n<!-- strip-codeblocks: off -->\n```\nkept\n```\n")
+        );
+        assert_eq!(table.restore(&masked), text);
+    }
+
+    #[test]
+    fn test_unknown_masks_are_left() {
+        let (_, table) = anonymize("```\na\n```");
+        assert_eq!(
+            table.restore("[block:0000000000000000] [block:"),
+            "[block:0000000000000000] [block:"
+        );
+        assert!(anonymize("no code").1.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "the mask must contain `{id}`")]
+    fn test_mask_without_id() {
+        anonymize_with("```\na\n```", &StripOptions::new(), "[code]");
+    }
+}
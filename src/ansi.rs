@@ -0,0 +1,247 @@
+//! Handling of ANSI escape sequences in terminal-captured text.
+
+use std::borrow::Cow;
+
+use crate::block::{info_words, trim_info};
+use crate::experimental::Engine;
+use crate::scan::blocks_with;
+use crate::{Attributes, CodeBlock, trace};
+
+const ESC: u8 = 0x1b;
+
+/// How ANSI escape sequences (colors, cursor movements, ...) in the text are handled.
+///
+/// Text captured from a terminal often has color codes around or inside
+/// fences, which then aren't recognized as fences anymore.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::{Ansi, StripOptions, strip_codeblocks_with};
+///
+/// let captured = "Output:\n\x1b[2m```\x1b[0m\n\x1b[32mok\x1b[0m\n\x1b[2m```\x1b[0m\n";
+///
+/// let transparent = StripOptions::new().ansi(Ansi::Transparent);
+/// assert_eq!(strip_codeblocks_with(captured, &transparent), "Output:\n\x1b[2m\x1b[32mok\x1b[0m\n\x1b[2m\x1b[0m\n");
+///
+/// let stripped = StripOptions::new().ansi(Ansi::Strip);
+/// assert_eq!(strip_codeblocks_with(captured, &stripped), "Output:\nok\n\n");
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+#[non_exhaustive]
+pub enum Ansi {
+    /// Treat escape sequences as ordinary text, so fences containing them aren't found.
+    #[default]
+    Keep,
+    /// Ignore escape sequences when finding fences, but keep them in the output.
+    ///
+    /// Only the fences themselves are removed: escape sequences around them
+    /// stay with the prose and content next to them.
+    Transparent,
+    /// Ignore escape sequences when finding fences, and remove them from the output.
+    ///
+    /// Block spans still refer to the original text, escape sequences included.
+    Strip,
+}
+
+/// Returns the length of the escape sequence starting with the `ESC` at the start of `bytes`.
+///
+/// Control Sequence Introducer (`ESC [`) and Operating System Command
+/// (`ESC ]`) sequences are recognized, as are two-byte `ESC X` sequences. An
+/// `ESC` at the end of the text is a sequence of its own.
+fn sequence_len(bytes: &[u8]) -> usize {
+    match bytes.get(1) {
+        Some(b'[') => {
+            // Parameter and intermediate bytes, then a final byte
+            let end = bytes[2..]
+                .iter()
+                .position(|byte| !(0x20..0x40).contains(byte));
+            match end {
+                Some(end) if (0x40..0x7f).contains(&bytes[2 + end]) => 2 + end + 1,
+                Some(end) => 2 + end,
+                None => bytes.len(),
+            }
+        }
+        Some(b']') => {
+            // Terminated by BEL or `ESC \`
+            let mut index = 2;
+            while index < bytes.len() {
+                match bytes[index] {
+                    0x07 => return index + 1,
+                    ESC if bytes.get(index + 1) == Some(&b'\\') => return index + 2,
+                    _ => index += 1,
+                }
+            }
+            bytes.len()
+        }
+        Some(byte) if byte.is_ascii() && !byte.is_ascii_control() => 2,
+        _ => 1,
+    }
+}
+
+/// Returns `text` without its escape sequences, and the position in `text` of
+/// every remaining byte.
+fn clean(text: &str) -> (String, Vec<usize>) {
+    let bytes = text.as_bytes();
+    let mut cleaned = Vec::with_capacity(bytes.len());
+    let mut positions = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+
+    while index < bytes.len() {
+        if bytes[index] == ESC {
+            index += sequence_len(&bytes[index..]);
+        } else {
+            cleaned.push(bytes[index]);
+            positions.push(index);
+            index += 1;
+        }
+    }
+
+    // Escape sequences are ASCII, so removing them keeps the text valid UTF-8
+    let cleaned = String::from_utf8(cleaned).expect("escape sequences are ASCII");
+    (cleaned, positions)
+}
+
+/// Removes the escape sequences from `text`, borrowing it when it has none.
+pub(crate) fn strip_ansi(text: &str) -> Cow<'_, str> {
+    if has_escapes(text) {
+        Cow::Owned(clean(text).0)
+    } else {
+        Cow::Borrowed(text)
+    }
+}
+
+/// Returns whether `text` contains escape sequences.
+pub(crate) fn has_escapes(text: &str) -> bool {
+    text.as_bytes().contains(&ESC)
+}
+
+/// Finds the fenced code blocks of `text` with `engine`, ignoring escape
+/// sequences, only opened at fences following a blank line if `blank_line`.
+///
+/// The blocks borrow from `text` and their spans refer to it. A block starts
+/// at its opening fence and ends after its closing fence, so escape sequences
+/// just outside of them are prose. Those on the rest of the opening fence line
+/// are dropped with it, and those just before the closing fence are content.
+pub(crate) fn transparent_codeblocks(
+    text: &str,
+    engine: Engine,
+    blank_line: bool,
+) -> Vec<CodeBlock<'_>> {
+    let (cleaned, positions) = clean(text);
+    trace::debug!(
+        removed = text.len() - cleaned.len(),
+        "ignoring ANSI escape sequences when finding fences"
+    );
+    // Where the cleaned byte at `index` starts, and where the one before it ends
+    let start = |index: usize| positions.get(index).copied().unwrap_or(text.len());
+    let end = |index: usize| match index {
+        0 => 0,
+        _ => positions[index - 1] + 1,
+    };
+
+    blocks_with(&cleaned, engine, blank_line)
+        .map(|block| {
+            let info_start = block.info_raw.as_ptr() as usize - cleaned.as_ptr() as usize;
+            let info_end = info_start + block.info_raw.len();
+            let info_raw = if info_start == info_end {
+                ""
+            } else {
+                &text[start(info_start)..end(info_end)]
+            };
+            let info = trim_info(info_raw);
+            let content_span = end(block.content_span.start)..start(block.content_span.end);
+            // A fence interrupted by escape sequences isn't a fence of the text
+            let fence = &text[start(info_start - block.fence.len())..end(info_start)];
+            let fence = if fence == block.fence { fence } else { "" };
+
+            CodeBlock {
+                info,
+                info_raw,
+                fence,
+                language: info_words(info).next(),
+                content: &text[content_span.clone()],
+                span: start(block.span.start)..end(block.span.end),
+                content_span,
+                attributes: Attributes::parse(info),
+                index: block.index,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{StripOptions, extract_codeblocks, strip_codeblocks_with};
+
+    #[test]
+    fn test_sequence_lengths() {
+        assert_eq!(sequence_len(b"\x1b[0m rest"), 4);
+        assert_eq!(sequence_len(b"\x1b[38;5;208mx"), 11);
+        assert_eq!(sequence_len(b"\x1b]0;title\x07x"), 10);
+        assert_eq!(sequence_len(b"\x1b]8;;url\x1b\\x"), 10);
+        assert_eq!(sequence_len(b"\x1bMx"), 2);
+        assert_eq!(sequence_len(b"\x1b"), 1);
+        assert_eq!(sequence_len(b"\x1b[12"), 4);
+    }
+
+    #[test]
+    fn test_keep_treats_sequences_as_text() {
+        let input = "\x1b[1m```\x1b[36mrust\x1b[0m\ncode\n\x1b[1m```\x1b[0m";
+        assert_ne!(extract_codeblocks(input)[0].language, Some("rust"));
+
+        let parser = StripOptions::new().engine(Engine::Parser);
+        assert_eq!(strip_codeblocks_with(input, &parser), input);
+        assert_eq!(
+            strip_codeblocks_with(input, &parser.ansi(Ansi::Transparent)),
+            "\x1b[1mcode\n\x1b[1m\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn test_transparent_block_fields() {
+        let input = "Intro \x1b[1m```\x1b[36mrust\x1b[0m\n\x1b[33mfn main() {}\n```\x1b[0m tail";
+        let blocks = transparent_codeblocks(input, Engine::Regex, false);
+
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].language, Some("rust"));
+        assert_eq!(blocks[0].info_raw, "rust");
+        assert_eq!(blocks[0].fence, "```");
+        assert_eq!(blocks[0].content, "\x1b[33mfn main() {}\n");
+        assert_eq!(blocks[0].span, 10..48);
+        assert_eq!(&input[blocks[0].content_span.clone()], blocks[0].content);
+    }
+
+    #[test]
+    fn test_transparent_parser_engine() {
+        let input = "\x1b[2m~~~\x1b[0m py\nx = 1\n\x1b[2m~~~\x1b[0m\n";
+        let blocks = transparent_codeblocks(input, Engine::Parser, false);
+
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].language, Some("py"));
+        assert_eq!(blocks[0].fence, "~~~");
+        assert_eq!(blocks[0].content, "x = 1\n\x1b[2m");
+    }
+
+    #[test]
+    fn test_strip_removes_every_sequence() {
+        let options = StripOptions::new().ansi(Ansi::Strip);
+        let input = "\x1b[31merror\x1b[0m: see\n\x1b[1m```sh\x1b[0m\n\x1b[32m$ ls\x1b[0m\n```\n";
+        assert_eq!(
+            strip_codeblocks_with(input, &options),
+            "error: see\n$ ls\n\n"
+        );
+
+        let kept = options.languages(["rust"]);
+        assert_eq!(
+            strip_codeblocks_with(input, &kept),
+            "error: see\n```sh\n$ ls\n```\n"
+        );
+    }
+}
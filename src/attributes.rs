@@ -0,0 +1,168 @@
+//! MkDocs-style `key="value"` attributes in info strings.
+
+use std::ops::RangeInclusive;
+
+/// The attributes of a code block understood by MkDocs and similar
+/// documentation tools, parsed from its info string.
+///
+/// Attributes are `key="value"` pairs following the language, as in
+/// ```` ```python title="app.py" hl_lines="2 4-5" linenums="10" ````, or
+/// wrapped in braces, as in ```` ``` { .python hl_lines="2" } ````. Values can
+/// be double-quoted, single-quoted or bare. Attributes that are missing or
+/// can't be parsed are left unset; any attribute can be read as text with
+/// [`CodeBlock::attribute`](crate::CodeBlock::attribute).
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::extract_codeblocks;
+///
+/// let blocks = extract_codeblocks("```python title=\"app.py\" hl_lines=\"1 3-4\"\nimport os\n```");
+/// let attributes = &blocks[0].attributes;
+///
+/// assert_eq!(blocks[0].language, Some("python"));
+/// assert_eq!(attributes.title, Some("app.py"));
+/// assert_eq!(attributes.hl_lines, [1..=1, 3..=4]);
+/// assert_eq!(attributes.linenums, None);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct Attributes<'a> {
+    /// The `title` attribute, a caption such as a file name.
+    pub title: Option<&'a str>,
+    /// The lines of the content to highlight, from the `hl_lines` attribute,
+    /// counted from 1. Single lines are one-line ranges.
+    pub hl_lines: Vec<RangeInclusive<usize>>,
+    /// The number of the first line, from the `linenums` attribute, when line
+    /// numbers are shown.
+    pub linenums: Option<usize>,
+}
+
+impl<'a> Attributes<'a> {
+    /// Parses the attributes of the info string `info`.
+    pub(crate) fn parse(info: &'a str) -> Self {
+        let mut attributes = Self::default();
+
+        for (key, value) in attribute_pairs(info) {
+            match key {
+                "title" => attributes.title = Some(value),
+                "hl_lines" => attributes.hl_lines = line_ranges(value).collect(),
+                // A step may follow the first line number, as in `linenums="1 2"`
+                "linenums" => {
+                    attributes.linenums = value
+                        .split_whitespace()
+                        .next()
+                        .and_then(|start| start.parse().ok());
+                }
+                _ => {}
+            }
+        }
+
+        attributes
+    }
+}
+
+/// Returns the `key=value` pairs of the info string `info`, with the quotes
+/// around values removed.
+pub(crate) fn attribute_pairs(info: &str) -> impl Iterator<Item = (&str, &str)> {
+    let mut rest = info;
+
+    std::iter::from_fn(move || {
+        loop {
+            rest = rest
+                .trim_start_matches(|c: char| c.is_whitespace() || matches!(c, ',' | '{' | '}'));
+            if rest.is_empty() {
+                return None;
+            }
+
+            let key_len = rest
+                .find(|c: char| c.is_whitespace() || matches!(c, '=' | ',' | '{' | '}'))
+                .unwrap_or(rest.len());
+            let key = &rest[..key_len];
+            rest = &rest[key_len..];
+
+            // Words without a value, like the language, are skipped
+            let Some(after_equals) = rest.strip_prefix('=') else {
+                continue;
+            };
+
+            let (value, after_value) = match after_equals.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    let quoted = &after_equals[1..];
+                    match quoted.find(quote) {
+                        Some(end) => (&quoted[..end], &quoted[end + 1..]),
+                        None => (quoted, ""),
+                    }
+                }
+                _ => {
+                    let end = after_equals
+                        .find(|c: char| c.is_whitespace() || matches!(c, ',' | '}'))
+                        .unwrap_or(after_equals.len());
+                    after_equals.split_at(end)
+                }
+            };
+            rest = after_value;
+
+            if !key.is_empty() {
+                return Some((key, value));
+            }
+        }
+    })
+}
+
+/// Parses a list of line numbers and ranges like `1 3-5`, skipping invalid entries.
+fn line_ranges(value: &str) -> impl Iterator<Item = RangeInclusive<usize>> + '_ {
+    value
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter_map(|entry| {
+            let (start, end) = entry.split_once('-').unwrap_or((entry, entry));
+            let start: usize = start.parse().ok()?;
+            let end: usize = end.parse().ok()?;
+            (start <= end).then_some(start..=end)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attribute_pairs() {
+        let pairs: Vec<_> =
+            attribute_pairs(r#"py title="My app.py" linenums='1 2' anchor=foo,x=1 =bare"#)
+                .collect();
+        assert_eq!(
+            pairs,
+            [
+                ("title", "My app.py"),
+                ("linenums", "1 2"),
+                ("anchor", "foo"),
+                ("x", "1"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_braced_attributes() {
+        let attributes = Attributes::parse(r#"{ .python title="a.py" hl_lines="2" }"#);
+        assert_eq!(attributes.title, Some("a.py"));
+        assert_eq!(attributes.hl_lines, [2..=2]);
+    }
+
+    #[test]
+    fn test_invalid_values_are_skipped() {
+        let attributes =
+            Attributes::parse(r#"rust hl_lines="x 3-1 4-6" linenums="one" title="unclosed"#);
+        assert_eq!(attributes.hl_lines, [4..=6]);
+        assert_eq!(attributes.linenums, None);
+        assert_eq!(attributes.title, Some("unclosed"));
+    }
+
+    #[test]
+    fn test_no_attributes() {
+        assert_eq!(
+            Attributes::parse("rust,ignore edition2021"),
+            Attributes::default()
+        );
+    }
+}
@@ -0,0 +1,329 @@
+//! Wrapping bare code back into fences, the reverse of stripping.
+
+use crate::scan::codeblocks;
+
+/// The signals of a language: prefixes of code lines in it, and text found
+/// anywhere in them.
+struct Signals {
+    language: &'static str,
+    starts: &'static [&'static str],
+    contains: &'static [&'static str],
+}
+
+/// The languages [`fence_bare_code`] recognizes, by the lines they look like.
+const LANGUAGES: &[Signals] = &[
+    Signals {
+        language: "rust",
+        starts: &[
+            "fn ",
+            "pub fn ",
+            "impl ",
+            "use std::",
+            "let mut ",
+            "struct ",
+            "#[derive",
+        ],
+        contains: &["println!(", ") -> ", "&mut "],
+    },
+    Signals {
+        language: "python",
+        starts: &["def ", "elif ", "from ", "import ", "class "],
+        contains: &["print(", "self.", "__init__"],
+    },
+    Signals {
+        language: "javascript",
+        starts: &["function ", "const ", "var ", "export ", "require("],
+        contains: &["console.log", "=> ", "===", "document."],
+    },
+    Signals {
+        language: "go",
+        starts: &["package ", "func "],
+        contains: &[":= ", "fmt."],
+    },
+    Signals {
+        language: "c",
+        starts: &["#include", "int main", "#define "],
+        contains: &["printf(", "malloc("],
+    },
+    Signals {
+        language: "java",
+        starts: &["public class ", "public static ", "private "],
+        contains: &["System.out", "new ArrayList"],
+    },
+    Signals {
+        language: "sql",
+        starts: &[
+            "SELECT ",
+            "INSERT INTO ",
+            "UPDATE ",
+            "DELETE FROM ",
+            "CREATE TABLE ",
+        ],
+        contains: &[" FROM ", " WHERE "],
+    },
+    Signals {
+        language: "sh",
+        starts: &[
+            "$ ", "sudo ", "cd ", "npm ", "cargo ", "git ", "pip ", "apt ", "echo ", "export ",
+            "mkdir ", "docker ",
+        ],
+        contains: &[" && ", " | grep"],
+    },
+    Signals {
+        language: "html",
+        starts: &["<!DOCTYPE", "<html", "<div", "<script", "</"],
+        contains: &[],
+    },
+];
+
+/// Returns whether `signals` match `line`, trimmed.
+fn signals_match(signals: &Signals, line: &str) -> bool {
+    signals.starts.iter().any(|start| line.starts_with(start))
+        || signals.contains.iter().any(|text| line.contains(text))
+}
+
+/// Returns whether `line` only holds whitespace.
+fn is_blank(line: &str) -> bool {
+    line.trim().is_empty()
+}
+
+/// Returns whether `line` is indented like an indented code block, by four
+/// columns or a tab.
+fn is_indented(line: &str) -> bool {
+    line.starts_with('\t') || line.starts_with("    ")
+}
+
+/// Returns whether `line` starts a markdown list item, heading, quote or table row.
+fn is_markdown(line: &str) -> bool {
+    let line = line.trim_start();
+    let ordered = line
+        .find(|c: char| !c.is_ascii_digit())
+        .is_some_and(|end| end > 0 && line[end..].starts_with([')', '.']));
+    ordered
+        || line.starts_with(['-', '*', '+', '>', '|']) && !line.starts_with("->")
+        || line.starts_with('#') && line.trim_start_matches('#').starts_with(' ')
+}
+
+/// Returns whether `line` looks like a line of code rather than prose.
+fn looks_like_code(line: &str) -> bool {
+    let line = line.trim();
+    if is_markdown(line) && !line.starts_with("*/") {
+        return false;
+    }
+    // Sentences of a few words are prose, whatever they start with
+    if line.ends_with(['.', '!', '?']) && line.split_whitespace().count() > 2 {
+        return false;
+    }
+    line.ends_with([';', '{', '}', '(', ')', '[', ']'])
+        || line.starts_with(['}', ']', ')'])
+        || ["//", "/*", "return", "if (", "for (", "while ("]
+            .iter()
+            .any(|start| line.starts_with(start))
+        || line.ends_with(':')
+            && [
+                "def ", "class ", "if ", "for ", "while ", "else", "elif ", "try", "except",
+                "with ",
+            ]
+            .iter()
+            .any(|start| line.starts_with(start))
+        || LANGUAGES.iter().any(|signals| signals_match(signals, line))
+}
+
+/// Guesses the language of `code`: the one whose signals match the most
+/// lines, unless another one matches as many.
+fn guess_language(code: &[&str]) -> Option<&'static str> {
+    let mut best = None;
+    let mut best_count = 0;
+    let mut tied = false;
+    for signals in LANGUAGES {
+        let count = code
+            .iter()
+            .filter(|line| signals_match(signals, line.trim()))
+            .count();
+        if count > best_count {
+            (best, best_count, tied) = (Some(signals.language), count, false);
+        } else if count == best_count && count > 0 {
+            tied = true;
+        }
+    }
+    best.filter(|_| !tied)
+}
+
+/// Returns `line` without the indentation of an indented code block.
+fn unindent(line: &str) -> &str {
+    line.strip_prefix('\t')
+        .or_else(|| line.strip_prefix("    "))
+        .unwrap_or(line.trim_start_matches([' ', '\t']))
+}
+
+/// Writes `code` to `output` in a fence of backticks, with `language` in its
+/// info string if there is one.
+fn push_fenced(output: &mut String, code: &[&str], language: Option<&str>) {
+    let newline = if code[0].ends_with("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let longest = code
+        .iter()
+        .flat_map(|line| line.split(|c| c != '`'))
+        .map(str::len)
+        .max()
+        .unwrap_or(0);
+    let fence = "`".repeat(longest.max(2) + 1);
+
+    output.push_str(&fence);
+    output.push_str(language.unwrap_or(""));
+    output.push_str(newline);
+    for line in code {
+        output.push_str(line);
+    }
+    if !output.ends_with('\n') {
+        output.push_str(newline);
+    }
+    output.push_str(&fence);
+    if code.last().is_some_and(|line| line.ends_with('\n')) {
+        output.push_str(newline);
+    }
+}
+
+/// Wraps the bare code of `prose`, text outside fenced blocks, in fences.
+fn fence_prose(prose: &str, output: &mut String) {
+    let lines: Vec<&str> = prose.split_inclusive('\n').collect();
+    // Whether the previous paragraph was a list, whose indented paragraphs
+    // continue its items instead of being code
+    let mut in_list = false;
+    let mut i = 0;
+
+    while i < lines.len() {
+        if is_blank(lines[i]) {
+            output.push_str(lines[i]);
+            i += 1;
+            continue;
+        }
+        let mut end = i;
+        while end < lines.len() && !is_blank(lines[end]) {
+            end += 1;
+        }
+        let paragraph = &lines[i..end];
+
+        if paragraph.iter().all(|line| is_indented(line)) && !in_list {
+            // Blank lines don't end indented code followed by more of it
+            let mut next = end;
+            while next < lines.len() && (is_blank(lines[next]) || is_indented(lines[next])) {
+                next += 1;
+                if !is_blank(lines[next - 1]) {
+                    end = next;
+                }
+            }
+            let code: Vec<&str> = lines[i..end].iter().map(|line| unindent(line)).collect();
+            push_fenced(output, &code, guess_language(&code));
+        } else if paragraph.len() > 1 && paragraph.iter().all(|line| looks_like_code(line)) {
+            push_fenced(output, paragraph, guess_language(paragraph));
+            in_list = false;
+        } else {
+            paragraph.iter().for_each(|line| output.push_str(line));
+            in_list = is_markdown(paragraph[0]) || in_list && is_indented(paragraph[0]);
+        }
+        i = end;
+    }
+}
+
+/// Wraps un-fenced code in fenced code blocks, guessing their language: the
+/// reverse of [`strip_codeblocks`](crate::strip_codeblocks), for normalizing
+/// scraped text before rendering it as markdown again.
+///
+/// Paragraphs outside fenced blocks are taken for code when:
+///
+/// - all their lines are indented by four spaces or a tab, like indented
+///   code blocks, and they don't follow a list, whose items they would
+///   continue. The indentation is removed, and blank lines between indented
+///   lines stay in the block.
+/// - they have several lines, and all of them look like code: they end with
+///   a semicolon or a bracket, open a Python block, are comments, or start
+///   or contain what code in one of the recognized languages does. Sentences
+///   and markdown lists, headings, quotes and tables aren't code.
+///
+/// The language of a block is the one of Rust, Python, JavaScript, Go, C,
+/// Java, SQL, shell and HTML matching the most lines, and is left out if the
+/// code is ambiguous. Fences are long enough not to be closed by backticks
+/// in the code, and end lines like the code does. Existing fenced blocks and
+/// prose are copied as they are.
+///
+/// These are heuristics: short snippets can stay bare, and code-like prose,
+/// such as a list of shell commands with a line each, can be fenced.
+///
+/// # Arguments
+///
+/// * `text` - The text containing bare code
+///
+/// # Returns
+///
+/// The text with its bare code fenced.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::fence_bare_code;
+///
+/// let scraped = "Add this:\n\nfn main() {\n    println!(\"hi\");\n}\n\nThen run:\n\n    cargo run\n";
+///
+/// assert_eq!(
+///     fence_bare_code(scraped),
+///     "Add this:\n\n```rust\nfn main() {\n    println!(\"hi\");\n}\n```\n\nThen run:\n\n```sh\ncargo run\n```\n"
+/// );
+/// ```
+pub fn fence_bare_code(text: &str) -> String {
+    let mut output = String::with_capacity(text.len() + 32);
+    let mut last = 0;
+    for block in codeblocks(text) {
+        fence_prose(&text[last..block.span.start], &mut output);
+        output.push_str(&text[block.span.clone()]);
+        last = block.span.end;
+    }
+    fence_prose(&text[last..], &mut output);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_looks_like_code() {
+        assert!(looks_like_code("let x = 1;"));
+        assert!(looks_like_code("    if x:"));
+        assert!(looks_like_code("SELECT * FROM users"));
+        assert!(!looks_like_code("Call the function (see below)."));
+        assert!(!looks_like_code("- item;"));
+        assert!(!looks_like_code("Hello world"));
+    }
+
+    #[test]
+    fn test_guess_language() {
+        assert_eq!(
+            guess_language(&["def f(self):", "    return self.x"]),
+            Some("python")
+        );
+        assert_eq!(
+            guess_language(&["package main", "func main() {", "}"]),
+            Some("go")
+        );
+        assert_eq!(guess_language(&["x();", "y();"]), None);
+    }
+
+    #[test]
+    fn test_lists_and_fences_are_left_alone() {
+        let text = "1. Install:\n\n    cargo install x\n\n```\nlet a = 1;\nlet b = 2;\n```\n";
+        assert_eq!(fence_bare_code(text), text);
+    }
+
+    #[test]
+    fn test_fence_longer_than_backticks() {
+        let text = "\tprint(\"```\")\r\n\r\n\tprint(1)\r\nDone";
+        assert_eq!(
+            fence_bare_code(text),
+            "````python\r\nprint(\"```\")\r\n\r\nprint(1)\r\n````\r\nDone"
+        );
+    }
+}
@@ -0,0 +1,350 @@
+//! The [`CodeBlock`] type describing a single fenced code block.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::Range;
+
+use crate::attributes::attribute_pairs;
+use crate::title::preceding_title;
+use crate::{Attributes, fence_content};
+
+/// A fenced code block found in a markdown document.
+///
+/// All string fields borrow from the document the block was found in, so
+/// inspecting blocks never copies their content. Blocks are ordered by their
+/// position in the document, so sorting blocks selected in different ways
+/// puts them back in document order.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct CodeBlock<'a> {
+    /// The info string following the opening fence, with surrounding
+    /// spaces and tabs removed (e.g. `rust ignore` for ```` ```rust ignore ````).
+    ///
+    /// A fence followed only by spaces and tabs, like ```` ```   ````, has an
+    /// empty info string and no language, as in CommonMark, with every engine.
+    pub info: &'a str,
+    /// The info string exactly as it follows the opening fence, surrounding
+    /// whitespace included and line ending excluded, for downstream parsing.
+    ///
+    /// Backtick fences can't have backticks in their info string, but tilde
+    /// fences can, so with the [parser](crate::experimental::Engine::Parser)
+    /// engine this may contain backticks.
+    pub info_raw: &'a str,
+    /// The opening fence, such as ```` ``` ```` or `~~~~`, or `""` for blocks
+    /// without one, such as HTML `<pre>` blocks and blocks created with
+    /// [`new`](Self::new).
+    pub fence: &'a str,
+    /// The language identifier: the first word of the info string, if any.
+    ///
+    /// Words are separated by whitespace or commas, so both ```` ```rust ignore ````
+    /// and ```` ```rust,ignore ```` have the language `rust`.
+    pub language: Option<&'a str>,
+    /// The content between the fences, exactly as it appears in the document.
+    pub content: &'a str,
+    /// Byte range of the whole block, fences included, in the document.
+    pub span: Range<usize>,
+    /// Byte range of [`content`](Self::content) in the document.
+    pub content_span: Range<usize>,
+    /// The MkDocs-style attributes of the info string, such as `title` and `hl_lines`.
+    pub attributes: Attributes<'a>,
+    /// The position of the block among the blocks of the document, starting at 0.
+    pub index: usize,
+}
+
+impl<'a> CodeBlock<'a> {
+    /// Creates a block with the info string `info` and the content `content`,
+    /// e.g. to build a [`Document`](crate::Document) from generated code.
+    ///
+    /// The block isn't part of any document, so its spans are empty and its
+    /// index is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strip_codeblocks::CodeBlock;
+    ///
+    /// let block = CodeBlock::new("rust ignore", "fn main() {}\n");
+    /// assert_eq!(block.language, Some("rust"));
+    /// assert_eq!(block.to_string(), "```rust ignore\nfn main() {}\n```");
+    /// ```
+    pub fn new(info: &'a str, content: &'a str) -> Self {
+        let info_raw = info;
+        let info = trim_info(info);
+        Self {
+            info,
+            info_raw,
+            fence: "",
+            language: info_words(info).next(),
+            content,
+            span: 0..0,
+            content_span: 0..0,
+            attributes: Attributes::parse(info),
+            index: 0,
+        }
+    }
+
+    /// Returns the words of the info string, split on whitespace and commas.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strip_codeblocks::extract_codeblocks;
+    ///
+    /// let blocks = extract_codeblocks("```rust,no_run edition2021\nloop {}\n```");
+    /// let words: Vec<_> = blocks[0].info_words().collect();
+    /// assert_eq!(words, ["rust", "no_run", "edition2021"]);
+    /// ```
+    pub fn info_words(&self) -> impl Iterator<Item = &'a str> + Clone + use<'a> {
+        info_words(self.info)
+    }
+
+    /// Returns the value of the `key="value"` attribute `key` of the info
+    /// string, without its quotes, or `None` if it has no such attribute.
+    ///
+    /// See [`Attributes`] for the syntax; the attributes tools commonly act
+    /// on are also parsed into [`attributes`](Self::attributes).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strip_codeblocks::extract_codeblocks;
+    ///
+    /// let blocks = extract_codeblocks("```yaml anchor_linenums=\"true\"\na: 1\n```");
+    /// assert_eq!(blocks[0].attribute("anchor_linenums"), Some("true"));
+    /// assert_eq!(blocks[0].attribute("title"), None);
+    /// ```
+    pub fn attribute(&self, key: &str) -> Option<&'a str> {
+        attribute_pairs(self.info)
+            .filter(|(name, _)| *name == key)
+            .map(|(_, value)| value)
+            .last()
+    }
+
+    /// Returns the title of the block in `text`, the document it was found in,
+    /// to label extracted snippets.
+    ///
+    /// This is the [`title`](Attributes::title) attribute of the info string
+    /// if there is one. Otherwise it is guessed from the document: a caption
+    /// in bold on the line right before the block (blank lines aside), such as
+    /// `**Example:**`, or else the nearest heading before the block. Lines of
+    /// other code blocks are never taken for headings.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strip_codeblocks::extract_codeblocks;
+    ///
+    /// let text = "## Install\n```sh\nmake\n```\n**Run:**\n```sh\n# as root\n./app\n```\nAnd:\n```sh\nls\n```";
+    /// let blocks = extract_codeblocks(text);
+    ///
+    /// assert_eq!(blocks[0].title(text), Some("Install"));
+    /// assert_eq!(blocks[1].title(text), Some("Run"));
+    /// assert_eq!(blocks[2].title(text), Some("Install"));
+    /// ```
+    pub fn title(&self, text: &'a str) -> Option<&'a str> {
+        self.attributes
+            .title
+            .or_else(|| preceding_title(text, self.span.start))
+    }
+
+    /// Returns the line and column range of the whole block in `text`, the
+    /// document it was found in.
+    ///
+    /// The range starts at the first character of the opening fence and ends
+    /// just after the last character of the closing fence. Lines and columns
+    /// are 1-based, and columns count characters rather than bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strip_codeblocks::{Position, extract_codeblocks};
+    ///
+    /// let text = "Intro\n\nSee: ```sh\nls\n```\n";
+    /// let blocks = extract_codeblocks(text);
+    ///
+    /// let range = blocks[0].line_range(text);
+    /// assert_eq!(range.start, Position::new(3, 6));
+    /// assert_eq!(range.end, Position::new(5, 4));
+    /// ```
+    pub fn line_range(&self, text: &str) -> Range<Position> {
+        position(text, self.span.start)..position(text, self.span.end)
+    }
+
+    /// Returns the range of the whole block in `text`, the document it was
+    /// found in, counted in characters (Unicode scalar values) rather than bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strip_codeblocks::extract_codeblocks;
+    ///
+    /// let text = "Voilà : ```\nok\n```";
+    /// let blocks = extract_codeblocks(text);
+    ///
+    /// assert_eq!(blocks[0].span, 9..19);
+    /// assert_eq!(blocks[0].char_range(text), 8..18);
+    /// ```
+    pub fn char_range(&self, text: &str) -> Range<usize> {
+        let start = text[..self.span.start].chars().count();
+        start..start + text[self.span.clone()].chars().count()
+    }
+}
+
+/// Orders blocks by their position in the document: by start, then by end.
+///
+/// The other fields only break ties, so that blocks compare equal exactly when
+/// they are equal.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::extract_codeblocks;
+///
+/// let blocks = extract_codeblocks("```sh\nls\n```\n```rust\nfn a() {}\n```");
+/// let mut longest_first = blocks.clone();
+/// longest_first.sort_by_key(|block| std::cmp::Reverse(block.content.len()));
+///
+/// longest_first.sort();
+/// assert_eq!(longest_first, blocks);
+/// ```
+impl Ord for CodeBlock<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let key = |block: &Self| {
+            (
+                block.span.start,
+                block.span.end,
+                block.index,
+                block.content_span.start,
+                block.content_span.end,
+                block.info_raw,
+                block.info,
+                block.language,
+                block.content,
+            )
+        };
+        fn lines<'b>(attributes: &'b Attributes<'_>) -> impl Iterator<Item = (usize, usize)> + 'b {
+            let ranges = attributes.hl_lines.iter();
+            ranges.map(|range| (*range.start(), *range.end()))
+        }
+
+        key(self)
+            .cmp(&key(other))
+            .then_with(|| self.attributes.title.cmp(&other.attributes.title))
+            .then_with(|| self.attributes.linenums.cmp(&other.attributes.linenums))
+            .then_with(|| lines(&self.attributes).cmp(lines(&other.attributes)))
+    }
+}
+
+impl PartialOrd for CodeBlock<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// A line and column in a document, both 1-based.
+///
+/// Columns count characters (Unicode scalar values), not bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
+pub struct Position {
+    /// The line number, starting at 1.
+    pub line: usize,
+    /// The column number, starting at 1.
+    pub column: usize,
+}
+
+impl Position {
+    /// Creates the position of `column` on `line`.
+    pub fn new(line: usize, column: usize) -> Self {
+        Self { line, column }
+    }
+}
+
+/// Returns the position of the byte at `offset` in `text`.
+fn position(text: &str, offset: usize) -> Position {
+    let before = &text[..offset];
+    let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+    Position {
+        line: before.matches('\n').count() + 1,
+        column: before[line_start..].chars().count() + 1,
+    }
+}
+
+/// Displays the block as a fenced code block with its info string and content.
+///
+/// The fence is chosen like [`fence_content`] does, so the output is the block
+/// as it appears in the document when that used a plain fence of the same
+/// size, and a normalized equivalent otherwise.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::extract_codeblocks;
+///
+/// let blocks = extract_codeblocks("Text ```sh\nls\n``` more");
+/// assert_eq!(blocks[0].to_string(), "```sh\nls\n```");
+/// ```
+impl fmt::Display for CodeBlock<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&fence_content(self.content, self.info))
+    }
+}
+
+/// Returns the info string `raw`, what follows a fence on its line, without
+/// the carriage return of its line ending and surrounding spaces and tabs, as
+/// CommonMark trims it.
+///
+/// Other whitespace, such as no-break spaces, is part of the info string, so
+/// an info string of spaces and tabs is empty and its block has no language,
+/// whatever engine found it.
+pub(crate) fn trim_info(raw: &str) -> &str {
+    raw.strip_suffix('\r')
+        .unwrap_or(raw)
+        .trim_matches([' ', '\t'])
+}
+
+/// Splits an info string into words on whitespace and commas.
+pub(crate) fn info_words(info: &str) -> impl Iterator<Item = &str> + Clone {
+    info.split([' ', '\t', ',']).filter(|word| !word.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extract_codeblocks;
+
+    #[test]
+    fn test_whitespace_info_strings() {
+        use crate::experimental::{Engine, extract_codeblocks_with};
+
+        let cases = [
+            ("```   \nx\n```", "", "   "),
+            ("``` \t \r\nx\r\n```", "", " \t "),
+            ("```\t rust \t\nx\n```", "rust", "\t rust \t"),
+            ("```\u{a0}\nx\n```", "\u{a0}", "\u{a0}"),
+        ];
+        for (text, info, info_raw) in cases {
+            for engine in [Engine::Regex, Engine::Parser] {
+                let block = &extract_codeblocks_with(text, engine)[0];
+                assert_eq!((block.info, block.info_raw), (info, info_raw), "{text:?}");
+                assert_eq!(block.language, info_words(info).next());
+            }
+        }
+        assert_eq!(CodeBlock::new(" \t", "x\n").language, None);
+        assert_eq!(trim_info("  \r"), "");
+    }
+
+    #[test]
+    fn test_positions_count_characters() {
+        let text = "é\r\n```\nü\n```";
+        let block = &extract_codeblocks(text)[0];
+
+        assert_eq!(
+            block.line_range(text),
+            Position::new(2, 1)..Position::new(4, 4)
+        );
+        assert_eq!(block.char_range(text), 3..12);
+        assert_eq!(position(text, 0), Position::new(1, 1));
+        assert_eq!(position(text, 2), Position::new(1, 2));
+    }
+}
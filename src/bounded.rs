@@ -0,0 +1,272 @@
+//! Extracting code blocks into fixed-capacity collections, without allocating
+//! storage for them.
+
+use std::error::Error;
+use std::fmt;
+use std::ops::Index;
+
+use crate::CodeBlock;
+use crate::scan::codeblocks;
+
+/// The error returned by [`extract_codeblocks_bounded`] and
+/// [`extract_codeblocks_bounded_by`] when the blocks don't fit in the
+/// collection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct CapacityExceeded {
+    /// How many code blocks the text has.
+    pub required: usize,
+    /// How many blocks the collection took before it was full.
+    pub capacity: usize,
+}
+
+impl fmt::Display for CapacityExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "the text has {} code blocks, but the collection holds {}",
+            self.required, self.capacity
+        )
+    }
+}
+
+impl Error for CapacityExceeded {}
+
+/// A collection of at most `N` code blocks, stored inline, for
+/// [`extract_codeblocks_bounded`].
+///
+/// Blocks are indexed and iterated over in the order they were pushed.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BlockArray<'a, const N: usize> {
+    blocks: [Option<CodeBlock<'a>>; N],
+    len: usize,
+}
+
+impl<'a, const N: usize> BlockArray<'a, N> {
+    /// Creates an empty collection.
+    pub const fn new() -> Self {
+        Self {
+            blocks: [const { None }; N],
+            len: 0,
+        }
+    }
+
+    /// Appends `block`, or returns it when the collection is full, like
+    /// `heapless::Vec::push`.
+    #[expect(
+        clippy::result_large_err,
+        reason = "the block is given back, like heapless does"
+    )]
+    pub fn push(&mut self, block: CodeBlock<'a>) -> Result<(), CodeBlock<'a>> {
+        match self.blocks.get_mut(self.len) {
+            Some(slot) => {
+                *slot = Some(block);
+                self.len += 1;
+                Ok(())
+            }
+            None => Err(block),
+        }
+    }
+
+    /// Returns the block at `index`, if there is one.
+    pub fn get(&self, index: usize) -> Option<&CodeBlock<'a>> {
+        self.blocks[..self.len].get(index)?.as_ref()
+    }
+
+    /// Returns an iterator over the blocks, in the order they were pushed.
+    pub fn iter(&self) -> impl Iterator<Item = &CodeBlock<'a>> {
+        self.blocks[..self.len].iter().flatten()
+    }
+
+    /// Returns the number of blocks.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether there are no blocks.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns how many blocks the collection holds, `N`.
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Removes every block.
+    pub fn clear(&mut self) {
+        self.blocks[..self.len].fill(None);
+        self.len = 0;
+    }
+}
+
+impl<const N: usize> Default for BlockArray<'_, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, const N: usize> Index<usize> for BlockArray<'a, N> {
+    type Output = CodeBlock<'a>;
+
+    fn index(&self, index: usize) -> &CodeBlock<'a> {
+        self.get(index).unwrap_or_else(|| {
+            panic!(
+                "index out of bounds: the len is {} but the index is {index}",
+                self.len
+            )
+        })
+    }
+}
+
+impl<'s, 'a, const N: usize> IntoIterator for &'s BlockArray<'a, N> {
+    type Item = &'s CodeBlock<'a>;
+    type IntoIter = std::iter::Flatten<std::slice::Iter<'s, Option<CodeBlock<'a>>>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.blocks[..self.len].iter().flatten()
+    }
+}
+
+/// Extracts the fenced code blocks of `text` into `blocks`, a collection of
+/// at most `N` blocks, for firmware post-processing model output with little
+/// heap.
+///
+/// This is [`extract_codeblocks_bounded_by`] pushing to `blocks`. Blocks are
+/// appended after those `blocks` already holds.
+///
+/// # Arguments
+///
+/// * `text` - The markdown text to extract code blocks from
+/// * `blocks` - Where the code blocks are appended
+///
+/// # Returns
+///
+/// How many blocks were appended, or when they don't all fit, how many the
+/// text has. `blocks` is full in that case, holding the first blocks.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::{BlockArray, extract_codeblocks_bounded};
+///
+/// let markdown = "```sh\nls\n```\n```py\nx = 1\n```";
+///
+/// let mut blocks = BlockArray::<4>::new();
+/// assert_eq!(extract_codeblocks_bounded(markdown, &mut blocks), Ok(2));
+/// assert_eq!(blocks[1].language, Some("py"));
+///
+/// let mut blocks = BlockArray::<1>::new();
+/// let error = extract_codeblocks_bounded(markdown, &mut blocks).unwrap_err();
+/// assert_eq!((error.required, error.capacity), (2, 1));
+/// assert_eq!(blocks[0].content, "ls\n");
+/// ```
+pub fn extract_codeblocks_bounded<'a, const N: usize>(
+    text: &'a str,
+    blocks: &mut BlockArray<'a, N>,
+) -> Result<usize, CapacityExceeded> {
+    #[expect(
+        clippy::result_large_err,
+        reason = "the block is given back, like heapless does"
+    )]
+    extract_codeblocks_bounded_by(text, |block| blocks.push(block))
+}
+
+/// Extracts the fenced code blocks of `text` with `push`, which appends a
+/// block to a fixed-capacity collection, or returns it when the collection
+/// is full.
+///
+/// `push` has the signature of the `push` method of fixed-capacity vectors,
+/// such as `heapless::Vec`, so blocks can be extracted into one with
+/// `|block| vec.push(block)`. Blocks are found like
+/// [`extract_codeblocks`](crate::extract_codeblocks) does, and borrow their
+/// strings from `text`, so that no storage is allocated for the blocks. This
+/// is not free of allocation: a block with `hl_lines` in its info string owns
+/// the parsed lines in [`Attributes::hl_lines`](crate::Attributes::hl_lines),
+/// and the regex engine sets up its search state on the first call on a
+/// thread. Once a block is refused, later blocks are only counted.
+///
+/// # Arguments
+///
+/// * `text` - The markdown text to extract code blocks from
+/// * `push` - Appends a block, returning it back when there is no room
+///
+/// # Returns
+///
+/// How many blocks were pushed, or when one was refused, how many the text
+/// has and how many were pushed.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::{BlockArray, extract_codeblocks_bounded_by};
+///
+/// let markdown = "```sh\nls\n```\n```py\nx = 1\n```\n```sh\npwd\n```";
+///
+/// // Keeping the shell blocks in a collection of two
+/// let mut shell = BlockArray::<2>::new();
+/// let pushed = extract_codeblocks_bounded_by(markdown, |block| match block.language {
+///     Some("sh") => shell.push(block),
+///     _ => Ok(()),
+/// });
+///
+/// assert_eq!(pushed, Ok(3));
+/// assert_eq!(shell[1].content, "pwd\n");
+/// ```
+pub fn extract_codeblocks_bounded_by<'a>(
+    text: &'a str,
+    mut push: impl FnMut(CodeBlock<'a>) -> Result<(), CodeBlock<'a>>,
+) -> Result<usize, CapacityExceeded> {
+    let mut blocks = codeblocks(text);
+    let mut pushed = 0;
+    for block in blocks.by_ref() {
+        if push(block).is_err() {
+            return Err(CapacityExceeded {
+                required: pushed + 1 + blocks.count(),
+                capacity: pushed,
+            });
+        }
+        pushed += 1;
+    }
+    Ok(pushed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extract_codeblocks;
+
+    #[test]
+    fn test_matches_extract() {
+        let text = "```rust\nfn a() {}\n```\ntext\n~~~\nnot a fence\n```\n```\nb\n```";
+        let mut blocks = BlockArray::<8>::new();
+        assert_eq!(extract_codeblocks_bounded(text, &mut blocks), Ok(2));
+        assert!(blocks.iter().eq(extract_codeblocks(text).iter()));
+        assert_eq!(blocks.get(2), None);
+
+        blocks.clear();
+        assert!(blocks.is_empty());
+        assert_eq!(extract_codeblocks_bounded("no code", &mut blocks), Ok(0));
+    }
+
+    #[test]
+    fn test_zero_capacity() {
+        let mut blocks = BlockArray::<0>::default();
+        assert_eq!(
+            extract_codeblocks_bounded("```\na\n```", &mut blocks),
+            Err(CapacityExceeded {
+                required: 1,
+                capacity: 0
+            })
+        );
+        assert_eq!(blocks.capacity(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "the len is 1 but the index is 1")]
+    fn test_index_out_of_bounds() {
+        let mut blocks = BlockArray::<2>::new();
+        extract_codeblocks_bounded("```\na\n```", &mut blocks).unwrap();
+        let _ = &blocks[1];
+    }
+}
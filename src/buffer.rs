@@ -0,0 +1,202 @@
+//! Stripping into caller-provided buffers, without allocating.
+
+use std::error::Error;
+use std::fmt;
+
+use crate::StripOptions;
+use crate::strip::strip_to;
+
+/// The error returned by [`strip_codeblocks_into`] when the output doesn't
+/// fit in the buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct BufferTooSmall {
+    /// How many bytes the whole output takes.
+    pub required: usize,
+    /// How many bytes the buffer has.
+    pub capacity: usize,
+}
+
+impl fmt::Display for BufferTooSmall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "the output takes {} bytes, but the buffer has {}",
+            self.required, self.capacity
+        )
+    }
+}
+
+impl Error for BufferTooSmall {}
+
+/// Copies whole pieces of output into a buffer while they fit, and counts
+/// the length of the whole output.
+struct SliceWriter<'b> {
+    buffer: &'b mut [u8],
+    required: usize,
+}
+
+impl fmt::Write for SliceWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let start = self.required;
+        self.required += s.len();
+        // Once a piece didn't fit, no later one is written
+        if self.required <= self.buffer.len() {
+            self.buffer[start..self.required].copy_from_slice(s.as_bytes());
+        }
+        Ok(())
+    }
+}
+
+/// Strips fenced code blocks like
+/// [`strip_codeblocks_with`](crate::strip_codeblocks_with), writing the
+/// result into `buffer`.
+///
+/// Stripping with the default options doesn't allocate, once the regex
+/// engine has set up its search state on the first call on a thread: the
+/// output is copied from the input straight into the buffer, for embedding
+/// where per-request heap allocation is forbidden. Options that transform code, like
+/// [`strip_prompts`](StripOptions::strip_prompts) or
+/// [`Action::FirstLine`](crate::Action::FirstLine), allocate for the blocks
+/// they change. The bytes written are valid UTF-8, so `&buffer[..len]` can be
+/// turned into a `&str` with [`std::str::from_utf8`].
+///
+/// # Arguments
+///
+/// * `text` - The markdown text containing code blocks to strip
+/// * `options` - Which blocks are stripped and how
+/// * `buffer` - Where the stripped text is written
+///
+/// # Returns
+///
+/// How many bytes of `buffer` the stripped text takes, or when it doesn't
+/// fit, the capacity it needs. The contents of the buffer are unspecified in
+/// that case.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::{StripOptions, strip_codeblocks_into};
+///
+/// let mut buffer = [0; 16];
+/// let options = StripOptions::new();
+///
+/// let len = strip_codeblocks_into("Run:\n```sh\nls\n```", &options, &mut buffer).unwrap();
+/// assert_eq!(&buffer[..len], b"Run:\nls\n");
+///
+/// let error = strip_codeblocks_into("```\nmore than sixteen bytes\n```", &options, &mut buffer);
+/// assert_eq!(error.unwrap_err().required, 24);
+/// ```
+pub fn strip_codeblocks_into(
+    text: &str,
+    options: &StripOptions,
+    buffer: &mut [u8],
+) -> Result<usize, BufferTooSmall> {
+    let mut writer = SliceWriter {
+        buffer,
+        required: 0,
+    };
+    strip_to(text, options, None, &mut writer).expect("the writer never fails");
+
+    let capacity = writer.buffer.len();
+    if writer.required > capacity {
+        return Err(BufferTooSmall {
+            required: writer.required,
+            capacity,
+        });
+    }
+    Ok(writer.required)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strip_codeblocks_with;
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+
+    /// The system allocator, counting the allocations of each thread.
+    struct Counting;
+
+    thread_local! {
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    // SAFETY: every call is forwarded to the system allocator.
+    unsafe impl GlobalAlloc for Counting {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+            // SAFETY: the caller upholds the contract of `alloc`.
+            unsafe { System.alloc(layout) }
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            // SAFETY: the caller upholds the contract of `dealloc`.
+            unsafe { System.dealloc(ptr, layout) }
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: Counting = Counting;
+
+    /// Returns how many allocations `f` made on this thread.
+    fn allocations(f: impl FnOnce()) -> usize {
+        let before = ALLOCATIONS.with(Cell::get);
+        f();
+        ALLOCATIONS.with(Cell::get) - before
+    }
+
+    #[test]
+    fn test_required_capacity_is_exact() {
+        let options = StripOptions::new();
+        let input = "Intro é\n```rust\nfn main() {}\n```\nOutro";
+        let expected = strip_codeblocks_with(input, &options);
+
+        let mut buffer = vec![0; expected.len()];
+        assert_eq!(
+            strip_codeblocks_into(input, &options, &mut buffer),
+            Ok(expected.len())
+        );
+        assert_eq!(std::str::from_utf8(&buffer), Ok(expected.as_str()));
+
+        let error = strip_codeblocks_into(input, &options, &mut buffer[..10]).unwrap_err();
+        assert_eq!(
+            error,
+            BufferTooSmall {
+                required: expected.len(),
+                capacity: 10
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "the output takes 28 bytes, but the buffer has 10"
+        );
+    }
+
+    #[test]
+    fn test_empty_output_fits_empty_buffer() {
+        let options = StripOptions::new().action(crate::Action::Remove);
+        assert_eq!(
+            strip_codeblocks_into("```\nx\n```", &options, &mut []),
+            Ok(0)
+        );
+    }
+
+    #[test]
+    fn test_default_options_do_not_allocate() {
+        let options = StripOptions::new();
+        let mut buffer = [0; 256];
+        for input in [
+            "Run:\n```sh\nls\n```\nDone.",
+            "| a | ```b``` |\n|---|---|\n| ``` | c |\n```\nd\n```",
+            "```\nunclosed",
+        ] {
+            // The regex engine sets up its search state for this thread on first use
+            strip_codeblocks_into(input, &options, &mut buffer).unwrap();
+            let count = allocations(|| {
+                strip_codeblocks_into(input, &options, &mut buffer).unwrap();
+            });
+            assert_eq!(count, 0, "{input:?}");
+        }
+    }
+}
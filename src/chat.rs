@@ -0,0 +1,181 @@
+//! Stripping chat transcripts message by message, by role.
+
+use std::collections::BTreeMap;
+
+use crate::{StripOptions, strip_codeblocks_with};
+
+/// Who a chat message is from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
+pub enum Role {
+    /// Instructions given to the model.
+    System,
+    /// A message from the user.
+    User,
+    /// A reply of the model.
+    Assistant,
+    /// The result of a tool call.
+    Tool,
+}
+
+/// How the messages of each [`Role`] are stripped by [`strip_chat_with`].
+///
+/// Assistant messages are stripped with the default options, and messages
+/// of other roles are left untouched, unless set otherwise.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::{Action, ChatPolicy, Role, StripOptions, strip_chat_with};
+///
+/// let policy = ChatPolicy::new().role(
+///     Role::Tool,
+///     StripOptions::new().action(Action::Placeholder("[output]".into())),
+/// );
+///
+/// let messages = [(Role::Tool, "```\n200 OK\n```"), (Role::Assistant, "```sh\nls\n```")];
+/// assert_eq!(
+///     strip_chat_with(&messages, &policy),
+///     [(Role::Tool, "[output]".to_string()), (Role::Assistant, "ls\n".to_string())]
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ChatPolicy {
+    by_role: BTreeMap<Role, StripOptions>,
+}
+
+impl Default for ChatPolicy {
+    fn default() -> Self {
+        Self {
+            by_role: BTreeMap::from([(Role::Assistant, StripOptions::new())]),
+        }
+    }
+}
+
+impl ChatPolicy {
+    /// Creates the policy stripping assistant messages only, with the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Strips the messages of `role` according to `options`.
+    pub fn role(mut self, role: Role, options: StripOptions) -> Self {
+        self.by_role.insert(role, options);
+        self
+    }
+
+    /// Leaves the messages of `role` untouched.
+    pub fn untouched(mut self, role: Role) -> Self {
+        self.by_role.remove(&role);
+        self
+    }
+
+    /// Returns the options the messages of `role` are stripped with, if any.
+    fn options_for(&self, role: Role) -> Option<&StripOptions> {
+        self.by_role.get(&role)
+    }
+}
+
+/// Strips the fenced code blocks of the assistant messages of a chat,
+/// leaving the messages of other roles untouched.
+///
+/// This is [`strip_chat_with`] with the default [`ChatPolicy`].
+///
+/// # Arguments
+///
+/// * `messages` - The messages of the chat, with their roles
+///
+/// # Returns
+///
+/// The messages in the same order, with their roles.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::{Role, strip_chat};
+///
+/// let messages = [
+///     (Role::User, "Why does ```x``` fail?"),
+///     (Role::Assistant, "Try:\n```sh\nmake clean\n```"),
+/// ];
+/// assert_eq!(
+///     strip_chat(&messages),
+///     [
+///         (Role::User, "Why does ```x``` fail?".to_string()),
+///         (Role::Assistant, "Try:\nmake clean\n".to_string()),
+///     ]
+/// );
+/// ```
+pub fn strip_chat(messages: &[(Role, &str)]) -> Vec<(Role, String)> {
+    strip_chat_with(messages, &ChatPolicy::new())
+}
+
+/// Strips the fenced code blocks of the messages of a chat according to the
+/// options `policy` sets for their role.
+///
+/// # Arguments
+///
+/// * `messages` - The messages of the chat, with their roles
+/// * `policy` - How the messages of each role are stripped
+///
+/// # Returns
+///
+/// The messages in the same order, with their roles.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::{ChatPolicy, Role, StripOptions, strip_chat_with};
+///
+/// let policy = ChatPolicy::new().role(Role::User, StripOptions::new().languages(["sh"]));
+///
+/// let messages = [(Role::User, "```sh\nls\n```\n```py\npass\n```")];
+/// assert_eq!(
+///     strip_chat_with(&messages, &policy),
+///     [(Role::User, "ls\n\n```py\npass\n```".to_string())]
+/// );
+/// ```
+pub fn strip_chat_with(messages: &[(Role, &str)], policy: &ChatPolicy) -> Vec<(Role, String)> {
+    messages
+        .iter()
+        .map(|&(role, text)| {
+            let text = match policy.options_for(role) {
+                Some(options) => strip_codeblocks_with(text, options),
+                None => text.to_string(),
+            };
+            (role, text)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_policy() {
+        let messages = [
+            (Role::System, "Answer in ```sh\nblocks\n```"),
+            (Role::Tool, "```\nlog\n```"),
+            (Role::Assistant, "```\nx\n```"),
+        ];
+        assert_eq!(
+            strip_chat(&messages),
+            [
+                (Role::System, "Answer in ```sh\nblocks\n```".to_string()),
+                (Role::Tool, "```\nlog\n```".to_string()),
+                (Role::Assistant, "x\n".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_untouched_role() {
+        let policy = ChatPolicy::new().untouched(Role::Assistant);
+        assert_eq!(
+            strip_chat_with(&[(Role::Assistant, "```\nx\n```")], &policy),
+            [(Role::Assistant, "```\nx\n```".to_string())]
+        );
+        assert!(strip_chat(&[]).is_empty());
+    }
+}
@@ -0,0 +1,314 @@
+//! Splitting documents into size-bounded chunks that keep code blocks whole.
+
+use std::borrow::Cow;
+use std::fmt;
+use std::ops::Range;
+
+use crate::StripOptions;
+use crate::region::Regions;
+use crate::scan::codeblocks_for;
+use crate::strip::{block_end, block_output, prose_output};
+
+/// Options controlling how [`chunk_document`] splits a document.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::{ChunkOptions, StripOptions};
+///
+/// let options = ChunkOptions::new(1000).strip(StripOptions::new());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ChunkOptions {
+    pub(crate) max_len: usize,
+    pub(crate) strip: Option<StripOptions>,
+}
+
+impl ChunkOptions {
+    /// Creates options for chunks of at most `max_len` bytes, left unstripped.
+    pub fn new(max_len: usize) -> Self {
+        Self {
+            max_len,
+            strip: None,
+        }
+    }
+
+    /// Strips the code blocks of each chunk according to `options`.
+    ///
+    /// Chunks are then bounded by the length of their stripped text, and
+    /// blocks are found with the [engine](StripOptions::engine) of `options`.
+    pub fn strip(mut self, options: StripOptions) -> Self {
+        self.strip = Some(options);
+        self
+    }
+}
+
+/// A piece of a document produced by [`chunk_document`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct Chunk<'a> {
+    /// The text of the chunk, stripped if [requested](ChunkOptions::strip).
+    ///
+    /// It is borrowed from the document unless a block in it was changed.
+    pub text: Cow<'a, str>,
+    /// Byte range of the chunk in the original document.
+    pub span: Range<usize>,
+}
+
+/// Displays the text of the chunk.
+impl fmt::Display for Chunk<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+/// Splits markdown text into chunks of bounded size, never splitting a code block.
+///
+/// Chunks are filled greedily in document order. Prose is split at the last
+/// line break that fits, or in the middle of a line when a single line is too
+/// long. Code blocks are never split: a block that doesn't fit in the current
+/// chunk starts a new one, and a block longer than the limit gets a chunk of
+/// its own, exceeding it.
+///
+/// The spans of the chunks are contiguous and cover the whole document, so
+/// without stripping, concatenating the chunks gives back `text`.
+///
+/// # Arguments
+///
+/// * `text` - The markdown text to split
+/// * `options` - The size limit, and how chunks are stripped
+///
+/// # Returns
+///
+/// The chunks of `text`, in document order.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::{ChunkOptions, StripOptions, chunk_document};
+///
+/// let markdown = "Intro line\nSecond line\n```rust\nfn main() {}\n```\nOutro";
+///
+/// let chunks = chunk_document(markdown, ChunkOptions::new(20));
+/// assert_eq!(chunks.len(), 4);
+/// assert_eq!(chunks[0].text, "Intro line\n");
+/// assert_eq!(chunks[2].text, "```rust\nfn main() {}\n```");
+///
+/// let chunks = chunk_document(markdown, ChunkOptions::new(20).strip(StripOptions::new()));
+/// assert_eq!(chunks[2].text, "fn main() {}\n\nOutro");
+/// ```
+pub fn chunk_document<'a>(text: &'a str, options: ChunkOptions) -> Vec<Chunk<'a>> {
+    let default_options = StripOptions::new();
+    let strip_options = options.strip.as_ref().unwrap_or(&default_options);
+    let mut chunker = Chunker::new(text, options.max_len, strip_options);
+    let newline = strip_options.newline.resolve(text);
+
+    let regions = Regions::new(text, strip_options);
+
+    for block in codeblocks_for(text, strip_options) {
+        chunker.push_prose(chunker.end..block.span.start);
+
+        let (end, output) = match &options.strip {
+            Some(options) => {
+                let action = regions.action_for(&block, options);
+                let end = block_end(text, &block, action, options);
+                (end, block_output(text, &block, action, options, newline))
+            }
+            None => (block.span.end, Cow::Borrowed(&text[block.span.clone()])),
+        };
+        chunker.push_block(block.span.start..end, &output);
+    }
+
+    chunker.push_prose(chunker.end..text.len());
+    chunker.finish()
+}
+
+/// Accumulates pieces of a document into chunks.
+struct Chunker<'a, 'o> {
+    text: &'a str,
+    max_len: usize,
+    /// How prose is copied into chunks.
+    options: &'o StripOptions,
+    chunks: Vec<Chunk<'a>>,
+    /// Where the current chunk starts and ends in `text`.
+    start: usize,
+    end: usize,
+    /// Length of the text of the current chunk.
+    len: usize,
+    /// The text of the current chunk, once it differs from `text[start..end]`.
+    changed: Option<String>,
+}
+
+impl<'a, 'o> Chunker<'a, 'o> {
+    fn new(text: &'a str, max_len: usize, options: &'o StripOptions) -> Self {
+        Self {
+            text,
+            max_len,
+            options,
+            chunks: Vec::new(),
+            start: 0,
+            end: 0,
+            len: 0,
+            changed: None,
+        }
+    }
+
+    /// Adds prose, splitting it across chunks as needed.
+    fn push_prose(&mut self, mut range: Range<usize>) {
+        while !range.is_empty() {
+            let prose = &self.text[range.clone()];
+            let room = self.max_len.saturating_sub(self.len);
+            if prose.len() <= room {
+                self.append(range.clone(), &prose_output(prose, self.options));
+                return;
+            }
+
+            let cut = match prose[..floor_char_boundary(prose, room)].rfind('\n') {
+                Some(newline) => newline + 1,
+                // Start a new chunk rather than splitting a line that would fit in one
+                None if self.len > 0 => {
+                    self.flush();
+                    continue;
+                }
+                None => {
+                    let cut = floor_char_boundary(prose, room);
+                    if cut > 0 {
+                        cut
+                    } else {
+                        // Always make progress, even if a single character doesn't fit
+                        prose.chars().next().map_or(0, char::len_utf8)
+                    }
+                }
+            };
+
+            let output = prose_output(&prose[..cut], self.options);
+            self.append(range.start..range.start + cut, &output);
+            self.flush();
+            range.start += cut;
+        }
+    }
+
+    /// Adds a whole code block of the document at `range`, stripped to `output`.
+    fn push_block(&mut self, range: Range<usize>, output: &str) {
+        if self.len > 0 && self.len + output.len() > self.max_len {
+            self.flush();
+        }
+        self.append(range, output);
+    }
+
+    /// Appends the piece of the document at `range`, whose text is `output`.
+    fn append(&mut self, range: Range<usize>, output: &str) {
+        if self.changed.is_none() && output != &self.text[range.clone()] {
+            self.changed = Some(self.text[self.start..range.start].to_string());
+        }
+        if let Some(changed) = &mut self.changed {
+            changed.push_str(output);
+        }
+        self.end = range.end;
+        self.len += output.len();
+    }
+
+    /// Ends the current chunk, if it isn't empty.
+    fn flush(&mut self) {
+        if self.end > self.start {
+            let text = match self.changed.take() {
+                Some(changed) => Cow::Owned(changed),
+                None => Cow::Borrowed(&self.text[self.start..self.end]),
+            };
+            self.chunks.push(Chunk {
+                text,
+                span: self.start..self.end,
+            });
+        }
+        self.start = self.end;
+        self.len = 0;
+    }
+
+    fn finish(mut self) -> Vec<Chunk<'a>> {
+        self.flush();
+        self.chunks
+    }
+}
+
+/// Returns the largest character boundary of `text` not after `index`.
+fn floor_char_boundary(text: &str, index: usize) -> usize {
+    let mut index = index.min(text.len());
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Action, strip_codeblocks_with};
+
+    const INPUT: &str = "# Title\n\nSome prose that goes on.\n```rust\nfn a() {}\n```\nMiddle\n```sh\nls -la\n```\nEnd";
+
+    #[test]
+    fn test_chunks_cover_document() {
+        for max_len in [0, 1, 5, 16, 30, 1000] {
+            let chunks = chunk_document(INPUT, ChunkOptions::new(max_len));
+            let rebuilt: String = chunks.iter().map(|chunk| &*chunk.text).collect();
+            assert_eq!(rebuilt, INPUT, "{max_len}");
+            assert!(chunks.windows(2).all(|w| w[0].span.end == w[1].span.start));
+        }
+    }
+
+    #[test]
+    fn test_blocks_are_never_split() {
+        for max_len in [0, 5, 16, 30] {
+            for chunk in chunk_document(INPUT, ChunkOptions::new(max_len)) {
+                assert_eq!(chunk.text.matches("```").count() % 2, 0, "{chunk:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_chunks_respect_limit_except_for_blocks() {
+        let chunks = chunk_document(INPUT, ChunkOptions::new(16));
+        for chunk in &chunks {
+            assert!(
+                chunk.text.len() <= 16 || chunk.text.starts_with("```"),
+                "{chunk:?}"
+            );
+        }
+        assert_eq!(chunks[0].text, "# Title\n\n");
+    }
+
+    #[test]
+    fn test_stripped_chunks_match_strip() {
+        for options in [
+            StripOptions::new(),
+            StripOptions::new().action(Action::Remove),
+            StripOptions::new()
+                .languages(["sh"])
+                .preserve_whitespace(true),
+        ] {
+            let chunks = chunk_document(INPUT, ChunkOptions::new(20).strip(options.clone()));
+            let rebuilt: String = chunks.iter().map(|chunk| &*chunk.text).collect();
+            assert_eq!(rebuilt, strip_codeblocks_with(INPUT, &options));
+        }
+    }
+
+    #[test]
+    fn test_unchanged_chunks_are_borrowed() {
+        let options = ChunkOptions::new(1000).strip(StripOptions::new().languages(["python"]));
+        let chunks = chunk_document(INPUT, options);
+        assert!(matches!(
+            chunks[..],
+            [Chunk {
+                text: Cow::Borrowed(_),
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn test_long_lines_split_on_char_boundaries() {
+        let chunks = chunk_document("ééééé", ChunkOptions::new(3));
+        let texts: Vec<_> = chunks.iter().map(|chunk| &*chunk.text).collect();
+        assert_eq!(texts, ["é", "é", "é", "é", "é"]);
+    }
+}
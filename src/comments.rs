@@ -0,0 +1,395 @@
+//! Removing comments from unwrapped code, by language.
+
+use std::borrow::Cow;
+
+/// The comment and string literal syntax of a family of languages.
+#[derive(Debug)]
+struct Syntax {
+    /// Markers starting a comment running to the end of the line.
+    line: &'static [&'static str],
+    /// Delimiters of block comments.
+    block: &'static [(&'static str, &'static str)],
+    /// Whether block comments nest, as in Rust and Haskell.
+    nested: bool,
+    /// Quotes of string literals with backslash escapes.
+    quotes: &'static [u8],
+    /// Quotes of string literals without escapes, such as `'` in shells.
+    raw_quotes: &'static [u8],
+    /// Whether `'` only quotes single characters, leaving Rust lifetimes alone.
+    char_literals: bool,
+    /// Whether tripled quotes open strings running to the same triple, as in Python.
+    triple_quotes: bool,
+    /// Whether line comments only start words, as `#` in shells but not in `$#`.
+    word_start: bool,
+}
+
+/// The defaults of [`Syntax`], for brevity in the tables below.
+const NONE: Syntax = Syntax {
+    line: &[],
+    block: &[],
+    nested: false,
+    quotes: b"",
+    raw_quotes: b"",
+    char_literals: false,
+    triple_quotes: false,
+    word_start: false,
+};
+
+const C: Syntax = Syntax {
+    line: &["//"],
+    block: &[("/*", "*/")],
+    quotes: b"\"'",
+    ..NONE
+};
+
+const JAVASCRIPT: Syntax = Syntax {
+    quotes: b"\"'`",
+    ..C
+};
+
+const GO: Syntax = Syntax {
+    raw_quotes: b"`",
+    ..C
+};
+
+const RUST: Syntax = Syntax {
+    nested: true,
+    quotes: b"\"",
+    char_literals: true,
+    ..C
+};
+
+const CSS: Syntax = Syntax { line: &[], ..C };
+
+const SHELL: Syntax = Syntax {
+    line: &["#"],
+    quotes: b"\"",
+    raw_quotes: b"'",
+    word_start: true,
+    ..NONE
+};
+
+const HASH: Syntax = Syntax {
+    line: &["#"],
+    quotes: b"\"'",
+    ..NONE
+};
+
+const PYTHON: Syntax = Syntax {
+    triple_quotes: true,
+    ..HASH
+};
+
+const YAML: Syntax = Syntax {
+    word_start: true,
+    ..HASH
+};
+
+const SQL: Syntax = Syntax {
+    line: &["--"],
+    block: &[("/*", "*/")],
+    raw_quotes: b"'\"",
+    ..NONE
+};
+
+const LUA: Syntax = Syntax {
+    line: &["--"],
+    block: &[("--[[", "]]")],
+    quotes: b"\"'",
+    ..NONE
+};
+
+const HASKELL: Syntax = Syntax {
+    line: &["--"],
+    block: &[("{-", "-}")],
+    nested: true,
+    quotes: b"\"",
+    ..NONE
+};
+
+const LISP: Syntax = Syntax {
+    line: &[";"],
+    quotes: b"\"",
+    ..NONE
+};
+
+const MARKUP: Syntax = Syntax {
+    block: &[("<!--", "-->")],
+    ..NONE
+};
+
+/// Returns the syntax of `language`, in any case, if it is known.
+fn syntax(language: &str) -> Option<&'static Syntax> {
+    let syntax = match language.to_ascii_lowercase().as_str() {
+        "c" | "h" | "cpp" | "c++" | "cc" | "cxx" | "hpp" | "cs" | "csharp" | "c#" | "java"
+        | "kotlin" | "kt" | "scala" | "swift" | "dart" | "groovy" | "zig" | "proto" => &C,
+        "js" | "javascript" | "jsx" | "mjs" | "cjs" | "ts" | "typescript" | "tsx" | "json5"
+        | "jsonc" => &JAVASCRIPT,
+        "go" | "golang" => &GO,
+        "rust" | "rs" => &RUST,
+        "css" => &CSS,
+        "scss" | "less" => &C,
+        "sh" | "bash" | "zsh" | "shell" | "fish" | "ksh" => &SHELL,
+        "python" | "py" | "python3" | "py3" => &PYTHON,
+        "ruby" | "rb" | "perl" | "pl" | "r" | "toml" | "make" | "makefile" | "dockerfile"
+        | "cmake" | "elixir" | "ex" | "exs" | "nim" | "julia" | "jl" => &HASH,
+        "yaml" | "yml" => &YAML,
+        "sql" | "mysql" | "postgresql" | "postgres" | "psql" | "plsql" | "sqlite" => &SQL,
+        "lua" => &LUA,
+        "haskell" | "hs" | "elm" => &HASKELL,
+        "lisp" | "clojure" | "clj" | "scheme" | "elisp" | "emacs-lisp" | "racket" => &LISP,
+        "html" | "xml" | "svg" | "xhtml" | "vue" => &MARKUP,
+        _ => return None,
+    };
+    Some(syntax)
+}
+
+/// Returns the `content` of a block in `language` without its comments.
+///
+/// The content is returned as is if the language isn't known or the content
+/// has no comment. See [`StripOptions::strip_comments`](crate::StripOptions::strip_comments).
+pub(crate) fn strip_comments<'a>(language: Option<&str>, content: &'a str) -> Cow<'a, str> {
+    match language.and_then(syntax) {
+        Some(syntax) => syntax.strip(content),
+        None => Cow::Borrowed(content),
+    }
+}
+
+impl Syntax {
+    /// Returns `code` without its comments, dropping the lines only holding comments.
+    fn strip<'a>(&self, code: &'a str) -> Cow<'a, str> {
+        let bytes = code.as_bytes();
+        let mut output = String::with_capacity(code.len());
+        let mut found = false;
+        // Where the current line starts in the output
+        let mut line_start = 0;
+        // Whether a comment was removed from the current line
+        let mut commented = false;
+
+        let mut i = 0;
+        if self.line.contains(&"#") && code.starts_with("#!") {
+            i = code.find('\n').map_or(code.len(), |newline| newline + 1);
+            output.push_str(&code[..i]);
+            line_start = i;
+        }
+
+        while i < code.len() {
+            let rest = &code[i..];
+            if let Some(&(open, close)) = self.block.iter().find(|(open, _)| rest.starts_with(open))
+            {
+                let end = self.block_end(code, i + open.len(), open, close);
+                if let Some(newline) = code[i..end].rfind('\n') {
+                    end_line(
+                        &mut output,
+                        line_start,
+                        true,
+                        code[..i + newline].ends_with('\r'),
+                    );
+                    line_start = output.len();
+                }
+                found = true;
+                commented = true;
+                i = end;
+            } else if bytes[i] == b'\n' {
+                end_line(
+                    &mut output,
+                    line_start,
+                    commented,
+                    code[..i].ends_with('\r'),
+                );
+                line_start = output.len();
+                commented = false;
+                i += 1;
+            } else if self.starts_line_comment(code, i) {
+                found = true;
+                commented = true;
+                i += rest.find('\n').unwrap_or(rest.len());
+            } else if let Some(end) = self.string_end(code, i) {
+                let literal = &code[i..end];
+                output.push_str(literal);
+                if let Some(newline) = literal.rfind('\n') {
+                    line_start = output.len() - (literal.len() - newline - 1);
+                    commented = false;
+                }
+                i = end;
+            } else {
+                let c = rest.chars().next().expect("not at the end");
+                output.push(c);
+                i += c.len_utf8();
+            }
+        }
+
+        if !found {
+            return Cow::Borrowed(code);
+        }
+        if commented {
+            let len = output[line_start..].trim_end().len();
+            output.truncate(line_start + len);
+        }
+        Cow::Owned(output)
+    }
+
+    /// Returns whether a line comment starts at `i` in `code`.
+    fn starts_line_comment(&self, code: &str, i: usize) -> bool {
+        self.line.iter().any(|marker| code[i..].starts_with(marker))
+            && (!self.word_start
+                || code[..i]
+                    .chars()
+                    .next_back()
+                    .is_none_or(char::is_whitespace))
+    }
+
+    /// Returns where the block comment whose content starts at `from` in
+    /// `code` ends, after its `close` delimiter, or the end of `code` if it
+    /// isn't closed.
+    fn block_end(&self, code: &str, from: usize, open: &str, close: &str) -> usize {
+        let bytes = code.as_bytes();
+        let mut depth = 1;
+        let mut j = from;
+        while j < bytes.len() {
+            if bytes[j..].starts_with(close.as_bytes()) {
+                j += close.len();
+                depth -= 1;
+                if depth == 0 {
+                    return j;
+                }
+            } else if self.nested && bytes[j..].starts_with(open.as_bytes()) {
+                j += open.len();
+                depth += 1;
+            } else {
+                j += 1;
+            }
+        }
+        code.len()
+    }
+
+    /// Returns where the string literal starting at `i` in `code` ends, after
+    /// its closing quote, if one starts there.
+    fn string_end(&self, code: &str, i: usize) -> Option<usize> {
+        let bytes = code.as_bytes();
+        let quote = bytes[i];
+        if self.raw_quotes.contains(&quote) {
+            return Some(literal_end(code, i + 1, &[quote], false));
+        }
+        if self.char_literals && quote == b'\'' {
+            // A lifetime like `'a` is no literal, unlike `'a'` and `'\n'`
+            if bytes.get(i + 1) == Some(&b'\\') {
+                return Some(literal_end(code, i + 1, b"'", true));
+            }
+            let c = code[i + 1..].chars().next()?;
+            let end = i + 1 + c.len_utf8();
+            return (bytes.get(end) == Some(&b'\'')).then_some(end + 1);
+        }
+        if !self.quotes.contains(&quote) {
+            return None;
+        }
+
+        if self.triple_quotes && bytes[i..].starts_with(&[quote; 3]) {
+            return Some(literal_end(code, i + 3, &[quote; 3], true));
+        }
+        Some(literal_end(code, i + 1, &[quote], true))
+    }
+}
+
+/// Returns where the string literal whose content starts at `from` in `code`
+/// ends, after its `close` quote, skipping quotes escaped with a backslash if
+/// `escapes`, or the end of `code` if it isn't closed.
+fn literal_end(code: &str, from: usize, close: &[u8], escapes: bool) -> usize {
+    let bytes = code.as_bytes();
+    let mut j = from;
+    while j < bytes.len() {
+        if escapes && bytes[j] == b'\\' {
+            j += 2;
+        } else if bytes[j..].starts_with(close) {
+            return j + close.len();
+        } else {
+            j += 1;
+        }
+    }
+    code.len()
+}
+
+/// Ends the line of `output` starting at `line_start` with a line break.
+///
+/// A line a comment was removed from loses its trailing whitespace, and the
+/// carriage return of a `crlf` line ending with it, which is put back, unless
+/// it only held comments, in which case it is removed.
+fn end_line(output: &mut String, line_start: usize, commented: bool, crlf: bool) {
+    if !commented {
+        output.push('\n');
+        return;
+    }
+    let len = output[line_start..].trim_end().len();
+    output.truncate(line_start + len);
+    if len > 0 {
+        output.push_str(if crlf { "\r\n" } else { "\n" });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strip(language: &str, code: &str) -> String {
+        strip_comments(Some(language), code).into_owned()
+    }
+
+    #[test]
+    fn test_line_and_block_comments() {
+        assert_eq!(
+            strip(
+                "rust",
+                "// Doc\nfn main() { // entry\n    /* a\n b */ run();\n}\n"
+            ),
+            "fn main() {\n run();\n}\n"
+        );
+        assert_eq!(strip("rs", "/* /* nested */ still */x"), "x");
+        assert_eq!(
+            strip("C", "a /* x */ b;\r\n// c\r\nd; // e\r\n"),
+            "a  b;\r\nd;\r\n"
+        );
+        assert_eq!(strip("sql", "SELECT 1; -- one\n/* two */"), "SELECT 1;\n");
+        assert_eq!(strip("lua", "--[[ a\nb ]]\nprint(1) -- c"), "print(1)");
+        assert_eq!(strip("html", "<p>x</p><!-- y -->\n"), "<p>x</p>\n");
+    }
+
+    #[test]
+    fn test_string_literals() {
+        assert_eq!(
+            strip("js", "fetch(\"http://x\", `//${a}`); // go\n"),
+            "fetch(\"http://x\", `//${a}`);\n"
+        );
+        assert_eq!(
+            strip("py", "s = '#fff'  # color\n\"\"\"\n# kept\n\"\"\"\n"),
+            "s = '#fff'\n\"\"\"\n# kept\n\"\"\"\n"
+        );
+        assert_eq!(
+            strip("rust", "fn f<'a>(c: char) -> bool { c == '\"' } // q"),
+            "fn f<'a>(c: char) -> bool { c == '\"' }"
+        );
+        assert_eq!(strip("go", "s := `a\\` // b"), "s := `a\\`");
+        assert_eq!(
+            strip("sh", "echo 'a\\' \"$#\" ${#x} # n"),
+            "echo 'a\\' \"$#\" ${#x}"
+        );
+    }
+
+    #[test]
+    fn test_untouched() {
+        let shebang = "#!/bin/sh\nls\n";
+        assert!(matches!(
+            strip_comments(Some("sh"), shebang),
+            Cow::Borrowed(_)
+        ));
+        assert!(matches!(
+            strip_comments(Some("text"), "// x"),
+            Cow::Borrowed(_)
+        ));
+        assert!(matches!(strip_comments(None, "# x"), Cow::Borrowed(_)));
+        assert!(matches!(
+            strip_comments(Some("c"), "a\n\nb\n"),
+            Cow::Borrowed(_)
+        ));
+        assert_eq!(strip("yaml", "url: a#b\n# c\n"), "url: a#b\n");
+    }
+}
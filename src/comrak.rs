@@ -0,0 +1,156 @@
+//! Stripping code blocks from a [`comrak`] AST.
+
+use std::borrow::Cow;
+
+use comrak::Arena;
+use comrak::nodes::{Node, NodeValue};
+
+use crate::block::info_words;
+use crate::content::first_line;
+use crate::hash::placeholder_output;
+use crate::{Action, StripOptions};
+
+/// Strips the fenced code blocks of a `comrak` AST in place.
+///
+/// Each fenced code block under `root` is handled according to the action
+/// `options` select for its language: unwrapped blocks become paragraphs of
+/// their content lines, removed blocks are detached, kept blocks are left
+/// alone, placeholders replace the block with a paragraph of the
+/// placeholder text, and blocks cut to their first line become a paragraph
+/// of it and the marker. Indented code blocks are left alone. New nodes are
+/// allocated in `arena`, which must be the arena the document was parsed into.
+///
+/// Only the language filters and action options are applied; options
+/// transforming the content of unwrapped blocks (such as
+/// [`StripOptions::strip_prompts`]) are not. Neither are
+/// [`select`](StripOptions::select), [`scope`](StripOptions::scope),
+/// [`html`](StripOptions::html),
+/// [`require_blank_line_before_fence`](StripOptions::require_blank_line_before_fence),
+/// since blocks are found by the parser, in its dialect,
+/// [`render_safety`](StripOptions::render_safety),
+/// [`strip_comments`](StripOptions::strip_comments),
+/// [`trailing_newline`](StripOptions::trailing_newline),
+/// [`merge_adjacent`](StripOptions::merge_adjacent),
+/// [`region_markers`](StripOptions::region_markers),
+/// [`math`](StripOptions::math) for display math in prose,
+/// [`shrink`](StripOptions::shrink) and [`dedent`](StripOptions::dedent).
+///
+/// Requires the `comrak` feature.
+///
+/// # Examples
+///
+/// ```
+/// use comrak::{Arena, Options, format_commonmark, parse_document};
+/// use strip_codeblocks::{StripOptions, strip_comrak_codeblocks};
+///
+/// let arena = Arena::new();
+/// let root = parse_document(&arena, "Intro\n\n```sh\nls\n```\n", &Options::default());
+///
+/// strip_comrak_codeblocks(&arena, root, &StripOptions::new());
+///
+/// let mut output = String::new();
+/// format_commonmark(root, &Options::default(), &mut output).unwrap();
+/// assert_eq!(output, "Intro\n\nls\n");
+/// ```
+pub fn strip_comrak_codeblocks<'a>(arena: &'a Arena<'a>, root: Node<'a>, options: &StripOptions) {
+    // Collect first: the tree can't be modified while traversing it
+    let blocks: Vec<_> = root
+        .descendants()
+        .filter(
+            |node| matches!(&node.data.borrow().value, NodeValue::CodeBlock(block) if block.fenced),
+        )
+        .collect();
+
+    for node in blocks {
+        let (action, literal) = {
+            let data = node.data.borrow();
+            let NodeValue::CodeBlock(block) = &data.value else {
+                unreachable!("only code blocks were collected")
+            };
+            let language = info_words(block.info.trim()).next();
+            (
+                options.action_for_language(language).clone(),
+                block.literal.clone(),
+            )
+        };
+
+        match action {
+            Action::Keep => {}
+            Action::Remove => node.detach(),
+            Action::Placeholder(placeholder) => {
+                let placeholder = placeholder_output(&placeholder, &literal, options, "\n");
+                replace_with_paragraph(arena, node, &placeholder)
+            }
+            Action::FirstLine(marker) => {
+                let kept = first_line(Cow::Borrowed(&literal), &marker, "\n");
+                replace_with_paragraph(arena, node, kept.strip_suffix('\n').unwrap_or(&kept))
+            }
+            _ => {
+                replace_with_paragraph(arena, node, literal.strip_suffix('\n').unwrap_or(&literal))
+            }
+        }
+    }
+}
+
+/// Turns `node` into a paragraph of the lines of `text`.
+fn replace_with_paragraph<'a>(arena: &'a Arena<'a>, node: Node<'a>, text: &str) {
+    node.data.borrow_mut().value = NodeValue::Paragraph;
+
+    for (index, line) in text.split('\n').enumerate() {
+        if index > 0 {
+            node.append(arena.alloc(NodeValue::SoftBreak.into()));
+        }
+        node.append(arena.alloc(NodeValue::Text(line.to_string().into()).into()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use comrak::{Options, format_commonmark, parse_document};
+
+    fn strip(markdown: &str, options: &StripOptions) -> String {
+        let arena = Arena::new();
+        let root = parse_document(&arena, markdown, &Options::default());
+        strip_comrak_codeblocks(&arena, root, options);
+
+        let mut output = String::new();
+        format_commonmark(root, &Options::default(), &mut output).unwrap();
+        output
+    }
+
+    #[test]
+    fn test_unwrap_multiline_block() {
+        let output = strip("```python\nx = 1\ny = 2\n```\n", &StripOptions::new());
+        assert_eq!(output, "x = 1\ny = 2\n");
+    }
+
+    #[test]
+    fn test_remove_and_placeholder() {
+        let markdown = "Before\n\n```sh\nls\n```\n\nAfter\n";
+        assert_eq!(
+            strip(markdown, &StripOptions::new().action(Action::Remove)),
+            "Before\n\nAfter\n"
+        );
+        assert_eq!(
+            strip(
+                markdown,
+                &StripOptions::new().action(Action::Placeholder("(code omitted)".into()))
+            ),
+            "Before\n\n(code omitted)\n\nAfter\n"
+        );
+    }
+
+    #[test]
+    fn test_unselected_and_indented_blocks_are_kept() {
+        let markdown = "``` toml\n[a]\n```\n\n    indented\n";
+        let options = StripOptions::new()
+            .languages(["rust"])
+            .action(Action::Remove);
+        assert_eq!(
+            strip(markdown, &options),
+            strip(markdown, &StripOptions::new().action(Action::Keep))
+        );
+        assert!(strip(markdown, &options).contains("indented"));
+    }
+}
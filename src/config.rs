@@ -0,0 +1,278 @@
+//! Reading [`StripOptions`] from JSON and TOML configuration.
+
+use std::error::Error;
+use std::fmt;
+
+use crate::StripOptions;
+
+/// The error returned when options can't be read from configuration.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct OptionsError {
+    /// The line of the error, starting at 1.
+    pub line: usize,
+    /// The column of the error, starting at 1.
+    pub column: usize,
+    /// What is wrong, such as an unknown option or an invalid value.
+    pub message: String,
+}
+
+impl fmt::Display for OptionsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.line, self.column, self.message
+        )
+    }
+}
+
+impl Error for OptionsError {}
+
+/// Returns the line and column of byte `offset` in `source`, starting at 1.
+fn position(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..source.floor_char_boundary(offset)];
+    let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
+impl StripOptions {
+    /// Reads options from a JSON object, whose keys are the names of the
+    /// setters of `StripOptions`.
+    ///
+    /// Options that aren't set keep their default. Enumerations are written in
+    /// snake case, such as `"first_line"` or `"match_input"`, and actions
+    /// holding text as objects, such as `{"placeholder": "[code]"}`. A
+    /// [`ReplacementMap`](crate::ReplacementMap) can't be read from
+    /// configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `json` - The JSON object
+    ///
+    /// # Returns
+    ///
+    /// The options, or the first syntax error, unknown option or invalid value
+    /// of `json`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strip_codeblocks::{Action, StripOptions};
+    ///
+    /// let options =
+    ///     StripOptions::from_json(r#"{"languages": ["sh"], "action": {"placeholder": "[code]"}}"#)?;
+    /// assert_eq!(
+    ///     options,
+    ///     StripOptions::new().languages(["sh"]).action(Action::Placeholder("[code]".into()))
+    /// );
+    ///
+    /// let error = StripOptions::from_json(r#"{"action": "delete"}"#).unwrap_err();
+    /// assert_eq!(error.column, 19);
+    /// # Ok::<(), strip_codeblocks::OptionsError>(())
+    /// ```
+    pub fn from_json(json: &str) -> Result<Self, OptionsError> {
+        serde_json::from_str(json).map_err(|error| {
+            // The message of the error, without the position serde_json appends
+            let message = error.to_string();
+            let message = match message.rfind(" at line ") {
+                Some(position) => message[..position].to_string(),
+                None => message,
+            };
+            OptionsError {
+                line: error.line(),
+                column: error.column(),
+                message,
+            }
+        })
+    }
+
+    /// Returns a JSON Schema of the objects [`from_json`](Self::from_json)
+    /// reads, for typing configuration in other languages, such as the
+    /// TypeScript of a frontend calling WASM bindings.
+    ///
+    /// The schema follows draft 2020-12 and gives the default of each option.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strip_codeblocks::StripOptions;
+    ///
+    /// let schema: serde_json::Value = serde_json::from_str(StripOptions::json_schema())?;
+    /// assert_eq!(schema["properties"]["trailing_newline"]["default"], "keep");
+    /// # Ok::<(), serde_json::Error>(())
+    /// ```
+    pub fn json_schema() -> &'static str {
+        include_str!("options.schema.json")
+    }
+
+    /// Reads options from a TOML document, like [`from_json`](Self::from_json).
+    ///
+    /// # Arguments
+    ///
+    /// * `toml` - The TOML document
+    ///
+    /// # Returns
+    ///
+    /// The options, or the first syntax error, unknown option or invalid value
+    /// of `toml`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strip_codeblocks::{Shrink, StripOptions, TrailingNewline};
+    ///
+    /// let options = StripOptions::from_toml(
+    ///     "trailing_newline = \"always\"\nshrink = { max_lines = 40, keep = 10 }\n",
+    /// )?;
+    /// assert_eq!(
+    ///     options,
+    ///     StripOptions::new()
+    ///         .trailing_newline(TrailingNewline::Always)
+    ///         .shrink(Shrink::new(40, 10))
+    /// );
+    ///
+    /// let error = StripOptions::from_toml("strip_prompts = true\nlangauges = [\"sh\"]").unwrap_err();
+    /// assert_eq!((error.line, error.column), (2, 1));
+    /// # Ok::<(), strip_codeblocks::OptionsError>(())
+    /// ```
+    pub fn from_toml(toml: &str) -> Result<Self, OptionsError> {
+        toml::from_str(toml).map_err(|error| {
+            let (line, column) = error
+                .span()
+                .map_or((1, 1), |span| position(toml, span.start));
+            OptionsError {
+                line,
+                column,
+                message: error.message().to_string(),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Action, LanguageMatch, Newline};
+
+    #[test]
+    fn test_every_kind_of_option() {
+        let json = r#"{
+            "action": {"first_line": "…"},
+            "language_match": {"ignore_case": true},
+            "newline": "crlf",
+            "math": "remove",
+            "select": "lang:rust & lines>1",
+            "shrink": {"max_lines": 10, "keep": 2, "marker": "[{omitted}]"}
+        }"#;
+        assert_eq!(
+            StripOptions::from_json(json).unwrap(),
+            StripOptions::new()
+                .action(Action::FirstLine("…".into()))
+                .language_match(LanguageMatch::new().ignore_case(true))
+                .newline(Newline::CrLf)
+                .math(Action::Remove)
+                .select(crate::BlockMatcher::language("rust") & crate::BlockMatcher::lines(2..))
+                .shrink(crate::Shrink::new(10, 2).marker("[{omitted}]"))
+        );
+        assert_eq!(
+            StripOptions::from_toml("").unwrap(),
+            StripOptions::default()
+        );
+    }
+
+    #[test]
+    fn test_errors_point_at_the_value() {
+        let error = StripOptions::from_toml("ansi = \"strip\"\nnewline = \"cr\"\n").unwrap_err();
+        assert_eq!((error.line, error.column), (2, 11));
+        assert!(error.message.contains("unknown variant `cr`"));
+
+        let error = StripOptions::from_toml("select = \"lang:\"").unwrap_err();
+        assert!(
+            error
+                .message
+                .contains("column 6: expected a pattern after `lang:`")
+        );
+
+        let error = StripOptions::from_json("{\n  \"strict\": 1\n}").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "line 2, column 13: invalid type: integer `1`, expected a boolean"
+        );
+    }
+
+    /// Returns the fields the `Deserialize` implementation of [`StripOptions`]
+    /// reads, as its derive passes them to `deserialize_struct`.
+    fn deserialized_fields() -> &'static [&'static str] {
+        use serde::de::{self, value::Error};
+
+        struct Fields<'a>(&'a mut &'static [&'static str]);
+
+        impl<'de> de::Deserializer<'de> for Fields<'_> {
+            type Error = Error;
+
+            fn deserialize_any<V: de::Visitor<'de>>(self, _: V) -> Result<V::Value, Error> {
+                Err(de::Error::custom("expected a struct"))
+            }
+
+            fn deserialize_struct<V: de::Visitor<'de>>(
+                self,
+                _: &'static str,
+                fields: &'static [&'static str],
+                _: V,
+            ) -> Result<V::Value, Error> {
+                *self.0 = fields;
+                Err(de::Error::custom("fields recorded"))
+            }
+
+            serde::forward_to_deserialize_any! {
+                bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+                bytes byte_buf option unit unit_struct newtype_struct seq tuple
+                tuple_struct map enum identifier ignored_any
+            }
+        }
+
+        let mut fields: &'static [&'static str] = &[];
+        let _ = <StripOptions as serde::Deserialize>::deserialize(Fields(&mut fields));
+        fields
+    }
+
+    #[test]
+    fn test_json_schema_matches_options() {
+        let schema: serde_json::Value = serde_json::from_str(StripOptions::json_schema()).unwrap();
+        let properties = schema["properties"].as_object().unwrap();
+
+        // The schema describes exactly the fields that are deserialized
+        let mut described: Vec<&str> = properties.keys().map(String::as_str).collect();
+        let mut fields = deserialized_fields().to_vec();
+        described.sort_unstable();
+        fields.sort_unstable();
+        assert_eq!(described, fields);
+
+        // A document setting every property to its default is accepted, as
+        // unknown fields are denied
+        let defaults: serde_json::Map<_, _> = properties
+            .iter()
+            .map(|(name, property)| (name.clone(), property["default"].clone()))
+            .collect();
+        assert_eq!(
+            StripOptions::from_json(&serde_json::Value::Object(defaults).to_string()),
+            Ok(StripOptions::new())
+        );
+
+        // Every variant of an enumeration is accepted
+        for (name, property) in properties {
+            for value in property["enum"].as_array().into_iter().flatten() {
+                let document = serde_json::json!({ name: value }).to_string();
+                assert!(
+                    StripOptions::from_json(&document).is_ok(),
+                    "{name}: {value}"
+                );
+            }
+        }
+        assert!(StripOptions::from_json(r#"{"scope": [0, 1]}"#).is_err());
+    }
+}
@@ -0,0 +1,150 @@
+//! Handling of console session blocks (commands prefixed by `$ ` prompts mixed with output).
+
+use std::borrow::Cow;
+
+use crate::StripOptions;
+
+/// Languages whose blocks may contain console sessions.
+const SESSION_LANGUAGES: &[&str] = &[
+    "console",
+    "shell",
+    "shell-session",
+    "bash",
+    "bash-session",
+    "sh",
+    "sh-session",
+    "zsh",
+    "zsh-session",
+    "terminal",
+];
+
+/// Returns the `content` of a block in `language` with prompts and/or output
+/// removed according to `options`.
+///
+/// Only session blocks (see [`StripOptions::strip_prompts`]) are rewritten; the
+/// content of any other block is returned as is.
+pub(crate) fn session_content<'a>(
+    language: Option<&str>,
+    content: &'a str,
+    options: &StripOptions,
+) -> Cow<'a, str> {
+    if !(options.strip_prompts || options.drop_output) || !is_session(language, content) {
+        return Cow::Borrowed(content);
+    }
+
+    let mut output = String::with_capacity(content.len());
+    // Whether the previous command line ended with a `\` line continuation
+    let mut continued = false;
+
+    for line in content.split_inclusive('\n') {
+        let (is_command, command) = if continued {
+            // Continuation lines are part of the command, sometimes behind a `> ` prompt
+            (true, line.trim_start().strip_prefix("> ").unwrap_or(line))
+        } else {
+            match prompt_command(line) {
+                Some(command) => (true, command),
+                None => (false, line),
+            }
+        };
+
+        continued = is_command && command.trim_end().ends_with('\\');
+
+        if is_command {
+            output.push_str(if options.strip_prompts { command } else { line });
+        } else if !options.drop_output {
+            output.push_str(line);
+        }
+    }
+
+    Cow::Owned(output)
+}
+
+/// Whether a block is tagged as a shell language and its content contains at least one prompt.
+fn is_session(language: Option<&str>, content: &str) -> bool {
+    let tagged = language.is_some_and(|language| {
+        SESSION_LANGUAGES.contains(&language.to_ascii_lowercase().as_str())
+    });
+
+    tagged && content.lines().any(|line| prompt_command(line).is_some())
+}
+
+/// Returns the command following the `$ ` prompt of `line`, if it has one.
+pub(crate) fn prompt_command(line: &str) -> Option<&str> {
+    let line = line.trim_start();
+    line.strip_prefix("$ ")
+        .or_else(|| (line.trim_end() == "$").then(|| &line[1..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SESSION_LANGUAGES;
+    use crate::{StripOptions, strip_codeblocks_with};
+
+    const SESSION: &str = "```console\n$ echo hi\nhi\n$ ls\na  b\n```";
+
+    #[test]
+    fn test_strip_prompts() {
+        let options = StripOptions::new().strip_prompts(true);
+        assert_eq!(
+            strip_codeblocks_with(SESSION, &options),
+            "echo hi\nhi\nls\na  b\n"
+        );
+    }
+
+    #[test]
+    fn test_drop_output() {
+        let options = StripOptions::new().drop_output(true);
+        assert_eq!(
+            strip_codeblocks_with(SESSION, &options),
+            "$ echo hi\n$ ls\n"
+        );
+    }
+
+    #[test]
+    fn test_runnable_script_with_continuation() {
+        let input = "```bash\n$ cargo build \\\n>   --release\n   Compiling foo\n$ ./foo\n```";
+        let options = StripOptions::new().strip_prompts(true).drop_output(true);
+        assert_eq!(
+            strip_codeblocks_with(input, &options),
+            "cargo build \\\n  --release\n./foo\n"
+        );
+    }
+
+    #[test]
+    fn test_blocks_without_prompts_are_untouched() {
+        let options = StripOptions::new().strip_prompts(true).drop_output(true);
+
+        let script = "```bash\necho hi\n```";
+        assert_eq!(strip_codeblocks_with(script, &options), "echo hi\n");
+
+        let python = "```python\n$ not a prompt\n```";
+        assert_eq!(strip_codeblocks_with(python, &options), "$ not a prompt\n");
+    }
+
+    #[test]
+    fn test_session_languages() {
+        // The names listed in the docs of `StripOptions::strip_prompts`
+        let documented = [
+            "console",
+            "terminal",
+            "shell",
+            "bash",
+            "sh",
+            "zsh",
+            "shell-session",
+            "bash-session",
+            "sh-session",
+            "zsh-session",
+        ];
+        assert_eq!(documented.len(), SESSION_LANGUAGES.len());
+        let options = StripOptions::new().strip_prompts(true);
+        for language in documented {
+            let input = format!("```{language}\n$ ls\n```");
+            assert_eq!(
+                strip_codeblocks_with(&input, &options),
+                "ls\n",
+                "{language}"
+            );
+        }
+    }
+}
@@ -0,0 +1,152 @@
+//! The transformations applied to the content of unwrapped blocks.
+
+use std::borrow::Cow;
+
+use crate::render_safety::render_safe;
+use crate::{Ansi, CodeBlock, StripOptions, ansi, comments, console, entities, html};
+
+/// Returns the content of `block` of `text` as it appears once unwrapped with `options`.
+///
+/// Transformations run in a fixed order: [dedenting](StripOptions::dedent),
+/// ANSI escape sequence removal, HTML entity decoding, console session
+/// handling, [comment removal](StripOptions::strip_comments),
+/// [render safety](StripOptions::render_safety), then, in
+/// [HTML](StripOptions::html) mode, [script](StripOptions::html_scripts)
+/// handling. The content
+/// is borrowed when none of them applies, and none does in
+/// [whitespace-preserving](StripOptions::preserve_whitespace) mode. The
+/// content is `continued` when it follows another block's instead of going
+/// where the fence stood, as when [merging](StripOptions::merge_adjacent).
+pub(crate) fn unwrapped_content<'a>(
+    text: &'a str,
+    block: &CodeBlock<'a>,
+    options: &StripOptions,
+    continued: bool,
+) -> Cow<'a, str> {
+    if options.preserve_whitespace {
+        return Cow::Borrowed(block.content);
+    }
+
+    let content = match &options.dedent {
+        Some(dedent) => dedent.apply(text, block, continued),
+        None => Cow::Borrowed(block.content),
+    };
+    let content = match options.ansi {
+        Ansi::Strip => then(content, ansi::strip_ansi),
+        _ => content,
+    };
+    let content = then(content, |content| {
+        entities::decode(content, options.html_entities)
+    });
+    let content = then(content, |content| {
+        console::session_content(block.language, content, options)
+    });
+    let content = if options.strip_comments {
+        then(content, |content| {
+            comments::strip_comments(block.language, content)
+        })
+    } else {
+        content
+    };
+    let content = then(content, |content| {
+        render_safe(content, options.render_safety)
+    });
+    if options.html {
+        then(content, |content| {
+            html::defuse_raw_text(content, options.html_scripts)
+        })
+    } else {
+        content
+    }
+}
+
+/// Returns the first line of `content`, followed by a `marker` line ending
+/// with `newline` if anything but blank lines follows it.
+pub(crate) fn first_line<'a>(content: Cow<'a, str>, marker: &str, newline: &str) -> Cow<'a, str> {
+    let mut filter = FirstLineFilter::default();
+    let len = filter.feed(&content).len();
+    if !filter.truncated() {
+        return content;
+    }
+
+    let mut output = String::with_capacity(len + marker.len() + newline.len());
+    output.push_str(&content[..len]);
+    if !marker.is_empty() {
+        output.push_str(marker);
+        output.push_str(newline);
+    }
+    Cow::Owned(output)
+}
+
+/// Keeps the first line of content fed piece by piece, for streaming [`first_line`].
+#[derive(Debug, Clone, Default)]
+pub(crate) struct FirstLineFilter {
+    /// Whether the first line has ended.
+    ended: bool,
+    /// Whether anything but blank lines followed it.
+    truncated: bool,
+}
+
+impl FirstLineFilter {
+    /// Returns the part of the next `piece` of content belonging to the first line.
+    pub(crate) fn feed<'p>(&mut self, piece: &'p str) -> &'p str {
+        if self.ended {
+            self.truncated |= !piece.trim().is_empty();
+            return "";
+        }
+
+        match piece.find('\n') {
+            Some(newline) => {
+                self.ended = true;
+                self.truncated = !piece[newline + 1..].trim().is_empty();
+                &piece[..=newline]
+            }
+            None => piece,
+        }
+    }
+
+    /// Returns whether content was dropped after the first line, calling for a marker.
+    pub(crate) fn truncated(&self) -> bool {
+        self.truncated
+    }
+}
+
+/// Applies `transform` to `content`, only allocating when the transformation does.
+fn then<'a>(content: Cow<'a, str>, transform: impl FnOnce(&str) -> Cow<'_, str>) -> Cow<'a, str> {
+    match content {
+        Cow::Borrowed(content) => transform(content),
+        Cow::Owned(content) => {
+            let transformed = match transform(&content) {
+                Cow::Owned(transformed) => Some(transformed),
+                Cow::Borrowed(_) => None,
+            };
+            Cow::Owned(transformed.unwrap_or(content))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_line() {
+        let first = |content| first_line(Cow::Borrowed(content), "…", "\n");
+        assert_eq!(first("ls\na\nb\n"), "ls\n…\n");
+        assert_eq!(first("ls\n\n  \n"), "ls\n\n  \n");
+        assert_eq!(first("ls"), "ls");
+        assert_eq!(first(""), "");
+        assert_eq!(first_line(Cow::Borrowed("ls\na"), "", "\n"), "ls\n");
+    }
+
+    #[test]
+    fn test_first_line_filter_across_pieces() {
+        let mut filter = FirstLineFilter::default();
+        let kept: String = ["$ car", "go b", "uild\n  Comp", "iling\n"]
+            .iter()
+            .map(|piece| filter.feed(piece))
+            .collect();
+        assert_eq!(kept, "$ cargo build\n");
+        assert!(filter.truncated());
+    }
+}
@@ -0,0 +1,239 @@
+//! Finding where code blocks sit in the structure of their document.
+
+use crate::CodeBlock;
+use crate::title::{BlocksBefore, atx_heading, lines_before, setext_underline};
+
+/// Where a code block sits in the structure of its document, as returned
+/// by [`CodeBlock::context`].
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::extract_codeblocks;
+///
+/// let text = "# Guide\n## Setup\n- Install:\n  ```sh\n  make\n  ```\n> ```\n> quoted\n> ```";
+/// let blocks = extract_codeblocks(text);
+///
+/// let context = blocks[0].context(text);
+/// assert_eq!((context.list_depth, context.quote_depth), (1, 0));
+/// assert_eq!(context.heading_path, ["Guide", "Setup"]);
+/// assert_eq!(blocks[1].context(text).quote_depth, 1);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct BlockContext<'a> {
+    /// How many list items the block is nested in, 0 at the top level.
+    pub list_depth: usize,
+    /// How many block quotes the block is nested in, 0 at the top level.
+    pub quote_depth: usize,
+    /// The headings of the sections the block is in, outermost first, such
+    /// as `["Guide", "Setup"]` for a block under `## Setup` under `# Guide`.
+    pub heading_path: Vec<&'a str>,
+}
+
+/// Returns `line` without its leading block quote markers, with how many there were.
+fn without_quote_markers(mut line: &str) -> (usize, &str) {
+    let mut markers = 0;
+    while let Some(rest) = line.trim_start_matches([' ', '\t']).strip_prefix('>') {
+        markers += 1;
+        line = rest.strip_prefix([' ', '\t']).unwrap_or(rest);
+    }
+    (markers, line)
+}
+
+/// Returns the width of the indentation of `line`, counting tabs as 4 columns.
+fn indent(line: &str) -> usize {
+    line.chars()
+        .map_while(|c| match c {
+            ' ' => Some(1),
+            '\t' => Some(4),
+            _ => None,
+        })
+        .sum()
+}
+
+/// Returns `line` without the list item marker starting it, such as `- ` or
+/// `1. `, after its indentation, if it has one.
+fn without_list_marker(line: &str) -> Option<&str> {
+    let item = line.trim_start_matches([' ', '\t']);
+    let rest = match item.strip_prefix(['-', '*', '+']) {
+        Some(rest) => rest,
+        None => {
+            let digits = item.len() - item.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+            if !(1..=9).contains(&digits) {
+                return None;
+            }
+            item[digits..].strip_prefix(['.', ')'])?
+        }
+    };
+    match rest.strip_prefix([' ', '\t']) {
+        Some(rest) => Some(rest),
+        None => rest
+            .trim_end_matches(['\r', '\n'])
+            .is_empty()
+            .then_some(rest),
+    }
+}
+
+impl<'a> CodeBlock<'a> {
+    /// Returns where the block sits in the structure of `text`, the document
+    /// it was found in: the lists and block quotes it is nested in, and the
+    /// headings of the sections it is in.
+    ///
+    /// This lets callers decide what to do with a block based on the document
+    /// structure, such as only unwrapping top-level blocks and keeping those
+    /// in quotes. The structure is read from the lines before the block,
+    /// skipping those of other code blocks, so a `# comment` in a shell block
+    /// isn't taken for a heading: a list item contains the block if it is
+    /// indented less than the block, and ATX and setext headings make up the
+    /// path. Nothing is computed until this is called.
+    ///
+    /// Each call reads the document from its start to the block, in time
+    /// linear in that length, so calling it for every block of a document
+    /// reads the document once per block.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strip_codeblocks::extract_codeblocks;
+    ///
+    /// let text = "# Notes\n```sh\n# not a heading\n```\n1. Step:\n   - Run:\n     ```sh\n     ls\n     ```";
+    /// let blocks = extract_codeblocks(text);
+    ///
+    /// // Only unwrap top-level blocks
+    /// let top_level: Vec<_> = blocks
+    ///     .iter()
+    ///     .filter(|block| block.context(text).list_depth == 0)
+    ///     .collect();
+    /// assert_eq!(top_level.len(), 1);
+    /// assert_eq!(blocks[1].context(text).list_depth, 2);
+    /// assert_eq!(blocks[1].context(text).heading_path, ["Notes"]);
+    /// ```
+    pub fn context(&self, text: &'a str) -> BlockContext<'a> {
+        let line_start = text[..self.span.start]
+            .rfind('\n')
+            .map_or(0, |newline| newline + 1);
+        let (quote_depth, mut prefix) = without_quote_markers(&text[line_start..self.span.start]);
+
+        // List items opened on the fence line itself, as in `- ```sh`
+        let mut list_depth = 0;
+        let mut within = indent(prefix);
+        while let Some(rest) = without_list_marker(prefix) {
+            if list_depth == 0 {
+                within = indent(prefix);
+            }
+            list_depth += 1;
+            prefix = rest;
+        }
+
+        let mut blocks = BlocksBefore::new(text, self.span.start);
+        let mut in_lists = within > 0;
+        let mut headings: Vec<(usize, &'a str)> = Vec::new();
+        let mut underline = None;
+        for (range, line) in lines_before(text, self.span.start) {
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            if blocks.contain(range.start) {
+                underline = None;
+                continue;
+            }
+
+            let (quotes, item) = without_quote_markers(line);
+            if in_lists && !item.trim().is_empty() {
+                let line_indent = indent(item);
+                if quotes < quote_depth {
+                    in_lists = false;
+                } else if line_indent < within && without_list_marker(item).is_some() {
+                    list_depth += 1;
+                    within = line_indent;
+                    in_lists = within > 0;
+                } else if line_indent == 0 {
+                    in_lists = false;
+                }
+            }
+
+            let heading = match underline {
+                Some(level) if !line.trim().is_empty() => Some((level, line.trim())),
+                _ => atx_heading(line).map(|title| {
+                    let level =
+                        line.trim_start().len() - line.trim_start().trim_start_matches('#').len();
+                    (level, title)
+                }),
+            };
+            if let Some((level, title)) = heading
+                && quotes == 0
+                && headings.last().is_none_or(|&(inner, _)| level < inner)
+            {
+                headings.push((level, title));
+            }
+
+            underline = setext_underline(line).then(|| match line.trim().as_bytes()[0] {
+                b'=' => 1,
+                _ => 2,
+            });
+        }
+
+        BlockContext {
+            list_depth,
+            quote_depth,
+            heading_path: headings.into_iter().rev().map(|(_, title)| title).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extract_codeblocks;
+
+    #[test]
+    fn test_markers() {
+        assert_eq!(without_quote_markers("> > x"), (2, "x"));
+        assert_eq!(without_quote_markers("  >x"), (1, "x"));
+        assert_eq!(without_list_marker("  - item"), Some("item"));
+        assert_eq!(without_list_marker("10) item"), Some("item"));
+        assert_eq!(without_list_marker("-"), Some(""));
+        assert_eq!(without_list_marker("-x"), None);
+        assert_eq!(without_list_marker("2024.x"), None);
+        assert_eq!(indent("\t  x"), 6);
+    }
+
+    #[test]
+    fn test_list_depth() {
+        let text = "- a\n  - b\n\n  ```\n  in a\n  ```\n- c\n  ```\n  in c\n  ```\ntext\n   ```\n   top\n   ```\n* ```\n  x\n  ```";
+        let depths: Vec<_> = extract_codeblocks(text)
+            .iter()
+            .map(|block| block.context(text).list_depth)
+            .collect();
+        assert_eq!(depths, [1, 1, 0, 1]);
+    }
+
+    #[test]
+    fn test_quote_depth() {
+        let text = "> - item\n>   ```\n>   x\n>   ```\n> > ```\n> > y\n> > ```";
+        let contexts: Vec<_> = extract_codeblocks(text)
+            .iter()
+            .map(|block| {
+                let context = block.context(text);
+                (context.quote_depth, context.list_depth)
+            })
+            .collect();
+        assert_eq!(contexts, [(1, 1), (2, 0)]);
+    }
+
+    #[test]
+    fn test_heading_path() {
+        let text = "Guide\n=====\n## Old\n## Install\n### Linux\nText\n```sh\n# comment\n```\n#### Debian\n```\nx\n```\n# Other\n```\ny\n```";
+        let paths: Vec<_> = extract_codeblocks(text)
+            .iter()
+            .map(|block| block.context(text).heading_path)
+            .collect();
+        assert_eq!(
+            paths,
+            [
+                vec!["Guide", "Install", "Linux"],
+                vec!["Guide", "Install", "Linux", "Debian"],
+                vec!["Other"],
+            ]
+        );
+    }
+}
@@ -0,0 +1,117 @@
+//! Stripping with a deadline, for latency-sensitive callers.
+
+use std::error::Error;
+use std::fmt;
+use std::time::Instant;
+
+use crate::StripOptions;
+use crate::scan::codeblocks_for;
+use crate::strip::try_for_each_piece;
+
+/// The error returned by [`strip_with_deadline`] when the deadline passes
+/// before the whole text is stripped.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct DeadlineExceeded {
+    /// The stripped text of the start of the input, up to `processed`.
+    pub partial: String,
+    /// How many bytes of the input were stripped into `partial`.
+    pub processed: usize,
+}
+
+impl fmt::Display for DeadlineExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "deadline exceeded after stripping {} bytes",
+            self.processed
+        )
+    }
+}
+
+impl Error for DeadlineExceeded {}
+
+/// Strips fenced code blocks from markdown text, giving up once `deadline` passes.
+///
+/// This behaves like [`strip_codeblocks`](crate::strip_codeblocks), but the
+/// deadline is checked before stripping each block and before copying each
+/// run of prose, so that enormous or adversarial inputs can't hold up a
+/// request. A deadline that has already passed fails before any work is done.
+///
+/// # Arguments
+///
+/// * `text` - The markdown text containing code blocks to strip
+/// * `deadline` - When to give up
+///
+/// # Returns
+///
+/// The stripped text, or once the deadline passed, the stripped start of
+/// the text in a [`DeadlineExceeded`] error.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::{Duration, Instant};
+///
+/// use strip_codeblocks::strip_with_deadline;
+///
+/// let input = "```sh\nls\n```";
+///
+/// let deadline = Instant::now() + Duration::from_secs(1);
+/// assert_eq!(strip_with_deadline(input, deadline).unwrap(), "ls\n");
+///
+/// let error = strip_with_deadline(input, Instant::now()).unwrap_err();
+/// assert_eq!((error.partial.as_str(), error.processed), ("", 0));
+/// ```
+pub fn strip_with_deadline(text: &str, deadline: Instant) -> Result<String, DeadlineExceeded> {
+    let options = StripOptions::new();
+    let mut output = String::with_capacity(text.len());
+    let mut processed = 0;
+
+    let blocks = codeblocks_for(text, &options);
+    let result = try_for_each_piece(text, blocks, &options, |piece| {
+        if Instant::now() >= deadline {
+            return Err(());
+        }
+        output.push_str(&piece.output);
+        processed = piece.span.end;
+        Ok(())
+    });
+
+    match result {
+        Ok(()) => Ok(output),
+        Err(()) => Err(DeadlineExceeded {
+            partial: output,
+            processed,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::strip_codeblocks;
+
+    #[test]
+    fn test_output_matches_strip_codeblocks() {
+        let input = "a\n```rust\nfn a() {}\n```\nb ```x``` c\n```\nunclosed";
+        let deadline = Instant::now() + Duration::from_secs(60);
+        assert_eq!(
+            strip_with_deadline(input, deadline).unwrap(),
+            strip_codeblocks(input)
+        );
+    }
+
+    #[test]
+    fn test_passed_deadline() {
+        let error = strip_with_deadline("a\n```\nb\n```", Instant::now()).unwrap_err();
+        assert_eq!(error.processed, 0);
+        assert!(error.partial.is_empty());
+        assert_eq!(
+            error.to_string(),
+            "deadline exceeded after stripping 0 bytes"
+        );
+    }
+}
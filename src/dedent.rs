@@ -0,0 +1,310 @@
+//! Removing the indentation of indented and blockquoted fences from their content.
+
+use std::borrow::Cow;
+
+use crate::CodeBlock;
+use crate::experimental::parser::{FOOTNOTE_INDENT, footnote_label};
+
+/// How [`StripOptions::dedent`](crate::StripOptions::dedent) removes the
+/// indentation and blockquote markers of fences from their unwrapped content.
+///
+/// Tabs in the removed indentation advance to the next multiple of
+/// `tab_width` columns, 4 by default, so that code indented with a mix of
+/// tabs and spaces keeps its alignment.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::{Dedent, StripOptions, strip_codeblocks_with};
+///
+/// let options = StripOptions::new().dedent(Dedent::new().tab_width(2));
+///
+/// let text = "\t```py\n\tif x:\n\t\tpass\n\t```";
+/// assert_eq!(strip_codeblocks_with(text, &options), "\tif x:\n  \tpass\n");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(default, deny_unknown_fields)
+)]
+#[non_exhaustive]
+pub struct Dedent {
+    tab_width: usize,
+}
+
+impl Default for Dedent {
+    fn default() -> Self {
+        Self { tab_width: 4 }
+    }
+}
+
+impl Dedent {
+    /// Removes indentation with tabs 4 columns wide.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the width of tabs in columns, at least 1.
+    pub fn tab_width(mut self, tab_width: usize) -> Self {
+        self.tab_width = tab_width.max(1);
+        self
+    }
+
+    /// Returns the column after `c`, found at `column`.
+    fn advance(&self, column: usize, c: char) -> usize {
+        match c {
+            '\t' => column + self.tab_width - column % self.tab_width,
+            _ => column + 1,
+        }
+    }
+
+    /// Returns the content of `block` in `text` without the indentation and
+    /// blockquote markers preceding its opening fence.
+    ///
+    /// The first line goes where the fence stood, after them, unless
+    /// `continued`, and the other lines get them back with tabs expanded, so
+    /// the code stays in its container at the same columns. Blank lines get
+    /// the blockquote markers only.
+    pub(crate) fn apply<'a>(
+        &self,
+        text: &'a str,
+        block: &CodeBlock<'a>,
+        continued: bool,
+    ) -> Cow<'a, str> {
+        let prefix = fence_prefix(text, block);
+        if prefix.is_empty() {
+            return Cow::Borrowed(block.content);
+        }
+
+        let mut expanded = String::with_capacity(prefix.len());
+        let (width, markers) = if footnote_label(prefix).is_some() {
+            // Footnote definitions continue with indented lines
+            expanded.push_str(&" ".repeat(FOOTNOTE_INDENT));
+            (FOOTNOTE_INDENT, 0)
+        } else {
+            let width = prefix.chars().fold(0, |column, c| {
+                let next = self.advance(column, c);
+                match c {
+                    '\t' => expanded.extend(std::iter::repeat_n(' ', next - column)),
+                    _ => expanded.push(c),
+                }
+                next
+            });
+            (width, prefix.matches('>').count())
+        };
+
+        let mut output = String::with_capacity(block.content.len());
+        for (number, line) in block.content.split_inclusive('\n').enumerate() {
+            let rest = self.strip_prefix(line, markers, width);
+            if number > 0 || continued {
+                if rest.trim().is_empty() {
+                    output.push_str(expanded.trim_end());
+                } else {
+                    output.push_str(&expanded);
+                }
+            }
+            if rest.trim().is_empty() {
+                output.push_str(&rest[rest.trim_end().len()..]);
+            } else {
+                output.push_str(&rest);
+            }
+        }
+        Cow::Owned(output)
+    }
+
+    /// Returns `line` without its first `markers` blockquote markers and
+    /// without whitespace up to column `width`. A tab crossing that column
+    /// leaves the spaces past it.
+    fn strip_prefix<'l>(&self, line: &'l str, markers: usize, width: usize) -> Cow<'l, str> {
+        let mut column = 0;
+        let mut rest = line;
+        for _ in 0..markers {
+            let trimmed = rest.trim_start_matches([' ', '\t']);
+            let Some(after) = trimmed.strip_prefix('>') else {
+                break;
+            };
+            column = rest[..rest.len() - trimmed.len()]
+                .chars()
+                .fold(column, |column, c| self.advance(column, c))
+                + 1;
+            rest = after;
+        }
+
+        let mut end = 0;
+        for c in rest.chars() {
+            if column >= width || !matches!(c, ' ' | '\t') {
+                break;
+            }
+            column = self.advance(column, c);
+            end += 1;
+        }
+        match column.saturating_sub(width) {
+            0 => Cow::Borrowed(&rest[end..]),
+            spaces => Cow::Owned(" ".repeat(spaces) + &rest[end..]),
+        }
+    }
+}
+
+/// Returns what precedes the opening fence of `block` on its line in `text`,
+/// if it only holds indentation and blockquote markers, or a footnote label,
+/// or else nothing.
+fn fence_prefix<'a>(text: &'a str, block: &CodeBlock<'_>) -> &'a str {
+    let line_start = text[..block.span.start]
+        .rfind('\n')
+        .map_or(0, |newline| newline + 1);
+    let line = &text[line_start..block.content_span.start];
+    let label = footnote_label(line).unwrap_or(0);
+    match line[label..].find(['`', '~']).map(|end| label + end) {
+        Some(end)
+            if line[label..end]
+                .chars()
+                .all(|c| matches!(c, ' ' | '\t') || c == '>' && label == 0) =>
+        {
+            &line[..end]
+        }
+        _ => "",
+    }
+}
+
+/// Returns the number of spaces indenting the opening fence of `block` in
+/// `text`, the indentation of footnote definitions if the fence follows a
+/// footnote label, or 0 if the fence doesn't start its line.
+fn fence_indent(text: &str, block: &CodeBlock<'_>) -> usize {
+    let line_start = text[..block.span.start]
+        .rfind('\n')
+        .map_or(0, |newline| newline + 1);
+    let line = &text[line_start..block.content_span.start];
+    if let Some(label) = footnote_label(line)
+        && line[label..]
+            .trim_start_matches([' ', '\t'])
+            .starts_with(['`', '~'])
+    {
+        return FOOTNOTE_INDENT;
+    }
+    let fence = line.trim_start_matches(' ');
+    if fence.starts_with(['`', '~']) {
+        line.len() - fence.len()
+    } else {
+        0
+    }
+}
+
+/// Returns `content` with up to `indent` leading spaces removed from each line.
+fn dedent(content: &str, indent: usize) -> Cow<'_, str> {
+    if indent == 0
+        || !content
+            .split_inclusive('\n')
+            .any(|line| line.starts_with(' '))
+    {
+        return Cow::Borrowed(content);
+    }
+
+    let mut output = String::with_capacity(content.len());
+    for line in content.split_inclusive('\n') {
+        let spaces = line.len() - line.trim_start_matches(' ').len();
+        output.push_str(&line[spaces.min(indent)..]);
+    }
+    Cow::Owned(output)
+}
+
+impl<'a> CodeBlock<'a> {
+    /// Returns the content of the block in `text`, the document it was found
+    /// in, without the indentation of its fences.
+    ///
+    /// When the opening fence is indented, as in a list item, its content is
+    /// usually indented as much, and that indentation isn't part of the code.
+    /// As in CommonMark, each line loses as many leading spaces as precede the
+    /// opening fence, or all it has if it has fewer. Lines indented further
+    /// keep their extra indentation, so the code keeps its alignment.
+    ///
+    /// The content is borrowed from `text`, without copying, unless
+    /// indentation is removed, so extracting the blocks of a corpus only
+    /// allocates for indented blocks. [`content`](Self::content) always
+    /// borrows the content exactly as it appears.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::borrow::Cow;
+    ///
+    /// use strip_codeblocks::extract_codeblocks;
+    ///
+    /// let text = "1. Build:\n   ```sh\n   make \\\n     -j4\n   ```\n```\nls\n```";
+    /// let blocks = extract_codeblocks(text);
+    ///
+    /// assert_eq!(blocks[0].dedented_content(text), "make \\\n  -j4\n");
+    /// assert!(matches!(blocks[1].dedented_content(text), Cow::Borrowed("ls\n")));
+    /// ```
+    pub fn dedented_content(&self, text: &'a str) -> Cow<'a, str> {
+        dedent(self.content, fence_indent(text, self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::experimental::{Engine, extract_codeblocks_with};
+    use crate::extract_codeblocks;
+
+    #[test]
+    fn test_fence_indent() {
+        let text = "  ```\n  a\n  ```\nRun: ```sh\nb\n```";
+        let blocks = extract_codeblocks(text);
+        assert_eq!(fence_indent(text, &blocks[0]), 2);
+        assert_eq!(fence_indent(text, &blocks[1]), 0);
+
+        let blocks = extract_codeblocks_with(text, Engine::Parser);
+        assert_eq!(fence_indent(text, &blocks[0]), 2);
+    }
+
+    #[test]
+    fn test_fence_prefix() {
+        let text = "> \t```\nx\n```\nRun: ```sh\nb\n```";
+        let blocks = extract_codeblocks(text);
+        assert_eq!(fence_prefix(text, &blocks[0]), "> \t");
+        assert_eq!(fence_prefix(text, &blocks[1]), "");
+
+        let text = "[^1]: ```\n    x\n    ```\n[^2]: > ```\ny\n```";
+        let blocks = extract_codeblocks(text);
+        assert_eq!(fence_prefix(text, &blocks[0]), "[^1]: ");
+        assert_eq!(fence_indent(text, &blocks[0]), 4);
+        assert_eq!(fence_prefix(text, &blocks[1]), "");
+    }
+
+    #[test]
+    fn test_strip_prefix() {
+        let dedent = Dedent::new();
+        assert_eq!(dedent.strip_prefix("> > x\n", 1, 2), "> x\n");
+        assert_eq!(dedent.strip_prefix("\t\tx", 0, 2), "  \tx");
+        assert_eq!(dedent.strip_prefix("  \t x", 0, 4), " x");
+        assert_eq!(dedent.strip_prefix("lazy", 1, 2), "lazy");
+    }
+
+    #[test]
+    fn test_apply_keeps_alignment() {
+        let text = "1. a\n   ```sh\n   ls\n\n     -l\n   ```\n";
+        let block = &extract_codeblocks(text)[0];
+        assert_eq!(Dedent::new().apply(text, block, false), "ls\n\n     -l\n");
+        assert_eq!(Dedent::new().apply(text, block, true), "   ls\n\n     -l\n");
+
+        let text = "> ```\n> a\n>\n>  b\n> ```";
+        let block = &extract_codeblocks(text)[0];
+        assert_eq!(Dedent::new().apply(text, block, false), "a\n>\n>  b\n>");
+
+        let text = "[^1]: ```py\n    if x:\n        pass\n    ```\n";
+        let block = &extract_codeblocks_with(text, Engine::Parser)[0];
+        assert_eq!(
+            Dedent::new().apply(text, block, false),
+            "if x:\n        pass\n"
+        );
+    }
+
+    #[test]
+    fn test_dedent() {
+        assert_eq!(dedent("  a\n b\n    c\nd", 2), "a\nb\n  c\nd");
+        assert_eq!(dedent("\ta\n", 2), "\ta\n");
+        assert!(matches!(dedent("a\n  b\n", 0), Cow::Borrowed(_)));
+        assert!(matches!(dedent("a\nb\n", 2), Cow::Borrowed(_)));
+    }
+}
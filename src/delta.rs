@@ -0,0 +1,304 @@
+//! Incremental stripping of streamed text.
+
+use crate::block::{info_words, trim_info};
+use crate::content::FirstLineFilter;
+use crate::newline::with_newlines;
+use crate::{Action, StripOptions, trace};
+
+/// Strips fenced code blocks from text that arrives in pieces, e.g. the deltas
+/// of a streamed chat completion.
+///
+/// Each call to [`push`](Self::push) returns the stripped text that has become
+/// safe to display. Characters that might belong to a fence (trailing
+/// backticks, or an opening fence line whose end hasn't arrived yet) are
+/// withheld until the next delta tells what they are, so fences never flash on
+/// screen. Block content is released as it streams in.
+///
+/// Concatenating the outputs of every [`push`](Self::push) and of
+/// [`finish`](Self::finish) gives the same result as
+/// [`strip_codeblocks_with`](crate::strip_codeblocks_with) on the whole text,
+/// except that:
+///
+/// - Blocks are always found with the default [`Engine::Regex`](crate::experimental::Engine::Regex).
+/// - Fence-like backticks in GFM table rows are treated as fences.
+/// - With [`Newline::Auto`](crate::Newline::Auto), inserted line breaks take
+///   the line ending of the opening fence line of their block, since the rest
+///   of the document isn't known yet.
+/// - A block still open when the stream ends is treated as closed, since its
+///   content has already been displayed.
+/// - Only the language filters and action options are applied; options
+///   transforming the content of unwrapped blocks (such as
+///   [`StripOptions::strip_prompts`]) need the whole block and are not, and
+///   neither are [`StripOptions::preserve_whitespace`] and [`StripOptions::ansi`].
+///   Nor are options that need more of the document than has arrived, or that
+///   look past fenced blocks: [`select`](StripOptions::select),
+///   [`scope`](StripOptions::scope), [`html`](StripOptions::html),
+///   [`require_blank_line_before_fence`](StripOptions::require_blank_line_before_fence),
+///   [`render_safety`](StripOptions::render_safety),
+///   [`strip_comments`](StripOptions::strip_comments),
+///   [`trailing_newline`](StripOptions::trailing_newline),
+///   [`merge_adjacent`](StripOptions::merge_adjacent),
+///   [`hash_placeholders`](StripOptions::hash_placeholders), since placeholders
+///   are emitted before the content is known,
+///   [`region_markers`](StripOptions::region_markers),
+///   [`math`](StripOptions::math) for display math in prose,
+///   [`shrink`](StripOptions::shrink) and [`dedent`](StripOptions::dedent).
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::DeltaStripper;
+///
+/// let mut stripper = DeltaStripper::new();
+/// let mut displayed = String::new();
+///
+/// for delta in ["Here:\n`", "``rust\nfn ma", "in() {}\n``", "`\nDone."] {
+///     displayed.push_str(&stripper.push(delta));
+///     assert!(!displayed.contains("```"));
+/// }
+/// displayed.push_str(&stripper.finish());
+///
+/// assert_eq!(displayed, "Here:\nfn main() {}\n\nDone.");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct DeltaStripper {
+    options: StripOptions,
+    /// Received text that hasn't been released yet.
+    pending: String,
+    /// The action of the block being streamed, or `None` in prose.
+    block: Option<Action>,
+    /// The line ending of the opening fence line of the block being streamed.
+    line_ending: &'static str,
+    /// The first line of the block being streamed, for [`Action::FirstLine`].
+    first_line: FirstLineFilter,
+}
+
+impl DeltaStripper {
+    /// Creates a stripper with the default behavior of [`strip_codeblocks`](crate::strip_codeblocks).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a stripper applying `options`' language filters and action.
+    pub fn with_options(options: StripOptions) -> Self {
+        Self {
+            options,
+            ..Self::default()
+        }
+    }
+
+    /// Feeds the next piece of text, returning the newly displayable stripped text.
+    pub fn push(&mut self, delta: &str) -> String {
+        self.pending.push_str(delta);
+        let mut output = String::new();
+
+        loop {
+            if self.block.is_some() {
+                if !self.release_content(&mut output) {
+                    break;
+                }
+            } else if !self.release_prose(&mut output) {
+                break;
+            }
+        }
+
+        output
+    }
+
+    /// Ends the stream, returning whatever text was still withheld.
+    pub fn finish(mut self) -> String {
+        if self.block.is_some() {
+            trace::debug!("stream ended inside a code block, treated as closed");
+        }
+        match &self.block {
+            // An unterminated opening fence line is just prose
+            None => self.pending,
+            Some(Action::Unwrap | Action::Keep) => self.pending,
+            Some(Action::FirstLine(_)) => {
+                let mut output = String::new();
+                self.show(self.pending.len(), &mut output);
+                self.close(&mut output);
+                output
+            }
+            Some(_) => String::new(),
+        }
+    }
+
+    /// Releases prose up to the next fence. Returns whether a block was opened.
+    fn release_prose(&mut self, output: &mut String) -> bool {
+        let Some(start) = self.pending.find("```") else {
+            // Trailing backticks might be the start of a fence
+            let safe = safe_len(&self.pending);
+            output.push_str(&self.pending[..safe]);
+            self.pending.drain(..safe);
+            return false;
+        };
+
+        output.push_str(&self.pending[..start]);
+        self.pending.drain(..start);
+
+        let info_start = 3;
+        match self.pending[info_start..].find(['\n', '`']) {
+            // The opening fence line isn't complete yet
+            None => false,
+            // Backticks can't appear in the info string, so this isn't a fence
+            // after all: release a backtick and look again from the next one
+            Some(offset) if self.pending.as_bytes()[info_start + offset] == b'`' => {
+                output.push('`');
+                self.pending.drain(..1);
+                true
+            }
+            Some(offset) => {
+                let newline = info_start + offset;
+                let info = trim_info(&self.pending[info_start..newline]);
+                let action = self
+                    .options
+                    .action_for_language(info_words(info).next())
+                    .clone();
+                self.line_ending = self.options.newline.resolve(&self.pending[..=newline]);
+
+                match &action {
+                    Action::Keep => output.push_str(&self.pending[..=newline]),
+                    Action::Placeholder(placeholder) => {
+                        output.push_str(&with_newlines(placeholder, self.line_ending));
+                    }
+                    _ => {}
+                }
+
+                self.pending.drain(..=newline);
+                self.block = Some(action);
+                self.first_line = FirstLineFilter::default();
+                true
+            }
+        }
+    }
+
+    /// Releases block content up to the closing fence. Returns whether the block was closed.
+    fn release_content(&mut self, output: &mut String) -> bool {
+        let Some(end) = self.pending.find("```") else {
+            // Trailing backticks might be the start of the closing fence
+            let safe = safe_len(&self.pending);
+            self.show(safe, output);
+            self.pending.drain(..safe);
+            return false;
+        };
+
+        self.show(end, output);
+        self.close(output);
+        self.pending.drain(..end + 3);
+        self.block = None;
+        true
+    }
+
+    /// Releases the first `len` bytes of pending block content, as far as the block's action shows them.
+    fn show(&mut self, len: usize, output: &mut String) {
+        match &self.block {
+            Some(Action::Unwrap | Action::Keep) => output.push_str(&self.pending[..len]),
+            Some(Action::FirstLine(_)) => {
+                output.push_str(self.first_line.feed(&self.pending[..len]))
+            }
+            _ => {}
+        }
+    }
+
+    /// Releases what follows the content of the block being closed.
+    fn close(&self, output: &mut String) {
+        match &self.block {
+            Some(Action::Keep) => output.push_str("```"),
+            Some(Action::FirstLine(marker))
+                if self.first_line.truncated() && !marker.is_empty() =>
+            {
+                output.push_str(marker);
+                output.push_str(self.line_ending);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Returns the length of the prefix of `text` that can't be part of a fence
+/// starting at its end, i.e. `text` without up to two trailing backticks.
+fn safe_len(text: &str) -> usize {
+    let backticks = text.len() - text.trim_end_matches('`').len();
+    text.len() - backticks.min(2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strip_codeblocks_with;
+
+    const INPUTS: &[&str] = &[
+        "```rust\nfn main() {}\n```",
+        "Before\n```rust\ncode here\n```\nAfter",
+        "Text with `inline` and ```\nblock code\n```",
+        "```rust\nfn a() {}\n```\n```python\nprint('b')\n```",
+        "More ``double backticks`` and ````\nfour\n```` fences",
+        "```\n\n```",
+        "Unterminated ```rust fence line",
+        "Ünïcödé ```\nça\n``` fin",
+        "Blank info ``` \t\r\npython\r\n```",
+    ];
+
+    fn stream(stripper: &mut DeltaStripper, input: &str, step: usize) -> String {
+        let chars: Vec<char> = input.chars().collect();
+        let mut output = String::new();
+        for chunk in chars.chunks(step) {
+            output.push_str(&stripper.push(&chunk.iter().collect::<String>()));
+        }
+        output
+    }
+
+    #[test]
+    fn test_streaming_matches_strip() {
+        for options in [
+            StripOptions::new(),
+            StripOptions::new().action(Action::Remove),
+            StripOptions::new().action(Action::Placeholder("[code]".into())),
+            StripOptions::new().action(Action::FirstLine("…".into())),
+            StripOptions::new().languages(["python"]),
+        ] {
+            for input in INPUTS {
+                for step in [1, 2, 3, 7, 100] {
+                    let mut stripper = DeltaStripper::with_options(options.clone());
+                    let mut output = stream(&mut stripper, input, step);
+                    output.push_str(&stripper.finish());
+                    assert_eq!(
+                        output,
+                        strip_codeblocks_with(input, &options),
+                        "{input:?} / {step}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_fences_are_never_displayed() {
+        let mut stripper = DeltaStripper::new();
+        let mut displayed = String::new();
+        for delta in ["Look: `", "`", "`", "py", "thon\nx = 1\n`", "`", "`", " ok"] {
+            displayed.push_str(&stripper.push(delta));
+            assert!(!displayed.contains('`'), "{displayed:?}");
+        }
+        assert_eq!(displayed, "Look: x = 1\n ok");
+    }
+
+    #[test]
+    fn test_unclosed_block_at_end_of_stream() {
+        let mut stripper = DeltaStripper::new();
+        let mut output = stripper.push("```sh\nls -la\n``");
+        assert_eq!(output, "ls -la\n");
+        output.push_str(&stripper.finish());
+        assert_eq!(output, "ls -la\n``");
+    }
+
+    #[test]
+    fn test_placeholder_line_endings() {
+        let options = StripOptions::new().action(Action::Placeholder("[code]\n".into()));
+        let mut stripper = DeltaStripper::with_options(options);
+        let mut output = stripper.push("Intro\r\n```sh\r\nls\r\n```\r\n");
+        output.push_str(&stripper.finish());
+        assert_eq!(output, "Intro\r\n[code]\r\n\r\n");
+    }
+}
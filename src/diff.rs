@@ -0,0 +1,382 @@
+//! Line-based diffs between a document and its stripped output.
+
+use std::fmt;
+use std::ops::Range;
+
+use crate::StripOptions;
+use crate::strip::for_each_piece;
+
+/// A line of a [`DiffHunk`], without its line ending.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum DiffLine<'a> {
+    /// A line found in both the original text and the output.
+    Unchanged(&'a str),
+    /// A line of the original text missing from the output.
+    Removed(&'a str),
+    /// A line of the output missing from the original text.
+    Added(String),
+}
+
+/// A run of lines stripping changed, as returned by [`diff_strip`].
+///
+/// A hunk covers the whole lines of one or more neighboring pieces stripping
+/// changed, such as a block and the prose sharing its lines. Within a hunk,
+/// lines kept by stripping, like the content of an unwrapped block, are
+/// [`Unchanged`](DiffLine::Unchanged).
+///
+/// Hunks display as unified diff hunks.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct DiffHunk<'a> {
+    /// The line of the original text the hunk starts at, from 1.
+    pub old_start: usize,
+    /// The line of the output the hunk starts at, from 1.
+    pub new_start: usize,
+    /// The lines of the hunk, in order.
+    pub lines: Vec<DiffLine<'a>>,
+}
+
+impl DiffHunk<'_> {
+    /// Returns the number of lines of the original text the hunk covers.
+    pub fn old_len(&self) -> usize {
+        self.lines
+            .iter()
+            .filter(|line| !matches!(line, DiffLine::Added(_)))
+            .count()
+    }
+
+    /// Returns the number of lines of the output the hunk covers.
+    pub fn new_len(&self) -> usize {
+        self.lines
+            .iter()
+            .filter(|line| !matches!(line, DiffLine::Removed(_)))
+            .count()
+    }
+}
+
+impl fmt::Display for DiffHunk<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Empty ranges start at the line before them in unified diffs
+        let range = |start: usize, len: usize| match len {
+            0 => format!("{},0", start - 1),
+            1 => start.to_string(),
+            _ => format!("{start},{len}"),
+        };
+        writeln!(
+            f,
+            "@@ -{} +{} @@",
+            range(self.old_start, self.old_len()),
+            range(self.new_start, self.new_len())
+        )?;
+
+        for line in &self.lines {
+            match line {
+                DiffLine::Unchanged(line) => writeln!(f, " {line}")?,
+                DiffLine::Removed(line) => writeln!(f, "-{line}")?,
+                DiffLine::Added(line) => writeln!(f, "+{line}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Describes the lines [`strip_codeblocks`](crate::strip_codeblocks) removes
+/// or alters in markdown text.
+///
+/// This is [`diff_strip_with`] with the default options.
+///
+/// # Arguments
+///
+/// * `text` - The markdown text containing code blocks to strip
+///
+/// # Returns
+///
+/// The hunks of lines changed by stripping, in document order.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::{DiffLine, diff_strip};
+///
+/// let hunks = diff_strip("Intro\n```sh\nls\n```\nOutro");
+///
+/// assert_eq!(hunks.len(), 1);
+/// assert_eq!(hunks[0].lines[0], DiffLine::Removed("```sh"));
+/// assert_eq!(hunks[0].lines[1], DiffLine::Unchanged("ls"));
+/// assert_eq!(
+///     hunks[0].to_string(),
+///     "@@ -2,3 +2,2 @@\n-```sh\n ls\n-```\n+\n"
+/// );
+/// ```
+pub fn diff_strip(text: &str) -> Vec<DiffHunk<'_>> {
+    diff_strip_with(text, &StripOptions::new())
+}
+
+/// Describes the lines [`strip_codeblocks_with`](crate::strip_codeblocks_with)
+/// removes or alters in markdown text with `options`.
+///
+/// The hunks come from the stripping pass itself, so they point at exactly
+/// the lines stripping changed, and never at lines it merely shifted.
+///
+/// # Arguments
+///
+/// * `text` - The markdown text containing code blocks to strip
+/// * `options` - Which blocks are stripped and how
+///
+/// # Returns
+///
+/// The hunks of lines changed by stripping, in document order.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::{Action, StripOptions, diff_strip_with};
+///
+/// let options = StripOptions::new().action(Action::Placeholder("[code]".into()));
+/// let hunks = diff_strip_with("# Title\n\n```rust\nfn main() {}\n```\n", &options);
+///
+/// assert_eq!(
+///     hunks[0].to_string(),
+///     "@@ -3,3 +3 @@\n-```rust\n-fn main() {}\n-```\n+[code]\n"
+/// );
+/// ```
+pub fn diff_strip_with<'a>(text: &'a str, options: &StripOptions) -> Vec<DiffHunk<'a>> {
+    let mut output = String::with_capacity(text.len());
+    // Each piece, by its span in `text`, its start in `output` and whether it changed
+    let mut pieces: Vec<(Range<usize>, usize, bool)> = Vec::new();
+    // Whole lines of `text` covering the changed pieces, neighbors merged
+    let mut regions: Vec<Range<usize>> = Vec::new();
+
+    for_each_piece(text, options, |piece| {
+        let changed = piece.output != text[piece.span.clone()];
+        if changed {
+            let start = line_start(text, piece.span.start);
+            let end = line_end(text, piece.span.end);
+            match regions.last_mut() {
+                Some(last) if start <= last.end => last.end = last.end.max(end),
+                _ => regions.push(start..end),
+            }
+        }
+        pieces.push((piece.span, output.len(), changed));
+        output.push_str(&piece.output);
+    });
+
+    // Regions only start and end in unchanged pieces or at their boundaries,
+    // where offsets in `text` map to offsets in `output`
+    let output_offset = |offset: usize| {
+        let index = pieces.partition_point(|(span, ..)| span.start <= offset);
+        match index.checked_sub(1).map(|index| &pieces[index]) {
+            Some((span, start, changed)) if offset < span.end => {
+                debug_assert!(!changed || offset == span.start);
+                start + (offset - span.start)
+            }
+            _ => output.len(),
+        }
+    };
+
+    let mut old_lines = LineCounter::default();
+    let mut new_lines = LineCounter::default();
+    regions
+        .into_iter()
+        .map(|region| {
+            let new = output_offset(region.start)..output_offset(region.end);
+            let removed: Vec<&str> = text[region.clone()].lines().collect();
+            let added: Vec<&str> = output[new.clone()].lines().collect();
+            DiffHunk {
+                old_start: old_lines.line_at(text, region.start),
+                new_start: new_lines.line_at(&output, new.start),
+                lines: align(&removed, &added),
+            }
+        })
+        .collect()
+}
+
+/// Returns the start of the line containing `offset`.
+fn line_start(text: &str, offset: usize) -> usize {
+    text[..offset].rfind('\n').map_or(0, |newline| newline + 1)
+}
+
+/// Returns the end of the line ending at or after `offset`, line break included.
+fn line_end(text: &str, offset: usize) -> usize {
+    if text[..offset].ends_with('\n') {
+        return offset;
+    }
+    text[offset..]
+        .find('\n')
+        .map_or(text.len(), |newline| offset + newline + 1)
+}
+
+/// Counts lines up to increasing offsets of a text.
+#[derive(Default)]
+struct LineCounter {
+    offset: usize,
+    lines: usize,
+}
+
+impl LineCounter {
+    /// Returns the line of `text` containing `offset`, from 1.
+    fn line_at(&mut self, text: &str, offset: usize) -> usize {
+        self.lines += text[self.offset..offset].matches('\n').count();
+        self.offset = offset;
+        self.lines + 1
+    }
+}
+
+/// The most edits [`align`] looks for before giving up on aligning lines.
+const MAX_EDITS: usize = 256;
+
+/// Aligns the `removed` lines with the `added` lines, finding the lines they
+/// have in common with Myers' algorithm.
+///
+/// Hunks needing more than [`MAX_EDITS`] edits, such as removed blocks, are
+/// described as all their lines removed then all the new ones added.
+fn align<'a>(removed: &[&'a str], added: &[&str]) -> Vec<DiffLine<'a>> {
+    let replaced = || {
+        let removed = removed.iter().copied().map(DiffLine::Removed);
+        removed
+            .chain(added.iter().map(|line| DiffLine::Added(line.to_string())))
+            .collect()
+    };
+
+    let (n, m) = (removed.len() as isize, added.len() as isize);
+    let max = (removed.len() + added.len()).min(MAX_EDITS);
+    let offset = max as isize + 1;
+    // The furthest line of `removed` reached on each diagonal, per number of edits
+    let mut furthest = vec![0isize; 2 * max + 3];
+    let mut trace = Vec::new();
+
+    for edits in 0..=max as isize {
+        trace.push(furthest.clone());
+        for diagonal in (-edits..=edits).step_by(2) {
+            let at = |diagonal: isize| furthest[(diagonal + offset) as usize];
+            let mut x = if diagonal == -edits
+                || (diagonal != edits && at(diagonal - 1) < at(diagonal + 1))
+            {
+                at(diagonal + 1)
+            } else {
+                at(diagonal - 1) + 1
+            };
+            let mut y = x - diagonal;
+            while x < n && y < m && removed[x as usize] == added[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            furthest[(diagonal + offset) as usize] = x;
+
+            if x >= n && y >= m {
+                return backtrack(&trace, offset, removed, added);
+            }
+        }
+    }
+
+    replaced()
+}
+
+/// Recovers the lines of the shortest edit script found by [`align`] from
+/// the furthest points it reached after each number of edits.
+fn backtrack<'a>(
+    trace: &[Vec<isize>],
+    offset: isize,
+    removed: &[&'a str],
+    added: &[&str],
+) -> Vec<DiffLine<'a>> {
+    let mut lines = Vec::with_capacity(removed.len() + added.len());
+    let (mut x, mut y) = (removed.len() as isize, added.len() as isize);
+
+    for (edits, furthest) in trace.iter().enumerate().rev() {
+        let edits = edits as isize;
+        let at = |diagonal: isize| furthest[(diagonal + offset) as usize];
+        let diagonal = x - y;
+        let previous =
+            if diagonal == -edits || (diagonal != edits && at(diagonal - 1) < at(diagonal + 1)) {
+                diagonal + 1
+            } else {
+                diagonal - 1
+            };
+        let previous_x = at(previous);
+        let previous_y = previous_x - previous;
+
+        while x > previous_x && y > previous_y {
+            x -= 1;
+            y -= 1;
+            lines.push(DiffLine::Unchanged(removed[x as usize]));
+        }
+        if edits > 0 {
+            if x == previous_x {
+                lines.push(DiffLine::Added(added[previous_y as usize].to_string()));
+            } else {
+                lines.push(DiffLine::Removed(removed[previous_x as usize]));
+            }
+        }
+        x = previous_x;
+        y = previous_y;
+    }
+
+    lines.reverse();
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Action;
+
+    #[test]
+    fn test_unchanged_documents_have_no_hunks() {
+        assert!(diff_strip("No code, only `inline` code.\n").is_empty());
+        let kept = StripOptions::new().languages(["rust"]);
+        assert!(diff_strip_with("```sh\nls\n```", &kept).is_empty());
+    }
+
+    #[test]
+    fn test_hunks_follow_shifted_lines() {
+        let input = "a\n```\nb\n```\nc\nd\n```sh\ne\n```\n";
+        let hunks = diff_strip(input);
+
+        assert_eq!(hunks.len(), 2);
+        assert_eq!((hunks[0].old_start, hunks[0].new_start), (2, 2));
+        // The first block lost a line, its fence lines but a blank line
+        assert_eq!((hunks[1].old_start, hunks[1].new_start), (7, 6));
+        assert_eq!(
+            hunks[1].lines,
+            [
+                DiffLine::Removed("```sh"),
+                DiffLine::Unchanged("e"),
+                DiffLine::Removed("```"),
+                DiffLine::Added(String::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_blocks_sharing_lines_make_one_hunk() {
+        let input = "Run ```sh\nls\n``` and ```sh\npwd\n```!\nDone";
+        let hunks = diff_strip(input);
+
+        assert_eq!(hunks.len(), 1);
+        assert_eq!(hunks[0].old_len(), 5);
+        assert_eq!(
+            hunks[0].to_string(),
+            "@@ -1,5 +1,3 @@\n-Run ```sh\n-ls\n-``` and ```sh\n-pwd\n-```!\n+Run ls\n+ and pwd\n+!\n"
+        );
+    }
+
+    #[test]
+    fn test_removed_blocks() {
+        let content = "line\n".repeat(300);
+        let input = format!("Before\n```\n{content}```\nAfter");
+        let options = StripOptions::new()
+            .action(Action::Remove)
+            .preserve_whitespace(true);
+        let hunks = diff_strip_with(&input, &options);
+
+        assert_eq!(hunks.len(), 1);
+        assert_eq!(hunks[0].old_len(), 302);
+        assert_eq!(hunks[0].new_len(), 0);
+        assert_eq!(
+            hunks[0].to_string().lines().next(),
+            Some("@@ -2,302 +1,0 @@")
+        );
+    }
+}
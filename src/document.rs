@@ -0,0 +1,235 @@
+//! The [`Document`] type: a markdown text split into segments.
+
+use std::borrow::Cow;
+use std::fmt::{self, Write};
+
+use crate::{CodeBlock, FencePolicy, Segment, segments};
+
+/// A markdown document, split once into prose and code [`Segment`]s.
+///
+/// Documents are created from text with [`Document::new`] or `.into()`, and
+/// display as the exact text they were created from.
+///
+/// They can also be built from segments, e.g. generated prose and code, with
+/// `.into()` or `collect()`. Their text is then markdown rendered from the
+/// segments, code blocks fenced as [`CodeBlock`] displays them on lines of
+/// their own, and the segments are kept as given. [`from_segments`](Self::from_segments)
+/// chooses the fences with a [`FencePolicy`] instead. Going the other way,
+/// [`into_segments`](Self::into_segments) hands the segments back for editing.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::Document;
+///
+/// let doc: Document = "Intro\n```rust\nfn main() {}\n```\nOutro".into();
+///
+/// assert_eq!(format!("{doc}"), doc.text());
+/// assert_eq!(doc.codeblocks().count(), 1);
+///
+/// let segments: Vec<String> = doc.segments().map(ToString::to_string).collect();
+/// assert_eq!(segments, ["Intro\n", "```rust\nfn main() {}\n```", "\nOutro"]);
+/// ```
+///
+/// Editing a document's segments and rendering it back:
+///
+/// ```
+/// use strip_codeblocks::{CodeBlock, Document, Segment};
+///
+/// let doc = Document::new("Run:\n```sh\nls\n```\n");
+/// let mut segments = doc.into_segments();
+/// segments.push(Segment::Prose("Then:"));
+/// segments.push(CodeBlock::new("sh", "make\n").into());
+///
+/// let edited: Document = segments.into();
+/// assert_eq!(edited.text(), "Run:\n```sh\nls\n```\nThen:\n```sh\nmake\n```");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Document<'a> {
+    text: Cow<'a, str>,
+    segments: Vec<Segment<'a>>,
+}
+
+impl<'a> Document<'a> {
+    /// Splits `text` into segments.
+    pub fn new(text: &'a str) -> Self {
+        Self {
+            text: Cow::Borrowed(text),
+            segments: segments(text),
+        }
+    }
+
+    /// Returns the text of the document.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns the segments of the document, in document order.
+    pub fn segments(&self) -> std::slice::Iter<'_, Segment<'a>> {
+        self.segments.iter()
+    }
+
+    /// Returns the code blocks of the document, in document order.
+    pub fn codeblocks(&self) -> impl Iterator<Item = &CodeBlock<'a>> {
+        self.segments.iter().filter_map(|segment| match segment {
+            Segment::Code(block) => Some(block),
+            Segment::Prose(_) => None,
+        })
+    }
+
+    /// Returns the segments of the document, for editing or rebuilding it.
+    pub fn into_segments(self) -> Vec<Segment<'a>> {
+        self.segments
+    }
+
+    /// Builds a document from `segments`, like `.into()`, fencing its code
+    /// blocks as `policy` says.
+    ///
+    /// With [`FencePolicy::PreserveOriginal`], blocks keep the fences they were
+    /// found behind, such as the tilde fences and longer fences of the
+    /// [parser](crate::experimental::Engine::Parser) engine, so editing some
+    /// blocks of a document leaves the others as they were written.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strip_codeblocks::experimental::{Engine, extract_codeblocks_with};
+    /// use strip_codeblocks::{Document, FencePolicy};
+    ///
+    /// let text = "~~~~md\n```\nnested\n```\n~~~~";
+    /// let block = extract_codeblocks_with(text, Engine::Parser).remove(0);
+    ///
+    /// let doc = Document::from_segments(vec![block.clone().into()], FencePolicy::PreserveOriginal);
+    /// assert_eq!(doc.text(), text);
+    ///
+    /// let doc = Document::from_segments(vec![block.into()], FencePolicy::Minimal);
+    /// assert_eq!(doc.text(), "````md\n```\nnested\n```\n````");
+    /// ```
+    pub fn from_segments(segments: Vec<Segment<'a>>, policy: FencePolicy) -> Self {
+        Self {
+            text: Cow::Owned(render(&segments, policy)),
+            segments,
+        }
+    }
+}
+
+/// Renders `segments` as markdown, putting every code block on lines of its
+/// own in fences chosen by `policy`.
+fn render(segments: &[Segment<'_>], policy: FencePolicy) -> String {
+    let mut text = String::new();
+
+    for (index, segment) in segments.iter().enumerate() {
+        if let Segment::Code(block) = segment {
+            if !text.is_empty() && !text.ends_with('\n') {
+                text.push('\n');
+            }
+            text.push_str(&policy.render(block));
+
+            let next_on_own_line = match segments.get(index + 1) {
+                Some(Segment::Prose(prose)) => prose.is_empty() || prose.starts_with(['\n', '\r']),
+                Some(Segment::Code(_)) => false,
+                None => true,
+            };
+            if !next_on_own_line {
+                text.push('\n');
+            }
+        } else {
+            write!(text, "{segment}").expect("writing to a String never fails");
+        }
+    }
+
+    text
+}
+
+impl<'a> From<&'a str> for Document<'a> {
+    fn from(text: &'a str) -> Self {
+        Self::new(text)
+    }
+}
+
+impl<'a> From<Vec<Segment<'a>>> for Document<'a> {
+    fn from(segments: Vec<Segment<'a>>) -> Self {
+        Self::from_segments(segments, FencePolicy::Minimal)
+    }
+}
+
+impl<'a> FromIterator<Segment<'a>> for Document<'a> {
+    fn from_iter<I: IntoIterator<Item = Segment<'a>>>(segments: I) -> Self {
+        segments.into_iter().collect::<Vec<_>>().into()
+    }
+}
+
+impl<'a> From<Document<'a>> for Vec<Segment<'a>> {
+    fn from(document: Document<'a>) -> Self {
+        document.segments
+    }
+}
+
+impl fmt::Display for Document<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_document_segments_match_segments() {
+        let input = "a\n```\nb\n```\nc";
+        let doc = Document::from(input);
+        assert!(doc.segments().eq(segments(input).iter()));
+        assert_eq!(doc.to_string(), input);
+    }
+
+    #[test]
+    fn test_segments_round_trip() {
+        let input = "# Title\n\n```rust\nfn a() {}\n```\n\nText\n```\nb\n```";
+        let rebuilt: Document = Document::new(input).into_segments().into();
+        assert_eq!(rebuilt.text(), input);
+    }
+
+    #[test]
+    fn test_blocks_are_rendered_on_lines_of_their_own() {
+        let doc: Document = [
+            Segment::Prose("Intro:"),
+            CodeBlock::new("py", "a = 1").into(),
+            CodeBlock::new("", "b\n").into(),
+            Segment::Prose("Outro"),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(doc.text(), "Intro:\n```py\na = 1\n```\n```\nb\n```\nOutro");
+        assert_eq!(doc.codeblocks().count(), 2);
+
+        // The rendered text parses back into the same blocks
+        let reparsed = Document::new(doc.text());
+        let contents: Vec<_> = reparsed.codeblocks().map(|block| block.content).collect();
+        assert_eq!(contents, ["a = 1\n", "b\n"]);
+    }
+
+    #[test]
+    fn test_fence_policies_stay_safe() {
+        let text = "```sh\nls\n```\n";
+        let mut segments = Document::new(text).into_segments();
+        let round_trip = Document::from_segments(segments.clone(), FencePolicy::PreserveOriginal);
+        assert_eq!(round_trip.text(), text);
+
+        // Edited content that would close the original fence gets a longer one
+        if let Segment::Code(block) = &mut segments[0] {
+            block.content = "```\n";
+        }
+        for policy in [FencePolicy::PreserveOriginal, FencePolicy::Fixed(2)] {
+            let doc = Document::from_segments(segments.clone(), policy);
+            assert_eq!(doc.text(), "````sh\n```\n````\n");
+        }
+    }
+
+    #[test]
+    fn test_empty_document() {
+        let doc = Document::new("");
+        assert_eq!(doc.segments().len(), 0);
+        assert_eq!(doc.to_string(), "");
+    }
+}
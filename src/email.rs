@@ -0,0 +1,240 @@
+//! Stripping both parts of an email, its plain text and its HTML.
+
+use std::borrow::Cow;
+
+use crate::{Action, HtmlEntities, StripOptions, strip_codeblocks_with};
+
+/// The plain text and HTML bodies of an email, returned by [`strip_email_with`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct EmailBody {
+    /// The `text/plain` part.
+    pub plain: String,
+    /// The `text/html` part.
+    pub html: String,
+}
+
+/// Returns `text` with the characters special in HTML escaped.
+fn escape(text: &str) -> Cow<'_, str> {
+    if !text.contains(['&', '<', '>']) {
+        return Cow::Borrowed(text);
+    }
+    let mut output = String::with_capacity(text.len() + 8);
+    for c in text.chars() {
+        match c {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            _ => output.push(c),
+        }
+    }
+    Cow::Owned(output)
+}
+
+/// Returns `action` with the text it inserts escaped for HTML.
+fn escape_action(action: &Action) -> Action {
+    match action {
+        Action::Placeholder(text) => Action::Placeholder(escape(text).into_owned()),
+        Action::FirstLine(marker) => Action::FirstLine(escape(marker).into_owned()),
+        action => action.clone(),
+    }
+}
+
+/// Returns the options stripping a plain text part like `options`.
+fn plain_options(options: &StripOptions) -> StripOptions {
+    options.clone().html(false)
+}
+
+/// Returns the options stripping an HTML part like `options` strips plain text.
+///
+/// `<pre>` elements are blocks too, their content stays escaped so the part
+/// stays valid HTML, and the text actions insert is escaped.
+fn html_options(options: &StripOptions) -> StripOptions {
+    let mut html = options.clone().html(true).html_entities(HtmlEntities::Keep);
+    html.action = escape_action(&options.action);
+    html.math = options.math.as_ref().map(escape_action);
+    html
+}
+
+/// Strips fenced code blocks from both bodies of an email, with the default
+/// options.
+///
+/// This is [`strip_email_with`] with [`StripOptions::new`].
+///
+/// # Arguments
+///
+/// * `plain` - The `text/plain` part of the email
+/// * `html` - The `text/html` part of the email
+///
+/// # Returns
+///
+/// Both parts, stripped.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::strip_email;
+///
+/// let body = strip_email(
+///     "Run:\n```sh\nmake\n```",
+///     "<p>Run:</p>\n<pre><code class=\"language-sh\">make\n</code></pre>",
+/// );
+///
+/// assert_eq!(body.plain, "Run:\nmake\n");
+/// assert_eq!(body.html, "<p>Run:</p>\nmake\n");
+/// ```
+pub fn strip_email(plain: &str, html: &str) -> EmailBody {
+    strip_email_with(plain, html, &StripOptions::new())
+}
+
+/// Strips fenced code blocks from both bodies of an email according to
+/// `options`, so readers of either part see the same blocks stripped.
+///
+/// The plain text part is stripped like [`strip_codeblocks_with`] does,
+/// without looking for HTML `<pre>` elements. In the HTML part, `<pre>`
+/// elements are [blocks too](StripOptions::html), which is how renderers
+/// write the fenced blocks of the plain text part, so both parts go through
+/// the same [languages](StripOptions::languages) filter and
+/// [action](StripOptions::action). The HTML part stays valid HTML: the
+/// content of its blocks isn't [decoded](StripOptions::html_entities), and
+/// the text of [placeholders](Action::Placeholder) and [first
+/// line](Action::FirstLine) markers is escaped.
+///
+/// # Arguments
+///
+/// * `plain` - The `text/plain` part of the email
+/// * `html` - The `text/html` part of the email
+/// * `options` - Which blocks are stripped and how
+///
+/// # Returns
+///
+/// Both parts, stripped.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::{Action, StripOptions, strip_email_with};
+///
+/// let options = StripOptions::new().action(Action::Placeholder("<code omitted>".into()));
+///
+/// let body = strip_email_with(
+///     "See:\n```rust\nfn main() {}\n```\n",
+///     "<p>See:</p><pre><code class=\"language-rust\">fn main() {}</code></pre>",
+///     &options,
+/// );
+///
+/// assert_eq!(body.plain, "See:\n<code omitted>\n");
+/// assert_eq!(body.html, "<p>See:</p>&lt;code omitted&gt;");
+/// ```
+pub fn strip_email_with(plain: &str, html: &str, options: &StripOptions) -> EmailBody {
+    EmailBody {
+        plain: strip_codeblocks_with(plain, &plain_options(options)),
+        html: strip_codeblocks_with(html, &html_options(options)),
+    }
+}
+
+/// Strips fenced code blocks from a decoded MIME part body, with the default
+/// options.
+///
+/// This is [`strip_mime_part_with`] with [`StripOptions::new`].
+///
+/// # Arguments
+///
+/// * `content_type` - The `Content-Type` of the part, such as `text/html; charset=utf-8`
+/// * `body` - The body of the part, after its transfer encoding is decoded
+///
+/// # Returns
+///
+/// The stripped body, or `body` itself if the part isn't text.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::strip_mime_part;
+///
+/// assert_eq!(strip_mime_part("text/plain; charset=utf-8", "```\nls\n```"), "ls\n");
+/// assert_eq!(strip_mime_part("text/csv", "```\nls\n```"), "```\nls\n```");
+/// ```
+pub fn strip_mime_part<'a>(content_type: &str, body: &'a str) -> Cow<'a, str> {
+    strip_mime_part_with(content_type, body, &StripOptions::new())
+}
+
+/// Strips fenced code blocks from a decoded MIME part body according to
+/// `options`, the way [`strip_email_with`] strips the part of its type.
+///
+/// `text/html` parts are stripped as HTML, `text/plain` and `text/markdown`
+/// parts as plain text, and other parts, such as attachments, are left as
+/// they are. Parameters of the content type, such as the charset, are
+/// ignored, as the body is already text.
+///
+/// # Arguments
+///
+/// * `content_type` - The `Content-Type` of the part, such as `text/html; charset=utf-8`
+/// * `body` - The body of the part, after its transfer encoding is decoded
+/// * `options` - Which blocks are stripped and how
+///
+/// # Returns
+///
+/// The stripped body, or `body` itself if the part isn't text.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::{Action, StripOptions, strip_mime_part_with};
+///
+/// let options = StripOptions::new().action(Action::Remove);
+///
+/// let body = "<p>Hi</p><PRE>ls</PRE>";
+/// assert_eq!(strip_mime_part_with("Text/HTML", body, &options), "<p>Hi</p>");
+/// ```
+pub fn strip_mime_part_with<'a>(
+    content_type: &str,
+    body: &'a str,
+    options: &StripOptions,
+) -> Cow<'a, str> {
+    let media_type = content_type.split(';').next().unwrap_or("").trim();
+    if media_type.eq_ignore_ascii_case("text/html") {
+        Cow::Owned(strip_codeblocks_with(body, &html_options(options)))
+    } else if media_type.eq_ignore_ascii_case("text/plain")
+        || media_type.eq_ignore_ascii_case("text/markdown")
+    {
+        Cow::Owned(strip_codeblocks_with(body, &plain_options(options)))
+    } else {
+        Cow::Borrowed(body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parts_strip_the_same_blocks() {
+        let options = StripOptions::new()
+            .languages(["sh"])
+            .action(Action::Remove)
+            .html(true)
+            .html_entities(HtmlEntities::Decode);
+        let body = strip_email_with(
+            "a\n```sh\nls\n```\n```rust\nx && y\n```\n<pre>p</pre>\n",
+            "<p>a</p><pre class=\"lang-sh\">ls</pre><pre><code class=\"language-rust\">x &amp;&amp; y</code></pre>",
+            &options,
+        );
+        assert_eq!(body.plain, "a\n\n```rust\nx && y\n```\n<pre>p</pre>\n");
+        assert_eq!(
+            body.html,
+            "<p>a</p><pre><code class=\"language-rust\">x &amp;&amp; y</code></pre>"
+        );
+    }
+
+    #[test]
+    fn test_escape_action() {
+        assert_eq!(escape("a < b & c"), "a &lt; b &amp; c");
+        assert!(matches!(escape("[code]"), Cow::Borrowed(_)));
+        assert_eq!(
+            escape_action(&Action::FirstLine("<more>".into())),
+            Action::FirstLine("&lt;more&gt;".into())
+        );
+        assert_eq!(escape_action(&Action::Keep), Action::Keep);
+    }
+}
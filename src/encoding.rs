@@ -0,0 +1,208 @@
+//! Decoding text of unknown encoding, such as user uploads.
+
+use std::borrow::Cow;
+
+use crate::StripOptions;
+use crate::strip::strip;
+
+/// A text encoding recognized by [`decode_text`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Encoding {
+    /// UTF-8, with or without a byte order mark.
+    Utf8,
+    /// UTF-16, little-endian.
+    Utf16Le,
+    /// UTF-16, big-endian.
+    Utf16Be,
+    /// ISO-8859-1, where each byte is the character of the same code point.
+    Latin1,
+}
+
+/// How many bytes [`detect_encoding`] looks at to tell UTF-16 apart.
+const SAMPLE_LEN: usize = 4096;
+
+/// Detects the encoding of `bytes`.
+///
+/// A byte order mark decides the encoding when there is one. Otherwise,
+/// text with many NUL bytes at every other position is taken for UTF-16, as
+/// mostly-ASCII text is, then valid UTF-8 for UTF-8. Anything else is Latin-1,
+/// which every byte sequence is valid in.
+fn detect_encoding(bytes: &[u8]) -> Encoding {
+    match bytes {
+        [0xef, 0xbb, 0xbf, ..] => return Encoding::Utf8,
+        [0xff, 0xfe, ..] => return Encoding::Utf16Le,
+        [0xfe, 0xff, ..] => return Encoding::Utf16Be,
+        _ => {}
+    }
+
+    let sample = &bytes[..bytes.len().min(SAMPLE_LEN)];
+    let zeros = |parity: usize| {
+        sample
+            .iter()
+            .skip(parity)
+            .step_by(2)
+            .filter(|&&byte| byte == 0)
+            .count()
+    };
+    let (even, odd) = (zeros(0), zeros(1));
+    let pairs = sample.len() / 2;
+    if pairs > 0 && bytes.len().is_multiple_of(2) {
+        // ASCII characters have their high byte zero
+        if odd * 4 > pairs && even * 4 < odd {
+            return Encoding::Utf16Le;
+        }
+        if even * 4 > pairs && odd * 4 < even {
+            return Encoding::Utf16Be;
+        }
+    }
+
+    if std::str::from_utf8(bytes).is_ok() {
+        Encoding::Utf8
+    } else {
+        Encoding::Latin1
+    }
+}
+
+/// Decodes `bytes` of unknown encoding into text.
+///
+/// The encoding is detected from a byte order mark, or failing that from the
+/// bytes themselves: UTF-16 is recognized by its NUL bytes, and text that
+/// isn't valid UTF-8 is read as Latin-1. The byte order mark is not part of
+/// the text. Invalid UTF-16, such as an unpaired surrogate, is decoded as
+/// `U+FFFD REPLACEMENT CHARACTER`.
+///
+/// # Arguments
+///
+/// * `bytes` - The encoded text
+///
+/// # Returns
+///
+/// The text, borrowed from `bytes` when they are UTF-8, and the detected encoding.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::{Encoding, decode_text};
+///
+/// assert_eq!(decode_text(b"caf\xc3\xa9"), ("café".into(), Encoding::Utf8));
+/// assert_eq!(decode_text(b"caf\xe9"), ("café".into(), Encoding::Latin1));
+/// assert_eq!(decode_text(b"\xff\xfeh\x00i\x00"), ("hi".into(), Encoding::Utf16Le));
+/// ```
+pub fn decode_text(bytes: &[u8]) -> (Cow<'_, str>, Encoding) {
+    let encoding = detect_encoding(bytes);
+    let text = match encoding {
+        Encoding::Utf8 => {
+            let bytes = bytes.strip_prefix(b"\xef\xbb\xbf").unwrap_or(bytes);
+            Cow::Borrowed(std::str::from_utf8(bytes).expect("detected as valid UTF-8"))
+        }
+        Encoding::Utf16Le => Cow::Owned(decode_utf16(bytes, u16::from_le_bytes)),
+        Encoding::Utf16Be => Cow::Owned(decode_utf16(bytes, u16::from_be_bytes)),
+        Encoding::Latin1 => Cow::Owned(bytes.iter().map(|&byte| char::from(byte)).collect()),
+    };
+    (text, encoding)
+}
+
+/// Decodes UTF-16 `bytes` whose code units are read by `unit`, dropping any byte order mark.
+fn decode_utf16(bytes: &[u8], unit: fn([u8; 2]) -> u16) -> String {
+    let pairs = bytes.chunks_exact(2);
+    let odd_byte = !pairs.remainder().is_empty();
+    let units = pairs.map(|pair| unit([pair[0], pair[1]]));
+
+    let mut text: String = char::decode_utf16(units)
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect();
+    if text.starts_with('\u{feff}') {
+        text.remove(0);
+    }
+    if odd_byte {
+        text.push(char::REPLACEMENT_CHARACTER);
+    }
+    text
+}
+
+/// Strips fenced code blocks from markdown text of unknown encoding.
+///
+/// The text is decoded like [`decode_text`] does, then stripped according to
+/// `options`. The output is always UTF-8, without a byte order mark.
+///
+/// # Arguments
+///
+/// * `bytes` - The encoded markdown text containing code blocks to strip
+/// * `options` - Which blocks are stripped and how
+///
+/// # Returns
+///
+/// The stripped text, and the encoding `bytes` were detected in.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::{Encoding, StripOptions, strip_encoded};
+///
+/// let utf16: Vec<u8> = "```sh\nls\n```"
+///     .encode_utf16()
+///     .flat_map(u16::to_le_bytes)
+///     .collect();
+///
+/// let (output, encoding) = strip_encoded(&utf16, &StripOptions::new());
+/// assert_eq!(output, "ls\n");
+/// assert_eq!(encoding, Encoding::Utf16Le);
+/// ```
+pub fn strip_encoded(bytes: &[u8], options: &StripOptions) -> (String, Encoding) {
+    let (text, encoding) = decode_text(bytes);
+    (strip(&text, options, None), encoding)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16(text: &str, unit: fn(u16) -> [u8; 2]) -> Vec<u8> {
+        text.encode_utf16().flat_map(unit).collect()
+    }
+
+    #[test]
+    fn test_byte_order_marks() {
+        assert_eq!(
+            decode_text(b"\xef\xbb\xbf# Hi"),
+            ("# Hi".into(), Encoding::Utf8)
+        );
+
+        let mut big_endian = vec![0xfe, 0xff];
+        big_endian.extend(utf16("naïve ```", u16::to_be_bytes));
+        assert_eq!(
+            decode_text(&big_endian),
+            ("naïve ```".into(), Encoding::Utf16Be)
+        );
+    }
+
+    #[test]
+    fn test_utf16_without_byte_order_mark() {
+        let text = "Intro\n```py\nprint('é')\n```\n";
+        assert_eq!(
+            decode_text(&utf16(text, u16::to_le_bytes)).1,
+            Encoding::Utf16Le
+        );
+        assert_eq!(
+            decode_text(&utf16(text, u16::to_be_bytes)).1,
+            Encoding::Utf16Be
+        );
+        assert_eq!(detect_encoding("a\0b".as_bytes()), Encoding::Utf8);
+    }
+
+    #[test]
+    fn test_invalid_utf16_is_replaced() {
+        let mut bytes = vec![0xff, 0xfe, b'a', 0x00];
+        bytes.extend([0x00, 0xd8, b'b', 0x00, b'c']);
+        assert_eq!(decode_text(&bytes).0, "a\u{fffd}b\u{fffd}");
+    }
+
+    #[test]
+    fn test_strip_latin1() {
+        let (output, encoding) =
+            strip_encoded(b"Caf\xe9:\n```\nna\xefve\n```", &StripOptions::new());
+        assert_eq!(output, "Café:\nnaïve\n");
+        assert_eq!(encoding, Encoding::Latin1);
+    }
+}
@@ -0,0 +1,123 @@
+//! Decoding HTML entities in block content.
+
+use std::borrow::Cow;
+
+/// How HTML entities (`&lt;`, `&amp;`, `&#39;`, ...) in the content of unwrapped blocks are handled.
+///
+/// Markdown that went through an HTML renderer often has its code escaped, so
+/// `a < b` appears as `a &lt; b` inside the fences. Decoding restores the
+/// original code. Entities in prose are never touched.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+#[non_exhaustive]
+pub enum HtmlEntities {
+    /// Leave entities as they are.
+    #[default]
+    Keep,
+    /// Decode numeric character references (`&#60;`, `&#x3C;`) and the named
+    /// entities `&lt;`, `&gt;`, `&amp;`, `&quot;`, `&apos;` and `&nbsp;`.
+    /// Other entities are left as they are.
+    Decode,
+}
+
+/// Decodes the HTML entities of `content` according to `policy`.
+pub(crate) fn decode(content: &str, policy: HtmlEntities) -> Cow<'_, str> {
+    if policy == HtmlEntities::Keep || !content.contains('&') {
+        return Cow::Borrowed(content);
+    }
+
+    let mut output = String::with_capacity(content.len());
+    let mut rest = content;
+
+    while let Some(start) = rest.find('&') {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        match decode_entity(rest) {
+            Some((decoded, len)) => {
+                output.push(decoded);
+                rest = &rest[len..];
+            }
+            None => {
+                output.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    output.push_str(rest);
+    Cow::Owned(output)
+}
+
+/// Decodes the entity at the start of `text`, returning the character and the entity's length.
+fn decode_entity(text: &str) -> Option<(char, usize)> {
+    // The longest supported entity is a hex reference to a 6-digit code point
+    let end = text.char_indices().take(12).find(|&(_, c)| c == ';')?.0;
+    let name = &text[1..end];
+
+    let decoded = match name {
+        "lt" => '<',
+        "gt" => '>',
+        "amp" => '&',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => '\u{a0}',
+        _ => {
+            let number = name.strip_prefix('#')?;
+            let code = match number.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => number.parse().ok()?,
+            };
+            char::from_u32(code)?
+        }
+    };
+
+    Some((decoded, end + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{StripOptions, strip_codeblocks_with};
+
+    #[test]
+    fn test_decode_entities() {
+        let decoded = decode(
+            "if a &lt; b &amp;&amp; c &gt; &#39;d&#x27; {}",
+            HtmlEntities::Decode,
+        );
+        assert_eq!(decoded, "if a < b && c > 'd' {}");
+    }
+
+    #[test]
+    fn test_unknown_and_malformed_entities_are_kept() {
+        let decoded = decode("&copy; & &amp &#xZZ; &#1114112; &lt;", HtmlEntities::Decode);
+        assert_eq!(decoded, "&copy; & &amp &#xZZ; &#1114112; <");
+    }
+
+    #[test]
+    fn test_only_block_content_is_decoded() {
+        let options = StripOptions::new().html_entities(HtmlEntities::Decode);
+        let input = "Use &lt;T&gt;:\n```rust\nVec&lt;T&gt;\n```";
+        assert_eq!(
+            strip_codeblocks_with(input, &options),
+            "Use &lt;T&gt;:\nVec<T>\n"
+        );
+    }
+
+    #[test]
+    fn test_keep_borrows() {
+        assert!(matches!(
+            decode("a &lt; b", HtmlEntities::Keep),
+            Cow::Borrowed(_)
+        ));
+        assert!(matches!(
+            decode("a < b", HtmlEntities::Decode),
+            Cow::Borrowed(_)
+        ));
+    }
+}
@@ -0,0 +1,86 @@
+//! Experimental APIs, which may change in minor releases.
+//!
+//! This module hosts a new, CommonMark-accurate parser for fenced code blocks.
+//! It can be selected at runtime with
+//! [`StripOptions::engine`](crate::StripOptions::engine), so the two
+//! engines can be compared on real documents before the parser becomes the
+//! default.
+//!
+//! ```
+//! use strip_codeblocks::experimental::Engine;
+//! use strip_codeblocks::{StripOptions, strip_codeblocks_with};
+//!
+//! let input = "````md\n```rust\nfn main() {}\n```\n````";
+//!
+//! // The regex engine pairs fences of three backticks with each other
+//! let regex = strip_codeblocks_with(input, &StripOptions::new());
+//! assert_eq!(regex, "`rust\nfn main() {}\n`");
+//!
+//! // The parser knows the four-backtick fence contains the three-backtick one
+//! let parser = strip_codeblocks_with(input, &StripOptions::new().engine(Engine::Parser));
+//! assert_eq!(parser, "```rust\nfn main() {}\n```\n");
+//! ```
+
+pub(crate) mod parser;
+
+use crate::CodeBlock;
+use crate::scan;
+
+/// The engine used to find fenced code blocks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+#[non_exhaustive]
+pub enum Engine {
+    /// The original engine, matching fences like the regular expression
+    /// ``(?s)```([^\n`]*)\n(.*?)``` ``.
+    ///
+    /// A fence is three backticks followed by an optional info string and a
+    /// newline, anywhere in the text (even mid-line), and the block ends at
+    /// the next three backticks. Blocks without a closing fence are left as
+    /// they are.
+    #[default]
+    Regex,
+    /// A line-based parser following the CommonMark rules for fenced code blocks.
+    ///
+    /// - Fences are lines of at least three backticks or tildes, indented by
+    ///   at most three spaces. Backtick fences can't have backticks in their
+    ///   info string; tilde fences can.
+    /// - A block is closed by a fence of the same character, at least as long
+    ///   as the opening one and followed only by whitespace. Fences of a
+    ///   different length or character inside the block are content.
+    /// - A block without a closing fence runs to the end of the document.
+    /// - As in GFM, fences can follow the label of a footnote definition, as
+    ///   in `[^1]: ```sh`, or continue it indented by four columns, and the
+    ///   block ends with the definition. The label and the indentation of the
+    ///   fence stay out of the block, so the definition survives stripping.
+    ///
+    /// Only fences at the top level of the document and in footnote
+    /// definitions are recognized for now: fences inside block quotes and
+    /// list items are not, and the content of indented fences keeps its
+    /// indentation.
+    Parser,
+}
+
+/// Extracts all fenced code blocks from markdown text using `engine`, in document order.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::experimental::{Engine, extract_codeblocks_with};
+///
+/// let markdown = "~~~python\nprint('~~~')\n~~~\n";
+///
+/// let blocks = extract_codeblocks_with(markdown, Engine::Parser);
+/// assert_eq!(blocks.len(), 1);
+/// assert_eq!(blocks[0].content, "print('~~~')\n");
+///
+/// // The regex engine only knows backtick fences
+/// assert!(extract_codeblocks_with(markdown, Engine::Regex).is_empty());
+/// ```
+pub fn extract_codeblocks_with(text: &str, engine: Engine) -> Vec<CodeBlock<'_>> {
+    scan::codeblocks_with(text, engine).collect()
+}
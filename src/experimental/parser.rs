@@ -0,0 +1,355 @@
+//! A line-based parser for CommonMark fenced code blocks.
+
+use std::ops::Range;
+
+use crate::block::{info_words, trim_info};
+use crate::scan::follows_blank_line;
+use crate::{Attributes, CodeBlock, trace};
+
+/// The indentation of the lines continuing a GFM footnote definition.
+pub(crate) const FOOTNOTE_INDENT: usize = 4;
+
+/// An opening fence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Fence {
+    /// The fence character, `` ` `` or `~`.
+    pub(crate) char: u8,
+    /// The number of fence characters.
+    pub(crate) len: usize,
+}
+
+/// Iterator over the fenced code blocks of a document, in document order.
+#[derive(Debug, Clone)]
+pub(crate) struct ParserBlocks<'a> {
+    text: &'a str,
+    /// Start of the next line to look at.
+    pos: usize,
+    /// The index of the next block.
+    index: usize,
+    /// Whether opening fences must follow a blank line.
+    blank_line: bool,
+    /// Where the last block ended.
+    previous_end: usize,
+    /// Whether the last line looked at is in a footnote definition.
+    footnote: bool,
+    /// Whether the last line looked at is blank.
+    blank: bool,
+}
+
+impl<'a> ParserBlocks<'a> {
+    pub(crate) fn new(text: &'a str) -> Self {
+        Self {
+            text,
+            pos: 0,
+            index: 0,
+            blank_line: false,
+            previous_end: 0,
+            footnote: false,
+            blank: false,
+        }
+    }
+
+    /// Only opens blocks at fences [following a blank line](follows_blank_line) if `required`.
+    pub(crate) fn require_blank_line(mut self, required: bool) -> Self {
+        self.blank_line = required;
+        self
+    }
+
+    /// Returns the range of the line starting at `start`, line ending included.
+    fn line(&self, start: usize) -> Range<usize> {
+        let end = self.text[start..]
+            .find('\n')
+            .map_or(self.text.len(), |offset| start + offset + 1);
+        start..end
+    }
+
+    /// Returns the part of `line` a fence can open, past the label or the
+    /// indentation of a footnote definition it starts or continues, keeping
+    /// track of footnote definitions.
+    ///
+    /// As in GFM, definitions continue with lines indented by four columns,
+    /// lazy continuation lines of a paragraph and blank lines.
+    fn container_line<'l>(&mut self, line: &'l str) -> &'l str {
+        let blank = line.trim().is_empty();
+        let was_blank = std::mem::replace(&mut self.blank, blank);
+        if let Some(label) = footnote_label(line) {
+            self.footnote = true;
+            return line[label..].trim_start_matches([' ', '\t']);
+        }
+        if self.footnote && !blank {
+            if indentation(line).0 >= FOOTNOTE_INDENT {
+                return strip_columns(line, FOOTNOTE_INDENT);
+            }
+            // Fences interrupt paragraphs rather than continue them lazily
+            if was_blank || opening_fence(line).is_some() {
+                self.footnote = false;
+            }
+        }
+        line
+    }
+}
+
+impl<'a> Iterator for ParserBlocks<'a> {
+    type Item = CodeBlock<'a>;
+
+    fn next(&mut self) -> Option<CodeBlock<'a>> {
+        while self.pos < self.text.len() {
+            let opening = self.line(self.pos);
+            self.pos = opening.end;
+
+            let line = self.container_line(&self.text[opening.clone()]);
+            let Some((fence, info)) = opening_fence(line) else {
+                continue;
+            };
+            // Blocks in footnote definitions leave their label and indentation out
+            let start = opening.end - line.len();
+            if self.blank_line && !follows_blank_line(self.text, start, self.previous_end) {
+                continue;
+            }
+
+            let indent = if self.footnote { FOOTNOTE_INDENT } else { 0 };
+            let content_start = opening.end;
+            let mut line_start = content_start;
+            let (content_end, end) = loop {
+                if line_start >= self.text.len() {
+                    // Unclosed blocks run to the end of the document
+                    trace::debug!(
+                        start = opening.start,
+                        "unclosed fence runs to the end of the document"
+                    );
+                    self.pos = self.text.len();
+                    break (self.text.len(), self.text.len());
+                }
+
+                let line = self.line(line_start);
+                let text = &self.text[line.clone()];
+                if indent > 0 && !text.trim().is_empty() && indentation(text).0 < indent {
+                    // The footnote definition ends, and the block with it
+                    self.footnote = false;
+                    self.pos = line.start;
+                    break (line.start, line.start);
+                }
+                if is_closing_fence(strip_columns(text, indent), fence) {
+                    self.pos = line.end;
+                    break (
+                        line.start,
+                        line.start + text.trim_end_matches(['\n', '\r']).len(),
+                    );
+                }
+                line_start = line.end;
+            };
+
+            self.previous_end = end;
+            self.blank = false;
+            // The fence characters run up to the info string
+            let info_start = info.as_ptr() as usize - self.text.as_ptr() as usize;
+            let info_raw = info.strip_suffix('\r').unwrap_or(info);
+            let info = trim_info(info_raw);
+            self.index += 1;
+            return Some(CodeBlock {
+                info,
+                info_raw,
+                fence: &self.text[info_start - fence.len..info_start],
+                language: info_words(info).next(),
+                content: &self.text[content_start..content_end],
+                span: start..end,
+                content_span: content_start..content_end,
+                attributes: Attributes::parse(info),
+                index: self.index - 1,
+            });
+        }
+
+        None
+    }
+}
+
+/// Returns the width of the indentation of `line` in columns (tabs advance to
+/// the next multiple of four), and the rest of the line.
+pub(crate) fn indentation(line: &str) -> (usize, &str) {
+    let mut columns = 0;
+    for (offset, byte) in line.bytes().enumerate() {
+        match byte {
+            b' ' => columns += 1,
+            b'\t' => columns += 4 - columns % 4,
+            _ => return (columns, &line[offset..]),
+        }
+    }
+    (columns, "")
+}
+
+/// Returns `line` without its indentation up to `columns` columns.
+fn strip_columns(line: &str, columns: usize) -> &str {
+    let mut width = 0;
+    for (offset, byte) in line.bytes().enumerate() {
+        if width >= columns {
+            return &line[offset..];
+        }
+        match byte {
+            b' ' => width += 1,
+            b'\t' => width += 4 - width % 4,
+            _ => return &line[offset..],
+        }
+    }
+    ""
+}
+
+/// Returns the length of the label `line` starts with, such as `[^1]:`, if it
+/// starts a GFM footnote definition.
+pub(crate) fn footnote_label(line: &str) -> Option<usize> {
+    let (indent, rest) = indentation(line);
+    let label = rest.strip_prefix("[^").filter(|_| indent <= 3)?;
+    let end = label.find(']')?;
+    if end == 0 || label[..end].contains(char::is_whitespace) || !label[end + 1..].starts_with(':')
+    {
+        return None;
+    }
+    Some(line.len() - rest.len() + end + 4)
+}
+
+/// Parses an opening fence line, returning the fence and its raw info string.
+pub(crate) fn opening_fence(line: &str) -> Option<(Fence, &str)> {
+    let (indent, rest) = indentation(line);
+    if indent > 3 {
+        return None;
+    }
+
+    let char = *rest.as_bytes().first()?;
+    if char != b'`' && char != b'~' {
+        return None;
+    }
+    let len = rest.bytes().take_while(|&byte| byte == char).count();
+    if len < 3 {
+        return None;
+    }
+
+    let info = rest[len..].strip_suffix('\n').unwrap_or(&rest[len..]);
+    if char == b'`' && info.contains('`') {
+        return None;
+    }
+    Some((Fence { char, len }, info))
+}
+
+/// Whether `line` closes a block opened by `fence`.
+pub(crate) fn is_closing_fence(line: &str, fence: Fence) -> bool {
+    let (indent, rest) = indentation(line);
+    let len = rest.bytes().take_while(|&byte| byte == fence.char).count();
+
+    indent <= 3 && len >= fence.len && rest[len..].trim().is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blocks(text: &str) -> Vec<CodeBlock<'_>> {
+        ParserBlocks::new(text).collect()
+    }
+
+    #[test]
+    fn test_basic_block() {
+        let input = "Intro\n```rust\nfn main() {}\n```\nOutro";
+        let blocks = blocks(input);
+
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].language, Some("rust"));
+        assert_eq!(blocks[0].content, "fn main() {}\n");
+        assert_eq!(&input[blocks[0].span.clone()], "```rust\nfn main() {}\n```");
+    }
+
+    #[test]
+    fn test_closing_fence_must_be_long_enough() {
+        let input = "````\n```\ninner\n```\n`````\nafter";
+        let blocks = blocks(input);
+
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].content, "```\ninner\n```\n");
+    }
+
+    #[test]
+    fn test_fence_characters_must_match() {
+        let input = "~~~\n```\n~~~";
+        assert_eq!(blocks(input)[0].content, "```\n");
+    }
+
+    #[test]
+    fn test_backticks_in_info_string() {
+        // Not a fence: the line is prose
+        assert!(blocks("``` a`b\ncode\n").is_empty());
+
+        let tilde = blocks("~~~ {.js title=`app.js`} \ncode\n~~~");
+        assert_eq!(tilde[0].info, "{.js title=`app.js`}");
+        assert_eq!(tilde[0].info_raw, " {.js title=`app.js`} ");
+        assert_eq!(tilde[0].language, Some("{.js"));
+        assert_eq!(tilde[0].fence, "~~~");
+    }
+
+    #[test]
+    fn test_info_raw_excludes_line_ending() {
+        assert_eq!(blocks("```rust \r\ncode\r\n```")[0].info_raw, "rust ");
+    }
+
+    #[test]
+    fn test_indentation() {
+        assert_eq!(blocks("   ```\ncode\n   ```").len(), 1);
+        assert!(blocks("    ```\ncode\n    ```").is_empty());
+        assert!(blocks("\t```\ncode\n\t```").is_empty());
+    }
+
+    #[test]
+    fn test_footnote_definitions() {
+        let input =
+            "[^1]: ```sh\n    ls\n    ```\n[^a]: Note:\n\n    ```\n    x\n\n    ```\n    More.\n";
+        let found = blocks(input);
+        assert_eq!(found.len(), 2);
+        assert_eq!(&input[found[0].span.clone()], "```sh\n    ls\n    ```");
+        assert_eq!(found[1].content, "    x\n\n");
+
+        // Definitions end at lines that aren't indented, and their blocks with them
+        let input = "[^1]:\n    ```\n    a\n\nb\n```\nc\n```";
+        let found = blocks(input);
+        assert_eq!(found[0].content, "    a\n\n");
+        assert_eq!(found[1].content, "c\n");
+        assert_eq!(blocks("[^1]: a\n\n```\n    x\n```")[0].content, "    x\n");
+        let found = blocks("[^1]: a\n\n    ```\nb\n    ```");
+        assert_eq!((found.len(), found[0].content), (1, ""));
+
+        assert_eq!(footnote_label("   [^note]: x"), Some(11));
+        assert_eq!(footnote_label("[^]: x"), None);
+        assert_eq!(footnote_label("[^a b]: x"), None);
+        assert_eq!(footnote_label("[ref]: /url"), None);
+    }
+
+    #[test]
+    fn test_unclosed_block_runs_to_end() {
+        let input = "text\n```sh\nls\n";
+        let blocks = blocks(input);
+
+        assert_eq!(blocks[0].content, "ls\n");
+        assert_eq!(blocks[0].span, 5..input.len());
+    }
+
+    #[test]
+    fn test_no_blocks_inside_unclosed_block() {
+        let found = blocks("```\n~~~\nb\n");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].content, "~~~\nb\n");
+    }
+
+    #[test]
+    fn test_mid_line_backticks_are_not_fences() {
+        assert!(blocks("Text with ```rust\nnot a block\n").is_empty());
+        assert!(blocks("inline ``` code ``` here").is_empty());
+    }
+
+    #[test]
+    fn test_closing_fence_with_trailing_whitespace_and_crlf() {
+        let input = "```\r\ncode\r\n```  \r\nafter";
+        let found = blocks(input);
+
+        assert_eq!(found[0].content, "code\r\n");
+        assert_eq!(&input[found[0].span.clone()], "```\r\ncode\r\n```  ");
+
+        // Anything but whitespace after the fence makes it content
+        assert_eq!(blocks("```\ncode\n``` x\n")[0].content, "code\n``` x\n");
+    }
+}
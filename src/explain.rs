@@ -0,0 +1,325 @@
+//! Human-readable traces of how a document is stripped.
+
+use std::fmt;
+
+use crate::experimental::Engine;
+use crate::region::Regions;
+use crate::report::{LineCounter, describe_action};
+use crate::scan::{Blocks, RegexBlocks, Skip, codeblocks_for};
+use crate::{Action, Ansi, StripOptions, ansi};
+
+/// A decision made while stripping a document, as returned by [`explain`].
+///
+/// Events display as a line of explanation, such as
+/// `line 3: fence of 4 backticks opened, info "rust"`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum TraceEvent {
+    /// A fence opened a code block.
+    FenceOpened {
+        /// The line of the fence, starting at 1.
+        line: usize,
+        /// The fence itself, such as ```` ``` ```` or `~~~~`.
+        fence: String,
+        /// The info string following the fence.
+        info: String,
+    },
+    /// A fence closed the code block opened last.
+    FenceClosed {
+        /// The line of the fence, starting at 1.
+        line: usize,
+        /// The fence itself.
+        fence: String,
+        /// Whether the fence follows content on its line.
+        mid_line: bool,
+    },
+    /// The code block opened last was never closed, and runs to the end of the document.
+    Unclosed {
+        /// The last line of the document, starting at 1.
+        line: usize,
+    },
+    /// Backticks that would open a block were left alone because they are in
+    /// a table row.
+    TableRowSkipped {
+        /// The line of the row, starting at 1.
+        line: usize,
+    },
+    /// Backticks that would open a block were left alone because no blank
+    /// line precedes them, as [required](StripOptions::require_blank_line_before_fence).
+    NoBlankLineSkipped {
+        /// The line of the backticks, starting at 1.
+        line: usize,
+    },
+    /// The code block opened last was stripped with this action.
+    Stripped {
+        /// The line the block starts at, starting at 1.
+        line: usize,
+        /// The action applied to the block.
+        action: Action,
+    },
+}
+
+impl TraceEvent {
+    /// Returns the line of the document the event is about, starting at 1.
+    pub fn line(&self) -> usize {
+        match self {
+            TraceEvent::FenceOpened { line, .. }
+            | TraceEvent::FenceClosed { line, .. }
+            | TraceEvent::Unclosed { line }
+            | TraceEvent::TableRowSkipped { line }
+            | TraceEvent::NoBlankLineSkipped { line }
+            | TraceEvent::Stripped { line, .. } => *line,
+        }
+    }
+}
+
+impl fmt::Display for TraceEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: ", self.line())?;
+        match self {
+            TraceEvent::FenceOpened { fence, info, .. } => {
+                write!(f, "fence of {} opened", describe_fence(fence))?;
+                match info.as_str() {
+                    "" => f.write_str(", no info string"),
+                    info => write!(f, ", info {info:?}"),
+                }
+            }
+            TraceEvent::FenceClosed {
+                fence, mid_line, ..
+            } => {
+                write!(f, "fence of {} closed the block", describe_fence(fence))?;
+                if *mid_line {
+                    f.write_str(" in the middle of the line")?;
+                }
+                Ok(())
+            }
+            TraceEvent::Unclosed { .. } => {
+                f.write_str("block never closed, it runs to the end of the document")
+            }
+            TraceEvent::TableRowSkipped { .. } => {
+                f.write_str("backticks in a table row left alone")
+            }
+            TraceEvent::NoBlankLineSkipped { .. } => {
+                f.write_str("backticks without a blank line before them left alone")
+            }
+            TraceEvent::Stripped { action, .. } => {
+                write!(f, "block {}", describe_action(action))
+            }
+        }
+    }
+}
+
+/// Describes `fence` by length and character, e.g. `4 backticks`.
+fn describe_fence(fence: &str) -> String {
+    let len = fence.chars().count();
+    match fence.chars().next() {
+        Some('~') => format!("{len} tildes"),
+        _ => format!("{len} backticks"),
+    }
+}
+
+/// Explains how [`strip_codeblocks`](crate::strip_codeblocks) strips markdown text.
+///
+/// This is [`explain_with`] with the default options.
+///
+/// # Arguments
+///
+/// * `text` - The markdown text to explain the stripping of
+///
+/// # Returns
+///
+/// The decisions made while stripping `text`, in document order.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::explain;
+///
+/// let trace: Vec<String> = explain("Intro\n```rust\nfn main() {}```\n")
+///     .iter()
+///     .map(ToString::to_string)
+///     .collect();
+///
+/// assert_eq!(
+///     trace,
+///     [
+///         "line 2: fence of 3 backticks opened, info \"rust\"",
+///         "line 3: fence of 3 backticks closed the block in the middle of the line",
+///         "line 2: block unwrapped",
+///     ]
+/// );
+/// ```
+pub fn explain(text: &str) -> Vec<TraceEvent> {
+    explain_with(text, &StripOptions::new())
+}
+
+/// Explains how [`strip_codeblocks_with`](crate::strip_codeblocks_with)
+/// strips markdown text with `options`.
+///
+/// Each block found is described by the fence opening it, the fence closing
+/// it (or the end of the document), then the action applied to it. Events
+/// come in document order, except that the action of a block follows its
+/// closing fence. With the default [engine](StripOptions::engine), the
+/// backticks skipped because they are in table rows are reported too.
+///
+/// # Arguments
+///
+/// * `text` - The markdown text to explain the stripping of
+/// * `options` - Which blocks are stripped and how
+///
+/// # Returns
+///
+/// The decisions made while stripping `text`.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::{StripOptions, TraceEvent, experimental::Engine, explain_with};
+///
+/// let options = StripOptions::new().engine(Engine::Parser).languages(["sh"]);
+/// let trace = explain_with("~~~~python\nprint(1)\n", &options);
+///
+/// assert_eq!(trace[1], TraceEvent::Unclosed { line: 2 });
+/// assert_eq!(trace[2].to_string(), "line 1: block kept");
+/// ```
+pub fn explain_with(text: &str, options: &StripOptions) -> Vec<TraceEvent> {
+    let transparent = options.ansi != Ansi::Keep && ansi::has_escapes(text);
+    let mut blocks = match options.engine {
+        Engine::Regex if !transparent => Blocks::Regex(
+            RegexBlocks::new(text)
+                .require_blank_line(options.require_blank_line_before_fence)
+                .record_skips(),
+        ),
+        _ => codeblocks_for(text, options),
+    };
+
+    let regions = Regions::new(text, options);
+    let mut lines = LineCounter::new(text);
+    let mut events = Vec::new();
+    let mut skipped = 0;
+    while let Some(block) = blocks.next() {
+        push_skipped(&blocks, &mut skipped, &mut lines, &mut events);
+        let opening = &text[block.span.start..];
+        let fence_char = opening.chars().next().unwrap_or('`');
+        let fence_len = opening.len() - opening.trim_start_matches(fence_char).len();
+        let line = lines.line(block.span.start);
+
+        events.push(TraceEvent::FenceOpened {
+            line,
+            fence: opening[..fence_len].to_string(),
+            info: block.info.to_string(),
+        });
+        if block.content_span.end < block.span.end {
+            events.push(TraceEvent::FenceClosed {
+                line: lines.line(block.content_span.end),
+                fence: text[block.content_span.end..block.span.end]
+                    .trim()
+                    .to_string(),
+                mid_line: !block.content.is_empty() && !block.content.ends_with('\n'),
+            });
+        } else {
+            // The line of the last character, not the empty line after a final line break
+            let end = block.span.end - usize::from(text[..block.span.end].ends_with('\n'));
+            events.push(TraceEvent::Unclosed {
+                line: lines.line(end),
+            });
+        }
+        events.push(TraceEvent::Stripped {
+            line,
+            action: regions.action_for(&block, options).clone(),
+        });
+    }
+    push_skipped(&blocks, &mut skipped, &mut lines, &mut events);
+    events
+}
+
+/// Pushes the events of the backticks `blocks` skipped since the first
+/// `reported`, which came after the blocks already explained and before the
+/// next one.
+fn push_skipped(
+    blocks: &Blocks<'_>,
+    reported: &mut usize,
+    lines: &mut LineCounter<'_>,
+    events: &mut Vec<TraceEvent>,
+) {
+    let Blocks::Regex(blocks) = blocks else {
+        return;
+    };
+    for &(start, skip) in &blocks.skipped()[*reported..] {
+        let line = lines.line(start);
+        events.push(match skip {
+            Skip::TableRow => TraceEvent::TableRowSkipped { line },
+            Skip::NoBlankLine => TraceEvent::NoBlankLineSkipped { line },
+        });
+    }
+    *reported = blocks.skipped().len();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table_rows_are_reported() {
+        let input = "| a | b |\n|---|---|\n| ```x | y``` |\n```sh\nls\n```\n";
+        let trace: Vec<String> = explain(input).iter().map(ToString::to_string).collect();
+        assert_eq!(
+            trace,
+            [
+                "line 3: backticks in a table row left alone",
+                "line 4: fence of 3 backticks opened, info \"sh\"",
+                "line 6: fence of 3 backticks closed the block",
+                "line 4: block unwrapped",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_skipped_backticks_between_blocks() {
+        let input = "```\na\n```\n| a | b |\n|---|---|\n| ```x | y |\n```\nb\n```\n";
+        let lines: Vec<usize> = explain(input).iter().map(TraceEvent::line).collect();
+        assert_eq!(lines, [1, 3, 1, 6, 7, 9, 7]);
+        assert_eq!(explain(input)[3], TraceEvent::TableRowSkipped { line: 6 });
+    }
+
+    #[test]
+    fn test_fences_without_blank_line_are_reported() {
+        let options = StripOptions::new().require_blank_line_before_fence(true);
+        let trace = explain_with("Use\n```\nx\n```\n", &options);
+        assert_eq!(trace, [TraceEvent::NoBlankLineSkipped { line: 2 }]);
+        assert_eq!(
+            trace[0].to_string(),
+            "line 2: backticks without a blank line before them left alone"
+        );
+    }
+
+    #[test]
+    fn test_parser_fences() {
+        let options = StripOptions::new().engine(Engine::Parser);
+        let trace = explain_with("~~~~ rust ignore\nfn f() {}\n  ~~~~~  \n", &options);
+        assert_eq!(
+            trace[..2],
+            [
+                TraceEvent::FenceOpened {
+                    line: 1,
+                    fence: "~~~~".into(),
+                    info: "rust ignore".into()
+                },
+                TraceEvent::FenceClosed {
+                    line: 3,
+                    fence: "~~~~~".into(),
+                    mid_line: false
+                },
+            ]
+        );
+        assert_eq!(
+            trace[1].to_string(),
+            "line 3: fence of 5 tildes closed the block"
+        );
+    }
+
+    #[test]
+    fn test_no_blocks() {
+        assert!(explain("Only `inline` code").is_empty());
+    }
+}
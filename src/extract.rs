@@ -0,0 +1,329 @@
+//! Extracting fenced code blocks from markdown text.
+
+use std::collections::HashMap;
+
+use crate::scan::codeblocks;
+use crate::{CodeBlock, MergedBlock, merge};
+
+/// Extracts all fenced code blocks from markdown text, in document order.
+///
+/// Every string of the blocks borrows from `text`, so extraction only
+/// allocates the returned `Vec`. The content of indented fences, as in list
+/// items, can be taken without its indentation with
+/// [`CodeBlock::dedented_content`], which only allocates for blocks it changes.
+///
+/// # Arguments
+///
+/// * `text` - The markdown text to extract code blocks from
+///
+/// # Returns
+///
+/// The code blocks found in `text`, borrowing their content from it.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::extract_codeblocks;
+///
+/// let markdown = "```rust\nfn main() {}\n```\nSome text.\n```python\nprint('hi')\n```";
+///
+/// let blocks = extract_codeblocks(markdown);
+/// assert_eq!(blocks.len(), 2);
+/// assert_eq!(blocks[0].language, Some("rust"));
+/// assert_eq!(blocks[1].content, "print('hi')\n");
+/// ```
+pub fn extract_codeblocks(text: &str) -> Vec<CodeBlock<'_>> {
+    codeblocks(text).collect()
+}
+
+/// Helpers for working with the blocks returned by [`extract_codeblocks`].
+///
+/// Implemented for slices of blocks, so the methods can be called directly on
+/// the returned `Vec`.
+pub trait CodeBlocks<'a> {
+    /// Groups the blocks by language.
+    ///
+    /// Blocks without a language are grouped under the empty string. Within
+    /// each group, blocks keep their document order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strip_codeblocks::{CodeBlocks, extract_codeblocks};
+    ///
+    /// let markdown = "```rust\nfn a() {}\n```\n```python\nb()\n```\n```rust\nfn c() {}\n```";
+    ///
+    /// let groups = extract_codeblocks(markdown).group_by_language();
+    /// assert_eq!(groups["rust"].len(), 2);
+    /// assert_eq!(groups["rust"][1].content, "fn c() {}\n");
+    /// assert_eq!(groups["python"].len(), 1);
+    /// ```
+    fn group_by_language(&self) -> HashMap<String, Vec<CodeBlock<'a>>>;
+
+    /// Groups the blocks by language, with groups ordered by first appearance.
+    ///
+    /// This is [`group_by_language`](Self::group_by_language) with a
+    /// deterministic group order, for exporters whose output must not depend
+    /// on hash map iteration order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strip_codeblocks::{CodeBlocks, extract_codeblocks};
+    ///
+    /// let markdown = "```sh\nls\n```\n```\nplain\n```\n```rust\nfn a() {}\n```\n```sh\npwd\n```";
+    ///
+    /// let groups = extract_codeblocks(markdown).group_by_language_ordered();
+    /// let languages: Vec<_> = groups.iter().map(|(language, _)| language.as_str()).collect();
+    /// assert_eq!(languages, ["sh", "", "rust"]);
+    /// assert_eq!(groups[0].1.len(), 2);
+    /// ```
+    fn group_by_language_ordered(&self) -> Vec<(String, Vec<CodeBlock<'a>>)>;
+
+    /// Merges runs of consecutive blocks of the same language, separated only
+    /// by blank lines in `text`, the document the blocks were extracted from.
+    ///
+    /// Blocks without a language are never merged, and make runs of their own.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strip_codeblocks::{CodeBlocks, extract_codeblocks};
+    ///
+    /// let markdown = "```sh\ncd app\n```\n\n```sh\nmake\n```\nThen:\n```sh\nmake test\n```";
+    ///
+    /// let runs = extract_codeblocks(markdown).merge_adjacent(markdown);
+    /// assert_eq!(runs.len(), 2);
+    /// assert_eq!(runs[0].content, "cd app\nmake\n");
+    /// assert_eq!(runs[0].blocks.len(), 2);
+    /// ```
+    fn merge_adjacent(&self, text: &str) -> Vec<MergedBlock<'a>>;
+
+    /// Returns the blocks in `language`, in document order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strip_codeblocks::{CodeBlocks, extract_codeblocks};
+    ///
+    /// let markdown = "```rust\nfn a() {}\n```\n```sh\nls\n```\n```rust\nfn b() {}\n```";
+    ///
+    /// let rust = extract_codeblocks(markdown).by_language("rust");
+    /// assert_eq!(rust.len(), 2);
+    /// assert_eq!(rust[1].index, 2);
+    /// ```
+    fn by_language(&self, language: &str) -> Vec<CodeBlock<'a>>;
+
+    /// Returns the block with the longest content, in bytes, or `None` if
+    /// there are no blocks. Of blocks equally long, the first is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strip_codeblocks::{CodeBlocks, extract_codeblocks};
+    ///
+    /// let markdown = "```sh\nls\n```\n```rust\nfn main() {}\n```\n```sh\ncd\n```";
+    ///
+    /// let blocks = extract_codeblocks(markdown);
+    /// assert_eq!(blocks.longest().unwrap().language, Some("rust"));
+    /// assert_eq!(blocks.by_language("sh").longest().unwrap().content, "ls\n");
+    /// ```
+    fn longest(&self) -> Option<&CodeBlock<'a>>;
+}
+
+impl<'a> CodeBlocks<'a> for [CodeBlock<'a>] {
+    fn group_by_language(&self) -> HashMap<String, Vec<CodeBlock<'a>>> {
+        let mut groups: HashMap<String, Vec<CodeBlock<'a>>> = HashMap::new();
+        for block in self {
+            let language = block.language.unwrap_or_default();
+            groups
+                .entry(language.to_string())
+                .or_default()
+                .push(block.clone());
+        }
+        groups
+    }
+
+    fn group_by_language_ordered(&self) -> Vec<(String, Vec<CodeBlock<'a>>)> {
+        let mut groups: Vec<(String, Vec<CodeBlock<'a>>)> = Vec::new();
+        for block in self {
+            let language = block.language.unwrap_or_default();
+            match groups.iter_mut().find(|(name, _)| name == language) {
+                Some((_, blocks)) => blocks.push(block.clone()),
+                None => groups.push((language.to_string(), vec![block.clone()])),
+            }
+        }
+        groups
+    }
+
+    fn merge_adjacent(&self, text: &str) -> Vec<MergedBlock<'a>> {
+        merge::merge_adjacent(text, self)
+    }
+
+    fn by_language(&self, language: &str) -> Vec<CodeBlock<'a>> {
+        self.iter()
+            .filter(|block| block.language == Some(language))
+            .cloned()
+            .collect()
+    }
+
+    fn longest(&self) -> Option<&CodeBlock<'a>> {
+        self.iter().rev().max_by_key(|block| block.content.len())
+    }
+}
+
+/// Rustdoc attributes that may appear in the info string of a Rust example.
+const RUSTDOC_ATTRIBUTES: &[&str] = &[
+    "rust",
+    "ignore",
+    "should_panic",
+    "no_run",
+    "compile_fail",
+    "test_harness",
+    "standalone_crate",
+];
+
+/// Info string words marking an example as not runnable.
+const SKIP_ATTRIBUTES: &[&str] = &["ignore", "no_run", "text"];
+
+/// Extracts the runnable examples written in `lang` from markdown text.
+///
+/// This mirrors how rustdoc picks doc tests, so tools testing the code blocks
+/// of a README select the same blocks as `cargo test` would:
+///
+/// - The info string is split into words on commas and whitespace.
+/// - A block is written in `lang` when its first word is `lang`. For `rust`,
+///   blocks without an info string, and blocks whose words are all rustdoc
+///   attributes (`ignore`, `should_panic`, `edition2021`, ...), count as Rust too.
+/// - Blocks marked `ignore` (or `ignore-<target>`), `no_run` or `text` are
+///   skipped, since they aren't meant to be run.
+///
+/// # Arguments
+///
+/// * `text` - The markdown text to extract examples from
+/// * `lang` - The language of the examples, e.g. `rust` or `python`
+///
+/// # Returns
+///
+/// The runnable example blocks, in document order.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::extract_doc_examples;
+///
+/// let readme = "```\nassert!(true);\n```\n```rust,no_run\nloop {}\n```\n```should_panic\npanic!();\n```\n```text\nnot code\n```";
+///
+/// let examples = extract_doc_examples(readme, "rust");
+/// assert_eq!(examples.len(), 2);
+/// assert_eq!(examples[0].content, "assert!(true);\n");
+/// assert_eq!(examples[1].content, "panic!();\n");
+/// ```
+pub fn extract_doc_examples<'a>(text: &'a str, lang: &str) -> Vec<CodeBlock<'a>> {
+    codeblocks(text)
+        .filter(|block| {
+            let mut words = block
+                .info
+                .split([' ', '\t', ','])
+                .filter(|word| !word.is_empty());
+            if words.clone().any(is_skip_attribute) {
+                return false;
+            }
+
+            match block.language {
+                Some(language) if language == lang => true,
+                None => lang == "rust",
+                Some(_) => lang == "rust" && words.all(is_rustdoc_attribute),
+            }
+        })
+        .collect()
+}
+
+fn is_skip_attribute(word: &str) -> bool {
+    SKIP_ATTRIBUTES.contains(&word) || word.starts_with("ignore-")
+}
+
+fn is_rustdoc_attribute(word: &str) -> bool {
+    RUSTDOC_ATTRIBUTES.contains(&word) || word.starts_with("edition") || word.starts_with("ignore-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_codeblocks() {
+        let input = "Before\n```rust\nfn main() {}\n```\nAfter `inline`";
+        let blocks = extract_codeblocks(input);
+
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].language, Some("rust"));
+        assert_eq!(blocks[0].content, "fn main() {}\n");
+    }
+
+    #[test]
+    fn test_extract_no_codeblocks() {
+        assert!(extract_codeblocks("Just `inline` code.").is_empty());
+    }
+
+    #[test]
+    fn test_group_by_language_keeps_document_order() {
+        let input = "```\na\n```\n```js\nb\n```\n```\nc\n```";
+        let blocks = extract_codeblocks(input);
+
+        let groups = blocks.group_by_language();
+        assert_eq!(groups.len(), 2);
+        let untagged: Vec<_> = groups[""].iter().map(|block| block.content).collect();
+        assert_eq!(untagged, ["a\n", "c\n"]);
+
+        let ordered = blocks.group_by_language_ordered();
+        assert_eq!(ordered[0].0, "");
+        assert_eq!(ordered[1].0, "js");
+        assert_eq!(ordered[0].1, groups[""]);
+    }
+
+    #[test]
+    fn test_indices_count_every_block() {
+        let input = "```sh\nls\n```\n~~~\nx\n~~~\n```\ny\n```";
+        fn indices<'a>(blocks: Vec<CodeBlock<'a>>) -> Vec<(usize, &'a str)> {
+            blocks
+                .iter()
+                .map(|block| (block.index, block.content))
+                .collect()
+        }
+
+        assert_eq!(
+            indices(extract_codeblocks(input)),
+            [(0, "ls\n"), (1, "y\n")]
+        );
+        assert_eq!(
+            indices(crate::experimental::extract_codeblocks_with(
+                input,
+                crate::experimental::Engine::Parser
+            )),
+            [(0, "ls\n"), (1, "x\n"), (2, "y\n")]
+        );
+        assert_eq!(indices(extract_doc_examples(input, "sh")), [(0, "ls\n")]);
+        assert_eq!(extract_codeblocks(input).longest().unwrap().index, 0);
+        assert!(extract_codeblocks("").longest().is_none());
+    }
+
+    #[test]
+    fn test_doc_examples_rust_attributes() {
+        let input = "```rust\na\n```\n```edition2021,should_panic\nb\n```\n```ignore\nc\n```\n```rust ignore-wasm32\nd\n```\n```compile_fail\ne\n```";
+        let contents: Vec<_> = extract_doc_examples(input, "rust")
+            .iter()
+            .map(|block| block.content)
+            .collect();
+        assert_eq!(contents, ["a\n", "b\n", "e\n"]);
+    }
+
+    #[test]
+    fn test_doc_examples_other_language() {
+        let input = "```python\nprint(1)\n```\n```\nuntagged\n```\n```python,no_run\nserve()\n```\n```rust\nfn f() {}\n```";
+        let examples = extract_doc_examples(input, "python");
+
+        assert_eq!(examples.len(), 1);
+        assert_eq!(examples[0].content, "print(1)\n");
+    }
+}
@@ -0,0 +1,458 @@
+//! Detecting fences, and wrapping content back into fenced code blocks.
+
+use std::ops::Range;
+
+use crate::CodeBlock;
+use crate::block::trim_info;
+use crate::experimental::parser::{Fence, ParserBlocks, indentation, opening_fence};
+use crate::strict::strip_containers;
+
+/// A fence line, as found by [`is_fence_line`] and [`find_fences`].
+///
+/// Fences follow the CommonMark rules the [parser](crate::experimental::Engine::Parser)
+/// engine and [strict](crate::StripOptions::strict) mode use: a line of at
+/// least three backticks or tildes, indented by at most three spaces,
+/// followed by an info string (without backticks, for backtick fences).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct FenceInfo<'a> {
+    /// The fence character, `` ` `` or `~`.
+    pub char: char,
+    /// The number of fence characters.
+    pub len: usize,
+    /// The width of the indentation before the fence, in columns.
+    pub indent: usize,
+    /// The info string following the fence, trimmed.
+    pub info: &'a str,
+    /// Byte range of the fence line, without its line ending.
+    pub span: Range<usize>,
+    /// Whether the fence closes a block rather than opening one. Always
+    /// `false` for fences returned by [`is_fence_line`], which can't know.
+    pub closing: bool,
+}
+
+impl FenceInfo<'_> {
+    /// Returns whether this fence closes a block opened by `opening`.
+    ///
+    /// A closing fence uses the same character, is at least as long, and has
+    /// no info string.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strip_codeblocks::is_fence_line;
+    ///
+    /// let opening = is_fence_line("````rust").unwrap();
+    ///
+    /// assert!(is_fence_line("`````").unwrap().closes(&opening));
+    /// assert!(!is_fence_line("```").unwrap().closes(&opening));
+    /// assert!(!is_fence_line("~~~~").unwrap().closes(&opening));
+    /// ```
+    pub fn closes(&self, opening: &FenceInfo<'_>) -> bool {
+        self.char == opening.char && self.len >= opening.len && self.info.is_empty()
+    }
+}
+
+/// Parses `line` as a fence line.
+///
+/// Any fence that can close a block can also open one, so this describes
+/// `line` as an opening fence; [`FenceInfo::closes`] tells whether it closes
+/// a given block.
+///
+/// # Arguments
+///
+/// * `line` - A line of markdown text, with or without its line ending
+///
+/// # Returns
+///
+/// The fence, with a span relative to `line`, or `None` if `line` isn't a fence line.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::is_fence_line;
+///
+/// let fence = is_fence_line("  ~~~~ python title=\"app.py\"\n").unwrap();
+/// assert_eq!((fence.char, fence.len, fence.indent), ('~', 4, 2));
+/// assert_eq!(fence.info, "python title=\"app.py\"");
+///
+/// assert!(is_fence_line("    ```").is_none()); // indented code
+/// assert!(is_fence_line("``` `x` ```").is_none()); // inline code
+/// ```
+pub fn is_fence_line(line: &str) -> Option<FenceInfo<'_>> {
+    let (fence, info) = opening_fence(line)?;
+    let end = line.trim_end_matches(['\n', '\r']).len();
+    Some(fence_info(line, fence, info, 0..end, false))
+}
+
+/// Finds the fences opening and closing the code blocks of `text`, in document order.
+///
+/// Fence-like lines inside a block, such as shorter fences, are content and
+/// are not returned. A block left unclosed has no closing fence.
+///
+/// # Arguments
+///
+/// * `text` - The markdown text to find fences in
+///
+/// # Returns
+///
+/// Every opening fence, each followed by its closing fence if it has one.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::find_fences;
+///
+/// let fences = find_fences("````md\n```\nnested\n```\n````\n~~~\nunclosed\n");
+///
+/// let lines: Vec<_> = fences.iter().map(|fence| (fence.len, fence.closing)).collect();
+/// assert_eq!(lines, [(4, false), (4, true), (3, false)]);
+/// ```
+pub fn find_fences(text: &str) -> Vec<FenceInfo<'_>> {
+    let mut fences = Vec::new();
+
+    for block in ParserBlocks::new(text) {
+        let opening_line = line_at(text, block.span.start);
+        let (fence, info) =
+            opening_fence(&text[opening_line.clone()]).expect("blocks start with a fence");
+        fences.push(fence_info(text, fence, info, opening_line, false));
+
+        if block.content_span.end < block.span.end {
+            let closing_line = line_at(text, block.content_span.end);
+            let (fence, info) =
+                opening_fence(&text[closing_line.clone()]).expect("closing fences are fence lines");
+            fences.push(fence_info(text, fence, info, closing_line, true));
+        }
+    }
+
+    fences
+}
+
+/// Returns whether every fence of `text` is closed, as a quick check of an
+/// LLM response before accepting it.
+///
+/// This is [`first_unbalanced_fence`] returning `None`.
+///
+/// # Arguments
+///
+/// * `text` - The markdown text to check
+///
+/// # Returns
+///
+/// `true` if no code block is left open at the end of `text`.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::fences_balanced;
+///
+/// assert!(fences_balanced("```sh\nls\n```\nDone"));
+/// assert!(!fences_balanced("```sh\nls\n"));
+/// ```
+pub fn fences_balanced(text: &str) -> bool {
+    first_unbalanced_fence(text).is_none()
+}
+
+/// Returns the line of the first fence of `text` that is never closed.
+///
+/// Fences follow the CommonMark rules of [`find_fences`], except that fences
+/// in block quotes and list items count too, as in [strict](crate::StripOptions::strict)
+/// mode. A block is closed by a fence of the same character, at least as long
+/// as the opening one and without an info string; other fence lines inside
+/// it are content. The check makes a single pass over the lines of `text`
+/// and doesn't allocate.
+///
+/// # Arguments
+///
+/// * `text` - The markdown text to check
+///
+/// # Returns
+///
+/// The line of the opening fence of the block left open, starting at 1, or
+/// `None` if every block is closed.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::first_unbalanced_fence;
+///
+/// assert_eq!(first_unbalanced_fence("> ```\n> quoted\n> ```\n\n````md\n```\n"), Some(5));
+/// assert_eq!(first_unbalanced_fence("- item\n  ```\n  code\n  ```"), None);
+/// ```
+pub fn first_unbalanced_fence(text: &str) -> Option<usize> {
+    let mut open: Option<(Fence, usize)> = None;
+
+    for (index, line) in text.lines().enumerate() {
+        let Some((fence, info)) = opening_fence(strip_containers(line)) else {
+            continue;
+        };
+        match open {
+            None => open = Some((fence, index + 1)),
+            Some((opening, _))
+                if fence.char == opening.char
+                    && fence.len >= opening.len
+                    && trim_info(info).is_empty() =>
+            {
+                open = None;
+            }
+            Some(_) => {}
+        }
+    }
+    open.map(|(_, line)| line)
+}
+
+/// Returns the range of the line of `text` starting at `start`, without its line ending.
+fn line_at(text: &str, start: usize) -> Range<usize> {
+    let line = &text[start..];
+    let line = &line[..line.find('\n').unwrap_or(line.len())];
+    start..start + line.trim_end_matches('\r').len()
+}
+
+/// Describes the fence `fence` with the raw info string `info`, on the line `span` of `text`.
+fn fence_info<'a>(
+    text: &'a str,
+    fence: Fence,
+    info: &'a str,
+    span: Range<usize>,
+    closing: bool,
+) -> FenceInfo<'a> {
+    FenceInfo {
+        char: char::from(fence.char),
+        len: fence.len,
+        indent: indentation(&text[span.clone()]).0,
+        info: trim_info(info),
+        span,
+        closing,
+    }
+}
+
+/// Wraps `content` in a fenced code block tagged with `lang`, the inverse of
+/// stripping with the [parser](crate::experimental::Engine::Parser) engine.
+///
+/// Only the parser engine reads fences by their length and character, as
+/// CommonMark does. The default [regex](crate::experimental::Engine::Regex)
+/// engine closes a block at the first triple backticks and doesn't know
+/// tilde fences, so it gives back the content only when the content has no
+/// triple backticks and the fence is one of backticks.
+///
+/// The fence is chosen so the block can't be closed early by the content:
+///
+/// - Backtick fences are used, one backtick longer than the longest run of
+///   backticks in the content (and at least three long).
+/// - If `lang` contains a backtick, which backtick fences don't allow in their
+///   info string, a tilde fence is used instead, sized the same way against
+///   runs of tildes.
+///
+/// A newline is added after the content if it doesn't end with one, so the
+/// closing fence is on its own line. No newline follows the closing fence.
+/// Newlines in `lang` are replaced by spaces.
+///
+/// When the content ends with a newline, stripping the result with the
+/// parser engine gives back the content exactly.
+///
+/// # Arguments
+///
+/// * `content` - The code to wrap
+/// * `lang` - The info string of the block, or `""` for none
+///
+/// # Returns
+///
+/// The fenced code block.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::experimental::Engine;
+/// use strip_codeblocks::{StripOptions, fence_content, strip_codeblocks, strip_codeblocks_with};
+///
+/// assert_eq!(fence_content("fn main() {}\n", "rust"), "```rust\nfn main() {}\n```");
+///
+/// // Content with a fence inside gets a longer fence
+/// assert_eq!(fence_content("```\nnested\n```", "md"), "````md\n```\nnested\n```\n````");
+///
+/// let code = "let s = `template`;\n";
+/// assert_eq!(strip_codeblocks(&fence_content(code, "js")), code);
+///
+/// // Nested fences round-trip with the parser engine
+/// let options = StripOptions::new().engine(Engine::Parser);
+/// let nested = "```\nnested\n```\n";
+/// assert_eq!(strip_codeblocks_with(&fence_content(nested, "md"), &options), nested);
+/// ```
+pub fn fence_content(content: &str, lang: &str) -> String {
+    let lang = lang.trim().replace(['\r', '\n'], " ");
+    let fence_char = if lang.contains('`') { '~' } else { '`' };
+    let fence = fence_char
+        .to_string()
+        .repeat(fence_len(content, fence_char));
+    wrap(content, &lang, &fence)
+}
+
+/// Returns `content` between an opening fence `fence` followed by `lang` and
+/// a closing fence `fence`.
+fn wrap(content: &str, lang: &str, fence: &str) -> String {
+    let mut output = String::with_capacity(content.len() + lang.len() + 2 * fence.len() + 2);
+    output.push_str(fence);
+    output.push_str(lang);
+    output.push('\n');
+    output.push_str(content);
+    if !content.is_empty() && !content.ends_with('\n') {
+        output.push('\n');
+    }
+    output.push_str(fence);
+    output
+}
+
+/// How the fences of code blocks are chosen when a [`Document`](crate::Document)
+/// is rendered from segments, with [`Document::from_segments`](crate::Document::from_segments).
+///
+/// Whatever the policy, a fence is never one the content of its block could
+/// close, and backtick fences give way to tilde fences for info strings with
+/// backticks, as in [`fence_content`].
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::{CodeBlock, Document, FencePolicy};
+///
+/// let segments = vec![CodeBlock::new("sh", "ls\n").into()];
+/// let doc = Document::from_segments(segments, FencePolicy::Fixed(5));
+///
+/// assert_eq!(doc.text(), "`````sh\nls\n`````");
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum FencePolicy {
+    /// Use the shortest fence the content can't close, at least three
+    /// characters long, like [`fence_content`].
+    #[default]
+    Minimal,
+    /// Use fences of the given length, at least three, or longer where the
+    /// content would close them.
+    Fixed(usize),
+    /// Use the fence the block was found behind, character and length, if
+    /// the content still can't close it, so unedited blocks render as they
+    /// were written. Blocks without a [fence](crate::CodeBlock::fence), and
+    /// blocks whose content would close theirs, get the minimal fence.
+    PreserveOriginal,
+}
+
+impl FencePolicy {
+    /// Returns `block` in fences chosen by this policy.
+    pub(crate) fn render(self, block: &CodeBlock<'_>) -> String {
+        let lang = block.info.replace(['\r', '\n'], " ");
+        let original = block.fence.chars().next();
+        let fence_char = match original {
+            _ if lang.contains('`') => '~',
+            Some(fence_char) if self == Self::PreserveOriginal => fence_char,
+            _ => '`',
+        };
+        let minimal = fence_len(block.content, fence_char);
+        let len = match self {
+            Self::Minimal => minimal,
+            Self::Fixed(len) => len.max(minimal),
+            Self::PreserveOriginal if original == Some(fence_char) => {
+                block.fence.len().max(minimal)
+            }
+            Self::PreserveOriginal => minimal,
+        };
+        wrap(block.content, &lang, &fence_char.to_string().repeat(len))
+    }
+}
+
+/// Returns the length of a fence of `fence_char` that can't be closed by `content`.
+pub(crate) fn fence_len(content: &str, fence_char: char) -> usize {
+    let longest_run = content
+        .split(|c| c != fence_char)
+        .map(|run| run.len())
+        .max()
+        .unwrap_or(0);
+
+    (longest_run + 1).max(3)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fences_match_the_parser() {
+        let input = "Intro\r\n   ```sh\r\nls\r\n   ```\r\n\t```\nnot a fence\n";
+        let fences = find_fences(input);
+
+        assert_eq!(fences.len(), 2);
+        assert_eq!(&input[fences[0].span.clone()], "   ```sh");
+        assert_eq!(&input[fences[1].span.clone()], "   ```");
+        assert!(fences[1].closing && fences[1].closes(&fences[0]));
+        assert_eq!(ParserBlocks::new(input).count(), 1);
+    }
+
+    #[test]
+    fn test_fence_lines() {
+        assert!(is_fence_line("``").is_none());
+        assert!(is_fence_line("text ```").is_none());
+        assert_eq!(is_fence_line("```\r\n").unwrap().span, 0..3);
+        assert_eq!(is_fence_line("~~~ a`b").unwrap().info, "a`b");
+    }
+
+    #[test]
+    fn test_balanced_fences() {
+        assert!(fences_balanced(""));
+        assert!(fences_balanced("Call ```inline``` code"));
+        // A shorter fence is content, a longer one closes the block
+        assert_eq!(first_unbalanced_fence("````\n```\n`````\n"), None);
+        assert_eq!(first_unbalanced_fence("```\n~~~\n```py\n"), Some(1));
+        assert_eq!(first_unbalanced_fence("```\na\n```\r\n1. ~~~\r\n"), Some(4));
+    }
+
+    #[test]
+    fn test_fence_without_language() {
+        assert_eq!(fence_content("code\n", ""), "```\ncode\n```");
+    }
+
+    #[test]
+    fn test_fence_adds_missing_newline() {
+        assert_eq!(fence_content("x = 1", "python"), "```python\nx = 1\n```");
+        assert_eq!(fence_content("", "python"), "```python\n```");
+    }
+
+    #[test]
+    fn test_fence_longer_than_backtick_runs() {
+        assert_eq!(
+            fence_content("a ````` b\n", ""),
+            "``````\na ````` b\n``````"
+        );
+    }
+
+    #[test]
+    fn test_tilde_fence_for_backticks_in_info() {
+        assert_eq!(
+            fence_content("~~~~\n", "weird`lang"),
+            "~~~~~weird`lang\n~~~~\n~~~~~"
+        );
+    }
+
+    #[test]
+    fn test_nested_fences_round_trip_with_the_parser() {
+        let options = crate::StripOptions::new().engine(crate::experimental::Engine::Parser);
+        for (content, lang) in [
+            ("```\nnested\n```\n", "md"),
+            ("````sh\nls\n````\n```\n", ""),
+            ("~~~\na\n~~~\n```\nb\n```\n", "weird`lang"),
+        ] {
+            let fenced = fence_content(content, lang);
+            assert_eq!(
+                crate::strip_codeblocks_with(&fenced, &options),
+                content,
+                "{fenced}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_fence_sanitizes_language() {
+        assert_eq!(
+            fence_content("a\n", " rust\nignore "),
+            "```rust ignore\na\n```"
+        );
+    }
+}
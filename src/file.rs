@@ -0,0 +1,199 @@
+//! Stripping files too large to load in memory, and readers of any kind.
+
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::StripOptions;
+use crate::strip::strip_to;
+
+/// Strips the fenced code blocks of the file at `path` into a new file at `out_path`.
+///
+/// The output is streamed to `out_path` as it is produced. With the `mmap`
+/// feature the input is memory-mapped instead of being read, so even
+/// multi-gigabyte files are processed without loading them into memory; the
+/// input must then not be modified by anyone else while it is stripped.
+///
+/// # Arguments
+///
+/// * `path` - The markdown file containing code blocks to strip
+/// * `out_path` - Where the stripped text is written; any existing file is replaced
+/// * `options` - Which blocks are stripped and how
+///
+/// # Returns
+///
+/// `Ok(())` once the whole output was written, or the first I/O error. Input
+/// that isn't valid UTF-8 is an [`InvalidData`](io::ErrorKind::InvalidData)
+/// error, and writing to the input file itself an
+/// [`InvalidInput`](io::ErrorKind::InvalidInput) one.
+///
+/// # Examples
+///
+/// ```no_run
+/// use strip_codeblocks::{StripOptions, strip_file};
+///
+/// strip_file("transcripts.md", "transcripts.txt", &StripOptions::new())?;
+/// # Ok::<_, std::io::Error>(())
+/// ```
+pub fn strip_file(
+    path: impl AsRef<Path>,
+    out_path: impl AsRef<Path>,
+    options: &StripOptions,
+) -> io::Result<()> {
+    let (path, out_path) = (path.as_ref(), out_path.as_ref());
+    if out_path.exists() && fs::canonicalize(path)? == fs::canonicalize(out_path)? {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "cannot strip a file into itself",
+        ));
+    }
+
+    let input = read(path)?;
+    strip_bytes(&input, BufWriter::new(File::create(out_path)?), options)
+}
+
+/// Strips the fenced code blocks of the text read from `reader`, writing the
+/// stripped text to `writer`.
+///
+/// `reader` and `writer` can be any [`Read`] and [`Write`], so compressed
+/// documents are stripped by passing a decoder and an encoder, such as
+/// `flate2::read::GzDecoder` and `zstd::Encoder`, without writing the
+/// decompressed text to a temporary file. The text is read whole before it is
+/// stripped, as a block is only known once its closing fence is found, and
+/// the output is streamed to `writer` as it is produced.
+///
+/// # Arguments
+///
+/// * `reader` - Where the markdown text containing code blocks is read from
+/// * `writer` - Where the stripped text is written
+/// * `options` - Which blocks are stripped and how
+///
+/// # Returns
+///
+/// `Ok(())` once the whole output was written and `writer` flushed, or the
+/// first I/O error. Input that isn't valid UTF-8 is an
+/// [`InvalidData`](io::ErrorKind::InvalidData) error.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::{StripOptions, strip_compressed_reader};
+///
+/// let input = "Run:\n```sh\nls\n```";
+/// let mut output = Vec::new();
+/// strip_compressed_reader(input.as_bytes(), &mut output, &StripOptions::new())?;
+///
+/// assert_eq!(output, b"Run:\nls\n");
+/// # Ok::<_, std::io::Error>(())
+/// ```
+pub fn strip_compressed_reader(
+    mut reader: impl Read,
+    writer: impl Write,
+    options: &StripOptions,
+) -> io::Result<()> {
+    let mut input = Vec::new();
+    reader.read_to_end(&mut input)?;
+    strip_bytes(&input, writer, options)
+}
+
+/// Strips `input`, which must be UTF-8, into `writer`.
+fn strip_bytes(input: &[u8], writer: impl Write, options: &StripOptions) -> io::Result<()> {
+    let text = std::str::from_utf8(input)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+    let mut output = IoWriter {
+        inner: writer,
+        error: None,
+    };
+    if strip_to(text, options, None, &mut output).is_err() {
+        return Err(output
+            .error
+            .unwrap_or_else(|| io::Error::other("formatting failed")));
+    }
+    output.inner.flush()
+}
+
+/// Maps the file at `path` into memory.
+#[cfg(feature = "mmap")]
+fn read(path: &Path) -> io::Result<impl std::ops::Deref<Target = [u8]>> {
+    let file = File::open(path)?;
+    // SAFETY: the mapping is only read, and the documentation of `strip_file`
+    // requires that the file is not modified while it is mapped.
+    unsafe { memmap2::Mmap::map(&file) }
+}
+
+/// Reads the file at `path`.
+#[cfg(not(feature = "mmap"))]
+fn read(path: &Path) -> io::Result<Vec<u8>> {
+    fs::read(path)
+}
+
+/// Adapts an [`io::Write`] to [`fmt::Write`], keeping the I/O error that
+/// interrupted writing.
+struct IoWriter<W> {
+    inner: W,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> fmt::Write for IoWriter<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.inner.write_all(s.as_bytes()).map_err(|err| {
+            self.error = Some(err);
+            fmt::Error
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strip_codeblocks_with;
+
+    #[test]
+    fn test_strip_file() {
+        let dir = std::env::temp_dir().join(format!("strip-file-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (input, output) = (dir.join("in.md"), dir.join("out.txt"));
+
+        let text = "Intro\n```rust\nfn main() {}\n```\n".repeat(1000);
+        fs::write(&input, &text).unwrap();
+        let options = StripOptions::new().preserve_whitespace(true);
+        strip_file(&input, &output, &options).unwrap();
+        assert_eq!(
+            fs::read_to_string(&output).unwrap(),
+            strip_codeblocks_with(&text, &options)
+        );
+
+        let err = strip_file(&input, &input, &options).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+        fs::write(&input, b"\xff\xfe").unwrap();
+        let err = strip_file(&input, &output, &options).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        fs::write(&input, "").unwrap();
+        strip_file(&input, &output, &options).unwrap();
+        assert_eq!(fs::read_to_string(&output).unwrap(), "");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_strip_compressed_reader() {
+        let text = "Intro\n```rust\nfn main() {}\n```\n".repeat(100);
+        let options = StripOptions::new().action(crate::Action::Remove);
+        let mut output = Vec::new();
+        // Reading in small pieces, like a decoder does
+        strip_compressed_reader(
+            io::BufReader::with_capacity(7, text.as_bytes()),
+            &mut output,
+            &options,
+        )
+        .unwrap();
+        assert_eq!(output, strip_codeblocks_with(&text, &options).as_bytes());
+
+        let err = strip_compressed_reader(&b"\xff"[..], io::sink(), &options).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
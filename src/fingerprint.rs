@@ -0,0 +1,296 @@
+//! Fingerprinting and comparing code blocks, for deduplication.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::strip::strip_to;
+use crate::{CodeBlock, StripOptions};
+
+/// FNV-1a offset basis and prime, 64-bit variant.
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// A byte never found in UTF-8 text, separating the parts of block ids.
+const SEPARATOR: &[u8] = &[0xff];
+
+/// An identifier of a code block that stays the same across unrelated edits
+/// of its document, as returned by [`CodeBlock::id`].
+///
+/// Ids display as 16 hexadecimal digits, and are ordered by their value, not
+/// by the position of their blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BlockId(u64);
+
+impl BlockId {
+    /// Returns the id as a number, e.g. to store it.
+    pub fn as_u64(self) -> u64 {
+        self.0
+    }
+}
+
+impl fmt::Display for BlockId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+impl CodeBlock<'_> {
+    /// Returns an id of the block in `text`, the document it was found in,
+    /// that survives edits elsewhere in the document, for caching what was
+    /// computed from blocks between versions of a document.
+    ///
+    /// The id is derived from the block's fence, info string and content,
+    /// and from how many identical copies of the block come before it in
+    /// `text`, rather than from its offset or [`index`](Self::index). It
+    /// changes when the block itself changes, or when a copy of it is added
+    /// or removed before it, and not when prose or other blocks are edited,
+    /// added or removed. Copies of a block in a document have different ids.
+    /// Like [fingerprints](Self::fingerprint), ids are 64-bit FNV-1a hashes,
+    /// stable across platforms, processes and crate versions, and not
+    /// cryptographic. Finding the copies reads the text before the block.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strip_codeblocks::extract_codeblocks;
+    ///
+    /// let before = "Intro\n```sh\nls\n```\n```sh\nls\n```";
+    /// let after = "New intro\n```py\npass\n```\n```sh\nls\n```\n```sh\nls\n```";
+    ///
+    /// let old = extract_codeblocks(before);
+    /// let new = extract_codeblocks(after);
+    ///
+    /// assert_eq!(old[0].id(before), new[1].id(after));
+    /// assert_eq!(old[1].id(before), new[2].id(after));
+    /// assert_ne!(old[0].id(before), old[1].id(before));
+    /// ```
+    pub fn id(&self, text: &str) -> BlockId {
+        let copies = match text.get(self.span.clone()) {
+            Some(source) if !source.is_empty() => text[..self.span.start].matches(source).count(),
+            _ => 0,
+        };
+        let mut hash = FNV_OFFSET;
+        for part in [self.fence, self.info_raw, self.content] {
+            hash = fnv1a(hash, part.as_bytes());
+            hash = fnv1a(hash, SEPARATOR);
+        }
+        BlockId(fnv1a(hash, &(copies as u64).to_le_bytes()))
+    }
+
+    /// Returns a hash of the block's content that ignores differences in whitespace.
+    ///
+    /// The content is split into whitespace-separated tokens, and the tokens
+    /// are hashed in order, so blocks that only differ in indentation, line
+    /// breaks or trailing spaces have the same fingerprint. The language and
+    /// info string are not part of the fingerprint.
+    ///
+    /// The hash is 64-bit FNV-1a, which is stable across platforms, processes
+    /// and crate versions, so fingerprints can be stored and compared later.
+    /// It is not a cryptographic hash.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strip_codeblocks::extract_codeblocks;
+    ///
+    /// let blocks = extract_codeblocks("```rust\nfn main() {\n    run();\n}\n```\n```\nfn main() { run(); }\n```");
+    /// assert_eq!(blocks[0].fingerprint(), blocks[1].fingerprint());
+    /// ```
+    pub fn fingerprint(&self) -> u64 {
+        let mut hash = FNV_OFFSET;
+        for (index, token) in self.content.split_whitespace().enumerate() {
+            if index > 0 {
+                hash = fnv1a(hash, b" ");
+            }
+            hash = fnv1a(hash, token.as_bytes());
+        }
+        hash
+    }
+}
+
+fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    for &byte in bytes {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+/// A writer hashing the output of stripping as it is written.
+struct HashingWriter {
+    output: String,
+    hash: u64,
+}
+
+impl fmt::Write for HashingWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.output.push_str(s);
+        self.hash = fnv1a(self.hash, s.as_bytes());
+        Ok(())
+    }
+}
+
+/// Strips fenced code blocks like [`strip_codeblocks`](crate::strip_codeblocks),
+/// also returning a hash of the output.
+///
+/// This is [`strip_and_hash_with`] with [`StripOptions::new`].
+///
+/// # Arguments
+///
+/// * `text` - The markdown text containing code blocks to strip
+///
+/// # Returns
+///
+/// The stripped text, and its hash.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::strip_and_hash;
+///
+/// let (output, hash) = strip_and_hash("```sh\nls\n```");
+/// assert_eq!(output, "ls\n");
+/// assert_eq!(hash, 0x1294_c619_1de9_ae06);
+/// assert_eq!(strip_and_hash("ls\n").1, hash);
+/// ```
+pub fn strip_and_hash(text: &str) -> (String, u64) {
+    strip_and_hash_with(text, &StripOptions::new())
+}
+
+/// Strips fenced code blocks like [`strip_codeblocks_with`](crate::strip_codeblocks_with),
+/// also returning a hash of the output, for deduplicating stripped documents.
+///
+/// The output is hashed piece by piece while it is written, so the result
+/// isn't read again to hash it. The hash is the 64-bit FNV-1a of the bytes of
+/// the output, exactly, so documents stripping to the same text have the same
+/// hash, which is stable across platforms, processes and crate versions like
+/// [fingerprints](CodeBlock::fingerprint). It is not a cryptographic hash.
+///
+/// # Arguments
+///
+/// * `text` - The markdown text containing code blocks to strip
+/// * `options` - Which blocks are stripped and how
+///
+/// # Returns
+///
+/// The stripped text, and its hash.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::{Action, StripOptions, strip_and_hash_with};
+///
+/// let options = StripOptions::new().action(Action::Remove);
+///
+/// let (_, a) = strip_and_hash_with("Hi\n```sh\nls\n```", &options);
+/// let (_, b) = strip_and_hash_with("Hi\n```py\npass\n```", &options);
+/// assert_eq!(a, b);
+/// ```
+pub fn strip_and_hash_with(text: &str, options: &StripOptions) -> (String, u64) {
+    let mut writer = HashingWriter {
+        output: String::with_capacity(text.len()),
+        hash: FNV_OFFSET,
+    };
+    strip_to(text, options, None, &mut writer).expect("hashing a String never fails");
+    (writer.output, writer.hash)
+}
+
+/// Returns how similar the contents of two blocks are, from `0.0` (nothing in common) to `1.0`.
+///
+/// The similarity is the Jaccard index of the sets of token trigrams (runs of
+/// three consecutive whitespace-separated tokens) of both blocks. Like
+/// [`CodeBlock::fingerprint`], it ignores whitespace differences, so blocks
+/// with the same fingerprint have a similarity of `1.0`. Blocks with fewer than
+/// three tokens are compared on their tokens, and two empty blocks are
+/// identical.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::{extract_codeblocks, similar_blocks};
+///
+/// let blocks = extract_codeblocks(
+///     "```\nlet total = items.iter().sum();\nprintln!(\"{total}\");\nreturn total;\n```\n\
+///      ```\nlet total = items.iter().sum();\nprintln!(\"{total}\");\nreturn total + 1;\n```\n\
+///      ```\nSELECT * FROM users;\n```",
+/// );
+///
+/// assert_eq!(similar_blocks(&blocks[0], &blocks[1]), 0.5);
+/// assert_eq!(similar_blocks(&blocks[0], &blocks[2]), 0.0);
+/// assert_eq!(similar_blocks(&blocks[0], &blocks[0]), 1.0);
+/// ```
+pub fn similar_blocks(a: &CodeBlock<'_>, b: &CodeBlock<'_>) -> f32 {
+    let a = shingles(a.content);
+    let b = shingles(b.content);
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+
+    let shared = a.intersection(&b).count();
+    let total = a.len() + b.len() - shared;
+    shared as f32 / total as f32
+}
+
+/// Returns the token trigrams of `content`, or its tokens if it has fewer than three.
+fn shingles(content: &str) -> HashSet<Vec<&str>> {
+    let tokens: Vec<&str> = content.split_whitespace().collect();
+    let size = tokens.len().min(3);
+    if size == 0 {
+        return HashSet::new();
+    }
+    tokens.windows(size).map(<[&str]>::to_vec).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::experimental::{Engine, extract_codeblocks_with};
+    use crate::extract_codeblocks;
+
+    #[test]
+    fn test_fingerprint_is_stable() {
+        let blocks = extract_codeblocks("```\nhello world\n```\n```\n\n```");
+        // Changing these values breaks stored fingerprints
+        assert_eq!(blocks[0].fingerprint(), 0x779a_65e7_023c_d2e7);
+        assert_eq!(blocks[1].fingerprint(), FNV_OFFSET);
+    }
+
+    #[test]
+    fn test_id_is_stable() {
+        let text = "```\nhello world\n```\n~~~\nhello world\n~~~";
+        let blocks = extract_codeblocks_with(text, Engine::Parser);
+        // Changing these values breaks stored ids
+        assert_eq!(blocks[0].id(text).to_string(), "fd23fdc44bd31158");
+        assert_ne!(blocks[0].id(text), blocks[1].id(text));
+        assert_eq!(
+            CodeBlock::new("", "hello world\n").id(""),
+            CodeBlock::new("", "hello world\n").id(text)
+        );
+    }
+
+    #[test]
+    fn test_fingerprint_differs_on_content() {
+        let blocks = extract_codeblocks("```\na b\n```\n```\nab\n```\n```\nb a\n```");
+        assert_ne!(blocks[0].fingerprint(), blocks[1].fingerprint());
+        assert_ne!(blocks[0].fingerprint(), blocks[2].fingerprint());
+    }
+
+    #[test]
+    fn test_strip_and_hash_hashes_the_output() {
+        let options = StripOptions::new().merge_adjacent(true).strip_prompts(true);
+        let text = "```sh\n$ ls\n```\n```sh\n$ pwd\n```\nDone";
+        let (output, hash) = strip_and_hash_with(text, &options);
+        assert_eq!(output, crate::strip_codeblocks_with(text, &options));
+        assert_eq!(hash, fnv1a(FNV_OFFSET, output.as_bytes()));
+    }
+
+    #[test]
+    fn test_similarity_of_short_blocks() {
+        let blocks =
+            extract_codeblocks("```\nls\n```\n```\n  ls  \n```\n```\npwd\n```\n```\n\n```");
+        assert_eq!(similar_blocks(&blocks[0], &blocks[1]), 1.0);
+        assert_eq!(similar_blocks(&blocks[0], &blocks[2]), 0.0);
+        assert_eq!(similar_blocks(&blocks[0], &blocks[3]), 0.0);
+        assert_eq!(similar_blocks(&blocks[3], &blocks[3]), 1.0);
+    }
+}
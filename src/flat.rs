@@ -0,0 +1,366 @@
+//! A flat representation of stripped text referencing the input, for bindings.
+
+use std::borrow::Cow;
+use std::error::Error;
+use std::{fmt, iter};
+
+use crate::report::LineCounter;
+use crate::scan::codeblocks_for;
+use crate::strip::try_for_each_piece;
+use crate::{Annotations, BlockReport, Report, SegmentKind, StripOptions};
+
+/// A run of stripped text, as a range of the input or of
+/// [generated](StrippedRef::generated) text.
+///
+/// The layout is `#[repr(C)]`, so that bindings to other languages can read
+/// the segments of a [`StrippedRef`] in place as an array of
+/// `{ uint32_t offset; uint32_t len; uint8_t kind; }` structures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(C)]
+pub struct FlatSegment {
+    /// Byte offset of the run in the input, or in the generated text if
+    /// [`GENERATED`](Self::GENERATED) is set in `kind`.
+    pub offset: u32,
+    /// Length of the run in bytes.
+    pub len: u32,
+    /// [`PROSE`](Self::PROSE) or [`CODE`](Self::CODE), with the
+    /// [`GENERATED`](Self::GENERATED) flag set if the run isn't in the input.
+    pub kind: u8,
+}
+
+impl FlatSegment {
+    /// The kind of runs of prose.
+    pub const PROSE: u8 = 0;
+    /// The kind of runs of what a code block was stripped to.
+    pub const CODE: u8 = 1;
+    /// The flag set in the kind of runs of generated text, such as
+    /// placeholders and transformed content, which aren't in the input.
+    pub const GENERATED: u8 = 0x80;
+
+    /// Returns whether the run is prose or comes from a code block.
+    pub fn segment_kind(&self) -> SegmentKind {
+        if self.kind & !Self::GENERATED == Self::CODE {
+            SegmentKind::Code
+        } else {
+            SegmentKind::Prose
+        }
+    }
+
+    /// Returns whether the run is in the generated text rather than the input.
+    pub fn is_generated(&self) -> bool {
+        self.kind & Self::GENERATED != 0
+    }
+}
+
+/// Stripped text as runs referencing the input, returned by [`strip_codeblocks_ref`].
+///
+/// Most of the stripped text is prose and code copied from the input, so
+/// only the text stripping generated is held here, and the rest is referred
+/// to by offset. Bindings to other languages, which already hold the input,
+/// can then rebuild the output, or only look at its prose, without every
+/// string crossing the language boundary.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct StrippedRef {
+    /// The runs of the stripped text, in order. Empty runs are omitted.
+    pub segments: Vec<FlatSegment>,
+    /// The text of the runs not in the input, one after the other.
+    pub generated: String,
+}
+
+impl StrippedRef {
+    /// Returns the text of `segment`, given the `input` it was stripped from.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the segment isn't in `input` or the generated text.
+    pub fn text<'a>(&'a self, input: &'a str, segment: &FlatSegment) -> &'a str {
+        let source = if segment.is_generated() {
+            &self.generated
+        } else {
+            input
+        };
+        let start = segment.offset as usize;
+        &source[start..start + segment.len as usize]
+    }
+
+    /// Returns the stripped text, given the `input` it was stripped from.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a segment isn't in `input` or the generated text.
+    pub fn rebuild(&self, input: &str) -> String {
+        self.segments
+            .iter()
+            .map(|segment| self.text(input, segment))
+            .collect()
+    }
+}
+
+/// The error returned by [`strip_codeblocks_ref`] and its variants when the
+/// input, or the text stripping generated, is too long for the 32-bit
+/// offsets of [`FlatSegment`]: 4 GiB or longer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct TooLarge {
+    /// How many bytes long the text is, or the generated text was when it
+    /// became too long.
+    pub len: usize,
+}
+
+impl fmt::Display for TooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "the text is {} bytes long, more than 32-bit offsets can refer to",
+            self.len
+        )
+    }
+}
+
+impl Error for TooLarge {}
+
+/// Strips fenced code blocks like [`strip_codeblocks`](crate::strip_codeblocks),
+/// returning the output as runs referencing `text`.
+///
+/// This is [`strip_codeblocks_ref_with`] with the default options.
+///
+/// # Arguments
+///
+/// * `text` - The markdown text containing code blocks to strip
+///
+/// # Returns
+///
+/// The runs of the stripped text, and the text stripping generated, or an
+/// error if `text` is 4 GiB or longer, as offsets are 32-bit.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::{FlatSegment, strip_codeblocks_ref};
+///
+/// let text = "Run:\n```sh\nls\n```";
+/// let stripped = strip_codeblocks_ref(text)?;
+///
+/// assert_eq!(
+///     stripped.segments,
+///     [
+///         FlatSegment { offset: 0, len: 5, kind: FlatSegment::PROSE },
+///         FlatSegment { offset: 11, len: 3, kind: FlatSegment::CODE },
+///     ]
+/// );
+/// assert_eq!(stripped.rebuild(text), "Run:\nls\n");
+/// # Ok::<(), strip_codeblocks::TooLarge>(())
+/// ```
+pub fn strip_codeblocks_ref(text: &str) -> Result<StrippedRef, TooLarge> {
+    strip_codeblocks_ref_with(text, &StripOptions::new())
+}
+
+/// Strips fenced code blocks according to `options`, returning the output
+/// as runs referencing `text`.
+///
+/// Runs copied from the input refer to it, and runs stripping generated,
+/// such as [placeholders](crate::Action::Placeholder) or code transformed
+/// by options like [`strip_comments`](StripOptions::strip_comments), refer
+/// to the [generated](StrippedRef::generated) text instead. Concatenating
+/// the runs gives what [`strip_codeblocks_with`](crate::strip_codeblocks_with) returns.
+///
+/// # Arguments
+///
+/// * `text` - The markdown text containing code blocks to strip
+/// * `options` - Which blocks are stripped and how
+///
+/// # Returns
+///
+/// The runs of the stripped text, and the text stripping generated, or an
+/// error if `text`, or the generated text, is 4 GiB or longer, as offsets
+/// are 32-bit.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::{Action, StripOptions, strip_codeblocks_ref_with};
+///
+/// let options = StripOptions::new().action(Action::Placeholder("[code]".into()));
+///
+/// let text = "See:\n```rust\nfn main() {}\n```\n";
+/// let stripped = strip_codeblocks_ref_with(text, &options)?;
+///
+/// assert_eq!(stripped.generated, "[code]");
+/// assert!(stripped.segments[1].is_generated());
+/// assert_eq!(stripped.rebuild(text), "See:\n[code]\n");
+/// # Ok::<(), strip_codeblocks::TooLarge>(())
+/// ```
+pub fn strip_codeblocks_ref_with(
+    text: &str,
+    options: &StripOptions,
+) -> Result<StrippedRef, TooLarge> {
+    strip_ref(text, options, None)
+}
+
+/// Strips fenced code blocks like [`strip_codeblocks_ref_with`], also
+/// reporting what was done.
+///
+/// # Arguments
+///
+/// * `text` - The markdown text containing code blocks to strip
+/// * `options` - Which blocks are stripped and how
+///
+/// # Returns
+///
+/// The runs of the stripped text, and a [`Report`] listing every code block
+/// found with the action applied to it, or an error if `text`, or the
+/// generated text, is 4 GiB or longer, as offsets are 32-bit.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::{Action, StripOptions, strip_codeblocks_ref_with_report};
+///
+/// let options = StripOptions::new().languages(["sh"]);
+///
+/// let text = "```sh\nls\n```\n```py\npass\n```";
+/// let (stripped, report) = strip_codeblocks_ref_with_report(text, &options)?;
+///
+/// assert_eq!(stripped.rebuild(text), "ls\n\n```py\npass\n```");
+/// assert_eq!(report.blocks[1].action, Action::Keep);
+/// # Ok::<(), strip_codeblocks::TooLarge>(())
+/// ```
+pub fn strip_codeblocks_ref_with_report(
+    text: &str,
+    options: &StripOptions,
+) -> Result<(StrippedRef, Report), TooLarge> {
+    let mut report = Report::default();
+    let stripped = strip_ref(text, options, Some(&mut report))?;
+    Ok((stripped, report))
+}
+
+/// Strips `text` according to `options` into runs, recording each block in `report` if given.
+fn strip_ref(
+    text: &str,
+    options: &StripOptions,
+    mut report: Option<&mut Report>,
+) -> Result<StrippedRef, TooLarge> {
+    offset(text.len())?;
+    let mut lines = LineCounter::new(text);
+    let mut stripped = StrippedRef::default();
+    let blocks = codeblocks_for(text, options);
+    try_for_each_piece(text, blocks, options, |piece| {
+        if let (Some(report), Some(block), Some(action)) =
+            (report.as_deref_mut(), &piece.block, piece.action)
+        {
+            for block in iter::once(block).chain(&piece.merged) {
+                report.blocks.push(BlockReport {
+                    language: block.language.map(str::to_string),
+                    span: block.span.clone(),
+                    lines: lines.lines(&block.span),
+                    action: action.clone(),
+                    annotations: Annotations::default(),
+                });
+            }
+        }
+        if piece.output.is_empty() {
+            return Ok(());
+        }
+        let kind = match piece.kind {
+            SegmentKind::Prose => FlatSegment::PROSE,
+            SegmentKind::Code => FlatSegment::CODE,
+        };
+        let borrowed = match &piece.output {
+            Cow::Borrowed(output) => offset_in(text, output),
+            Cow::Owned(_) => None,
+        };
+        let (offset, kind) = match borrowed {
+            Some(offset) => (offset, kind),
+            None => {
+                stripped.generated.push_str(&piece.output);
+                // Runs of the input fit once the input does, but not always generated ones
+                let end = self::offset(stripped.generated.len())?;
+                (
+                    end as usize - piece.output.len(),
+                    kind | FlatSegment::GENERATED,
+                )
+            }
+        };
+        // The input and the generated text fit, so runs of them do too
+        stripped.segments.push(FlatSegment {
+            offset: offset as u32,
+            len: piece.output.len() as u32,
+            kind,
+        });
+        Ok(())
+    })?;
+    Ok(stripped)
+}
+
+/// Returns `len` as a 32-bit offset, or an error if it doesn't fit in one.
+fn offset(len: usize) -> Result<u32, TooLarge> {
+    u32::try_from(len).map_err(|_| TooLarge { len })
+}
+
+/// Returns the offset of `output` in `text`, if it is a slice of it.
+fn offset_in(text: &str, output: &str) -> Option<usize> {
+    let offset = (output.as_ptr() as usize).checked_sub(text.as_ptr() as usize)?;
+    (offset + output.len() <= text.len()).then_some(offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Action, strip_codeblocks_with};
+
+    #[test]
+    fn test_matches_strip_codeblocks() {
+        let text = "Intro\n```sh\nls\n```\n```py\npass\n```\n```\nx\n```";
+        let options = [
+            StripOptions::new(),
+            StripOptions::new().action(Action::Remove),
+            StripOptions::new()
+                .languages(["py"])
+                .action(Action::Placeholder("[py]".into())),
+            StripOptions::new()
+                .strip_comments(true)
+                .merge_adjacent(true),
+        ];
+        for options in options {
+            let stripped = strip_codeblocks_ref_with(text, &options).unwrap();
+            assert_eq!(
+                stripped.rebuild(text),
+                strip_codeblocks_with(text, &options)
+            );
+            assert!(stripped.segments.iter().all(|segment| segment.len > 0));
+
+            let (with_report, report) = strip_codeblocks_ref_with_report(text, &options).unwrap();
+            assert_eq!(with_report, stripped);
+            assert_eq!(
+                report,
+                crate::strip_codeblocks_with_report(text, &options).1
+            );
+        }
+    }
+
+    #[test]
+    fn test_segment_kinds() {
+        let text = "a\n```\nb\n```";
+        let options = StripOptions::new().action(Action::Placeholder("[x]".into()));
+        let stripped = strip_codeblocks_ref_with(text, &options).unwrap();
+
+        let code = stripped.segments[1];
+        assert_eq!(code.kind, FlatSegment::CODE | FlatSegment::GENERATED);
+        assert_eq!(code.segment_kind(), SegmentKind::Code);
+        assert_eq!(stripped.text(text, &code), "[x]");
+        assert_eq!(stripped.segments[0].segment_kind(), SegmentKind::Prose);
+        assert!(!stripped.segments[0].is_generated());
+    }
+
+    #[test]
+    fn test_too_large() {
+        assert_eq!(offset(u32::MAX as usize), Ok(u32::MAX));
+        let error = offset(u32::MAX as usize + 1).unwrap_err();
+        assert_eq!(error, TooLarge { len: 1 << 32 });
+        assert_eq!(
+            error.to_string(),
+            "the text is 4294967296 bytes long, more than 32-bit offsets can refer to"
+        );
+    }
+}
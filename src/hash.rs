@@ -0,0 +1,225 @@
+//! Content hashes embedded in placeholders.
+
+use std::borrow::Cow;
+use std::fmt::Write;
+
+use crate::StripOptions;
+use crate::newline::with_newlines;
+
+/// The round constants of SHA-256.
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// The initial hash value of SHA-256.
+const H: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+/// Returns the SHA-256 digest of `bytes`.
+pub(crate) fn sha256(bytes: &[u8]) -> [u8; 32] {
+    let mut state = H;
+
+    // The message, a one bit, zeros, and the message length in bits
+    let bit_len = (bytes.len() as u64).wrapping_mul(8);
+    let mut tail = Vec::with_capacity(128);
+    let full_blocks = bytes.len() / 64 * 64;
+    tail.extend_from_slice(&bytes[full_blocks..]);
+    tail.push(0x80);
+    while tail.len() % 64 != 56 {
+        tail.push(0);
+    }
+    tail.extend_from_slice(&bit_len.to_be_bytes());
+
+    for block in bytes[..full_blocks]
+        .chunks_exact(64)
+        .chain(tail.chunks_exact(64))
+    {
+        compress(&mut state, block);
+    }
+
+    let mut digest = [0; 32];
+    for (bytes, word) in digest.chunks_exact_mut(4).zip(state) {
+        bytes.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+/// Updates `state` with a 64-byte `block` of the padded message.
+fn compress(state: &mut [u32; 8], block: &[u8]) {
+    let mut w = [0u32; 64];
+    for (word, bytes) in w.iter_mut().zip(block.chunks_exact(4)) {
+        *word = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    }
+    for i in 16..64 {
+        let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+        let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+        w[i] = w[i - 16]
+            .wrapping_add(s0)
+            .wrapping_add(w[i - 7])
+            .wrapping_add(s1);
+    }
+
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *state;
+    for i in 0..64 {
+        let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+        let choice = (e & f) ^ (!e & g);
+        let t1 = h
+            .wrapping_add(s1)
+            .wrapping_add(choice)
+            .wrapping_add(K[i])
+            .wrapping_add(w[i]);
+        let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+        let majority = (a & b) ^ (a & c) ^ (b & c);
+        let t2 = s0.wrapping_add(majority);
+
+        h = g;
+        g = f;
+        f = e;
+        e = d.wrapping_add(t1);
+        d = c;
+        c = b;
+        b = a;
+        a = t1.wrapping_add(t2);
+    }
+
+    for (word, value) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+        *word = word.wrapping_add(value);
+    }
+}
+
+/// Returns the SHA-256 digest of `bytes` in lowercase hexadecimal.
+pub(crate) fn sha256_hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(64);
+    for byte in sha256(bytes) {
+        write!(hex, "{byte:02x}").expect("writing to a String never fails");
+    }
+    hex
+}
+
+/// Returns `placeholder` with each `{sha256}` replaced by the SHA-256 digest
+/// of `content` in hexadecimal, and each `{sha256:N}` by its first `N` digits.
+pub(crate) fn with_hashes<'a>(placeholder: &'a str, content: &str) -> Cow<'a, str> {
+    if !placeholder.contains("{sha256") {
+        return Cow::Borrowed(placeholder);
+    }
+
+    let hex = sha256_hex(content.as_bytes());
+    let mut output = String::with_capacity(placeholder.len() + hex.len());
+    let mut rest = placeholder;
+    while let Some(start) = rest.find("{sha256") {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + "{sha256".len()..];
+
+        let (digits, end) = match after.find('}') {
+            Some(0) => (hex.len(), 1),
+            Some(close) => match after[..close]
+                .strip_prefix(':')
+                .and_then(|len| len.parse::<usize>().ok())
+            {
+                Some(len) => (len.min(hex.len()), close + 1),
+                None => (0, 0),
+            },
+            None => (0, 0),
+        };
+        if end == 0 {
+            // Not a hash: copy the brace and look further
+            output.push('{');
+            rest = &rest[start + 1..];
+            continue;
+        }
+
+        output.push_str(&hex[..digits]);
+        rest = &after[end..];
+    }
+    output.push_str(rest);
+    Cow::Owned(output)
+}
+
+/// Returns the text a block of `content` is replaced with by `placeholder`,
+/// with its hashes expanded when `options` ask for it, inserting line breaks
+/// as `newline`.
+pub(crate) fn placeholder_output<'a>(
+    placeholder: &'a str,
+    content: &str,
+    options: &StripOptions,
+    newline: &str,
+) -> Cow<'a, str> {
+    if !options.hash_placeholders {
+        return with_newlines(placeholder, newline);
+    }
+    match with_hashes(placeholder, content) {
+        Cow::Borrowed(placeholder) => with_newlines(placeholder, newline),
+        Cow::Owned(expanded) => Cow::Owned(with_newlines(&expanded, newline).into_owned()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sha256_vectors() {
+        assert_eq!(
+            sha256_hex(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            sha256_hex(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+        );
+        assert_eq!(
+            sha256_hex(&[b'a'; 1000]),
+            "41edece42d63e8d9bf515a9ba6932e1c20cbc9f5a5d134645adb5db1b9737ea3"
+        );
+    }
+
+    #[test]
+    fn test_with_hashes() {
+        assert!(matches!(
+            with_hashes("[code]", "x"),
+            Cow::Borrowed("[code]")
+        ));
+        assert_eq!(
+            with_hashes("[{sha256:8}] {sha256:99}", "abc"),
+            "[ba7816bf] ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            with_hashes("{sha256:x} {sha256", "abc"),
+            "{sha256:x} {sha256"
+        );
+    }
+
+    #[test]
+    fn test_hashed_placeholders() {
+        let placeholder = crate::Action::Placeholder("[{sha256:8}]".into());
+        let options = StripOptions::new().action(placeholder);
+        let input = "```sh\nabc```\r\n\r\n```sh\nabc```\r\n";
+        assert_eq!(
+            crate::strip_codeblocks_with(input, &options.clone().hash_placeholders(true)),
+            "[ba7816bf]\r\n\r\n[ba7816bf]\r\n"
+        );
+        assert_eq!(
+            crate::strip_codeblocks_with(
+                input,
+                &options.clone().merge_adjacent(true).hash_placeholders(true)
+            ),
+            format!("[{}]\r\n", &sha256_hex(b"abc\nabc")[..8])
+        );
+        assert_eq!(
+            crate::strip_codeblocks_with(input, &options),
+            "[{sha256:8}]\r\n\r\n[{sha256:8}]\r\n"
+        );
+    }
+}
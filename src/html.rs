@@ -0,0 +1,385 @@
+//! Finding HTML `<pre>` blocks, the HTML counterpart of fenced code blocks.
+
+use std::borrow::Cow;
+use std::ops::Range;
+
+use crate::{Attributes, CodeBlock};
+
+/// The elements whose content is raw text, code of another language.
+const RAW_TEXT: [&str; 2] = ["script", "style"];
+
+/// How the `<script>` and `<style>` elements of HTML are handled in
+/// [HTML](crate::StripOptions::html) mode.
+///
+/// Stripped text is often rendered as HTML again, where such elements run
+/// scripts or restyle the page. Their content is code of another language,
+/// which holds no blocks, and unwrapped code is prose once stripped, where
+/// they shouldn't appear.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+#[non_exhaustive]
+pub enum ScriptPolicy {
+    /// Look for no block in them, and remove them, content included, from
+    /// unwrapped code.
+    #[default]
+    Remove,
+    /// Look for no block in them, and escape their tags in unwrapped code,
+    /// so they show as text.
+    Escape,
+    /// Treat them like any other HTML.
+    Allow,
+}
+
+/// Returns the raw text element `text` starts a tag of, such as
+/// `<script>` or `</style >`, if it does.
+fn raw_text_tag(text: &str, closing: bool) -> Option<&'static str> {
+    let rest = text.strip_prefix('<')?;
+    let rest = if closing {
+        rest.strip_prefix('/')?
+    } else {
+        rest
+    };
+    RAW_TEXT.into_iter().find(|name| {
+        rest.get(..name.len())
+            .is_some_and(|start| start.eq_ignore_ascii_case(name))
+            && rest[name.len()..]
+                .chars()
+                .next()
+                .is_none_or(|c| c == '>' || c == '/' || c.is_ascii_whitespace())
+    })
+}
+
+/// Returns the start and the name of the first raw text element starting in
+/// `range` of `text`, if any.
+fn raw_text_start(text: &str, range: Range<usize>) -> Option<(usize, &'static str)> {
+    let from = range.start;
+    text.get(range)?
+        .match_indices('<')
+        .find_map(|(at, _)| Some((from + at, raw_text_tag(&text[from + at..], false)?)))
+}
+
+/// Returns the end of the raw text element `name` of `text` whose content
+/// starts at `from`: the end of its end tag, or of `text` if it isn't closed,
+/// as browsers do.
+fn raw_text_end(text: &str, name: &str, from: usize) -> usize {
+    let mut at = from;
+    while let Some(start) = find_ignore_case(text, "</", at) {
+        at = start + 2;
+        if raw_text_tag(&text[start..], true) == Some(name) {
+            return text[start..]
+                .find('>')
+                .map_or(text.len(), |end| start + end + 1);
+        }
+    }
+    text.len()
+}
+
+/// Returns `content`, unwrapped code, with its `<script>` and `<style>`
+/// elements handled according to `policy`.
+pub(crate) fn defuse_raw_text(content: &str, policy: ScriptPolicy) -> Cow<'_, str> {
+    let Some((mut start, mut name)) =
+        raw_text_start(content, 0..content.len()).filter(|_| policy != ScriptPolicy::Allow)
+    else {
+        return Cow::Borrowed(content);
+    };
+
+    let mut output = String::with_capacity(content.len() + 8);
+    let mut last = 0;
+    loop {
+        output.push_str(&content[last..start]);
+        let end = raw_text_end(content, name, start);
+        if policy == ScriptPolicy::Escape {
+            // The tags of the element are escaped, and its content shows as text
+            let element = &content[start..end];
+            let mut copied = 0;
+            for (at, _) in element.match_indices('<') {
+                let rest = &element[at..];
+                if raw_text_tag(rest, false).is_some() || raw_text_tag(rest, true).is_some() {
+                    output.push_str(&element[copied..at]);
+                    output.push_str("&lt;");
+                    copied = at + 1;
+                }
+            }
+            output.push_str(&element[copied..]);
+        }
+        last = end;
+        match raw_text_start(content, last..content.len()) {
+            Some(next) => (start, name) = next,
+            None => break,
+        }
+    }
+    output.push_str(&content[last..]);
+    Cow::Owned(output)
+}
+
+/// Returns the offset of the first occurrence of `needle`, which is
+/// lowercase ASCII, in `haystack` from `from` on, regardless of case.
+fn find_ignore_case(haystack: &str, needle: &str, from: usize) -> Option<usize> {
+    haystack.as_bytes()[from..]
+        .windows(needle.len())
+        .position(|window| window.eq_ignore_ascii_case(needle.as_bytes()))
+        .map(|offset| from + offset)
+}
+
+/// Returns the range of the start tag named `name` at the start of `text`,
+/// such as `<pre class="x">`, if there is one.
+fn start_tag(text: &str, name: &str) -> Option<Range<usize>> {
+    let rest = text.strip_prefix('<')?;
+    let after = rest.get(name.len()..)?;
+    if !rest[..name.len()].eq_ignore_ascii_case(name)
+        || !after.starts_with(|c: char| c == '>' || c.is_ascii_whitespace())
+    {
+        return None;
+    }
+    Some(0..text.find('>')? + 1)
+}
+
+/// Returns the value of the `class` attribute of the start tag `tag`, if any.
+fn class(tag: &str) -> Option<&str> {
+    let mut from = 0;
+    let value = loop {
+        let at = find_ignore_case(tag, "class", from)?;
+        from = at + 5;
+        // The attribute name must stand alone, as in `<pre class=x>` but not `subclass=`
+        let preceded = tag[..at].ends_with(|c: char| c.is_ascii_whitespace());
+        let rest = tag[from..].trim_start();
+        if preceded && let Some(value) = rest.strip_prefix('=') {
+            break value.trim_start();
+        }
+    };
+
+    match value.chars().next()? {
+        quote @ ('"' | '\'') => {
+            let value = &value[1..];
+            Some(&value[..value.find(quote)?])
+        }
+        _ => {
+            let end = value
+                .find(|c: char| c.is_ascii_whitespace() || c == '>')
+                .unwrap_or(value.len());
+            Some(&value[..end])
+        }
+    }
+}
+
+/// Returns the language of the class names `class`: the rest of the first
+/// one starting with `language-` or `lang-`, as set by highlighters.
+fn class_language(class: &str) -> Option<&str> {
+    class.split_ascii_whitespace().find_map(|name| {
+        name.strip_prefix("language-")
+            .or_else(|| name.strip_prefix("lang-"))
+            .filter(|language| !language.is_empty())
+    })
+}
+
+/// Iterator over the `<pre>` elements of a document, as code blocks.
+///
+/// The content of a block is what the `<pre>` element holds, or the
+/// `<code>` element it wraps, without the line break that may follow the
+/// start tag. The info string is the `class` attribute of the `<code>`
+/// element, or else of the `<pre>` element, and the language the class
+/// names like `language-rust` give. `<code>` elements outside `<pre>`
+/// elements are inline code, and aren't blocks.
+#[derive(Debug)]
+pub(crate) struct PreBlocks<'a> {
+    text: &'a str,
+    /// Where the next element is looked for.
+    pos: usize,
+}
+
+impl<'a> PreBlocks<'a> {
+    pub(crate) fn new(text: &'a str) -> Self {
+        Self { text, pos: 0 }
+    }
+}
+
+impl<'a> Iterator for PreBlocks<'a> {
+    type Item = CodeBlock<'a>;
+
+    fn next(&mut self) -> Option<CodeBlock<'a>> {
+        let text = self.text;
+        loop {
+            let start = find_ignore_case(text, "<pre", self.pos)?;
+            self.pos = start + 4;
+            let Some(pre_tag) = start_tag(&text[start..], "pre") else {
+                continue;
+            };
+            let inner_start = start + pre_tag.end;
+            let end_tag = find_ignore_case(text, "</pre>", inner_start)?;
+            self.pos = end_tag + 6;
+
+            let mut info = class(&text[start..inner_start]).unwrap_or("");
+            let mut content_span = inner_start..end_tag;
+            // A `<code>` element wrapping the whole content holds the code
+            let inner = &text[content_span.clone()];
+            let trimmed = inner.trim_start();
+            if let Some(code_tag) = start_tag(trimmed, "code") {
+                let code_start = content_span.start + inner.len() - trimmed.len();
+                let inner = inner.trim_end();
+                let code_end = content_span.start + inner.len();
+                let closed = inner
+                    .as_bytes()
+                    .get(inner.len().saturating_sub(7)..)
+                    .is_some_and(|end| end.eq_ignore_ascii_case(b"</code>"));
+                // The end tag can't overlap the start tag, as in `<code</code>`
+                if closed && code_end - 7 >= code_start + code_tag.end {
+                    if let Some(code_class) = class(&trimmed[code_tag.clone()]) {
+                        info = code_class;
+                    }
+                    content_span = code_start + code_tag.end..code_end - 7;
+                }
+            }
+
+            // Browsers drop a line break right after the start tag
+            let content = &text[content_span.clone()];
+            if content.starts_with("\r\n") {
+                content_span.start += 2;
+            } else if content.starts_with('\n') {
+                content_span.start += 1;
+            }
+
+            return Some(CodeBlock {
+                info,
+                info_raw: info,
+                fence: "",
+                language: class_language(info),
+                content: &text[content_span.clone()],
+                span: start..self.pos,
+                content_span,
+                attributes: Attributes::default(),
+                index: 0,
+            });
+        }
+    }
+}
+
+/// Returns `fenced`, the fenced code blocks of `text`, with its `<pre>`
+/// blocks, in document order and renumbered.
+///
+/// Of blocks overlapping each other, such as a fence in a `<pre>` element or
+/// a `<pre>` element in a fenced block of HTML, the one starting first is kept.
+/// Unless `scripts` allows them, blocks in `<script>` and `<style>` elements
+/// are left out, as their content is code of another language.
+pub(crate) fn with_pre_blocks<'a>(
+    text: &'a str,
+    fenced: impl Iterator<Item = CodeBlock<'a>>,
+    scripts: ScriptPolicy,
+) -> Vec<CodeBlock<'a>> {
+    let mut candidates: Vec<_> = fenced.chain(PreBlocks::new(text)).collect();
+    candidates.sort_by_key(|block| block.span.start);
+
+    let mut blocks: Vec<CodeBlock<'a>> = Vec::with_capacity(candidates.len());
+    // The end of the last block or raw text element, before which no block starts
+    let mut prose = 0;
+    for mut block in candidates {
+        if block.span.start < prose {
+            continue;
+        }
+        if scripts != ScriptPolicy::Allow {
+            while let Some((start, name)) = raw_text_start(text, prose..block.span.start) {
+                prose = raw_text_end(text, name, start);
+            }
+            if block.span.start < prose {
+                continue;
+            }
+        }
+        block.index = blocks.len();
+        prose = block.span.end;
+        blocks.push(block);
+    }
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{StripOptions, strip_codeblocks_with};
+
+    #[test]
+    fn test_tags_and_classes() {
+        assert_eq!(start_tag("<PRE class=x>", "pre"), Some(0..13));
+        assert_eq!(start_tag("<prefix>", "pre"), None);
+        assert_eq!(
+            class("<code class=\"hl language-rust\">"),
+            Some("hl language-rust")
+        );
+        assert_eq!(class("<pre data-subclass=a CLASS='b'>"), Some("b"));
+        assert_eq!(class("<pre class=lang-py>"), Some("lang-py"));
+        assert_eq!(class("<pre>"), None);
+        assert_eq!(class_language("hl language-rust"), Some("rust"));
+        assert_eq!(class_language("language-"), None);
+    }
+
+    #[test]
+    fn test_pre_blocks() {
+        let text = "<p>Use <code>ls</code>:</p>\n<pre><code class=\"language-sh\">\nls -l\n</code></pre>\n<pre>plain</pre>";
+        let blocks: Vec<_> = PreBlocks::new(text).collect();
+
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].language, Some("sh"));
+        assert_eq!(blocks[0].content, "ls -l\n");
+        assert_eq!(
+            &text[blocks[0].span.clone()],
+            "<pre><code class=\"language-sh\">\nls -l\n</code></pre>"
+        );
+        assert_eq!((blocks[1].info, blocks[1].content), ("", "plain"));
+        assert_eq!(PreBlocks::new("<pre>unclosed").count(), 0);
+    }
+
+    #[test]
+    fn test_multibyte_content_before_end_tag() {
+        let options = StripOptions::new().html(true);
+        assert_eq!(
+            strip_codeblocks_with("<pre><code>érust*/</pre>", &options),
+            "<code>érust*/"
+        );
+        assert_eq!(
+            strip_codeblocks_with("<pre><code>é</code></pre>", &options),
+            "é"
+        );
+        assert_eq!(strip_codeblocks_with("<pre>日本</pre>", &options), "日本");
+    }
+
+    #[test]
+    fn test_raw_text_elements() {
+        assert_eq!(raw_text_tag("<SCRIPT src=a>", false), Some("script"));
+        assert_eq!(raw_text_tag("</style >", true), Some("style"));
+        assert_eq!(raw_text_tag("<scripts>", false), None);
+        assert_eq!(raw_text_end("<style>a</Style><p>", "style", 7), 16);
+        assert_eq!(raw_text_end("<script>a</style>", "script", 8), 17);
+
+        let content = "a<script>x()</script>b<STYLE>p {}";
+        assert_eq!(defuse_raw_text(content, ScriptPolicy::Remove), "ab");
+        assert_eq!(
+            defuse_raw_text(content, ScriptPolicy::Escape),
+            "a&lt;script>x()&lt;/script>b&lt;STYLE>p {}"
+        );
+        assert_eq!(defuse_raw_text(content, ScriptPolicy::Allow), content);
+    }
+
+    #[test]
+    fn test_blocks_in_scripts_are_left_out() {
+        let text = "<script>let s = `\n```\nx\n```\n<pre>y</pre>`;</script>\n<pre>z</pre>\n<style>";
+        let blocks = with_pre_blocks(text, crate::scan::codeblocks(text), ScriptPolicy::Remove);
+        assert_eq!(blocks.len(), 1);
+        assert_eq!((blocks[0].content, blocks[0].index), ("z", 0));
+        let blocks = with_pre_blocks(text, crate::scan::codeblocks(text), ScriptPolicy::Allow);
+        assert_eq!(blocks.len(), 3);
+    }
+
+    #[test]
+    fn test_overlapping_blocks() {
+        let options = StripOptions::new().html(true);
+        assert_eq!(
+            strip_codeblocks_with(
+                "```html\n<pre>x</pre>\n```\n<pre>```\ny\n```</pre>",
+                &options
+            ),
+            "<pre>x</pre>\n\n```\ny\n```"
+        );
+    }
+}
@@ -0,0 +1,184 @@
+//! Stripping markdown held in the string fields of JSON documents.
+
+use std::error::Error;
+use std::fmt;
+
+use serde_json::Value;
+
+use crate::StripOptions;
+use crate::strip::strip;
+
+/// An error returned by [`strip_in_json`] and [`strip_in_json_with`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum JsonError {
+    /// The input isn't valid JSON.
+    Syntax {
+        /// The line of the error, starting at 1.
+        line: usize,
+        /// The column of the error, starting at 1.
+        column: usize,
+        /// What is wrong.
+        message: String,
+    },
+    /// No value is found at the pointer.
+    Missing(String),
+    /// The value at the pointer isn't a string.
+    NotAString(String),
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonError::Syntax {
+                line,
+                column,
+                message,
+            } => write!(f, "line {line}, column {column}: {message}"),
+            JsonError::Missing(pointer) => write!(f, "no value at `{pointer}`"),
+            JsonError::NotAString(pointer) => write!(f, "the value at `{pointer}` is not a string"),
+        }
+    }
+}
+
+impl Error for JsonError {}
+
+impl From<serde_json::Error> for JsonError {
+    fn from(error: serde_json::Error) -> Self {
+        // The message of the error, without the position serde_json appends
+        let message = error.to_string();
+        let message = match message.rfind(" at line ") {
+            Some(position) => message[..position].to_string(),
+            None => message,
+        };
+        JsonError::Syntax {
+            line: error.line(),
+            column: error.column(),
+            message,
+        }
+    }
+}
+
+/// Strips the fenced code blocks of the strings at `pointers` in a JSON
+/// document, such as the message contents of a chat completion response.
+///
+/// This is [`strip_in_json_with`] with the default options.
+///
+/// # Arguments
+///
+/// * `json` - The JSON document
+/// * `pointers` - The [JSON Pointers](https://www.rfc-editor.org/rfc/rfc6901) of the strings to strip
+///
+/// # Returns
+///
+/// The document with the strings stripped, or why it couldn't be.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::strip_in_json;
+///
+/// let response = r#"{"choices":[{"message":{"content":"Run:\n```sh\nls\n```"}}]}"#;
+///
+/// assert_eq!(
+///     strip_in_json(response, &["/choices/0/message/content"]).unwrap(),
+///     r#"{"choices":[{"message":{"content":"Run:\nls\n"}}]}"#
+/// );
+/// ```
+pub fn strip_in_json(json: &str, pointers: &[&str]) -> Result<String, JsonError> {
+    strip_in_json_with(json, pointers, &StripOptions::new())
+}
+
+/// Strips the fenced code blocks of the strings at `pointers` in a JSON
+/// document according to `options`.
+///
+/// The document is parsed, each string is stripped in place, and the
+/// document is serialized back, compactly. Keys keep their order, but the
+/// original whitespace is not preserved, and numbers are written the way
+/// `serde_json` writes them. The document is refused as a whole when one of
+/// the pointers doesn't point at a string, so that a response of an
+/// unexpected shape isn't passed on half-stripped.
+///
+/// # Arguments
+///
+/// * `json` - The JSON document
+/// * `pointers` - The [JSON Pointers](https://www.rfc-editor.org/rfc/rfc6901) of the strings to strip
+/// * `options` - Which blocks are stripped and how
+///
+/// # Returns
+///
+/// The document with the strings stripped, or why it couldn't be.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::{Action, JsonError, StripOptions, strip_in_json_with};
+///
+/// let options = StripOptions::new().action(Action::Remove);
+/// let json = r#"{"a": "x```\ny```", "b": 1}"#;
+///
+/// assert_eq!(strip_in_json_with(json, &["/a"], &options).unwrap(), r#"{"a":"x","b":1}"#);
+/// assert_eq!(
+///     strip_in_json_with(json, &["/b"], &options),
+///     Err(JsonError::NotAString("/b".into()))
+/// );
+/// ```
+pub fn strip_in_json_with(
+    json: &str,
+    pointers: &[&str],
+    options: &StripOptions,
+) -> Result<String, JsonError> {
+    let mut document: Value = serde_json::from_str(json)?;
+
+    for pointer in pointers {
+        match document.pointer_mut(pointer) {
+            Some(Value::String(text)) => *text = strip(text, options, None),
+            Some(_) => return Err(JsonError::NotAString(pointer.to_string())),
+            None => return Err(JsonError::Missing(pointer.to_string())),
+        }
+    }
+
+    Ok(serde_json::to_string(&document)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_several_pointers() {
+        let json = r#"{"z": "```\na\n```", "list": ["```py\nb\n```", "keep ```\nc\n```"]}"#;
+        assert_eq!(
+            strip_in_json(json, &["/list/0", "/z"]).unwrap(),
+            r#"{"z":"a\n","list":["b\n","keep ```\nc\n```"]}"#
+        );
+    }
+
+    #[test]
+    fn test_escaped_pointer_tokens() {
+        let json = r#"{"a/b": {"~c": "```\nx\n```"}}"#;
+        assert_eq!(
+            strip_in_json(json, &["/a~1b/~0c"]).unwrap(),
+            r#"{"a/b":{"~c":"x\n"}}"#
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            strip_in_json(r#"{"a": "x"}"#, &["/b"]),
+            Err(JsonError::Missing("/b".into()))
+        );
+
+        let error = strip_in_json("{\n  \"a\": }", &["/a"]).unwrap_err();
+        assert!(matches!(
+            error,
+            JsonError::Syntax {
+                line: 2,
+                column: 8,
+                ..
+            }
+        ));
+        assert!(!error.to_string().contains(" at line "));
+    }
+}
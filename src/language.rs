@@ -0,0 +1,341 @@
+//! Comparing the languages of a language filter with the languages of blocks.
+
+use std::hash::{Hash, Hasher};
+use std::sync::OnceLock;
+
+use regex::{Regex, RegexBuilder};
+
+/// The regexes of the language filters of [`StripOptions`](crate::StripOptions)
+/// in [regex](LanguageMatch::regex) mode, compiled once, the first time a
+/// block is matched against them.
+///
+/// The regexes are derived from the other options, so they take no part in
+/// comparing and hashing options.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct LanguageRegexes(OnceLock<CompiledRegexes>);
+
+/// The compiled regexes of the language filters, `None` for each filter that
+/// isn't a valid regex.
+#[derive(Debug, Clone)]
+pub(crate) struct CompiledRegexes {
+    pub(crate) languages: Vec<Option<Regex>>,
+    pub(crate) deny_languages: Vec<Option<Regex>>,
+}
+
+impl LanguageRegexes {
+    /// Returns the regexes of `languages` and `deny_languages`, compiling
+    /// them the first time.
+    pub(crate) fn get_or_compile(
+        &self,
+        matching: &LanguageMatch,
+        languages: Option<&[String]>,
+        deny_languages: &[String],
+    ) -> &CompiledRegexes {
+        self.0.get_or_init(|| {
+            let compile = |filters: &[String]| -> Vec<_> {
+                filters
+                    .iter()
+                    .map(|filter| matching.compile(filter))
+                    .collect()
+            };
+            CompiledRegexes {
+                languages: compile(languages.unwrap_or_default()),
+                deny_languages: compile(deny_languages),
+            }
+        })
+    }
+
+    /// Forgets the regexes, once the filters they were compiled from change.
+    pub(crate) fn clear(&mut self) {
+        self.0 = OnceLock::new();
+    }
+}
+
+impl PartialEq for LanguageRegexes {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for LanguageRegexes {}
+
+impl Hash for LanguageRegexes {
+    fn hash<H: Hasher>(&self, _: &mut H) {}
+}
+
+/// How [`StripOptions::languages`](crate::StripOptions::languages) compares
+/// its languages with the languages of blocks.
+///
+/// By default languages match only when they are equal. Each setter relaxes
+/// the comparison, and they can be combined.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::LanguageMatch;
+///
+/// let matching = LanguageMatch::new().ignore_case(true).trim(true).prefix(true);
+///
+/// assert!(matching.matches(" Python ", "python3"));
+/// assert!(!LanguageMatch::new().matches("python", "python3"));
+///
+/// // One pattern selects every Python variant
+/// assert!(LanguageMatch::new().glob(true).matches("py*", "pycon"));
+/// assert!(LanguageMatch::new().regex(true).matches("py(thon)?3?", "python3"));
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(default, deny_unknown_fields)
+)]
+pub struct LanguageMatch {
+    ignore_case: bool,
+    trim: bool,
+    prefix: bool,
+    glob: bool,
+    regex: bool,
+}
+
+impl LanguageMatch {
+    /// Creates a comparison matching equal languages only.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Compares languages regardless of ASCII case, so `RUST` matches `rust`.
+    pub fn ignore_case(mut self, ignore_case: bool) -> Self {
+        self.ignore_case = ignore_case;
+        self
+    }
+
+    /// Ignores whitespace around the languages of the filter, so ` rust `
+    /// matches `rust`, as when languages are read from a configuration file.
+    pub fn trim(mut self, trim: bool) -> Self {
+        self.trim = trim;
+        self
+    }
+
+    /// Matches the languages starting with a language of the filter, so
+    /// `python` matches `python3`.
+    pub fn prefix(mut self, prefix: bool) -> Self {
+        self.prefix = prefix;
+        self
+    }
+
+    /// Reads the languages of the filter as glob patterns, in which `*`
+    /// matches any run of characters and `?` any single one, so `python*`
+    /// matches `python` and `python3`.
+    pub fn glob(mut self, glob: bool) -> Self {
+        self.glob = glob;
+        self
+    }
+
+    /// Reads the languages of the filter as regular expressions, in the
+    /// syntax of the [`regex`] crate, which must match whole languages, so
+    /// `py(thon)?3?|pycon` matches `py`, `python3` and `pycon` but not `pyx`.
+    ///
+    /// [`StripOptions`](crate::StripOptions) compile each expression once,
+    /// the first time a block is matched against it, while
+    /// [`matches`](Self::matches) compiles it on each call. An invalid
+    /// expression matches nothing. Takes precedence over [`glob`](Self::glob).
+    pub fn regex(mut self, regex: bool) -> Self {
+        self.regex = regex;
+        self
+    }
+
+    /// Returns whether `language`, the language of a block, matches `filter`,
+    /// a language of the filter.
+    ///
+    /// Globs and regexes are matched with the other settings too: regardless
+    /// of case if [`ignore_case`](Self::ignore_case) is set, and against the
+    /// start of the language only if [`prefix`](Self::prefix) is. A filter that is empty, once trimmed if requested, matches nothing.
+    pub fn matches(&self, filter: &str, language: &str) -> bool {
+        let filter = if self.trim { filter.trim() } else { filter };
+        if filter.is_empty() {
+            return false;
+        }
+        if self.regex {
+            return self.regex_matches(filter, language);
+        }
+        if self.glob && filter.contains(['*', '?']) {
+            return self.glob_matches(filter, language);
+        }
+
+        let compared = if self.prefix {
+            match language.get(..filter.len()) {
+                Some(start) => start,
+                None => return false,
+            }
+        } else {
+            language
+        };
+
+        if self.ignore_case {
+            compared.eq_ignore_ascii_case(filter)
+        } else {
+            compared == filter
+        }
+    }
+
+    /// Returns whether `language` matches the glob pattern `filter`.
+    fn glob_matches(&self, filter: &str, language: &str) -> bool {
+        let pattern: Vec<char> = filter.chars().collect();
+        let language: Vec<char> = language.chars().collect();
+        let same = |p: char, c: char| {
+            p == '?' || p == c || (self.ignore_case && p.eq_ignore_ascii_case(&c))
+        };
+
+        // Where to resume after the last `*`: its position, and the
+        // character of the language it is tried to end at
+        let mut star = None;
+        let (mut p, mut c) = (0, 0);
+        while c < language.len() {
+            if p == pattern.len() && self.prefix {
+                return true;
+            }
+            if p < pattern.len() && pattern[p] == '*' {
+                star = Some((p, c));
+                p += 1;
+            } else if p < pattern.len() && same(pattern[p], language[c]) {
+                p += 1;
+                c += 1;
+            } else if let Some((star_p, star_c)) = star {
+                star = Some((star_p, star_c + 1));
+                p = star_p + 1;
+                c = star_c + 1;
+            } else {
+                return false;
+            }
+        }
+        pattern[p..].iter().all(|&p| p == '*')
+    }
+
+    /// Returns whether `language` matches the regular expression `filter`.
+    fn regex_matches(&self, filter: &str, language: &str) -> bool {
+        self.compile(filter)
+            .is_some_and(|regex| regex.is_match(language))
+    }
+
+    /// Returns whether languages are compared with regexes.
+    pub(crate) fn is_regex(&self) -> bool {
+        self.regex
+    }
+
+    /// Compiles `filter` into the regex matching the languages it matches in
+    /// [regex](Self::regex) mode, or `None` if it matches nothing.
+    pub(crate) fn compile(&self, filter: &str) -> Option<Regex> {
+        let filter = if self.trim { filter.trim() } else { filter };
+        if filter.is_empty() {
+            return None;
+        }
+        let end = if self.prefix { "" } else { "$" };
+        RegexBuilder::new(&format!("^(?:{filter}){end}"))
+            .case_insensitive(self.ignore_case)
+            .build()
+            .ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{StripOptions, strip_codeblocks_with};
+
+    #[test]
+    fn test_exact_by_default() {
+        let exact = LanguageMatch::new();
+        assert!(exact.matches("rust", "rust"));
+        assert!(!exact.matches("rust", "Rust"));
+        assert!(!exact.matches(" rust", "rust"));
+        assert!(!exact.matches("", ""));
+    }
+
+    #[test]
+    fn test_prefix() {
+        let prefix = LanguageMatch::new().prefix(true);
+        assert!(prefix.matches("py", "python"));
+        assert!(!prefix.matches("python", "py"));
+        // Prefixes ending inside a character don't panic
+        assert!(!prefix.matches("ab", "aé"));
+        assert!(!prefix.trim(true).matches("  ", "rust"));
+    }
+
+    #[test]
+    fn test_glob() {
+        let glob = LanguageMatch::new().glob(true);
+        assert!(glob.matches("py*", "py"));
+        assert!(glob.matches("*sh", "bash"));
+        assert!(glob.matches("p?th*n", "python"));
+        assert!(glob.matches("a*b*c", "aXbYbZc"));
+        assert!(!glob.matches("py*", "cpython"));
+        assert!(!glob.matches("r?st", "rust1"));
+        assert!(glob.ignore_case(true).matches("PY*", "Python"));
+        assert!(glob.prefix(true).matches("r?s", "rust"));
+        // Without wildcards, filters are compared as usual
+        assert!(glob.trim(true).matches(" c ", "c"));
+    }
+
+    #[test]
+    fn test_regex() {
+        let regex = LanguageMatch::new().regex(true);
+        assert!(regex.matches("py(thon)?3?|pycon", "pycon"));
+        assert!(regex.matches("py|python", "python"));
+        assert!(!regex.matches("py(thon)?", "pyx"));
+        assert!(!regex.matches("(", "("));
+        assert!(regex.prefix(true).matches("py", "pyx"));
+        assert!(regex.ignore_case(true).matches("rust", "RUST"));
+        assert!(regex.glob(true).matches("c.*", "cpp"));
+
+        let options = StripOptions::new()
+            .languages(["py.*"])
+            .language_match(regex);
+        assert_eq!(
+            strip_codeblocks_with(
+                "```python3
+a
+```
+```sh
+b
+```",
+                &options
+            ),
+            "a
+
+```sh
+b
+```"
+        );
+    }
+
+    #[test]
+    fn test_regexes_follow_the_filters() {
+        let input = "```python3\na\n```\n```sh\nb\n```";
+        let options = StripOptions::new()
+            .language_match(LanguageMatch::new().regex(true))
+            .languages(["py.*"]);
+        assert_eq!(strip_codeblocks_with(input, &options), "a\n\n```sh\nb\n```");
+
+        // Changing a filter after it was compiled compiles it again
+        let options = options.deny_languages(["python3"]);
+        assert_eq!(strip_codeblocks_with(input, &options), input);
+        let stripper = options.languages(["s+h"]).deny_languages(["x"]).build();
+        assert_eq!(stripper.strip(input), "```python3\na\n```\nb\n");
+    }
+
+    #[test]
+    fn test_language_filter() {
+        let options = StripOptions::new()
+            .languages([" Rust "])
+            .language_match(LanguageMatch::new().ignore_case(true).trim(true));
+        let input = "```RUST\na\n```\n```rust,ignore\nb\n```\n```rusty\nc\n```";
+        assert_eq!(
+            strip_codeblocks_with(input, &options),
+            "a\n\nb\n\n```rusty\nc\n```"
+        );
+        assert_eq!(
+            options.clone().build().strip(input),
+            strip_codeblocks_with(input, &options)
+        );
+    }
+}
@@ -0,0 +1,119 @@
+//! Language lists managed outside of code, in files or environment variables.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Parses a list of languages, one per line or separated by commas.
+///
+/// Whitespace around languages is ignored, as are blank lines and lines
+/// starting with `#`, which can hold comments. The list can be given to
+/// [`StripOptions::languages`](crate::StripOptions::languages) as an
+/// allow-list, or to [`StripOptions::deny_languages`](crate::StripOptions::deny_languages)
+/// as a deny-list.
+///
+/// # Arguments
+///
+/// * `source` - The list
+///
+/// # Returns
+///
+/// The languages of the list, in order.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::parse_language_list;
+///
+/// let source = "# Never unwrapped, see the data policy\nsql\n\nhcl, terraform\n";
+/// assert_eq!(parse_language_list(source), ["sql", "hcl", "terraform"]);
+/// ```
+pub fn parse_language_list(source: &str) -> Vec<String> {
+    source
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .flat_map(|line| line.split(','))
+        .map(str::trim)
+        .filter(|language| !language.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Reads a list of languages from the file at `path`, as parsed by
+/// [`parse_language_list`], so that fleet-wide policies can be changed
+/// without recompiling their callers.
+///
+/// # Arguments
+///
+/// * `path` - The file holding the list
+///
+/// # Returns
+///
+/// The languages of the list, or the error reading the file.
+///
+/// # Examples
+///
+/// ```no_run
+/// use strip_codeblocks::{StripOptions, read_language_list};
+///
+/// let options = StripOptions::new().deny_languages(read_language_list("/etc/strip-deny.txt")?);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn read_language_list(path: impl AsRef<Path>) -> io::Result<Vec<String>> {
+    Ok(parse_language_list(&fs::read_to_string(path)?))
+}
+
+/// Reads a list of languages from the environment variable `name`, as
+/// parsed by [`parse_language_list`].
+///
+/// # Arguments
+///
+/// * `name` - The name of the variable
+///
+/// # Returns
+///
+/// The languages of the list, or `None` if the variable isn't set or isn't
+/// valid Unicode.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::{StripOptions, env_language_list};
+///
+/// let mut options = StripOptions::new();
+/// if let Some(denied) = env_language_list("MY_APP_DENIED_LANGUAGES") {
+///     options = options.deny_languages(denied);
+/// }
+/// ```
+pub fn env_language_list(name: &str) -> Option<Vec<String>> {
+    std::env::var(name)
+        .ok()
+        .map(|source| parse_language_list(&source))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_language_list() {
+        assert_eq!(
+            parse_language_list("c#\r\n  # sql\n ,  go,\n"),
+            ["c#", "go"]
+        );
+        assert!(parse_language_list("").is_empty());
+    }
+
+    #[test]
+    fn test_read_language_list() {
+        let path = std::env::temp_dir().join(format!(
+            "strip-codeblocks-languages-{}.txt",
+            std::process::id()
+        ));
+        fs::write(&path, "sql\nsh\n").unwrap();
+        assert_eq!(read_language_list(&path).unwrap(), ["sql", "sh"]);
+        fs::remove_file(&path).unwrap();
+
+        assert!(read_language_list(&path).is_err());
+    }
+}
@@ -8,7 +8,104 @@
 //! - Removes fenced code blocks (triple backticks: \`\`\`)
 //! - Preserves the content inside code blocks
 //! - Keeps inline code blocks (single backticks: \`) intact
+//! - Leaves backticks inside GFM table cells alone
+//! - Requires a blank line before fences on request, like strict dialects
 //! - Handles code blocks with or without language identifiers
+//! - Offers an experimental CommonMark-accurate parser (see [`experimental`])
+//! - Wraps content back into safely sized fences
+//! - Exposes its fence detection as standalone low-level functions
+//! - Checks that every fence is closed before an LLM response is accepted
+//! - Filters `pulldown-cmark` event streams (`pulldown` feature)
+//! - Transforms `comrak` ASTs in place (`comrak` feature)
+//! - Replaces code blocks with links to stored attachments
+//! - Writes output into any `fmt::Write`, including fixed-capacity buffers
+//! - Strips into caller-provided byte buffers without allocating, reporting the capacity needed
+//! - Extracts blocks into fixed-capacity arrays, without allocating storage for them (`bounded` feature)
+//! - Strips files larger than memory, memory-mapping them (`mmap` feature)
+//! - Strips from any reader into any writer, such as the decoder and encoder of a compressed file
+//! - Strips huge documents on a thread pool, deterministically (`parallel` feature)
+//! - Offers presets for language model answers, documentation and redaction
+//! - Reads options from JSON or TOML configuration, validating it, and exports a JSON Schema of it (`serde` feature)
+//! - Strips markdown inside JSON string fields selected by JSON Pointers (`json` feature)
+//! - Strips chat transcripts message by message, with a policy per role
+//! - Annotates blocks with typed data flowing through pipelines to their reports
+//! - Detects UTF-16 and Latin-1 input and strips it to UTF-8 (`encoding` feature)
+//! - Strips UTF-16 code units to UTF-16, for JavaScript strings, converting only what changes
+//! - Strips streamed text incrementally without ever displaying fences
+//! - Turns console session transcripts into runnable scripts
+//! - Extracts the blocks of one language into a script, refusing ambiguous documents
+//! - Decodes HTML entities inside unwrapped code
+//! - Strips HTML `<pre>` blocks like fences, leaving inline `<code>` alone
+//! - Keeps `<script>` and `<style>` out of prose in HTML mode, for re-rendered output
+//! - Strips the plain text and HTML parts of an email alike
+//! - Keeps unwrapped code from rendering as markdown headings, quotes or lists
+//! - Finds fences in terminal captures despite ANSI color codes
+//! - Copies prose byte for byte on request, and verifies that it was
+//! - Describes the lines stripping removed or altered as diff hunks
+//! - Extracts code blocks, including rustdoc-style runnable examples
+//! - Wraps bare code back into fences, guessing its language
+//! - Reports the list depth, quote depth and heading path of each block
+//! - Extracts without copying, dedenting the content of indented fences on request
+//! - Unwraps indented and blockquoted fences in place, tabs and alignment kept
+//! - Unwraps fences in GFM footnote definitions, keeping the footnote intact
+//! - Sorts, indexes and selects extracted blocks by language or length
+//! - Numbers the blocks left in the output with `Listing N:` labels
+//! - Labels blocks with the heading or bold caption preceding them
+//! - Strips the prose instead, keeping only the code blocks, fenced or unwrapped
+//! - Parses MkDocs-style attributes (`title`, `hl_lines`, `linenums`) from info strings
+//! - Locates blocks by byte, character, or line and column
+//! - Validates block content per language, with a built-in JSON check
+//! - Parses documents once into displayable segments
+//! - Iterates over the prose of documents without building stripped text
+//! - Builds documents from segments and renders them back to markdown
+//! - Keeps the original fences of blocks when rendering edited documents
+//! - Computes per-language block statistics across corpora
+//! - Displays reports as tables of blocks, lines and actions, for logs
+//! - Fingerprints and compares blocks for deduplication
+//! - Identifies blocks with ids that survive unrelated edits, for caching
+//! - Hashes the stripped text while writing it, for deduplicating documents
+//! - Chunks documents for retrieval without splitting code blocks
+//! - Counts prose and code tokens with any tokenizer while stripping
+//! - Describes stripped text as flat runs of the input, for bindings
+//! - Returns the input without copying it when no block is changed
+//! - Emits `tracing` spans and events for debugging (`tracing` feature)
+//! - Explains each fence and action in plain words, to debug surprising output
+//! - Calls hooks around each block stripped, for logging and counters, leaving output alone
+//! - Pins down its behavior in a table of cases services can run in their CI (`spec`)
+//! - Gives up at a deadline with the partial result, for latency-sensitive callers
+//! - Strips untrusted input in linear time, without panicking, in bounded memory
+//! - Summarizes blocks by their first line, typically a command
+//! - Shrinks long blocks to their first and last lines around an omission marker
+//! - Removes comments from unwrapped code, by language
+//! - Strips each side of unified diffs, keeping a valid patch
+//! - Strips nested markdown-in-markdown level by level until stable
+//! - Embeds content hashes in placeholders, for auditing redacted output
+//! - Masks blocks with stable ids, keeping a table that restores them
+//! - Inserts line breaks matching the document's line endings
+//! - Ends the output with a line break always, never, or like the document
+//! - Fails on ambiguous structures in strict mode instead of guessing
+//! - Merges consecutive blocks of the same language into one
+//! - Protects sections of a document between `strip-codeblocks: off`/`on` comments
+//! - Compiles options once to strip many documents without per-call setup
+//! - Matches languages regardless of case, surrounding whitespace, or version suffix
+//! - Matches languages against glob patterns or regular expressions
+//! - Selects blocks with expressions like `lang:rust & lines>10`
+//! - Only strips blocks after or before a position, such as a final answer
+//! - Selects blocks by language and unwraps, keeps, removes or replaces them
+//! - Loads language allow- and deny-lists from files or environment variables
+//! - Declares a different action per language in one table
+//! - Keeps LaTeX math blocks verbatim, or replaces them under a policy of their own
+//! - Composes further prose and code transformations in the same pass
+//! - Frames unwrapped blocks with generated headers and footers
+//!
+//! ## Thread safety
+//!
+//! Every public type is `Send + Sync`, and there is no global state. Options
+//! compiled into a [`Stripper`], or a [`Pipeline`], can be built once and
+//! shared by any number of threads, by reference or by cloning them, which
+//! is cheap: clones share what was compiled. Per-document state, such as
+//! that of a [`DeltaStripper`] following a response stream, is owned by
+//! the value holding it.
 //!
 //! ## Usage
 //!
@@ -43,12 +140,251 @@
 //! assert_eq!(output, "This has `inline code` and code block\n");
 //! ```
 
-use regex::Regex;
+mod annotations;
+mod anonymize;
+mod ansi;
+mod attributes;
+mod bare;
+mod block;
+#[cfg(feature = "bounded")]
+mod bounded;
+mod buffer;
+mod chat;
+mod chunk;
+mod comments;
+#[cfg(feature = "comrak")]
+mod comrak;
+#[cfg(feature = "serde")]
+mod config;
+mod console;
+mod content;
+mod context;
+mod deadline;
+mod dedent;
+mod delta;
+mod diff;
+mod document;
+mod email;
+#[cfg(feature = "encoding")]
+mod encoding;
+mod entities;
+pub mod experimental;
+mod explain;
+mod extract;
+mod fence;
+mod file;
+mod fingerprint;
+mod flat;
+mod hash;
+mod html;
+#[cfg(feature = "json")]
+mod json;
+mod language;
+mod language_list;
+mod links;
+mod listings;
+mod matcher;
+mod math;
+mod merge;
+mod newline;
+mod options;
+#[cfg(feature = "parallel")]
+mod parallel;
+mod patch;
+mod pipeline;
+mod preserve;
+mod preset;
+mod prose;
+#[cfg(feature = "pulldown")]
+mod pulldown;
+mod region;
+mod render_safety;
+mod replacements;
+mod report;
+mod scan;
+mod script;
+mod segment;
+mod shrink;
+pub mod spec;
+mod stable;
+mod stats;
+mod strict;
+mod strip;
+mod stripper;
+mod table;
+mod title;
+mod tokens;
+mod trace;
+mod utf16;
+mod validate;
+
+use std::borrow::Cow;
+
+pub use annotations::Annotations;
+pub use anonymize::{MaskTable, anonymize, anonymize_with};
+pub use ansi::Ansi;
+pub use attributes::Attributes;
+pub use bare::fence_bare_code;
+pub use block::{CodeBlock, Position};
+#[cfg(feature = "bounded")]
+pub use bounded::{
+    BlockArray, CapacityExceeded, extract_codeblocks_bounded, extract_codeblocks_bounded_by,
+};
+pub use buffer::{BufferTooSmall, strip_codeblocks_into};
+pub use chat::{ChatPolicy, Role, strip_chat, strip_chat_with};
+pub use chunk::{Chunk, ChunkOptions, chunk_document};
+#[cfg(feature = "comrak")]
+pub use comrak::strip_comrak_codeblocks;
+#[cfg(feature = "serde")]
+pub use config::OptionsError;
+pub use context::BlockContext;
+pub use deadline::{DeadlineExceeded, strip_with_deadline};
+pub use dedent::Dedent;
+pub use delta::DeltaStripper;
+pub use diff::{DiffHunk, DiffLine, diff_strip, diff_strip_with};
+pub use document::Document;
+pub use email::{EmailBody, strip_email, strip_email_with, strip_mime_part, strip_mime_part_with};
+#[cfg(feature = "encoding")]
+pub use encoding::{Encoding, decode_text, strip_encoded};
+pub use entities::HtmlEntities;
+pub use explain::{TraceEvent, explain, explain_with};
+pub use extract::{CodeBlocks, extract_codeblocks, extract_doc_examples};
+pub use fence::{
+    FenceInfo, FencePolicy, fence_content, fences_balanced, find_fences, first_unbalanced_fence,
+    is_fence_line,
+};
+pub use file::{strip_compressed_reader, strip_file};
+pub use fingerprint::{BlockId, similar_blocks, strip_and_hash, strip_and_hash_with};
+pub use flat::{
+    FlatSegment, StrippedRef, TooLarge, strip_codeblocks_ref, strip_codeblocks_ref_with,
+    strip_codeblocks_ref_with_report,
+};
+pub use html::ScriptPolicy;
+#[cfg(feature = "json")]
+pub use json::{JsonError, strip_in_json, strip_in_json_with};
+pub use language::LanguageMatch;
+pub use language_list::{env_language_list, parse_language_list, read_language_list};
+pub use links::{export_codeblocks_to_dir, replace_codeblocks_with_links};
+pub use listings::{Listing, Listings, label_listings, label_listings_with};
+pub use matcher::{BlockMatcher, MatcherError};
+pub use merge::MergedBlock;
+pub use newline::{Newline, TrailingNewline};
+pub use options::{Action, StripOptions};
+pub use patch::{strip_patch, strip_patch_with};
+pub use pipeline::Pipeline;
+pub use preserve::{ProseMismatch, verify_prose_preserved};
+pub use preset::Preset;
+pub use prose::{strip_prose, strip_prose_with};
+#[cfg(feature = "pulldown")]
+pub use pulldown::StripCodeBlocks;
+pub use render_safety::RenderSafety;
+pub use replacements::ReplacementMap;
+pub use report::{BlockReport, Report};
+pub use script::{ScriptError, Suspicion, extract_script};
+pub use segment::{Segment, prose_spans, segments};
+pub use shrink::{Shrink, shrink_codeblocks};
+pub use stable::{strip_until_stable, strip_until_stable_with};
+pub use stats::{LangStats, language_histogram};
+pub use strict::{Ambiguity, StripError};
+pub use stripper::Stripper;
+pub use tokens::{SegmentKind, SegmentTokens, TokenCounts, strip_and_count_tokens};
+pub use utf16::{strip_codeblocks_lossy_utf16, strip_codeblocks_lossy_utf16_with};
+pub use validate::{InvalidBlock, ValidationError, Validators, validate_codeblocks};
+
+// Every public type can be shared between threads, as documented; this
+// fails to compile if one stops being `Send + Sync`.
+const _: () = {
+    const fn send_sync<T: Send + Sync>() {}
+
+    send_sync::<Action>();
+    send_sync::<Ambiguity>();
+    send_sync::<Annotations>();
+    send_sync::<Ansi>();
+    send_sync::<Attributes<'static>>();
+    send_sync::<BlockContext<'static>>();
+    send_sync::<BlockId>();
+    send_sync::<BlockMatcher>();
+    send_sync::<BlockReport>();
+    send_sync::<BufferTooSmall>();
+    send_sync::<ChatPolicy>();
+    send_sync::<Chunk<'static>>();
+    send_sync::<ChunkOptions>();
+    send_sync::<CodeBlock<'static>>();
+    send_sync::<DeadlineExceeded>();
+    send_sync::<Dedent>();
+    send_sync::<DeltaStripper>();
+    send_sync::<DiffHunk<'static>>();
+    send_sync::<DiffLine<'static>>();
+    send_sync::<Document<'static>>();
+    send_sync::<EmailBody>();
+    send_sync::<FenceInfo<'static>>();
+    send_sync::<FencePolicy>();
+    send_sync::<FlatSegment>();
+    send_sync::<HtmlEntities>();
+    send_sync::<InvalidBlock<'static>>();
+    send_sync::<LangStats>();
+    send_sync::<LanguageMatch>();
+    send_sync::<Listing>();
+    send_sync::<Listings>();
+    send_sync::<MaskTable>();
+    send_sync::<MatcherError>();
+    send_sync::<MergedBlock<'static>>();
+    send_sync::<Newline>();
+    send_sync::<Pipeline>();
+    send_sync::<Position>();
+    send_sync::<Preset>();
+    send_sync::<ProseMismatch>();
+    send_sync::<RenderSafety>();
+    send_sync::<ReplacementMap>();
+    send_sync::<Report>();
+    send_sync::<Role>();
+    send_sync::<ScriptError>();
+    send_sync::<ScriptPolicy>();
+    send_sync::<Segment<'static>>();
+    send_sync::<SegmentKind>();
+    send_sync::<SegmentTokens>();
+    send_sync::<Shrink>();
+    send_sync::<spec::SpecCase>();
+    send_sync::<spec::SpecDrift>();
+    send_sync::<spec::SpecFailure>();
+    send_sync::<StripError>();
+    send_sync::<StripOptions>();
+    send_sync::<StrippedRef>();
+    send_sync::<Stripper>();
+    send_sync::<Suspicion>();
+    send_sync::<TokenCounts>();
+    send_sync::<TooLarge>();
+    send_sync::<TraceEvent>();
+    send_sync::<TrailingNewline>();
+    send_sync::<ValidationError>();
+    send_sync::<Validators>();
+    send_sync::<experimental::Engine>();
+    #[cfg(feature = "bounded")]
+    send_sync::<BlockArray<'static, 1>>();
+    #[cfg(feature = "bounded")]
+    send_sync::<CapacityExceeded>();
+    #[cfg(feature = "encoding")]
+    send_sync::<Encoding>();
+    #[cfg(feature = "json")]
+    send_sync::<JsonError>();
+    #[cfg(feature = "serde")]
+    send_sync::<OptionsError>();
+    #[cfg(feature = "pulldown")]
+    send_sync::<StripCodeBlocks<std::vec::IntoIter<pulldown_cmark::Event<'static>>>>();
+};
 
 /// Strips fenced code blocks from markdown text while preserving the inner content.
 ///
 /// This function removes markdown fenced code blocks (triple backticks) but keeps
-/// the content inside them. Inline code blocks (single backticks) are left untouched.
+/// the content inside them. Inline code blocks (single backticks) are left
+/// untouched, and so are blocks in the `math` language, as with the default
+/// [`math`](StripOptions::math) policy of [`strip_codeblocks_with`].
+///
+/// Stripping is idempotent for single-level input: when the only triple
+/// backticks of `text` are the fences of its blocks, the output has none, so
+/// stripping it again changes nothing. Blocks nested in blocks, such as
+/// markdown examples in a markdown block, take a pass per level; see
+/// [`strip_until_stable`].
 ///
 /// # Arguments
 ///
@@ -70,28 +406,253 @@ use regex::Regex;
 /// //Inline code is preserved
 /// ```
 pub fn strip_codeblocks(text: &str) -> String {
-    // Match fenced code blocks: ```optional_lang\n...content...\n```
-    // This regex matches:
-    // - Three backticks (```)
-    // - Optional language identifier (any characters except newline and backtick)
-    // - Newline
-    // - Content (non-greedy, including newlines)
-    // - Three backticks (```)
-    // The (?s) flag makes . match newlines
-    let re = Regex::new(r"(?s)```[^\n`]*\n(.*?)```").unwrap();
-
-    re.replace_all(text, |caps: &regex::Captures| {
-        // Extract the content (first capture group)
-        caps.get(1)
-            .map_or(String::new(), |m| m.as_str().to_string())
-    })
-    .to_string()
+    strip::strip(text, &StripOptions::new(), None)
+}
+
+/// Strips fenced code blocks from untrusted markdown text, with guaranteed bounds.
+///
+/// Like [`strip_codeblocks`], this keeps the content of every block and drops
+/// its fences, but blocks are found with the CommonMark
+/// [parser](experimental::Engine::Parser) engine, so fences only count at the
+/// start of a line. For any input, this function:
+///
+/// - runs in time linear in the length of `text`,
+/// - never panics,
+/// - allocates once, a string of at most `text.len()` bytes, since the output
+///   is made of bytes of `text`.
+///
+/// These guarantees are part of the API and are exercised by the fuzz targets
+/// in the `fuzz` directory.
+///
+/// # Arguments
+///
+/// * `text` - The markdown text containing code blocks to strip
+///
+/// # Returns
+///
+/// A new string with fenced code blocks removed, but their content preserved.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::strip_codeblocks_hardened;
+///
+/// let untrusted = "Intro\n```sh\nls\n```\nNot a fence: ```\n";
+/// assert_eq!(strip_codeblocks_hardened(untrusted), "Intro\nls\n\nNot a fence: ```\n");
+/// ```
+pub fn strip_codeblocks_hardened(text: &str) -> String {
+    let options = StripOptions::new().engine(experimental::Engine::Parser);
+    let mut output = String::with_capacity(text.len());
+    strip::strip_to(text, &options, None, &mut output).expect("writing to a String never fails");
+    output
+}
+
+/// Strips fenced code blocks from markdown text, keeping only the first line of each.
+///
+/// The rest of a block is replaced with a `…` line, so long command outputs
+/// are summarized by the command that produced them. This is
+/// [`Action::FirstLine`] applied to every block.
+///
+/// # Arguments
+///
+/// * `text` - The markdown text containing code blocks to strip
+///
+/// # Returns
+///
+/// A new string with each fenced code block replaced by its first line.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::strip_codeblocks_keeping_first_line;
+///
+/// let log = "Build:\n```console\n$ cargo build\n   Compiling foo v0.1.0\n    Finished dev\n```\n";
+/// assert_eq!(strip_codeblocks_keeping_first_line(log), "Build:\n$ cargo build\n…\n\n");
+/// ```
+pub fn strip_codeblocks_keeping_first_line(text: &str) -> String {
+    let options = StripOptions::new().action(Action::FirstLine("…".into()));
+    strip::strip(text, &options, None)
+}
+
+/// Strips fenced code blocks from markdown text according to `options`.
+///
+/// With default options this behaves exactly like [`strip_codeblocks`]. See
+/// [`StripOptions`] for the available adjustments.
+///
+/// # Arguments
+///
+/// * `text` - The markdown text containing code blocks to strip
+/// * `options` - Which blocks are stripped and how
+///
+/// # Returns
+///
+/// A new string with the selected fenced code blocks stripped according to `options`.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::{StripOptions, strip_codeblocks_with};
+///
+/// let transcript = "Install it:\n```console\n$ cargo install ripgrep\n    Updating crates.io index\n```";
+///
+/// let result = strip_codeblocks_with(transcript, &StripOptions::new().strip_prompts(true));
+/// assert_eq!(result, "Install it:\ncargo install ripgrep\n    Updating crates.io index\n");
+/// ```
+pub fn strip_codeblocks_with(text: &str, options: &StripOptions) -> String {
+    strip::strip(text, options, None)
+}
+
+/// Strips fenced code blocks like [`strip_codeblocks_with`], failing on
+/// ambiguous structures in [strict](StripOptions::strict) mode.
+///
+/// # Arguments
+///
+/// * `text` - The markdown text containing code blocks to strip
+/// * `options` - Which blocks are stripped and how, and whether to be strict
+///
+/// # Returns
+///
+/// The stripped text, or in strict mode the first ambiguous structure of `text`.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::{StripOptions, try_strip_codeblocks_with};
+///
+/// let options = StripOptions::new().strict(true);
+///
+/// assert_eq!(try_strip_codeblocks_with("```sh\nls\n```", &options).unwrap(), "ls\n");
+///
+/// let error = try_strip_codeblocks_with("```\nls\n````", &options).unwrap_err();
+/// assert_eq!(error.to_string(), "line 3: fence length does not match the opening fence");
+/// ```
+pub fn try_strip_codeblocks_with(text: &str, options: &StripOptions) -> Result<String, StripError> {
+    if options.strict {
+        strict::check(text, options)?;
+    }
+    Ok(strip::strip(text, options, None))
+}
+
+/// Strips fenced code blocks like [`strip_codeblocks_with`], borrowing `text` when nothing changes.
+///
+/// When every block is kept, e.g. because a [language
+/// filter](StripOptions::languages) matches none of them, the input is returned
+/// as is instead of being copied, so large pass-through corpora cost nothing.
+///
+/// # Arguments
+///
+/// * `text` - The markdown text containing code blocks to strip
+/// * `options` - Which blocks are stripped and how
+///
+/// # Returns
+///
+/// `text` itself if no block was changed, or else the stripped text.
+///
+/// # Examples
+///
+/// ```
+/// use std::borrow::Cow;
+/// use strip_codeblocks::{StripOptions, strip_codeblocks_cow};
+///
+/// let options = StripOptions::new().languages(["python"]);
+///
+/// let unchanged = strip_codeblocks_cow("```rust\nfn main() {}\n```", &options);
+/// assert!(matches!(unchanged, Cow::Borrowed(_)));
+///
+/// let stripped = strip_codeblocks_cow("```python\npass\n```", &options);
+/// assert_eq!(stripped, "pass\n");
+/// ```
+pub fn strip_codeblocks_cow<'a>(text: &'a str, options: &StripOptions) -> Cow<'a, str> {
+    strip::strip_cow(text, options)
+}
+
+/// Strips fenced code blocks like [`strip_codeblocks_with`], writing the result into `writer`.
+///
+/// The output is written piece by piece into anything implementing
+/// [`fmt::Write`](std::fmt::Write), such as a `String` or a fixed-capacity,
+/// stack-allocated buffer, without building an intermediate string.
+///
+/// # Arguments
+///
+/// * `text` - The markdown text containing code blocks to strip
+/// * `options` - Which blocks are stripped and how
+/// * `writer` - Where the stripped text is written
+///
+/// # Returns
+///
+/// `Ok(())` once the whole output was written, or the first error returned by
+/// `writer` (e.g. because a fixed-capacity buffer is full).
+///
+/// # Examples
+///
+/// ```
+/// use std::fmt::Write;
+/// use strip_codeblocks::{StripOptions, strip_codeblocks_to_writer};
+///
+/// let mut output = String::from("> ");
+/// strip_codeblocks_to_writer("```sh\nls\n```", &StripOptions::new(), &mut output).unwrap();
+/// assert_eq!(output, "> ls\n");
+/// ```
+pub fn strip_codeblocks_to_writer<W: std::fmt::Write + ?Sized>(
+    text: &str,
+    options: &StripOptions,
+    writer: &mut W,
+) -> std::fmt::Result {
+    strip::strip_to(text, options, None, writer)
+}
+
+/// Strips fenced code blocks like [`strip_codeblocks_with`], also reporting what was done.
+///
+/// # Arguments
+///
+/// * `text` - The markdown text containing code blocks to strip
+/// * `options` - Which blocks are stripped and how
+///
+/// # Returns
+///
+/// The stripped text, and a [`Report`] listing every code block found with the
+/// action applied to it.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::{Action, StripOptions, strip_codeblocks_with_report};
+///
+/// let options = StripOptions::new().languages(["json"]).action(Action::Remove);
+/// let input = "```json\n{}\n```\n```sh\nls\n```";
+///
+/// let (output, report) = strip_codeblocks_with_report(input, &options);
+/// assert_eq!(output, "\n```sh\nls\n```");
+/// assert_eq!(report.blocks[0].action, Action::Remove);
+/// assert_eq!(report.blocks[1].action, Action::Keep);
+/// ```
+pub fn strip_codeblocks_with_report(text: &str, options: &StripOptions) -> (String, Report) {
+    let mut report = Report::default();
+    let output = strip::strip(text, options, Some(&mut report));
+    (output, report)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_iterators_are_send_sync() {
+        fn send_sync<T: Send + Sync>(_: T) {}
+
+        let text = "a\n```sh\nls\n```";
+        let document = Document::new(text);
+        let blocks = extract_codeblocks(text);
+        let (_, report) = strip_codeblocks_with_report(text, &StripOptions::new());
+        let (_, listings) = label_listings(text);
+
+        send_sync(prose_spans(text));
+        send_sync(document.codeblocks());
+        send_sync(blocks[0].info_words());
+        send_sync(report.changed());
+        send_sync(listings.iter());
+    }
+
     #[test]
     fn test_basic_codeblock() {
         let input = "```rust\nfn main() {}\n```";
@@ -0,0 +1,224 @@
+//! Replacing code blocks with links to externally stored attachments.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::CodeBlock;
+use crate::scan::replace_codeblocks;
+
+/// Replaces each fenced code block with a markdown link to wherever `store` put it.
+///
+/// `store` is called once per block, in document order, with the block and its
+/// 1-based position among the document's blocks. It returns the link target
+/// (a URL or path) under which the block's content can be found. The block is
+/// then replaced by a link of the form `[rust code block 1](target)`. Brackets
+/// in the language are escaped, and a target with spaces, parentheses or other
+/// characters markdown gives a meaning to is written as `<target>`, so the
+/// link always renders as one.
+///
+/// This flattens long answers into readable prose plus attachments. Use
+/// [`export_codeblocks_to_dir`] to store the blocks as files in a directory.
+///
+/// # Arguments
+///
+/// * `text` - The markdown text containing code blocks to replace
+/// * `store` - Callback storing a block and returning its link target
+///
+/// # Returns
+///
+/// The text with every fenced code block replaced by a link, or the first error
+/// returned by `store`.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::replace_codeblocks_with_links;
+///
+/// let markdown = "Run this:\n```sh\nmake\n```\nand that:\n```\nmake install\n```\n";
+///
+/// let mut attachments = Vec::new();
+/// let result = replace_codeblocks_with_links(markdown, |block, index| {
+///     attachments.push(block.content.to_string());
+///     Ok::<_, std::convert::Infallible>(format!("https://example.com/{index}"))
+/// })
+/// .unwrap();
+///
+/// assert_eq!(
+///     result,
+///     "Run this:\n[sh code block 1](https://example.com/1)\nand that:\n[code block 2](https://example.com/2)\n"
+/// );
+/// assert_eq!(attachments, ["make\n", "make install\n"]);
+/// ```
+pub fn replace_codeblocks_with_links<E>(
+    text: &str,
+    mut store: impl FnMut(&CodeBlock<'_>, usize) -> Result<String, E>,
+) -> Result<String, E> {
+    let mut index = 0;
+
+    replace_codeblocks(text, |block, output| {
+        index += 1;
+        let target = store(block, index)?;
+
+        output.push('[');
+        if let Some(language) = block.language {
+            push_link_text(output, language);
+            output.push(' ');
+        }
+        output.push_str(&format!("code block {index}]("));
+        push_destination(output, &target);
+        output.push(')');
+        Ok(())
+    })
+}
+
+/// Writes `text` as part of the text of a link, escaping what would end it early.
+fn push_link_text(output: &mut String, text: &str) {
+    for c in text.chars() {
+        if matches!(c, '[' | ']' | '\\') {
+            output.push('\\');
+        }
+        output.push(c);
+    }
+}
+
+/// Writes `target` as the destination of a link: as it is when markdown reads
+/// it back unchanged, otherwise between `<` and `>` with the characters
+/// special there escaped, and line breaks, which it can't hold, percent-encoded.
+fn push_destination(output: &mut String, target: &str) {
+    let plain = !target.is_empty()
+        && !target.contains(|c: char| c.is_ascii_control() || " <>()\\".contains(c));
+    if plain {
+        output.push_str(target);
+        return;
+    }
+    output.push('<');
+    for c in target.chars() {
+        match c {
+            '<' | '>' | '\\' => {
+                output.push('\\');
+                output.push(c);
+            }
+            '\n' => output.push_str("%0A"),
+            '\r' => output.push_str("%0D"),
+            c => output.push(c),
+        }
+    }
+    output.push('>');
+}
+
+/// Writes each fenced code block to a file in `dir` and replaces it with a link to that file.
+///
+/// Blocks are written as `block-1.rs`, `block-2.py`, ... with the extension
+/// guessed from the block's language (`txt` when unknown). The directory is
+/// created if it doesn't exist, and existing files with the same names are
+/// overwritten. Links point to `dir` joined with the file name.
+///
+/// # Examples
+///
+/// ```no_run
+/// use strip_codeblocks::export_codeblocks_to_dir;
+///
+/// let markdown = "Here you go:\n```python\nprint('hi')\n```\n";
+/// let result = export_codeblocks_to_dir(markdown, "attachments").unwrap();
+/// assert_eq!(result, "Here you go:\n[python code block 1](attachments/block-1.py)\n");
+/// ```
+pub fn export_codeblocks_to_dir(text: &str, dir: impl AsRef<Path>) -> io::Result<String> {
+    let dir = dir.as_ref();
+    fs::create_dir_all(dir)?;
+
+    replace_codeblocks_with_links(text, |block, index| {
+        let extension = block.language.map_or("txt", extension_for);
+        let path = dir.join(format!("block-{index}.{extension}"));
+        fs::write(&path, block.content)?;
+        Ok(path.display().to_string())
+    })
+}
+
+/// Guesses a file extension for a language identifier.
+fn extension_for(language: &str) -> &str {
+    match language.to_ascii_lowercase().as_str() {
+        "rust" | "rs" => "rs",
+        "python" | "py" | "python3" => "py",
+        "javascript" | "js" => "js",
+        "typescript" | "ts" => "ts",
+        "bash" | "sh" | "shell" | "zsh" => "sh",
+        "c" => "c",
+        "c++" | "cpp" => "cpp",
+        "go" | "golang" => "go",
+        "java" => "java",
+        "ruby" | "rb" => "rb",
+        "json" => "json",
+        "toml" => "toml",
+        "yaml" | "yml" => "yaml",
+        "html" => "html",
+        "css" => "css",
+        "sql" => "sql",
+        "markdown" | "md" => "md",
+        _ => "txt",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::Infallible;
+
+    #[test]
+    fn test_links_replace_blocks() {
+        let input = "Before\n```rust\ncode here\n```\nAfter";
+        let output = replace_codeblocks_with_links(input, |_, index| {
+            Ok::<_, Infallible>(format!("snippet-{index}.rs"))
+        })
+        .unwrap();
+        assert_eq!(output, "Before\n[rust code block 1](snippet-1.rs)\nAfter");
+    }
+
+    #[test]
+    fn test_links_escape_text_and_target() {
+        let input = "```c[]\na\n```\n```\nb\n```\n```\nc\n```";
+        let targets = ["my code/block (1).c", "a]b", "x<y>\\z"];
+        let output = replace_codeblocks_with_links(input, |_, index| {
+            Ok::<_, Infallible>(targets[index - 1].to_string())
+        })
+        .unwrap();
+        assert_eq!(
+            output,
+            "[c\\[\\] code block 1](<my code/block (1).c>)\n[code block 2](a]b)\n[code block 3](<x\\<y\\>\\\\z>)"
+        );
+    }
+
+    #[test]
+    fn test_links_store_error_is_returned() {
+        let input = "```\na\n```\n```\nb\n```";
+        let mut calls = 0;
+        let result = replace_codeblocks_with_links(input, |_, _| {
+            calls += 1;
+            Err("storage unavailable")
+        });
+        assert_eq!(result, Err("storage unavailable"));
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn test_export_codeblocks_to_dir() {
+        let dir = std::env::temp_dir().join(format!("strip-codeblocks-{}", std::process::id()));
+        let input = "```python\nprint('a')\n```\n```\nplain\n```";
+
+        let output = export_codeblocks_to_dir(input, &dir).unwrap();
+
+        let first = dir.join("block-1.py");
+        let second = dir.join("block-2.txt");
+        assert_eq!(
+            output,
+            format!(
+                "[python code block 1]({})\n[code block 2]({})",
+                first.display(),
+                second.display()
+            )
+        );
+        assert_eq!(fs::read_to_string(first).unwrap(), "print('a')\n");
+        assert_eq!(fs::read_to_string(second).unwrap(), "plain\n");
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,224 @@
+//! Numbering the blocks left in stripped text with `Listing N:` labels.
+
+use std::ops::Range;
+
+use crate::math::is_math;
+use crate::strip::for_each_piece;
+use crate::title::Titles;
+use crate::{Action, StripOptions};
+
+/// The template of the labels of [`label_listings`].
+const DEFAULT_LABEL: &str = "Listing {n}";
+
+/// A block labeled by [`label_listings_with`], for later references to it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct Listing {
+    /// The number of the listing, counting labeled blocks from 1.
+    pub number: usize,
+    /// The label, such as `Listing 3`, without the colon ending its line.
+    pub label: String,
+    /// The language of the block, if it had one.
+    pub language: Option<String>,
+    /// The [title](crate::CodeBlock::title) of the block, if it has one.
+    pub title: Option<String>,
+    /// Byte range of the block, fences included, in the original document.
+    ///
+    /// A listing of [merged](StripOptions::merge_adjacent) blocks spans them all.
+    pub span: Range<usize>,
+    /// Byte range of what the block was stripped to in the output, after its label line.
+    pub output_span: Range<usize>,
+    /// The action applied to the block.
+    pub action: Action,
+}
+
+/// The listings of a document, looked up by label.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Listings {
+    listings: Vec<Listing>,
+}
+
+impl Listings {
+    /// Returns the listing labeled `label`, such as `Listing 3`, if there is one.
+    pub fn get(&self, label: &str) -> Option<&Listing> {
+        self.listings.iter().find(|listing| listing.label == label)
+    }
+
+    /// Returns an iterator over the listings, in document order.
+    pub fn iter(&self) -> impl Iterator<Item = &Listing> {
+        self.listings.iter()
+    }
+
+    /// Returns the number of listings.
+    pub fn len(&self) -> usize {
+        self.listings.len()
+    }
+
+    /// Returns whether the document has no listing.
+    pub fn is_empty(&self) -> bool {
+        self.listings.is_empty()
+    }
+}
+
+/// Strips fenced code blocks like [`strip_codeblocks`](crate::strip_codeblocks),
+/// numbering the blocks with `Listing N:` labels.
+///
+/// This is [`label_listings_with`] with the default options and the
+/// `Listing {n}` label.
+///
+/// # Arguments
+///
+/// * `text` - The markdown text containing code blocks to strip
+///
+/// # Returns
+///
+/// The stripped text with labels, and its listings.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::label_listings;
+///
+/// let text = "Build:\n```sh\nmake\n```\nRun:\n```sh\n./app\n```";
+/// let (output, listings) = label_listings(text);
+///
+/// assert_eq!(output, "Build:\nListing 1:\nmake\n\nRun:\nListing 2:\n./app\n");
+/// assert_eq!(&output[listings.get("Listing 2").unwrap().output_span.clone()], "./app\n");
+/// ```
+pub fn label_listings(text: &str) -> (String, Listings) {
+    label_listings_with(text, &StripOptions::new(), DEFAULT_LABEL)
+}
+
+/// Strips fenced code blocks according to `options`, putting a label line
+/// before each block whose content is left, and returns the listings by label.
+///
+/// Blocks that are unwrapped, kept or cut to their [first line](Action::FirstLine)
+/// are numbered from 1 in document order, and their label is `label` with
+/// `{n}` replaced by their number, followed by a colon on a line of its own.
+/// Removed and replaced blocks and [math](StripOptions::math) aren't
+/// labeled or counted, so the numbers are those a reader of the stripped
+/// text sees. The label lines end like [`StripOptions::newline`] says.
+///
+/// # Arguments
+///
+/// * `text` - The markdown text containing code blocks to strip
+/// * `options` - Which blocks are stripped and how
+/// * `label` - The label of the listings, with `{n}` standing for their number
+///
+/// # Returns
+///
+/// The stripped text with labels, and its listings.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::{Action, StripOptions, label_listings_with};
+///
+/// let options = StripOptions::new().languages(["rust"]).action(Action::Remove);
+///
+/// let text = "## Setup\n```rust\nfn a() {}\n```\n```toml\n[a]\n```\n```yaml\nb: 1\n```";
+/// let (output, listings) = label_listings_with(text, &options, "Snippet {n}");
+///
+/// assert_eq!(output, "## Setup\n\nSnippet 1:\n```toml\n[a]\n```\nSnippet 2:\n```yaml\nb: 1\n```");
+/// let listing = listings.get("Snippet 1").unwrap();
+/// assert_eq!((listing.language.as_deref(), listing.title.as_deref()), (Some("toml"), Some("Setup")));
+/// ```
+pub fn label_listings_with(text: &str, options: &StripOptions, label: &str) -> (String, Listings) {
+    let newline = options.newline.resolve(text);
+    let mut output = String::with_capacity(text.len());
+    let mut listings = Listings::default();
+    let mut titles = Titles::new(text);
+
+    for_each_piece(text, options, |piece| {
+        let labeled = matches!(
+            piece.action,
+            Some(Action::Unwrap | Action::Keep | Action::FirstLine(_))
+        );
+        let Some(block) = piece
+            .block
+            .as_ref()
+            .filter(|block| labeled && !is_math(block.language))
+        else {
+            output.push_str(&piece.output);
+            return;
+        };
+
+        let number = listings.len() + 1;
+        let label = label.replace("{n}", &number.to_string());
+        if !output.is_empty() && !output.ends_with('\n') {
+            output.push_str(newline);
+        }
+        output.push_str(&label);
+        output.push(':');
+        output.push_str(newline);
+        let start = output.len();
+        output.push_str(&piece.output);
+
+        listings.listings.push(Listing {
+            number,
+            label,
+            language: block.language.map(str::to_string),
+            title: block
+                .attributes
+                .title
+                .or_else(|| titles.before(block.span.start))
+                .map(str::to_string),
+            span: piece.span.clone(),
+            output_span: start..output.len(),
+            action: piece.action.cloned().unwrap_or_default(),
+        });
+    });
+
+    (output, listings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inline_and_merged_blocks() {
+        let options = StripOptions::new().merge_adjacent(true);
+        let text = "Run: ```sh\nls\n```\n```sh\npwd\n```\n```math\nx\n```";
+        let (output, listings) = label_listings_with(text, &options, "Listing {n}");
+
+        assert_eq!(output, "Run: \nListing 1:\nls\npwd\n\n```math\nx\n```");
+        assert_eq!(listings.len(), 1);
+        let listing = listings.get("Listing 1").unwrap();
+        assert_eq!(
+            &text[listing.span.clone()],
+            "```sh\nls\n```\n```sh\npwd\n```"
+        );
+        assert_eq!(listings.get("Listing 2"), None);
+    }
+
+    #[test]
+    fn test_titles_match_blocks() {
+        let text = "# Guide\n**Setup:**\n```sh\nmake\n```\nUsage\n---\n```sh\n# not a title\n```\n\n```sh title=\"run.sh\"\nrun\n```\n```sh\nls\n```";
+        let (_, listings) = label_listings(text);
+        let titles: Vec<_> = listings
+            .iter()
+            .map(|listing| listing.title.as_deref())
+            .collect();
+        let blocks = crate::extract_codeblocks(text);
+        assert_eq!(
+            titles,
+            blocks
+                .iter()
+                .map(|block| block.title(text))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            titles,
+            [Some("Setup"), Some("Usage"), Some("run.sh"), Some("Usage")]
+        );
+    }
+
+    #[test]
+    fn test_no_listings() {
+        let (output, listings) = label_listings("No code.\r\n");
+        assert_eq!(output, "No code.\r\n");
+        assert!(listings.is_empty());
+        assert_eq!(listings.iter().count(), 0);
+    }
+}
@@ -0,0 +1,450 @@
+//! Selecting blocks with predicates on their language, info string and size,
+//! combined in code or parsed from expressions like `lang:rust & lines>10`.
+
+use std::error::Error;
+use std::fmt;
+use std::ops::{BitAnd, BitOr, Bound, Not, RangeBounds, RangeInclusive};
+use std::str::FromStr;
+
+use crate::block::info_words;
+use crate::{CodeBlock, LanguageMatch};
+
+/// A predicate on code blocks, for [`StripOptions::select`](crate::StripOptions::select).
+///
+/// Matchers are built from predicates with `&`, `|` and `!`, or parsed from
+/// an expression with [`str::parse`], so selections can come from
+/// configuration. Expressions combine these predicates:
+///
+/// - `lang:PATTERN` matches blocks whose language matches `PATTERN`, in
+///   which `*` stands for any run of characters and `?` for any character.
+/// - `info:PATTERN` matches blocks with a word of their info string matching
+///   `PATTERN`, such as `info:ignore`.
+/// - `lines` and `bytes` compared to a number with `>`, `>=`, `<`, `<=` or
+///   `=` match blocks by the size of their content, such as `lines>10`.
+///
+/// `!` negates a predicate, `&` binds tighter than `|`, and parentheses
+/// group. Spaces between tokens are ignored.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::{BlockMatcher, CodeBlock};
+///
+/// let matcher: BlockMatcher = "lang:rust & lines>1 | !lang:mermaid".parse()?;
+/// assert_eq!(
+///     matcher,
+///     BlockMatcher::language("rust") & BlockMatcher::lines(2..) | !BlockMatcher::language("mermaid")
+/// );
+///
+/// assert!(matcher.matches(&CodeBlock::new("rust", "fn main() {\n}\n")));
+/// assert!(matcher.matches(&CodeBlock::new("sh", "ls\n")));
+/// assert!(!matcher.matches(&CodeBlock::new("mermaid", "graph TD\n")));
+/// # Ok::<(), strip_codeblocks::MatcherError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BlockMatcher {
+    node: Node,
+}
+
+/// The tree of a [`BlockMatcher`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Node {
+    Language(String),
+    InfoWord(String),
+    Lines(RangeInclusive<usize>),
+    Bytes(RangeInclusive<usize>),
+    Not(Box<Node>),
+    All(Vec<Node>),
+    Any(Vec<Node>),
+}
+
+/// Returns `range` as an inclusive range of sizes.
+fn inclusive(range: impl RangeBounds<usize>) -> RangeInclusive<usize> {
+    let start = match range.start_bound() {
+        Bound::Included(&start) => start,
+        Bound::Excluded(&start) => start.saturating_add(1),
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(&end) => end,
+        Bound::Excluded(&end) => match end.checked_sub(1) {
+            Some(end) => end,
+            // An empty range matches nothing
+            None => return RangeInclusive::new(1, 0),
+        },
+        Bound::Unbounded => usize::MAX,
+    };
+    start..=end
+}
+
+impl BlockMatcher {
+    /// Matches blocks whose language matches `pattern`, in which `*` stands
+    /// for any run of characters and `?` for any character.
+    pub fn language(pattern: impl Into<String>) -> Self {
+        Self {
+            node: Node::Language(pattern.into()),
+        }
+    }
+
+    /// Matches blocks with a word of their info string matching `pattern`,
+    /// a glob like those of [`language`](Self::language).
+    pub fn info_word(pattern: impl Into<String>) -> Self {
+        Self {
+            node: Node::InfoWord(pattern.into()),
+        }
+    }
+
+    /// Matches blocks whose content has a number of lines in `range`.
+    pub fn lines(range: impl RangeBounds<usize>) -> Self {
+        Self {
+            node: Node::Lines(inclusive(range)),
+        }
+    }
+
+    /// Matches blocks whose content has a length in bytes in `range`.
+    pub fn bytes(range: impl RangeBounds<usize>) -> Self {
+        Self {
+            node: Node::Bytes(inclusive(range)),
+        }
+    }
+
+    /// Returns whether `block` matches.
+    pub fn matches(&self, block: &CodeBlock<'_>) -> bool {
+        self.node.matches(block)
+    }
+}
+
+impl Node {
+    fn matches(&self, block: &CodeBlock<'_>) -> bool {
+        let glob = LanguageMatch::new().glob(true);
+        match self {
+            Node::Language(pattern) => block
+                .language
+                .is_some_and(|language| glob.matches(pattern, language)),
+            Node::InfoWord(pattern) => {
+                info_words(block.info).any(|word| glob.matches(pattern, word))
+            }
+            Node::Lines(range) => range.contains(&block.content.lines().count()),
+            Node::Bytes(range) => range.contains(&block.content.len()),
+            Node::Not(node) => !node.matches(block),
+            Node::All(nodes) => nodes.iter().all(|node| node.matches(block)),
+            Node::Any(nodes) => nodes.iter().any(|node| node.matches(block)),
+        }
+    }
+}
+
+impl BitAnd for BlockMatcher {
+    type Output = Self;
+
+    /// Matches blocks both matchers match.
+    fn bitand(self, other: Self) -> Self {
+        let node = match (self.node, other.node) {
+            (Node::All(mut nodes), Node::All(others)) => {
+                nodes.extend(others);
+                Node::All(nodes)
+            }
+            (Node::All(mut nodes), other) => {
+                nodes.push(other);
+                Node::All(nodes)
+            }
+            (node, other) => Node::All(vec![node, other]),
+        };
+        Self { node }
+    }
+}
+
+impl BitOr for BlockMatcher {
+    type Output = Self;
+
+    /// Matches blocks either matcher matches.
+    fn bitor(self, other: Self) -> Self {
+        let node = match (self.node, other.node) {
+            (Node::Any(mut nodes), Node::Any(others)) => {
+                nodes.extend(others);
+                Node::Any(nodes)
+            }
+            (Node::Any(mut nodes), other) => {
+                nodes.push(other);
+                Node::Any(nodes)
+            }
+            (node, other) => Node::Any(vec![node, other]),
+        };
+        Self { node }
+    }
+}
+
+impl Not for BlockMatcher {
+    type Output = Self;
+
+    /// Matches blocks the matcher doesn't match.
+    fn not(self) -> Self {
+        Self {
+            node: Node::Not(Box::new(self.node)),
+        }
+    }
+}
+
+/// The error returned when a [`BlockMatcher`] expression can't be parsed.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct MatcherError {
+    /// The column of the error in the expression, starting at 1.
+    pub column: usize,
+    /// What is wrong, such as an unknown predicate or a missing parenthesis.
+    pub message: String,
+}
+
+impl fmt::Display for MatcherError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "column {}: {}", self.column, self.message)
+    }
+}
+
+impl Error for MatcherError {}
+
+/// A recursive descent parser of matcher expressions.
+struct Parser<'a> {
+    source: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    /// Returns an error at the current position.
+    fn error(&self, message: impl Into<String>) -> MatcherError {
+        MatcherError {
+            column: self.source[..self.pos].chars().count() + 1,
+            message: message.into(),
+        }
+    }
+
+    /// Skips whitespace and returns the rest of the expression.
+    fn rest(&mut self) -> &'a str {
+        let rest = &self.source[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+        &self.source[self.pos..]
+    }
+
+    /// Consumes `token` if the expression continues with it.
+    fn eat(&mut self, token: &str) -> bool {
+        let found = self.rest().starts_with(token);
+        if found {
+            self.pos += token.len();
+        }
+        found
+    }
+
+    /// `and ('|' and)*`
+    fn any(&mut self) -> Result<BlockMatcher, MatcherError> {
+        let mut matcher = self.all()?;
+        while self.eat("|") {
+            matcher = matcher | self.all()?;
+        }
+        Ok(matcher)
+    }
+
+    /// `unary ('&' unary)*`
+    fn all(&mut self) -> Result<BlockMatcher, MatcherError> {
+        let mut matcher = self.unary()?;
+        while self.eat("&") {
+            matcher = matcher & self.unary()?;
+        }
+        Ok(matcher)
+    }
+
+    /// `'!' unary | '(' any ')' | predicate`
+    fn unary(&mut self) -> Result<BlockMatcher, MatcherError> {
+        if self.eat("!") {
+            return Ok(!self.unary()?);
+        }
+        if self.eat("(") {
+            let matcher = self.any()?;
+            if !self.eat(")") {
+                return Err(self.error("expected `)`"));
+            }
+            return Ok(matcher);
+        }
+        self.predicate()
+    }
+
+    /// `lang:PATTERN`, `info:PATTERN`, or `lines` or `bytes` compared to a number.
+    fn predicate(&mut self) -> Result<BlockMatcher, MatcherError> {
+        let rest = self.rest();
+        let name_len = rest
+            .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+            .unwrap_or(rest.len());
+        let name = &rest[..name_len];
+        if name.is_empty() {
+            return Err(self.error("expected a predicate such as `lang:rust` or `lines>10`"));
+        }
+
+        match name {
+            "lang" | "info" => {
+                self.pos += name_len;
+                if !self.eat(":") {
+                    return Err(self.error(format!("expected `:` after `{name}`")));
+                }
+                let value = self.word();
+                if value.is_empty() {
+                    return Err(self.error(format!("expected a pattern after `{name}:`")));
+                }
+                Ok(match name {
+                    "lang" => BlockMatcher::language(value),
+                    _ => BlockMatcher::info_word(value),
+                })
+            }
+            "lines" | "bytes" => {
+                self.pos += name_len;
+                let range = self.comparison()?;
+                Ok(match name {
+                    "lines" => BlockMatcher::lines(range),
+                    _ => BlockMatcher::bytes(range),
+                })
+            }
+            _ => Err(self.error(format!("unknown predicate `{name}`"))),
+        }
+    }
+
+    /// Consumes the pattern of a predicate, up to whitespace or an operator.
+    fn word(&mut self) -> &'a str {
+        let rest = &self.source[self.pos..];
+        let len = rest
+            .find(|c: char| c.is_whitespace() || "()&|!".contains(c))
+            .unwrap_or(rest.len());
+        self.pos += len;
+        &rest[..len]
+    }
+
+    /// Consumes a comparison with a number, returning the sizes it accepts.
+    fn comparison(&mut self) -> Result<RangeInclusive<usize>, MatcherError> {
+        let operators = [">=", "<=", ">", "<", "="];
+        let Some(&operator) = operators.iter().find(|&&operator| self.eat(operator)) else {
+            return Err(self.error("expected `>`, `>=`, `<`, `<=` or `=`"));
+        };
+        let rest = self.rest();
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let number: usize = rest[..digits]
+            .parse()
+            .map_err(|_| self.error("expected a number"))?;
+        self.pos += digits;
+
+        Ok(match operator {
+            ">=" => inclusive(number..),
+            "<=" => inclusive(..=number),
+            ">" => inclusive((Bound::Excluded(number), Bound::Unbounded)),
+            "<" => inclusive(..number),
+            _ => number..=number,
+        })
+    }
+}
+
+impl FromStr for BlockMatcher {
+    type Err = MatcherError;
+
+    fn from_str(source: &str) -> Result<Self, MatcherError> {
+        let mut parser = Parser { source, pos: 0 };
+        let matcher = parser.any()?;
+        if !parser.rest().is_empty() {
+            let message = if parser.rest().starts_with(')') {
+                "unexpected `)`"
+            } else {
+                "expected `&`, `|` or the end of the expression"
+            };
+            return Err(parser.error(message));
+        }
+        Ok(matcher)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for BlockMatcher {
+    /// Reads a matcher from its expression.
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let source = String::deserialize(deserializer)?;
+        source.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> BlockMatcher {
+        source.parse().unwrap()
+    }
+
+    #[test]
+    fn test_precedence_and_grouping() {
+        let lang = BlockMatcher::language;
+        assert_eq!(
+            parse("lang:a | lang:b & !lang:c"),
+            lang("a") | lang("b") & !lang("c")
+        );
+        assert_eq!(
+            parse("( lang:a|lang:b )&lang:c"),
+            (lang("a") | lang("b")) & lang("c")
+        );
+        assert_eq!(parse("!!info:x"), !!BlockMatcher::info_word("x"));
+    }
+
+    #[test]
+    fn test_comparisons() {
+        let block = CodeBlock::new("", "a\nb\nc\n");
+        for (source, expected) in [
+            ("lines>2", true),
+            ("lines > 3", false),
+            ("lines>=3", true),
+            ("lines<3", false),
+            ("lines<=3", true),
+            ("lines=3", true),
+            ("bytes=6", true),
+            ("lines<0", false),
+        ] {
+            assert_eq!(parse(source).matches(&block), expected, "{source}");
+        }
+    }
+
+    #[test]
+    fn test_patterns() {
+        let block = CodeBlock::new("python3 title=app.py,ignore", "");
+        assert!(parse("lang:py*").matches(&block));
+        assert!(!parse("lang:py").matches(&block));
+        assert!(parse("info:ignore & info:title=*").matches(&block));
+        assert!(!parse("lang:*").matches(&CodeBlock::new("", "")));
+    }
+
+    #[test]
+    fn test_errors() {
+        for (source, column, message) in [
+            (
+                "",
+                1,
+                "expected a predicate such as `lang:rust` or `lines>10`",
+            ),
+            (
+                "lang:rust &",
+                12,
+                "expected a predicate such as `lang:rust` or `lines>10`",
+            ),
+            ("(lang:a", 8, "expected `)`"),
+            ("lang:a)", 7, "unexpected `)`"),
+            ("size>1", 1, "unknown predicate `size`"),
+            ("lines~1", 6, "expected `>`, `>=`, `<`, `<=` or `=`"),
+            ("lines>x", 7, "expected a number"),
+            ("lang:", 6, "expected a pattern after `lang:`"),
+            (
+                "lang:a lang:b",
+                8,
+                "expected `&`, `|` or the end of the expression",
+            ),
+        ] {
+            let error = source.parse::<BlockMatcher>().unwrap_err();
+            assert_eq!(
+                (error.column, error.message.as_str()),
+                (column, message),
+                "{source}"
+            );
+        }
+    }
+}
@@ -0,0 +1,134 @@
+//! LaTeX math, in `math` blocks and between `$$` delimiters.
+
+use std::borrow::Cow;
+use std::ops::Range;
+
+use crate::content::first_line;
+use crate::hash::placeholder_output;
+use crate::{Action, StripOptions};
+
+/// Returns whether a block in `language` holds math rather than code.
+pub(crate) fn is_math(language: Option<&str>) -> bool {
+    language.is_some_and(|language| language.eq_ignore_ascii_case("math"))
+}
+
+/// Returns the ranges of the display math in `prose`: from `$$` at the start
+/// of a line to the next `$$` at the end of one, delimiters included.
+fn display_math(prose: &str) -> Vec<Range<usize>> {
+    let mut spans = Vec::new();
+    let mut line_start = 0;
+    let mut opening = None;
+
+    while line_start < prose.len() {
+        let line_end = prose[line_start..]
+            .find('\n')
+            .map_or(prose.len(), |offset| line_start + offset);
+        let line = prose[line_start..line_end].trim_end_matches('\r');
+        let trimmed = line.trim();
+
+        let start = opening.unwrap_or_else(|| {
+            let indent = line.len() - line.trim_start().len();
+            line_start + indent
+        });
+        let opens = opening.is_none() && trimmed.starts_with("$$");
+        // The closing delimiter can be on the opening line, after the math
+        let closes = trimmed.ends_with("$$") && (opening.is_some() || trimmed.len() >= 4);
+        match (opening, opens, closes) {
+            (None, true, true) | (Some(_), _, true) => {
+                let end = line_start + line.trim_end().len();
+                spans.push(start..end);
+                opening = None;
+            }
+            (None, true, false) => opening = Some(start),
+            _ => {}
+        }
+        line_start = line_end + 1;
+    }
+    spans
+}
+
+/// Returns `prose` with its display math replaced according to the
+/// [math action](StripOptions::math), inserting line breaks as `newline`.
+pub(crate) fn math_output<'a>(
+    prose: Cow<'a, str>,
+    options: &StripOptions,
+    newline: &str,
+) -> Cow<'a, str> {
+    let action = options.math_action();
+    if *action == Action::Keep || options.preserve_whitespace || !prose.contains("$$") {
+        return prose;
+    }
+    let spans = display_math(&prose);
+    if spans.is_empty() {
+        return prose;
+    }
+
+    let mut output = String::with_capacity(prose.len());
+    let mut last = 0;
+    for span in spans {
+        output.push_str(&prose[last..span.start]);
+        let inner = &prose[span.start + 2..span.end - 2];
+        let inner = inner.strip_prefix('\n').unwrap_or(inner);
+        let replacement = match action {
+            Action::Unwrap | Action::Keep => Cow::Borrowed(inner),
+            Action::Remove => Cow::Borrowed(""),
+            Action::Placeholder(placeholder) => {
+                placeholder_output(placeholder, inner, options, newline)
+            }
+            Action::FirstLine(marker) => first_line(Cow::Borrowed(inner), marker, newline),
+        };
+        output.push_str(&replacement);
+        last = span.end;
+    }
+    output.push_str(&prose[last..]);
+    Cow::Owned(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{strip_codeblocks, strip_codeblocks_with};
+
+    #[test]
+    fn test_display_math_spans() {
+        let prose = "Where\n$$\nE = mc^2\n$$\nand $$x$$ inline, or\n  $$y$$\n$$ unclosed";
+        let spans: Vec<_> = display_math(prose)
+            .into_iter()
+            .map(|span| &prose[span])
+            .collect();
+        assert_eq!(spans, ["$$\nE = mc^2\n$$", "$$y$$"]);
+    }
+
+    #[test]
+    fn test_math_blocks_are_kept_by_default() {
+        let input = "```math\na^2\n```\n$$\nb^2\n$$\n```sh\nls\n```";
+        assert_eq!(
+            strip_codeblocks_with(input, &StripOptions::new()),
+            "```math\na^2\n```\n$$\nb^2\n$$\nls\n"
+        );
+
+        assert_eq!(
+            strip_codeblocks(input),
+            strip_codeblocks_with(input, &StripOptions::new())
+        );
+
+        let options = StripOptions::new().math(Action::Placeholder("[math]".into()));
+        assert_eq!(
+            strip_codeblocks_with(input, &options),
+            "[math]\n[math]\nls\n"
+        );
+        let options = StripOptions::new().math(Action::Unwrap);
+        assert_eq!(strip_codeblocks_with(input, &options), "a^2\n\nb^2\n\nls\n");
+    }
+
+    #[test]
+    fn test_math_policy_is_separate_from_code_policy() {
+        let options = StripOptions::new()
+            .languages(["math", "sh"])
+            .action(Action::Remove);
+        assert_eq!(
+            strip_codeblocks_with("```Math\nx\n```\n```sh\nls\n```", &options),
+            "```Math\nx\n```\n"
+        );
+    }
+}
@@ -0,0 +1,171 @@
+//! Merging runs of consecutive code blocks of the same language.
+
+use std::borrow::Cow;
+use std::ops::Range;
+
+use crate::content::{first_line, unwrapped_content};
+use crate::hash::placeholder_output;
+use crate::shrink::shrunk;
+use crate::strip::block_output;
+use crate::{Action, CodeBlock, StripOptions};
+
+/// A run of consecutive code blocks of the same language, separated only by
+/// blank lines, as returned by [`CodeBlocks::merge_adjacent`](crate::CodeBlocks::merge_adjacent).
+///
+/// A block that can't be merged with its neighbors makes a run of its own.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct MergedBlock<'a> {
+    /// The language of the blocks.
+    pub language: Option<&'a str>,
+    /// The contents of the blocks, one after the other.
+    ///
+    /// A line break is added after the content of a block that doesn't end
+    /// with one. The content is borrowed from the document for a single block.
+    pub content: Cow<'a, str>,
+    /// Byte range of the run in the document, from the opening fence of its
+    /// first block to the closing fence of its last block.
+    pub span: Range<usize>,
+    /// The blocks of the run, in document order.
+    pub blocks: Vec<CodeBlock<'a>>,
+}
+
+/// Returns whether `next` follows `previous` in `text` closely enough to be merged into it.
+///
+/// Both must have the same language, and only whitespace may separate them.
+/// Blocks without a language are never merged.
+pub(crate) fn mergeable(text: &str, previous: &CodeBlock<'_>, next: &CodeBlock<'_>) -> bool {
+    previous.language.is_some()
+        && previous.language == next.language
+        && text[previous.span.end..next.span.start].trim().is_empty()
+}
+
+/// Joins `contents`, adding a line break after each one not ending with one but the last.
+fn join<'a>(mut contents: impl Iterator<Item = Cow<'a, str>>) -> Cow<'a, str> {
+    let Some(first) = contents.next() else {
+        return Cow::Borrowed("");
+    };
+
+    let mut joined = first;
+    for content in contents {
+        let joined = joined.to_mut();
+        if !joined.is_empty() && !joined.ends_with('\n') {
+            joined.push('\n');
+        }
+        joined.push_str(&content);
+    }
+    joined
+}
+
+/// Returns the text `first` and the blocks `merged` into it are replaced with
+/// when `action` is applied to them.
+pub(crate) fn merged_output<'a>(
+    text: &'a str,
+    first: &CodeBlock<'a>,
+    merged: &[CodeBlock<'a>],
+    action: &'a Action,
+    options: &StripOptions,
+    newline: &str,
+) -> Cow<'a, str> {
+    let content = || {
+        join(
+            std::iter::once(first)
+                .chain(merged)
+                .enumerate()
+                .map(|(index, block)| unwrapped_content(text, block, options, index > 0)),
+        )
+    };
+
+    match action {
+        Action::Unwrap => shrunk(content(), options, newline),
+        Action::FirstLine(marker) => first_line(content(), marker, newline),
+        Action::Placeholder(placeholder) => {
+            let raw = join(
+                std::iter::once(first)
+                    .chain(merged)
+                    .map(|block| Cow::Borrowed(block.content)),
+            );
+            placeholder_output(placeholder, &raw, options, newline)
+        }
+        _ => block_output(text, first, action, options, newline),
+    }
+}
+
+/// Groups `blocks` of `text` into runs of mergeable blocks.
+pub(crate) fn merge_adjacent<'a>(text: &str, blocks: &[CodeBlock<'a>]) -> Vec<MergedBlock<'a>> {
+    let mut runs: Vec<MergedBlock<'a>> = Vec::new();
+
+    for block in blocks {
+        match runs.last_mut() {
+            Some(run) if mergeable(text, run.blocks.last().unwrap(), block) => {
+                run.span.end = block.span.end;
+                run.blocks.push(block.clone());
+            }
+            _ => runs.push(MergedBlock {
+                language: block.language,
+                content: Cow::Borrowed(""),
+                span: block.span.clone(),
+                blocks: vec![block.clone()],
+            }),
+        }
+    }
+
+    for run in &mut runs {
+        run.content = join(run.blocks.iter().map(|block| Cow::Borrowed(block.content)));
+    }
+    runs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        CodeBlocks, extract_codeblocks, strip_codeblocks_with, strip_codeblocks_with_report,
+    };
+
+    const SPLIT: &str = "Here:\n```rust\nfn a() {}\n```\n\n```rust\nfn b() {}```\n```rust\nfn c() {}\n```\nand\n```rust\nfn d() {}\n```\n";
+
+    #[test]
+    fn test_merge_adjacent_blocks() {
+        let runs = extract_codeblocks(SPLIT).merge_adjacent(SPLIT);
+
+        assert_eq!(runs.len(), 2);
+        assert_eq!(runs[0].blocks.len(), 3);
+        assert_eq!(runs[0].content, "fn a() {}\nfn b() {}\nfn c() {}\n");
+        assert_eq!(
+            &SPLIT[runs[0].span.clone()],
+            &SPLIT[6..SPLIT.find("\nand").unwrap()]
+        );
+        assert!(matches!(runs[1].content, Cow::Borrowed("fn d() {}\n")));
+    }
+
+    #[test]
+    fn test_untagged_and_other_languages_are_not_merged() {
+        let input = "```\na\n```\n```\nb\n```\n```sh\nc\n```\n```py\nd\n```";
+        assert_eq!(extract_codeblocks(input).merge_adjacent(input).len(), 4);
+    }
+
+    #[test]
+    fn test_strip_merged_blocks() {
+        let options = StripOptions::new().merge_adjacent(true);
+        assert_eq!(
+            strip_codeblocks_with(SPLIT, &options),
+            "Here:\nfn a() {}\nfn b() {}\nfn c() {}\n\nand\nfn d() {}\n\n"
+        );
+
+        let placeholder = options.clone().action(Action::Placeholder("[code]".into()));
+        assert_eq!(
+            strip_codeblocks_with(SPLIT, &placeholder),
+            "Here:\n[code]\nand\n[code]\n"
+        );
+
+        let (_, report) = strip_codeblocks_with_report(SPLIT, &options);
+        assert_eq!(report.blocks.len(), 4);
+    }
+
+    #[test]
+    fn test_kept_blocks_are_not_merged() {
+        let options = StripOptions::new().merge_adjacent(true).languages(["py"]);
+        assert_eq!(strip_codeblocks_with(SPLIT, &options), SPLIT);
+    }
+}
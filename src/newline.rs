@@ -0,0 +1,169 @@
+//! Line endings of the text inserted into stripped documents.
+
+use std::borrow::Cow;
+
+/// The line ending used for line breaks inserted into the output, such as
+/// those of multi-line placeholders.
+///
+/// Text copied from the document keeps its own line endings; only inserted
+/// text is converted, so that a document doesn't end up with mixed line endings.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::{Action, Newline, StripOptions, strip_codeblocks_with};
+///
+/// let options = StripOptions::new().action(Action::Placeholder("[code]\n".into()));
+/// let input = "Before\r\n```sh\r\nls\r\n```\r\nAfter\r\n";
+///
+/// assert_eq!(strip_codeblocks_with(input, &options), "Before\r\n[code]\r\n\r\nAfter\r\n");
+/// assert_eq!(
+///     strip_codeblocks_with(input, &options.newline(Newline::Lf)),
+///     "Before\r\n[code]\n\r\nAfter\r\n"
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+#[non_exhaustive]
+pub enum Newline {
+    /// Use the line ending most lines of the document end with, `\n` for a tie.
+    #[default]
+    Auto,
+    /// Use `\n`.
+    Lf,
+    /// Use `\r\n`.
+    #[cfg_attr(feature = "serde", serde(rename = "crlf"))]
+    CrLf,
+}
+
+impl Newline {
+    /// Returns the line ending to insert into the stripped `text`.
+    pub(crate) fn resolve(self, text: &str) -> &'static str {
+        match self {
+            Newline::Lf => "\n",
+            Newline::CrLf => "\r\n",
+            // Most documents have no carriage return at all, which a single
+            // byte search tells faster than counting line endings
+            Newline::Auto if !text.as_bytes().contains(&b'\r') => "\n",
+            Newline::Auto => {
+                let lf = text.bytes().filter(|&byte| byte == b'\n').count();
+                let crlf = text.matches("\r\n").count();
+                if crlf > lf - crlf { "\r\n" } else { "\n" }
+            }
+        }
+    }
+}
+
+/// Whether the stripped text ends with a line break, for
+/// [`StripOptions::trailing_newline`](crate::StripOptions::trailing_newline).
+///
+/// Without a setting, how the output ends depends on what ends the document:
+/// unwrapped content ends with its own line break, a placeholder with
+/// whatever its text ends with, and a document ending with a line break after
+/// its closing fence has it copied as well.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::{Action, StripOptions, TrailingNewline, strip_codeblocks_with};
+///
+/// let options = StripOptions::new().action(Action::Placeholder("[code]".into()));
+/// assert_eq!(strip_codeblocks_with("```\nls\n```", &options), "[code]");
+///
+/// let options = options.trailing_newline(TrailingNewline::Always);
+/// assert_eq!(strip_codeblocks_with("```\nls\n```", &options), "[code]\n");
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+#[non_exhaustive]
+pub enum TrailingNewline {
+    /// Leave the end of the output as stripping makes it.
+    #[default]
+    Keep,
+    /// End non-empty output with a line break, adding one if needed.
+    Always,
+    /// Don't end the output with a line break, removing any.
+    Never,
+    /// End the output with a line break exactly when the document ends with one.
+    MatchInput,
+}
+
+impl TrailingNewline {
+    /// Returns whether the stripped `text` should end with a line break, or
+    /// `None` to leave it as is.
+    pub(crate) fn wanted(self, text: &str) -> Option<bool> {
+        match self {
+            TrailingNewline::Keep => None,
+            TrailingNewline::Always => Some(true),
+            TrailingNewline::Never => Some(false),
+            TrailingNewline::MatchInput => Some(text.ends_with('\n')),
+        }
+    }
+}
+
+/// Returns `output` without the line breaks it ends with.
+pub(crate) fn without_trailing_newlines(output: Cow<'_, str>) -> Cow<'_, str> {
+    let len = output.trim_end_matches(['\r', '\n']).len();
+    match output {
+        Cow::Borrowed(output) => Cow::Borrowed(&output[..len]),
+        Cow::Owned(mut output) => {
+            output.truncate(len);
+            Cow::Owned(output)
+        }
+    }
+}
+
+/// Returns `inserted` with each of its line breaks, `\n` or `\r\n`, replaced by `newline`.
+pub(crate) fn with_newlines<'a>(inserted: &'a str, newline: &str) -> Cow<'a, str> {
+    let mismatched = match newline {
+        "\n" => inserted.contains("\r\n"),
+        _ => inserted
+            .match_indices('\n')
+            .any(|(index, _)| !inserted[..index].ends_with('\r')),
+    };
+    if !mismatched {
+        return Cow::Borrowed(inserted);
+    }
+
+    let mut output = String::with_capacity(inserted.len() + newline.len());
+    for line in inserted.split_inclusive('\n') {
+        match line.strip_suffix('\n') {
+            Some(line) => {
+                output.push_str(line.strip_suffix('\r').unwrap_or(line));
+                output.push_str(newline);
+            }
+            None => output.push_str(line),
+        }
+    }
+    Cow::Owned(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dominant_line_ending() {
+        assert_eq!(Newline::Auto.resolve(""), "\n");
+        assert_eq!(Newline::Auto.resolve("a\r\nb\r\nc\n"), "\r\n");
+        assert_eq!(Newline::Auto.resolve("a\r\nb\nc\n"), "\n");
+        assert_eq!(Newline::Auto.resolve("a\r\nb\n"), "\n");
+        assert_eq!(Newline::CrLf.resolve("a\nb\n"), "\r\n");
+    }
+
+    #[test]
+    fn test_with_newlines() {
+        assert!(matches!(with_newlines("a\nb", "\n"), Cow::Borrowed(_)));
+        assert!(matches!(with_newlines("a\r\nb", "\r\n"), Cow::Borrowed(_)));
+        assert_eq!(with_newlines("a\nb\r\nc\n", "\r\n"), "a\r\nb\r\nc\r\n");
+        assert_eq!(with_newlines("a\r\nb\n", "\n"), "a\nb\n");
+    }
+}
@@ -0,0 +1,828 @@
+//! Configuration for [`strip_codeblocks_with`](crate::strip_codeblocks_with).
+
+use std::ops::{Bound, Range, RangeBounds};
+
+use regex::Regex;
+
+use crate::experimental::Engine;
+use crate::language::{CompiledRegexes, LanguageRegexes};
+use crate::math::is_math;
+use crate::replacements::Replacements;
+use crate::{
+    Ansi, BlockMatcher, CodeBlock, Dedent, HtmlEntities, LanguageMatch, Newline, RenderSafety,
+    ScriptPolicy, Shrink, Stripper, TrailingNewline,
+};
+
+/// What happens to a fenced code block when stripping.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+#[non_exhaustive]
+pub enum Action {
+    /// Remove the fences but keep the content.
+    #[default]
+    Unwrap,
+    /// Leave the block untouched, fences included.
+    Keep,
+    /// Remove the block entirely, content included.
+    Remove,
+    /// Replace the block with the given text.
+    Placeholder(String),
+    /// Keep the first line of the content, typically a command, and replace
+    /// the rest with the given marker line, if anything but blank lines follows.
+    ///
+    /// The first line goes through the same transformations as unwrapped
+    /// content, so [prompts](StripOptions::strip_prompts) can be removed from it.
+    FirstLine(String),
+}
+
+/// Options controlling how fenced code blocks are stripped.
+///
+/// Options are built by chaining setters on [`StripOptions::new`]; the defaults
+/// match [`strip_codeblocks`](crate::strip_codeblocks).
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::{StripOptions, strip_codeblocks_with};
+///
+/// let options = StripOptions::new().strip_prompts(true).drop_output(true);
+///
+/// let input = "```console\n$ cargo --version\ncargo 1.90.0\n```";
+/// assert_eq!(strip_codeblocks_with(input, &options), "cargo --version\n");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(default, deny_unknown_fields)
+)]
+pub struct StripOptions {
+    pub(crate) engine: Engine,
+    pub(crate) action: Action,
+    pub(crate) languages: Option<Vec<String>>,
+    pub(crate) deny_languages: Vec<String>,
+    pub(crate) select: Option<BlockMatcher>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) scope: Option<Range<usize>>,
+    pub(crate) language_match: LanguageMatch,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) language_regexes: LanguageRegexes,
+    pub(crate) html_entities: HtmlEntities,
+    pub(crate) html: bool,
+    pub(crate) html_scripts: ScriptPolicy,
+    pub(crate) require_blank_line_before_fence: bool,
+    pub(crate) render_safety: RenderSafety,
+    pub(crate) strip_prompts: bool,
+    pub(crate) drop_output: bool,
+    pub(crate) strip_comments: bool,
+    pub(crate) preserve_whitespace: bool,
+    pub(crate) ansi: Ansi,
+    pub(crate) strict: bool,
+    pub(crate) newline: Newline,
+    pub(crate) trailing_newline: TrailingNewline,
+    pub(crate) merge_adjacent: bool,
+    pub(crate) hash_placeholders: bool,
+    pub(crate) region_markers: bool,
+    pub(crate) math: Option<Action>,
+    pub(crate) shrink: Option<Shrink>,
+    pub(crate) dedent: Option<Dedent>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) replacements: Option<Replacements>,
+}
+
+impl StripOptions {
+    /// Creates options with the default behavior of [`strip_codeblocks`](crate::strip_codeblocks).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the engine used to find code blocks. Defaults to [`Engine::Regex`].
+    ///
+    /// This is part of the [`experimental`](crate::experimental) API: the
+    /// available engines and their behavior may change in minor releases.
+    pub fn engine(mut self, engine: Engine) -> Self {
+        self.engine = engine;
+        self
+    }
+
+    /// Sets what happens to the selected code blocks. Defaults to [`Action::Unwrap`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strip_codeblocks::{Action, StripOptions, strip_codeblocks_with};
+    ///
+    /// let options = StripOptions::new().action(Action::Placeholder("[code omitted]".into()));
+    ///
+    /// let input = "See:\n```rust\nfn main() {}\n```\n";
+    /// assert_eq!(strip_codeblocks_with(input, &options), "See:\n[code omitted]\n");
+    /// ```
+    pub fn action(mut self, action: Action) -> Self {
+        self.action = action;
+        self
+    }
+
+    /// Only processes blocks whose language is one of `languages`.
+    ///
+    /// Blocks in other languages, and blocks without a language, are kept
+    /// untouched. By default every block is processed. Languages must be
+    /// equal to match, unless [`language_match`](Self::language_match) says otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strip_codeblocks::{StripOptions, strip_codeblocks_with};
+    ///
+    /// let options = StripOptions::new().languages(["rust"]);
+    ///
+    /// let input = "```rust\nfn main() {}\n```\n```mermaid\ngraph TD\n```";
+    /// assert_eq!(strip_codeblocks_with(input, &options), "fn main() {}\n\n```mermaid\ngraph TD\n```");
+    /// ```
+    pub fn languages<I, S>(mut self, languages: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.languages = Some(languages.into_iter().map(Into::into).collect());
+        self.language_regexes.clear();
+        self
+    }
+
+    /// Never processes blocks whose language is one of `languages`, keeping
+    /// them untouched whatever the [action](Self::action), the
+    /// [languages](Self::languages) filter or a [`ReplacementMap`](crate::ReplacementMap) say.
+    ///
+    /// This is for policies such as "never unwrap `sql`", which can be read
+    /// from a file or the environment with [`read_language_list`](crate::read_language_list)
+    /// and [`env_language_list`](crate::env_language_list). Languages match like
+    /// those of the filter, according to [`language_match`](Self::language_match).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strip_codeblocks::{StripOptions, strip_codeblocks_with};
+    ///
+    /// let options = StripOptions::new().deny_languages(["sql"]);
+    ///
+    /// let input = "```sql\nDROP TABLE users;\n```\n```sh\nls\n```";
+    /// assert_eq!(strip_codeblocks_with(input, &options), "```sql\nDROP TABLE users;\n```\nls\n");
+    /// ```
+    pub fn deny_languages<I, S>(mut self, languages: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.deny_languages = languages.into_iter().map(Into::into).collect();
+        self.language_regexes.clear();
+        self
+    }
+
+    /// Only processes blocks `matcher` matches, keeping the others untouched,
+    /// for selections by size or info string that languages can't express.
+    ///
+    /// The selection narrows the other filters: blocks it matches are still
+    /// kept if the [languages](Self::languages) filter or the
+    /// [denied languages](Self::deny_languages) say so. Matchers can be parsed
+    /// from expressions like `lang:rust & lines>10`, also when options are
+    /// read from configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strip_codeblocks::{Action, StripOptions, strip_codeblocks_with};
+    ///
+    /// let options = StripOptions::new()
+    ///     .select("lines>2 & !lang:mermaid".parse()?)
+    ///     .action(Action::Placeholder("[long code]".into()));
+    ///
+    /// let input = "```sh\nls\n```\n```py\na = 1\nb = 2\nc = 3\n```";
+    /// assert_eq!(strip_codeblocks_with(input, &options), "```sh\nls\n```\n[long code]");
+    /// # Ok::<(), strip_codeblocks::MatcherError>(())
+    /// ```
+    pub fn select(mut self, matcher: BlockMatcher) -> Self {
+        self.select = Some(matcher);
+        self
+    }
+
+    /// Only processes blocks starting in `range` of byte offsets, keeping the
+    /// others untouched, such as the reasoning before a final answer.
+    ///
+    /// Open ranges like `pos..` and `..pos` process the blocks after or
+    /// before a position. Blocks are in scope by where their opening fence
+    /// starts, so a block starting in the range is processed whole, and the
+    /// range narrows the other filters like the [selection](Self::select)
+    /// does. Offsets are into the text being stripped, so options with a
+    /// scope are meant for one document; the scope isn't read from
+    /// configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strip_codeblocks::{StripOptions, strip_codeblocks_with};
+    ///
+    /// let answer = "Tried:\n```sh\nls\n```\nFinal Answer:\n```sh\nmake\n```";
+    /// let start = answer.find("Final Answer:").unwrap();
+    ///
+    /// let options = StripOptions::new().scope(start..);
+    /// assert_eq!(
+    ///     strip_codeblocks_with(answer, &options),
+    ///     "Tried:\n```sh\nls\n```\nFinal Answer:\nmake\n"
+    /// );
+    /// ```
+    pub fn scope(mut self, range: impl RangeBounds<usize>) -> Self {
+        let start = match range.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start.saturating_add(1),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&end) => end.saturating_add(1),
+            Bound::Excluded(&end) => end,
+            Bound::Unbounded => usize::MAX,
+        };
+        self.scope = Some(start..end);
+        self
+    }
+
+    /// Sets how the languages given to [`languages`](Self::languages) match
+    /// the languages of blocks. Defaults to [`LanguageMatch::new`], matching
+    /// equal languages only.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strip_codeblocks::{LanguageMatch, StripOptions, strip_codeblocks_with};
+    ///
+    /// let options = StripOptions::new()
+    ///     .languages(["python"])
+    ///     .language_match(LanguageMatch::new().ignore_case(true).prefix(true));
+    ///
+    /// let input = "```Python3\nprint(1)\n```";
+    /// assert_eq!(strip_codeblocks_with(input, &options), "print(1)\n");
+    /// ```
+    pub fn language_match(mut self, language_match: LanguageMatch) -> Self {
+        self.language_match = language_match;
+        self.language_regexes.clear();
+        self
+    }
+
+    /// Sets how HTML entities in the content of unwrapped blocks are handled.
+    /// Defaults to [`HtmlEntities::Keep`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strip_codeblocks::{HtmlEntities, StripOptions, strip_codeblocks_with};
+    ///
+    /// let options = StripOptions::new().html_entities(HtmlEntities::Decode);
+    ///
+    /// let input = "```cpp\nstd::vector&lt;int&gt; v;\n```";
+    /// assert_eq!(strip_codeblocks_with(input, &options), "std::vector<int> v;\n");
+    /// ```
+    pub fn html_entities(mut self, html_entities: HtmlEntities) -> Self {
+        self.html_entities = html_entities;
+        self
+    }
+
+    /// Treats HTML `<pre>` elements, with or without a `<code>` element in
+    /// them, as code blocks too, for documents mixing markdown and HTML or
+    /// converted from HTML.
+    ///
+    /// As in markdown, where only fenced blocks are blocks, `<code>`
+    /// elements outside `<pre>` elements are inline code and are left as
+    /// they are. The language of a block is given by a class name like
+    /// `language-rust` or `lang-rust` on either element. Code in HTML is
+    /// usually escaped, so this goes with decoding
+    /// [entities](Self::html_entities). Blocks in `<script>` and `<style>`
+    /// elements are left alone, and such elements don't make it from
+    /// unwrapped code into prose, unless [`html_scripts`](Self::html_scripts)
+    /// says otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strip_codeblocks::{HtmlEntities, StripOptions, strip_codeblocks_with};
+    ///
+    /// let options = StripOptions::new().html(true).html_entities(HtmlEntities::Decode);
+    ///
+    /// let input = "<p>Run <code>make</code>:</p>\n<pre><code class=\"language-sh\">make &amp;&amp; ./app\n</code></pre>";
+    /// assert_eq!(
+    ///     strip_codeblocks_with(input, &options),
+    ///     "<p>Run <code>make</code>:</p>\nmake && ./app\n"
+    /// );
+    /// ```
+    pub fn html(mut self, html: bool) -> Self {
+        self.html = html;
+        self
+    }
+
+    /// Sets how `<script>` and `<style>` elements are handled in
+    /// [HTML](Self::html) mode. Defaults to [`ScriptPolicy::Remove`].
+    ///
+    /// Stripped HTML is often rendered again, where unwrapping an example
+    /// holding such an element, or decoding its escaped tags, would run the
+    /// script. By default, no block is looked for in these elements, and they
+    /// are removed from unwrapped code, after [entities](Self::html_entities)
+    /// are decoded. Outside HTML mode, they are left as they are.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strip_codeblocks::{HtmlEntities, ScriptPolicy, StripOptions, strip_codeblocks_with};
+    ///
+    /// let options = StripOptions::new().html(true).html_entities(HtmlEntities::Decode);
+    ///
+    /// let input = "<pre>Hi&lt;script&gt;alert(1)&lt;/script&gt;</pre>";
+    /// assert_eq!(strip_codeblocks_with(input, &options), "Hi");
+    ///
+    /// let options = options.html_scripts(ScriptPolicy::Escape);
+    /// assert_eq!(
+    ///     strip_codeblocks_with(input, &options),
+    ///     "Hi&lt;script>alert(1)&lt;/script>"
+    /// );
+    /// ```
+    pub fn html_scripts(mut self, policy: ScriptPolicy) -> Self {
+        self.html_scripts = policy;
+        self
+    }
+
+    /// Only opens code blocks at fences following a blank line, as some strict
+    /// markdown dialects require, so that backticks in tight prose aren't
+    /// taken for fences.
+    ///
+    /// An opening fence must then start its line, after any indentation and
+    /// blockquote markers, and the line before it must be blank, or hold only
+    /// blockquote markers. A fence at the start of the document or right after
+    /// another block counts as following a blank line. Other fence-like
+    /// backticks are prose, and a block may open further on. Both
+    /// [engines](Self::engine) follow this setting.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strip_codeblocks::{StripOptions, strip_codeblocks_with};
+    ///
+    /// let options = StripOptions::new().require_blank_line_before_fence(true);
+    ///
+    /// let input = "Wrap it in\n```\nlike this\n```\n\n```sh\nls\n```";
+    /// assert_eq!(
+    ///     strip_codeblocks_with(input, &options),
+    ///     "Wrap it in\n```\nlike this\n```\n\nls\n"
+    /// );
+    /// ```
+    pub fn require_blank_line_before_fence(mut self, required: bool) -> Self {
+        self.require_blank_line_before_fence = required;
+        self
+    }
+
+    /// Sets how lines of unwrapped code that would render as markdown
+    /// headings, quotes, lists or other structure are handled. Defaults to
+    /// [`RenderSafety::Off`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strip_codeblocks::{RenderSafety, StripOptions, strip_codeblocks_with};
+    ///
+    /// let options = StripOptions::new().render_safety(RenderSafety::Escape);
+    ///
+    /// let input = "```py\n# Steps\n1. parse()\nrun()\n```";
+    /// assert_eq!(strip_codeblocks_with(input, &options), "\\# Steps\n1\\. parse()\nrun()\n");
+    /// ```
+    pub fn render_safety(mut self, render_safety: RenderSafety) -> Self {
+        self.render_safety = render_safety;
+        self
+    }
+
+    /// Removes `$ ` prompt prefixes from command lines in console session blocks.
+    ///
+    /// Session blocks are blocks tagged `console`, `terminal`, `shell`, `bash`,
+    /// `sh` or `zsh`, or `shell-session`, `bash-session`, `sh-session` or
+    /// `zsh-session`, that contain at least one line starting with a `$ `
+    /// prompt. Blocks without prompts are left untouched.
+    pub fn strip_prompts(mut self, strip_prompts: bool) -> Self {
+        self.strip_prompts = strip_prompts;
+        self
+    }
+
+    /// Drops output lines (lines that are not commands) from console session blocks.
+    ///
+    /// Combined with [`strip_prompts`](Self::strip_prompts), this turns a
+    /// transcript into a runnable script.
+    pub fn drop_output(mut self, drop_output: bool) -> Self {
+        self.drop_output = drop_output;
+        self
+    }
+
+    /// Removes comments from the content of unwrapped blocks, for corpora of
+    /// code only.
+    ///
+    /// Comments are found by the language of the block, in any case: line
+    /// comments such as `//`, `#` and `--`, and block comments such as
+    /// `/* */` and `<!-- -->`, for C-like languages, JavaScript, Rust, Go,
+    /// Python, shells, Ruby, YAML, TOML, SQL, Lua, Haskell, Lisps, CSS and
+    /// HTML among others. Comment markers in string literals, like the `//`
+    /// of `"http://…"`, are left alone. Lines only holding comments are
+    /// removed, line breaks included, and lines ending with one lose the
+    /// whitespace preceding it. The `#!` line starting a script is kept, and
+    /// blocks without a language or in other languages are left as is.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strip_codeblocks::{StripOptions, strip_codeblocks_with};
+    ///
+    /// let options = StripOptions::new().strip_comments(true);
+    ///
+    /// let input = "```js\n// Fetch the page\nfetch(\"https://example.com\"); // GET\n```";
+    /// assert_eq!(
+    ///     strip_codeblocks_with(input, &options),
+    ///     "fetch(\"https://example.com\");\n"
+    /// );
+    /// ```
+    pub fn strip_comments(mut self, strip_comments: bool) -> Self {
+        self.strip_comments = strip_comments;
+        self
+    }
+
+    /// Copies every byte outside the fence lines of stripped blocks verbatim.
+    ///
+    /// Stripping normally removes the fences themselves but keeps the line
+    /// break after a closing fence, so unwrapping or removing a block leaves an
+    /// extra blank line behind. In whitespace-preserving mode, blocks that are
+    /// unwrapped, removed or cut to their [first line](Action::FirstLine) lose
+    /// their opening and closing fence lines whole,
+    /// line breaks included, and nothing else:
+    ///
+    /// - Prose is copied byte for byte, and can be checked with
+    ///   [`verify_prose_preserved`](crate::verify_prose_preserved).
+    /// - Unwrapped content is copied byte for byte too, so options transforming
+    ///   it (such as [`html_entities`](Self::html_entities) and
+    ///   [`strip_prompts`](Self::strip_prompts)) are ignored.
+    /// - A placeholder takes the place of the block's lines, keeping the line
+    ///   break after them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strip_codeblocks::{StripOptions, strip_codeblocks_with, verify_prose_preserved};
+    ///
+    /// let input = "Before\n```rust\ncode\n```\nAfter";
+    ///
+    /// assert_eq!(strip_codeblocks_with(input, &StripOptions::new()), "Before\ncode\n\nAfter");
+    ///
+    /// let output = strip_codeblocks_with(input, &StripOptions::new().preserve_whitespace(true));
+    /// assert_eq!(output, "Before\ncode\nAfter");
+    /// assert!(verify_prose_preserved(input, &output).is_ok());
+    /// ```
+    pub fn preserve_whitespace(mut self, preserve_whitespace: bool) -> Self {
+        self.preserve_whitespace = preserve_whitespace;
+        self
+    }
+
+    /// Sets how ANSI escape sequences in the text are handled. Defaults to [`Ansi::Keep`].
+    ///
+    /// In [whitespace-preserving](Self::preserve_whitespace) mode,
+    /// [`Ansi::Strip`] only affects how fences are found, like
+    /// [`Ansi::Transparent`].
+    pub fn ansi(mut self, ansi: Ansi) -> Self {
+        self.ansi = ansi;
+        self
+    }
+
+    /// Makes [`try_strip_codeblocks_with`](crate::try_strip_codeblocks_with)
+    /// fail on ambiguous structures instead of stripping them best-effort.
+    ///
+    /// Unclosed fences, closing fences of a different length than their
+    /// opening fence, and fences opened in the middle of a line (e.g. inside
+    /// an inline code span) are reported as [`StripError::Ambiguous`](crate::StripError::Ambiguous).
+    /// Functions that can't fail, like
+    /// [`strip_codeblocks_with`](crate::strip_codeblocks_with), always strip
+    /// best-effort.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strip_codeblocks::{Ambiguity, StripError, StripOptions, try_strip_codeblocks_with};
+    ///
+    /// let options = StripOptions::new().strict(true);
+    ///
+    /// let error = try_strip_codeblocks_with("Intro\n```rust\nfn main() {}\n", &options);
+    /// assert_eq!(error, Err(StripError::Ambiguous { line: 2, reason: Ambiguity::UnclosedFence }));
+    /// ```
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Sets the line ending of the line breaks inserted into the output, such
+    /// as those of multi-line placeholders. Defaults to [`Newline::Auto`],
+    /// the dominant line ending of the document.
+    pub fn newline(mut self, newline: Newline) -> Self {
+        self.newline = newline;
+        self
+    }
+
+    /// Sets whether the output ends with a line break. Defaults to
+    /// [`TrailingNewline::Keep`], leaving the end of the output as stripping
+    /// makes it, which depends on how the document ends.
+    ///
+    /// An added line break ends lines like [`newline`](Self::newline) says.
+    /// [`chunk_document`](crate::chunk_document) ignores this setting.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strip_codeblocks::{StripOptions, TrailingNewline, strip_codeblocks_with};
+    ///
+    /// let options = StripOptions::new().trailing_newline(TrailingNewline::MatchInput);
+    ///
+    /// assert_eq!(strip_codeblocks_with("Run:\n```sh\nls\n```", &options), "Run:\nls");
+    /// assert_eq!(strip_codeblocks_with("```sh\nls\n```\n", &options), "ls\n\n");
+    /// ```
+    pub fn trailing_newline(mut self, trailing_newline: TrailingNewline) -> Self {
+        self.trailing_newline = trailing_newline;
+        self
+    }
+
+    /// Merges consecutive blocks of the same language, separated only by blank
+    /// lines, into a single block before applying the action to it.
+    ///
+    /// Unwrapping a run of merged blocks outputs their contents one after the
+    /// other, and a placeholder replaces the whole run once. Blocks without a
+    /// language and [kept](Action::Keep) blocks are never merged. Every merged block
+    /// still appears in the [`Report`](crate::Report).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strip_codeblocks::{StripOptions, strip_codeblocks_with};
+    ///
+    /// let options = StripOptions::new().merge_adjacent(true);
+    ///
+    /// let input = "```sh\ncd app\n```\n\n```sh\nmake\n```\nDone";
+    /// assert_eq!(strip_codeblocks_with(input, &options), "cd app\nmake\n\nDone");
+    /// ```
+    pub fn merge_adjacent(mut self, merge_adjacent: bool) -> Self {
+        self.merge_adjacent = merge_adjacent;
+        self
+    }
+
+    /// Expands `{sha256}` in [placeholders](Action::Placeholder) to the
+    /// SHA-256 digest of the replaced block's content, in hexadecimal, and
+    /// `{sha256:N}` to its first `N` digits.
+    ///
+    /// The digest covers the content exactly as it appears between the fences,
+    /// so that an auditor can check an archived block against the placeholder
+    /// that replaced it without storing the mapping separately. A placeholder
+    /// replacing [merged](Self::merge_adjacent) blocks hashes their contents
+    /// joined as in [`MergedBlock::content`](crate::MergedBlock::content).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strip_codeblocks::{Action, StripOptions, strip_codeblocks_with};
+    ///
+    /// let options = StripOptions::new()
+    ///     .action(Action::Placeholder("[code sha256:{sha256:12} omitted]".into()))
+    ///     .hash_placeholders(true);
+    ///
+    /// assert_eq!(
+    ///     strip_codeblocks_with("```\nabc```", &options),
+    ///     "[code sha256:ba7816bf8f01 omitted]"
+    /// );
+    /// ```
+    pub fn hash_placeholders(mut self, hash_placeholders: bool) -> Self {
+        self.hash_placeholders = hash_placeholders;
+        self
+    }
+
+    /// Turns stripping off between `<!-- strip-codeblocks: off -->` and
+    /// `<!-- strip-codeblocks: on -->` markers, so that authors can protect
+    /// sections of a document from processing.
+    ///
+    /// Blocks starting in such a region are [kept](Action::Keep) whatever
+    /// their language. A marker is only recognized on a line of its own
+    /// outside code blocks, and a region that isn't turned back on lasts until
+    /// the end of the document. The markers themselves are copied like any
+    /// other prose.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strip_codeblocks::{StripOptions, strip_codeblocks_with};
+    ///
+    /// let options = StripOptions::new().region_markers(true);
+    ///
+    /// let input = "```sh\nls\n```\n<!-- strip-codeblocks: off -->\n```sh\nmake\n```";
+    /// assert_eq!(
+    ///     strip_codeblocks_with(input, &options),
+    ///     "ls\n\n<!-- strip-codeblocks: off -->\n```sh\nmake\n```"
+    /// );
+    /// ```
+    pub fn region_markers(mut self, region_markers: bool) -> Self {
+        self.region_markers = region_markers;
+        self
+    }
+
+    /// Sets what happens to LaTeX math, separately from the
+    /// [action](Self::action) applied to code. Defaults to [`Action::Keep`],
+    /// leaving math verbatim, fences and delimiters included.
+    ///
+    /// Math is held in blocks in the `math` language (in any case), as on
+    /// GitHub and GitLab, and between `$$` delimiters opening and closing
+    /// their own line. Math blocks get this action whatever the
+    /// [languages](Self::languages) filter or a [`ReplacementMap`](crate::ReplacementMap)
+    /// are. Display math is only changed when the action isn't
+    /// [`Action::Keep`], and is then unwrapped of its delimiters, removed, or
+    /// replaced like a block. [`chunk_document`](crate::chunk_document) and the
+    /// [whitespace-preserving](Self::preserve_whitespace) mode leave display
+    /// math as is.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strip_codeblocks::{Action, StripOptions, strip_codeblocks_with};
+    ///
+    /// let input = "```math\na^2 + b^2\n```\n```py\nc = 1\n```";
+    /// assert_eq!(
+    ///     strip_codeblocks_with(input, &StripOptions::new()),
+    ///     "```math\na^2 + b^2\n```\nc = 1\n"
+    /// );
+    ///
+    /// let options = StripOptions::new().math(Action::Placeholder("[formula]".into()));
+    /// assert_eq!(
+    ///     strip_codeblocks_with("Energy:\n$$\nE = mc^2\n$$\n", &options),
+    ///     "Energy:\n[formula]\n"
+    /// );
+    /// ```
+    pub fn math(mut self, action: Action) -> Self {
+        self.math = Some(action);
+        self
+    }
+
+    /// Shortens the content of long [unwrapped](Action::Unwrap) blocks to
+    /// their first and last lines, as described by `shrink`, so that stack
+    /// traces and dumps don't dominate the stripped text.
+    ///
+    /// Blocks that are kept, removed or replaced are left as is.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strip_codeblocks::{Shrink, StripOptions, strip_codeblocks_with};
+    ///
+    /// let options = StripOptions::new().shrink(Shrink::new(3, 1));
+    ///
+    /// let trace = "```\nError: boom\n  at a\n  at b\n  at c\n  at main\n```";
+    /// assert_eq!(
+    ///     strip_codeblocks_with(trace, &options),
+    ///     "Error: boom\n… (3 lines omitted)\n  at main\n"
+    /// );
+    /// ```
+    pub fn shrink(mut self, shrink: Shrink) -> Self {
+        self.shrink = Some(shrink);
+        self
+    }
+
+    /// Removes the indentation and blockquote markers of indented and
+    /// blockquoted fences from the content of [unwrapped](Action::Unwrap)
+    /// blocks, as described by `dedent`.
+    ///
+    /// Without it, the first line of such a block is indented twice, once by
+    /// what preceded the fence and once by its own indentation. With it, the
+    /// first line goes where the fence stood and the other lines keep the
+    /// container's indentation, normalized to spaces, so the code stays in its
+    /// list item or quote and keeps its alignment. After the label of a
+    /// footnote definition, as in `[^1]: ```sh`, the other lines keep the
+    /// four columns continuing the definition. Lines left blank lose their
+    /// trailing whitespace. [Whitespace-preserving](Self::preserve_whitespace)
+    /// mode leaves content as is.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strip_codeblocks::{Dedent, StripOptions, strip_codeblocks_with};
+    ///
+    /// let options = StripOptions::new().dedent(Dedent::new());
+    ///
+    /// let text = "1. Build:\n   ```sh\n   make \\\n     -j4\n   ```\n> ```\n> quoted\n> ```";
+    /// assert_eq!(
+    ///     strip_codeblocks_with(text, &options),
+    ///     "1. Build:\n   make \\\n     -j4\n\n> quoted\n>"
+    /// );
+    /// ```
+    pub fn dedent(mut self, dedent: Dedent) -> Self {
+        self.dedent = Some(dedent);
+        self
+    }
+
+    /// Compiles the options into a [`Stripper`], for stripping many documents
+    /// with the same options.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strip_codeblocks::StripOptions;
+    ///
+    /// let stripper = StripOptions::new().languages(["sh"]).build();
+    ///
+    /// assert_eq!(stripper.strip("```sh\nls\n```"), "ls\n");
+    /// assert_eq!(stripper.strip("```py\npass\n```"), "```py\npass\n```");
+    /// ```
+    pub fn build(self) -> Stripper {
+        Stripper::new(self)
+    }
+
+    /// Returns the action to apply to `block`.
+    pub(crate) fn action_for(&self, block: &CodeBlock<'_>) -> &Action {
+        if let Some(scope) = &self.scope
+            && !scope.contains(&block.span.start)
+        {
+            return &Action::Keep;
+        }
+        if let Some(select) = &self.select
+            && !select.matches(block)
+        {
+            return &Action::Keep;
+        }
+        self.action_for_language(block.language)
+    }
+
+    /// Returns the action to apply to math.
+    pub(crate) fn math_action(&self) -> &Action {
+        self.math.as_ref().unwrap_or(&Action::Keep)
+    }
+
+    /// Returns the action to apply to a block in `language`.
+    pub(crate) fn action_for_language(&self, language: Option<&str>) -> &Action {
+        let regexes = self.compiled_regexes();
+        if let Some(language) = language
+            && self.language_matches(
+                &self.deny_languages,
+                regexes.map(|regexes| &regexes.deny_languages[..]),
+                language,
+            )
+        {
+            return &Action::Keep;
+        }
+        if is_math(language) {
+            return self.math_action();
+        }
+        if let Some(replacements) = &self.replacements {
+            return replacements.action_for(language);
+        }
+
+        let selected = match &self.languages {
+            None => true,
+            Some(languages) => language.is_some_and(|language| {
+                self.language_matches(
+                    languages,
+                    regexes.map(|regexes| &regexes.languages[..]),
+                    language,
+                )
+            }),
+        };
+
+        if selected {
+            &self.action
+        } else {
+            &Action::Keep
+        }
+    }
+
+    /// Returns the compiled regexes of the language filters, in
+    /// [regex](LanguageMatch::regex) mode.
+    pub(crate) fn compiled_regexes(&self) -> Option<&CompiledRegexes> {
+        self.language_match.is_regex().then(|| {
+            self.language_regexes.get_or_compile(
+                &self.language_match,
+                self.languages.as_deref(),
+                &self.deny_languages,
+            )
+        })
+    }
+
+    /// Returns whether `language` matches one of `filters`, whose compiled
+    /// regexes are `regexes` in regex mode.
+    fn language_matches(
+        &self,
+        filters: &[String],
+        regexes: Option<&[Option<Regex>]>,
+        language: &str,
+    ) -> bool {
+        match regexes {
+            Some(regexes) => regexes
+                .iter()
+                .flatten()
+                .any(|regex| regex.is_match(language)),
+            None => filters
+                .iter()
+                .any(|filter| self.language_match.matches(filter, language)),
+        }
+    }
+}
@@ -0,0 +1,147 @@
+//! Stripping huge documents on a thread pool.
+
+use std::convert::Infallible;
+use std::ops::Range;
+
+use rayon::prelude::*;
+
+use crate::merge::mergeable;
+use crate::region::Regions;
+use crate::strip::{Piece, try_for_each_piece_in};
+use crate::{CodeBlock, StripOptions};
+
+/// The length from which documents are stripped in parallel.
+pub(crate) const MIN_LEN: usize = 4 << 20;
+
+/// The length of the parts a document is split into, at least.
+const PART_LEN: usize = 1 << 20;
+
+/// Splits `blocks` of `text` into runs starting about `part_len` bytes apart,
+/// never separating blocks that [merge](StripOptions::merge_adjacent).
+fn parts(
+    text: &str,
+    blocks: &[CodeBlock<'_>],
+    options: &StripOptions,
+    part_len: usize,
+) -> Vec<Range<usize>> {
+    let mut parts = Vec::new();
+    let mut first = 0;
+    let mut part_start = 0;
+
+    for index in 1..blocks.len() {
+        let (previous, next) = (&blocks[index - 1], &blocks[index]);
+        let long = next.span.start - part_start >= part_len;
+        if long && !(options.merge_adjacent && mergeable(text, previous, next)) {
+            parts.push(first..index);
+            first = index;
+            part_start = next.span.start;
+        }
+    }
+    parts.push(first..blocks.len());
+    parts
+}
+
+/// Strips `blocks` of `text` like `try_for_each_raw_piece`, producing the
+/// pieces of parts of the text on the rayon thread pool.
+///
+/// Parts are split at the start of a block, and each part is stripped with
+/// the line breaks and regions of the whole text, so the pieces are those of
+/// stripping on one thread, in the same order.
+pub(crate) fn try_for_each_raw_piece<'a, E>(
+    text: &'a str,
+    blocks: &[CodeBlock<'a>],
+    regions: &Regions,
+    newline: &str,
+    options: &'a StripOptions,
+    f: impl FnMut(Piece<'a>) -> Result<(), E>,
+) -> Result<(), E> {
+    let parts = parts(text, blocks, options, PART_LEN.max(text.len() / 64));
+    let bounds = |index: usize| {
+        let start = match index {
+            0 => 0,
+            _ => blocks[parts[index].start].span.start,
+        };
+        let end = parts
+            .get(index + 1)
+            .map_or(text.len(), |next| blocks[next.start].span.start);
+        start..end
+    };
+
+    let pieces: Vec<Vec<Piece<'a>>> = (0..parts.len())
+        .into_par_iter()
+        .map(|index| {
+            let mut pieces = Vec::new();
+            let blocks = blocks[parts[index].clone()].iter().cloned();
+            let Ok(()) = try_for_each_piece_in(
+                text,
+                bounds(index),
+                blocks,
+                regions,
+                newline,
+                options,
+                |piece| {
+                    pieces.push(piece);
+                    Ok::<_, Infallible>(())
+                },
+            );
+            pieces
+        })
+        .collect();
+    pieces.into_iter().flatten().try_for_each(f)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scan::codeblocks_for;
+    use crate::{Action, strip_codeblocks_with};
+
+    #[test]
+    fn test_parts_keep_merged_blocks_together() {
+        let text = "```sh\na\n```\n```sh\nb\n```\n```py\nc\n```";
+        let blocks: Vec<_> = codeblocks_for(text, &StripOptions::new()).collect();
+
+        assert_eq!(
+            parts(text, &blocks, &StripOptions::new(), 1),
+            [0..1, 1..2, 2..3]
+        );
+        let options = StripOptions::new().merge_adjacent(true);
+        assert_eq!(parts(text, &blocks, &options, 1), [0..2, 2..3]);
+        assert_eq!(parts(text, &[], &options, 1), vec![0..0; 1]);
+    }
+
+    #[test]
+    fn test_output_matches_sequential_stripping() {
+        let section = "# Step\r\n```sh\nmake\n```\n```sh\nls\n```\n$$\nx\n$$\n~~~\ny\n~~~\n";
+        let half = section.repeat(MIN_LEN / section.len() / 2 + 1);
+        let text = format!("{half}<!-- strip-codeblocks: off -->\n{half}");
+
+        for options in [
+            StripOptions::new(),
+            StripOptions::new()
+                .merge_adjacent(true)
+                .action(Action::Placeholder("[code]".into())),
+            StripOptions::new().preserve_whitespace(true),
+            StripOptions::new().region_markers(true),
+            StripOptions::new().math(Action::Remove),
+        ] {
+            let regions = Regions::new(&text, &options);
+            let newline = options.newline.resolve(&text);
+            let blocks: Vec<_> = codeblocks_for(&text, &options).collect();
+            let mut sequential = String::new();
+            let Ok(()) = try_for_each_piece_in(
+                &text,
+                0..text.len(),
+                blocks.iter().cloned(),
+                &regions,
+                newline,
+                &options,
+                |piece| {
+                    sequential.push_str(&piece.output);
+                    Ok::<_, Infallible>(())
+                },
+            );
+            assert_eq!(strip_codeblocks_with(&text, &options), sequential);
+        }
+    }
+}
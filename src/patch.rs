@@ -0,0 +1,395 @@
+//! Stripping the code blocks of markdown shown in unified diffs.
+
+use std::ops::Range;
+
+use crate::strip::{Piece, for_each_piece};
+use crate::{Action, SegmentKind, StripOptions};
+
+/// The counts and section heading of a hunk header, such as `@@ -1,3 +1,4 @@ fn main()`.
+struct HunkHeader<'a> {
+    /// The first line of the old side the hunk covers, from 1.
+    old_first: usize,
+    old_len: usize,
+    /// The first line of the new side the hunk covers, from 1.
+    new_first: usize,
+    new_len: usize,
+    /// What follows the closing `@@`, line break included.
+    section: &'a str,
+}
+
+/// Parses the start and length of a hunk range such as `1,3`, returning the
+/// first line covered, as an empty range starts at the line before it.
+fn parse_range(range: &str) -> Option<(usize, usize)> {
+    let (start, len) = match range.split_once(',') {
+        Some((start, len)) => (start.parse().ok()?, len.parse().ok()?),
+        None => (range.parse().ok()?, 1),
+    };
+    Some((if len == 0 { start + 1 } else { start }, len))
+}
+
+impl<'a> HunkHeader<'a> {
+    fn parse(line: &'a str) -> Option<Self> {
+        let rest = line.strip_prefix("@@ -")?;
+        let (old, rest) = rest.split_once(" +")?;
+        let (new, section) = rest.split_once(" @@")?;
+        let (old_first, old_len) = parse_range(old)?;
+        let (new_first, new_len) = parse_range(new)?;
+        Some(Self {
+            old_first,
+            old_len,
+            new_first,
+            new_len,
+            section,
+        })
+    }
+}
+
+/// Formats the range of a hunk starting at line `first`, from 1, over `len` lines.
+fn format_range(first: usize, len: usize) -> String {
+    match len {
+        0 => format!("{},0", first.saturating_sub(1)),
+        1 => first.to_string(),
+        _ => format!("{first},{len}"),
+    }
+}
+
+/// Returns what each line of `text` becomes once stripped with `options`,
+/// line breaks included, or `None` for lines left blank that weren't.
+///
+/// Text kept as is by stripping stays on its line. Text stripping changed,
+/// such as a placeholder, goes on the first line of what it replaces.
+fn stripped_lines(text: &str, options: &StripOptions) -> Vec<Option<String>> {
+    let lines: Vec<Range<usize>> = text
+        .split_inclusive('\n')
+        .scan(0, |start, line| {
+            let range = *start..*start + line.len();
+            *start = range.end;
+            Some(range)
+        })
+        .collect();
+    let line_of = |offset: usize| lines.partition_point(|line| line.end <= offset);
+    let mut outputs = vec![String::new(); lines.len()];
+
+    let keep = |outputs: &mut Vec<String>, range: Range<usize>| {
+        for index in line_of(range.start)..lines.len() {
+            let line = &lines[index];
+            if line.start >= range.end {
+                break;
+            }
+            outputs[index].push_str(&text[line.start.max(range.start)..line.end.min(range.end)]);
+        }
+    };
+    for_each_piece(text, options, |piece: Piece<'_>| {
+        let kept = match (piece.kind, &piece.block) {
+            (SegmentKind::Prose, _) => Some(piece.span.clone()),
+            (_, Some(_)) if piece.action == Some(&Action::Keep) => Some(piece.span.clone()),
+            (_, Some(block)) if piece.merged.is_empty() => Some(block.content_span.clone()),
+            _ => None,
+        };
+        match kept {
+            Some(range) if piece.output == text[range.clone()] => keep(&mut outputs, range),
+            _ if piece.span.start < text.len() => {
+                outputs[line_of(piece.span.start)].push_str(&piece.output)
+            }
+            _ => {}
+        }
+    });
+
+    outputs
+        .into_iter()
+        .zip(&lines)
+        .map(|(output, line)| {
+            let blank = |text: &str| text.trim_end_matches(['\r', '\n']).is_empty();
+            (!blank(&output) || blank(&text[line.clone()])).then_some(output)
+        })
+        .collect()
+}
+
+/// Returns what line `index` of a side became, or `None` for lines past its
+/// end, such as the empty last line of a truncated hunk.
+fn line_at(lines: &[Option<String>], index: usize) -> Option<&String> {
+    lines.get(index).and_then(Option::as_ref)
+}
+
+/// Appends `text` to `output` as lines prefixed with `prefix`, returning how many.
+fn push_lines(output: &mut String, prefix: char, text: &str) -> usize {
+    let mut count = 0;
+    for line in text.split_inclusive('\n') {
+        output.push(prefix);
+        output.push_str(line);
+        if !line.ends_with('\n') {
+            output.push('\n');
+        }
+        count += 1;
+    }
+    count
+}
+
+/// Strips the markdown of one hunk, side by side, appending it to `output`
+/// with its header when lines remain.
+///
+/// `shift` holds how many lines earlier hunks of the file added to each side,
+/// and is updated with those of this one.
+fn strip_hunk(
+    header: &HunkHeader<'_>,
+    lines: &[&str],
+    options: &StripOptions,
+    shift: &mut (isize, isize),
+    output: &mut String,
+) {
+    let mut old = String::new();
+    let mut new = String::new();
+    for line in lines {
+        let (prefix, text) = match line.chars().next() {
+            Some(prefix @ (' ' | '-' | '+' | '\\')) => (prefix, &line[1..]),
+            // Empty context lines, whose space some tools trim
+            _ => (' ', *line),
+        };
+        match prefix {
+            ' ' => {
+                old.push_str(text);
+                new.push_str(text);
+            }
+            '-' => old.push_str(text),
+            '+' => new.push_str(text),
+            _ => {}
+        }
+    }
+    let old_lines = stripped_lines(&old, options);
+    let new_lines = stripped_lines(&new, options);
+
+    let mut body = String::new();
+    let (mut old_index, mut new_index) = (0, 0);
+    let (mut old_len, mut new_len) = (0, 0);
+    for line in lines {
+        match line.chars().next() {
+            Some('-') => {
+                if let Some(text) = line_at(&old_lines, old_index) {
+                    old_len += push_lines(&mut body, '-', text);
+                }
+                old_index += 1;
+            }
+            Some('+') => {
+                if let Some(text) = line_at(&new_lines, new_index) {
+                    new_len += push_lines(&mut body, '+', text);
+                }
+                new_index += 1;
+            }
+            Some('\\') => body.push_str(line),
+            _ => {
+                let (old_text, new_text) = (
+                    line_at(&old_lines, old_index),
+                    line_at(&new_lines, new_index),
+                );
+                if old_text == new_text {
+                    if let Some(text) = old_text {
+                        let count = push_lines(&mut body, ' ', text);
+                        old_len += count;
+                        new_len += count;
+                    }
+                } else {
+                    if let Some(text) = old_text {
+                        old_len += push_lines(&mut body, '-', text);
+                    }
+                    if let Some(text) = new_text {
+                        new_len += push_lines(&mut body, '+', text);
+                    }
+                }
+                old_index += 1;
+                new_index += 1;
+            }
+        }
+    }
+
+    let old_first = header.old_first.saturating_add_signed(shift.0);
+    let new_first = header.new_first.saturating_add_signed(shift.1);
+    shift.0 += old_len as isize - header.old_len as isize;
+    shift.1 += new_len as isize - header.new_len as isize;
+    if old_len + new_len == 0 {
+        return;
+    }
+    output.push_str(&format!(
+        "@@ -{} +{} @@{}",
+        format_range(old_first, old_len),
+        format_range(new_first, new_len),
+        header.section
+    ));
+    output.push_str(&body);
+}
+
+/// Strips the fenced code blocks of the markdown shown in a unified diff,
+/// such as the output of `git diff`, keeping it a valid patch.
+///
+/// This is [`strip_patch_with`] with the default options.
+///
+/// # Arguments
+///
+/// * `patch` - The unified diff of markdown documents
+///
+/// # Returns
+///
+/// The patch between the stripped documents.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::strip_patch;
+///
+/// let patch = "@@ -1,3 +1,3 @@\n ```sh\n-make\n+make test\n ```\n";
+/// assert_eq!(strip_patch(patch), "@@ -1 +1 @@\n-make\n+make test\n");
+/// ```
+pub fn strip_patch(patch: &str) -> String {
+    strip_patch_with(patch, &StripOptions::new())
+}
+
+/// Strips the fenced code blocks of the markdown shown in a unified diff
+/// according to `options`, keeping it a valid patch.
+///
+/// Stripping the patch as plain text would pair the fence of a removed line
+/// with that of an added line. Instead, the old side of each hunk, its
+/// context and removed lines, and its new side, its context and added lines,
+/// are stripped on their own, and the hunk is rebuilt from what each line
+/// becomes on each side:
+///
+/// - Lines left blank by stripping that weren't, such as fence lines, are
+///   dropped, and text stripping changed, such as a placeholder, goes on the
+///   first line of what it replaces.
+/// - A context line stripped differently on each side becomes a removed and
+///   an added line.
+/// - Hunk headers are recounted, and hunks left empty are dropped, with the
+///   line numbers of the following hunks of the file shifted accordingly.
+///
+/// Each hunk is stripped on its own, since the lines between hunks aren't in
+/// the patch, so a block should start and end in the same hunk. File headers
+/// and other lines outside hunks are copied as they are.
+///
+/// # Arguments
+///
+/// * `patch` - The unified diff of markdown documents
+/// * `options` - Which blocks are stripped and how, on each side
+///
+/// # Returns
+///
+/// The patch between the stripped documents.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::{Action, StripOptions, strip_patch_with};
+///
+/// let options = StripOptions::new().action(Action::Placeholder("[code]".into()));
+///
+/// let patch = "--- a/README.md\n+++ b/README.md\n@@ -1,2 +1,5 @@\n Intro\n+```sh\n+ls\n+```\n Outro\n";
+/// assert_eq!(
+///     strip_patch_with(patch, &options),
+///     "--- a/README.md\n+++ b/README.md\n@@ -1,2 +1,3 @@\n Intro\n+[code]\n Outro\n"
+/// );
+/// ```
+pub fn strip_patch_with(patch: &str, options: &StripOptions) -> String {
+    let mut output = String::with_capacity(patch.len());
+    let mut shift = (0, 0);
+    let mut lines = patch.split_inclusive('\n').peekable();
+
+    while let Some(line) = lines.next() {
+        let Some(header) = HunkHeader::parse(line) else {
+            if line.starts_with("diff ") || line.starts_with("--- ") {
+                shift = (0, 0);
+            }
+            output.push_str(line);
+            continue;
+        };
+
+        // The hunk runs until both sides have all their lines
+        let (mut old_left, mut new_left) = (header.old_len, header.new_len);
+        let mut hunk = Vec::new();
+        while old_left + new_left > 0 || lines.peek().is_some_and(|line| line.starts_with('\\')) {
+            let Some(line) = lines.next() else {
+                break;
+            };
+            match line.chars().next() {
+                Some('-') => old_left = old_left.saturating_sub(1),
+                Some('+') => new_left = new_left.saturating_sub(1),
+                Some('\\') => {}
+                _ => {
+                    old_left = old_left.saturating_sub(1);
+                    new_left = new_left.saturating_sub(1);
+                }
+            }
+            hunk.push(line);
+        }
+        strip_hunk(&header, &hunk, options, &mut shift, &mut output);
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_header() {
+        let header = HunkHeader::parse("@@ -3,0 +4 @@ fn main()\n").unwrap();
+        assert_eq!(
+            (
+                header.old_first,
+                header.old_len,
+                header.new_first,
+                header.new_len
+            ),
+            (4, 0, 4, 1)
+        );
+        assert_eq!(header.section, " fn main()\n");
+        assert!(HunkHeader::parse("@@ nonsense\n").is_none());
+        assert_eq!(format_range(4, 0), "3,0");
+    }
+
+    #[test]
+    fn test_stripped_lines() {
+        let text = "Run: ```sh\nls\n```\n\nDone\n";
+        assert_eq!(
+            stripped_lines(text, &StripOptions::new()),
+            [
+                Some("Run: ".to_string()),
+                Some("ls\n".to_string()),
+                None,
+                Some("\n".to_string()),
+                Some("Done\n".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fences_pair_within_each_side() {
+        // Stripped as one text, `-```sh` would pair with `+```rust`
+        let patch = "@@ -1,3 +1,3 @@\n-```sh\n+```rust\n code\n ```\n";
+        assert_eq!(strip_patch(patch), "@@ -1 +1 @@\n code\n");
+    }
+
+    #[test]
+    fn test_context_stripped_differently() {
+        // Closing a block unwraps it on the new side only
+        let patch = "@@ -1,2 +1,3 @@\n ```\n x\n+```\n";
+        assert_eq!(strip_patch(patch), "@@ -1,2 +1 @@\n-```\n x\n");
+
+        let patch = "@@ -1,3 +1,2 @@\n-```\n ```\n x\n";
+        assert_eq!(strip_patch(patch), "@@ -1 +1,2 @@\n+```\n x\n");
+    }
+
+    #[test]
+    fn test_truncated_hunks() {
+        assert_eq!(strip_patch("@@ -1,2 +1,2 @@\n "), "");
+        assert_eq!(strip_patch("@@ -1,2 +1,2 @@\n-"), "");
+        assert_eq!(strip_patch("@@ -1,2 +1,2 @@\n+"), "");
+        assert_eq!(strip_patch("@@ -1,2 +1,2 @@\n a\n+"), "@@ -1 +1 @@\n a\n");
+        assert_eq!(format_range(0, 0), "0,0");
+    }
+
+    #[test]
+    fn test_later_hunks_shift() {
+        let patch = "diff --git a/a.md b/a.md\n--- a/a.md\n+++ b/a.md\n@@ -1,3 +1,3 @@\n ```\n-a\n+b\n ```\n@@ -10 +10 @@\n-c\n+d\n\\ No newline at end of file\n";
+        assert_eq!(
+            strip_patch(patch),
+            "diff --git a/a.md b/a.md\n--- a/a.md\n+++ b/a.md\n@@ -1 +1 @@\n-a\n+b\n@@ -8 +8 @@\n-c\n+d\n\\ No newline at end of file\n"
+        );
+    }
+}
@@ -0,0 +1,435 @@
+//! Composing transformations of stripped text with [`Pipeline`].
+
+use std::any::Any;
+use std::fmt;
+use std::iter;
+use std::sync::Arc;
+
+use crate::report::LineCounter;
+use crate::strip::{Piece, for_each_piece};
+use crate::{Action, Annotations, BlockReport, CodeBlock, Report, SegmentKind, StripOptions};
+
+/// Which pieces of the stripped text a stage transforms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Target {
+    Prose,
+    Code,
+    All,
+}
+
+type Transform = Arc<dyn Fn(&str, &Annotations) -> String + Send + Sync>;
+
+type Annotator = Arc<dyn Fn(&CodeBlock<'_>, &mut Annotations) + Send + Sync>;
+
+type Decorator = Arc<dyn Fn(&CodeBlock<'_>, usize) -> (String, String) + Send + Sync>;
+
+/// A transformation of the stripped text, run as part of a [`Pipeline`].
+#[derive(Clone)]
+struct Stage {
+    target: Target,
+    transform: Transform,
+}
+
+/// A sequence of transformations applied to stripped text in a single pass.
+///
+/// The document is parsed once. Each run of prose and each stripped code
+/// block then goes through the stages in the order they were added, so a
+/// stage knows whether it sees prose or code and every stage sees the output
+/// of the previous ones. Code stages apply to the content of unwrapped blocks
+/// and to kept blocks; placeholders and removed blocks are left alone.
+/// Unwrapped blocks can also be [decorated](Self::decorate) with a header and
+/// a footer, and every block [annotated](Self::annotate) with typed data that
+/// later stages and the [report](Self::run_with_report) read back.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::{Pipeline, StripOptions};
+///
+/// let pipeline = Pipeline::new()
+///     .options(StripOptions::new().strip_prompts(true))
+///     .normalize_whitespace()
+///     .map(|text| text.replace("hunter2", "[REDACTED]"))
+///     .map_prose(|prose| prose.replace("**", ""));
+///
+/// let input = "**Login:**   \n```sh\n$ login --password hunter2\n```\n";
+/// assert_eq!(pipeline.run(input), "Login:\nlogin --password [REDACTED]\n\n");
+/// ```
+#[derive(Clone, Default)]
+pub struct Pipeline {
+    options: StripOptions,
+    stages: Vec<Stage>,
+    annotators: Vec<Annotator>,
+    decorator: Option<Decorator>,
+}
+
+impl Pipeline {
+    /// Creates a pipeline stripping code blocks like [`strip_codeblocks`](crate::strip_codeblocks), without any stage.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Strips code blocks according to `options`, the first step of the pipeline.
+    pub fn options(mut self, options: StripOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Adds a stage transforming both prose and code.
+    pub fn map<F>(self, transform: F) -> Self
+    where
+        F: Fn(&str) -> String + Send + Sync + 'static,
+    {
+        self.stage(Target::All, transform)
+    }
+
+    /// Adds a stage transforming prose only.
+    pub fn map_prose<F>(self, transform: F) -> Self
+    where
+        F: Fn(&str) -> String + Send + Sync + 'static,
+    {
+        self.stage(Target::Prose, transform)
+    }
+
+    /// Adds a stage transforming code only.
+    pub fn map_code<F>(self, transform: F) -> Self
+    where
+        F: Fn(&str) -> String + Send + Sync + 'static,
+    {
+        self.stage(Target::Code, transform)
+    }
+
+    /// Adds a stage transforming the code of blocks [annotated](Self::annotate)
+    /// with a value of type `T`, which `transform` is called with.
+    ///
+    /// Blocks without such an annotation are left as they are.
+    pub fn map_annotated<T, F>(mut self, transform: F) -> Self
+    where
+        T: Any,
+        F: Fn(&T, &str) -> String + Send + Sync + 'static,
+    {
+        self.stages.push(Stage {
+            target: Target::Code,
+            transform: Arc::new(move |text, annotations| match annotations.get::<T>() {
+                Some(annotation) => transform(annotation, text),
+                None => text.to_string(),
+            }),
+        });
+        self
+    }
+
+    /// Attaches the value `annotate` returns for each code block to it, so
+    /// that multi-stage pipelines don't need side tables keyed by block index.
+    ///
+    /// `annotate` is called with every block found, whatever its action, and
+    /// the annotations attached to it by earlier calls of this method. The
+    /// value it returns, if any, replaces any annotation of the same type.
+    /// Annotations are attached before any stage runs, and can be read by
+    /// [`map_annotated`](Self::map_annotated) stages and in the
+    /// [`BlockReport`]s of [`run_with_report`](Self::run_with_report). Blocks
+    /// [merged](StripOptions::merge_adjacent) into one share its annotations.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strip_codeblocks::Pipeline;
+    ///
+    /// struct Lines(usize);
+    ///
+    /// let pipeline = Pipeline::new()
+    ///     .annotate(|block, _| Some(Lines(block.content.lines().count())))
+    ///     .map_annotated(|lines: &Lines, code| format!("# {} lines\n{code}", lines.0));
+    ///
+    /// let (output, report) = pipeline.run_with_report("```sh\nls\npwd\n```\n```py\npass\n```");
+    /// assert_eq!(output, "# 2 lines\nls\npwd\n\n# 1 lines\npass\n");
+    /// assert_eq!(report.blocks[1].annotations.get::<Lines>().unwrap().0, 1);
+    /// ```
+    pub fn annotate<T, F>(mut self, annotate: F) -> Self
+    where
+        T: Any + Send + Sync,
+        F: Fn(&CodeBlock<'_>, &Annotations) -> Option<T> + Send + Sync + 'static,
+    {
+        self.annotators.push(Arc::new(move |block, annotations| {
+            if let Some(annotation) = annotate(block, annotations) {
+                annotations.insert(annotation);
+            }
+        }));
+        self
+    }
+
+    /// Adds a stage removing trailing whitespace from every line and turning
+    /// `\r\n` line endings into `\n`, in both prose and code.
+    pub fn normalize_whitespace(self) -> Self {
+        self.map(normalize_whitespace)
+    }
+
+    /// Frames each unwrapped block with a header and a footer line computed by `decorate`.
+    ///
+    /// `decorate` is called with the block and its number in the document,
+    /// counting every block from 1, and returns the header and the footer.
+    /// Each goes on a line of its own, before and after the content, unless
+    /// it is empty. Decorations are added once the code stages have run, and
+    /// their line breaks follow [`StripOptions::newline`]. Only the last
+    /// decorator added is used.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strip_codeblocks::Pipeline;
+    ///
+    /// let pipeline = Pipeline::new().decorate(|block, number| {
+    ///     let language = block.language.unwrap_or("text");
+    ///     (format!("----- {language} snippet {number} -----"), String::new())
+    /// });
+    ///
+    /// let input = "Try:\n```rust\nfn main() {}\n```\n";
+    /// assert_eq!(pipeline.run(input), "Try:\n----- rust snippet 1 -----\nfn main() {}\n\n");
+    /// ```
+    pub fn decorate<F>(mut self, decorate: F) -> Self
+    where
+        F: Fn(&CodeBlock<'_>, usize) -> (String, String) + Send + Sync + 'static,
+    {
+        self.decorator = Some(Arc::new(decorate));
+        self
+    }
+
+    fn stage<F>(mut self, target: Target, transform: F) -> Self
+    where
+        F: Fn(&str) -> String + Send + Sync + 'static,
+    {
+        self.stages.push(Stage {
+            target,
+            transform: Arc::new(move |text, _| transform(text)),
+        });
+        self
+    }
+
+    /// Strips `text` and runs every stage on the result.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The markdown text containing code blocks to strip
+    ///
+    /// # Returns
+    ///
+    /// The stripped and transformed text.
+    pub fn run(&self, text: &str) -> String {
+        self.run_to(text, None)
+    }
+
+    /// Strips `text`, runs every stage on the result and reports what was
+    /// done to each block, with its [annotations](Self::annotate).
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The markdown text containing code blocks to strip
+    ///
+    /// # Returns
+    ///
+    /// The stripped and transformed text, and the report.
+    pub fn run_with_report(&self, text: &str) -> (String, Report) {
+        let mut report = Report::default();
+        let output = self.run_to(text, Some(&mut report));
+        (output, report)
+    }
+
+    /// Strips `text` and runs every stage on the result, recording each block in `report` if given.
+    fn run_to(&self, text: &str, mut report: Option<&mut Report>) -> String {
+        let mut output = String::with_capacity(text.len());
+        let newline = self.options.newline.resolve(text);
+        let mut number = 0;
+        let mut lines = LineCounter::new(text);
+
+        for_each_piece(text, &self.options, |piece| {
+            if piece.kind == SegmentKind::Code {
+                number += 1;
+            }
+            let mut annotations = Annotations::new();
+            if let Some(block) = &piece.block {
+                for annotate in &self.annotators {
+                    annotate(block, &mut annotations);
+                }
+            }
+            if let (Some(report), Some(block), Some(action)) =
+                (report.as_deref_mut(), &piece.block, piece.action)
+            {
+                for block in iter::once(block).chain(&piece.merged) {
+                    report.blocks.push(BlockReport {
+                        language: block.language.map(str::to_string),
+                        span: block.span.clone(),
+                        lines: lines.lines(&block.span),
+                        action: action.clone(),
+                        annotations: annotations.clone(),
+                    });
+                }
+            }
+            let decoration = match (&self.decorator, &piece.block, piece.action) {
+                (Some(decorate), Some(block), Some(Action::Unwrap)) => {
+                    Some(decorate(block, number))
+                }
+                _ => None,
+            };
+            let (header, footer) = decoration.unwrap_or_default();
+            if !header.is_empty() {
+                output.push_str(&header);
+                output.push_str(newline);
+            }
+            self.transform(&piece, &annotations, &mut output);
+            if !footer.is_empty() {
+                if !output.is_empty() && !output.ends_with('\n') {
+                    output.push_str(newline);
+                }
+                output.push_str(&footer);
+                output.push_str(newline);
+            }
+        });
+
+        output
+    }
+
+    /// Runs the stages targeting `piece` on it, appending the result to `output`.
+    fn transform(&self, piece: &Piece<'_>, annotations: &Annotations, output: &mut String) {
+        let target = match (piece.kind, piece.action) {
+            (SegmentKind::Prose, _) => Target::Prose,
+            (_, Some(Action::Unwrap | Action::Keep | Action::FirstLine(_))) => Target::Code,
+            _ => {
+                output.push_str(&piece.output);
+                return;
+            }
+        };
+
+        let mut stages = self
+            .stages
+            .iter()
+            .filter(|stage| stage.target == target || stage.target == Target::All);
+        let Some(first) = stages.next() else {
+            output.push_str(&piece.output);
+            return;
+        };
+        let transformed = stages.fold(
+            (first.transform)(&piece.output, annotations),
+            |text, stage| (stage.transform)(&text, annotations),
+        );
+        output.push_str(&transformed);
+    }
+}
+
+impl fmt::Debug for Pipeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let stages: Vec<_> = self.stages.iter().map(|stage| stage.target).collect();
+        f.debug_struct("Pipeline")
+            .field("options", &self.options)
+            .field("stages", &stages)
+            .field("annotators", &self.annotators.len())
+            .field("decorated", &self.decorator.is_some())
+            .finish()
+    }
+}
+
+/// Removes trailing whitespace from every line of `text` and normalizes line endings to `\n`.
+fn normalize_whitespace(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    for line in text.split_inclusive('\n') {
+        let (line, newline) = match line.strip_suffix('\n') {
+            Some(line) => (line, "\n"),
+            None => (line, ""),
+        };
+        output.push_str(line.trim_end_matches([' ', '\t', '\r']));
+        output.push_str(newline);
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strip_codeblocks_with;
+
+    #[test]
+    fn test_empty_pipeline_strips() {
+        let options = StripOptions::new().languages(["rust"]);
+        let input = "a\n```rust\nfn a() {}\n```\n```sh\nls\n```";
+        let pipeline = Pipeline::new().options(options.clone());
+        assert_eq!(pipeline.run(input), strip_codeblocks_with(input, &options));
+    }
+
+    #[test]
+    fn test_stages_run_in_order() {
+        let pipeline = Pipeline::new()
+            .map(|text| text.replace('a', "b"))
+            .map(|text| text.replace('b', "c"));
+        assert_eq!(pipeline.run("a\n```\nab\n```"), "c\ncc\n");
+
+        let pipeline = Pipeline::new()
+            .map(|text| text.replace('b', "c"))
+            .map(|text| text.replace('a', "b"));
+        assert_eq!(pipeline.run("a\n```\nab\n```"), "b\nbc\n");
+    }
+
+    #[test]
+    fn test_stage_targets() {
+        let pipeline = Pipeline::new()
+            .options(StripOptions::new().languages(["sh", "py"]))
+            .map_code(str::to_uppercase)
+            .map_prose(|prose| prose.replace("x", "y"));
+        let input = "x\n```sh\nls x\n```\n```toml\nx = 1\n```";
+        assert_eq!(pipeline.run(input), "y\nLS X\n\n```TOML\nX = 1\n```");
+
+        let placeholder = Pipeline::new()
+            .options(StripOptions::new().action(Action::Placeholder("[code]".into())))
+            .map(str::to_uppercase);
+        assert_eq!(placeholder.run("a ```\nb\n```"), "A [code]");
+    }
+
+    #[test]
+    fn test_normalize_whitespace() {
+        assert_eq!(normalize_whitespace("a  \r\nb\t\n\nc  "), "a\nb\n\nc");
+    }
+
+    #[test]
+    fn test_decorate_unwrapped_blocks() {
+        let pipeline = Pipeline::new()
+            .options(StripOptions::new().languages(["rust", "sh"]))
+            .map_code(str::to_uppercase)
+            .decorate(|block, number| {
+                (
+                    format!("<{}:{number}>", block.language.unwrap_or("")),
+                    "</>".into(),
+                )
+            });
+        let input = "a\n```toml\nx\n```\n```sh\nls\n```\n```rust\nfn a() {}```";
+        assert_eq!(
+            pipeline.run(input),
+            "a\n```TOML\nX\n```\n<sh:2>\nLS\n</>\n\n<rust:3>\nFN A() {}\n</>\n"
+        );
+    }
+
+    #[test]
+    fn test_annotations_flow_to_later_stages_and_report() {
+        struct Number(usize);
+
+        let pipeline = Pipeline::new()
+            .options(StripOptions::new().merge_adjacent(true))
+            .annotate(|block, _| (block.language == Some("sh")).then_some(Number(block.index)))
+            .annotate(|_, annotations| annotations.get::<Number>().map(|number| number.0 + 10))
+            .map_annotated(|number: &usize, code| format!("{number}: {code}"));
+        let input = "```sh\na\n```\n```sh\nb\n```\n\ntext\n```py\nc\n```";
+        let (output, report) = pipeline.run_with_report(input);
+
+        assert_eq!(output, "10: a\nb\n\n\ntext\nc\n");
+        assert_eq!(report.blocks.len(), 3);
+        assert_eq!(report.blocks[1].annotations.get::<usize>(), Some(&10));
+        assert!(report.blocks[2].annotations.is_empty());
+        assert_eq!(
+            report,
+            crate::strip_codeblocks_with_report(input, &pipeline.options).1
+        );
+        assert_eq!(output, pipeline.run(input));
+    }
+
+    #[test]
+    fn test_decorations_follow_line_endings() {
+        let pipeline = Pipeline::new().decorate(|_, _| ("[".into(), "]".into()));
+        assert_eq!(pipeline.run("```\r\nls\r\n```"), "[\r\nls\r\n]\r\n");
+    }
+}
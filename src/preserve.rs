@@ -0,0 +1,178 @@
+//! Checking that stripping left the prose of a document untouched.
+
+use std::error::Error;
+use std::fmt;
+use std::ops::Range;
+
+use crate::scan::codeblocks;
+use crate::strip::closing_line_break;
+
+/// A run of prose missing from, or altered in, a stripped document.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct ProseMismatch {
+    /// Byte range of the run in the original document.
+    pub span: Range<usize>,
+}
+
+impl fmt::Display for ProseMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "prose at bytes {}..{} was not preserved",
+            self.span.start, self.span.end
+        )
+    }
+}
+
+impl Error for ProseMismatch {}
+
+/// Checks that every byte of prose in `original` appears verbatim, in order, in `stripped`.
+///
+/// Prose is everything outside fenced code blocks, except the line break
+/// ending a closing fence line, which belongs to the fence. The runs of prose
+/// between blocks must be found in `stripped` in document order, the first one
+/// at its very start and the last one at its very end; whatever the blocks
+/// were replaced with in between is not checked.
+///
+/// Blocks are found with the default [`Engine::Regex`](crate::experimental::Engine::Regex).
+/// Text stripped with [`StripOptions::preserve_whitespace`](crate::StripOptions::preserve_whitespace)
+/// always passes.
+///
+/// # Arguments
+///
+/// * `original` - The markdown text before stripping
+/// * `stripped` - The same text after stripping
+///
+/// # Returns
+///
+/// `Ok(())` if the prose was preserved, or the first run of prose that wasn't.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::verify_prose_preserved;
+///
+/// let original = "Keep this\n```\ncode\n```\nand this";
+///
+/// assert!(verify_prose_preserved(original, "Keep this\ncode\nand this").is_ok());
+/// assert!(verify_prose_preserved(original, "Keep this\n[code]\nand this").is_ok());
+///
+/// let mismatch = verify_prose_preserved(original, "Keep this\ncode\nand that").unwrap_err();
+/// assert_eq!(mismatch.span, 23..31);
+/// ```
+pub fn verify_prose_preserved(original: &str, stripped: &str) -> Result<(), ProseMismatch> {
+    let mut runs = Vec::new();
+    let mut last = 0;
+    for block in codeblocks(original) {
+        runs.push(last..block.span.start);
+        last = block.span.end + closing_line_break(original, &block);
+    }
+    runs.push(last..original.len());
+
+    let (first, rest) = runs.split_first().expect("there is always a last run");
+    let Some((tail, middle)) = rest.split_last() else {
+        // No blocks at all: the text must be unchanged
+        if original != stripped {
+            return Err(ProseMismatch {
+                span: first.clone(),
+            });
+        }
+        return Ok(());
+    };
+
+    if !stripped.starts_with(&original[first.clone()]) {
+        return Err(ProseMismatch {
+            span: first.clone(),
+        });
+    }
+    let mut cursor = first.len();
+
+    for run in middle.iter().filter(|run| !run.is_empty()) {
+        match stripped[cursor..].find(&original[run.clone()]) {
+            Some(offset) => cursor += offset + run.len(),
+            None => return Err(ProseMismatch { span: run.clone() }),
+        }
+    }
+
+    let tail_start = stripped.len().checked_sub(tail.len());
+    if tail_start.is_none_or(|start| start < cursor) || !stripped.ends_with(&original[tail.clone()])
+    {
+        return Err(ProseMismatch { span: tail.clone() });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Action, StripOptions, strip_codeblocks_with};
+
+    const INPUT: &str =
+        "# Notes\r\n\r\n```sh\r\nls\r\n```\r\nText ```\ninline``` after\n```unclosed";
+
+    #[test]
+    fn test_preserving_mode_drops_fence_lines_only() {
+        let options = StripOptions::new().preserve_whitespace(true);
+        assert_eq!(
+            strip_codeblocks_with(INPUT, &options),
+            "# Notes\r\n\r\nls\r\nText inline after\n```unclosed"
+        );
+
+        let removed = strip_codeblocks_with("a\n```\nb\n```\nc", &options.action(Action::Remove));
+        assert_eq!(removed, "a\nc");
+    }
+
+    #[test]
+    fn test_preserving_mode_keeps_placeholder_lines() {
+        let options = StripOptions::new()
+            .preserve_whitespace(true)
+            .action(Action::Placeholder("[code]".into()));
+        assert_eq!(
+            strip_codeblocks_with("a\n```\nb\n```\nc", &options),
+            "a\n[code]\nc"
+        );
+    }
+
+    #[test]
+    fn test_preserving_mode_ignores_content_options() {
+        let options = StripOptions::new()
+            .preserve_whitespace(true)
+            .strip_prompts(true);
+        assert_eq!(
+            strip_codeblocks_with("```console\n$ ls\n```\n", &options),
+            "$ ls\n"
+        );
+    }
+
+    #[test]
+    fn test_stripped_output_passes() {
+        for options in [
+            StripOptions::new(),
+            StripOptions::new().preserve_whitespace(true),
+            StripOptions::new().action(Action::Remove),
+            StripOptions::new().languages(["rust"]),
+        ] {
+            let output = strip_codeblocks_with(INPUT, &options);
+            assert_eq!(verify_prose_preserved(INPUT, &output), Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_altered_prose_fails() {
+        let options = StripOptions::new().preserve_whitespace(true);
+        let output = strip_codeblocks_with(INPUT, &options);
+
+        let merged = output.replace("\r\n\r\n", "\r\n");
+        assert_eq!(
+            verify_prose_preserved(INPUT, &merged).unwrap_err().span,
+            0..11
+        );
+
+        let edited = output.replace("after", "later");
+        assert!(verify_prose_preserved(INPUT, &edited).is_err());
+
+        assert!(verify_prose_preserved("no blocks", "no block").is_err());
+        assert!(verify_prose_preserved("no blocks", "no blocks").is_ok());
+    }
+}
@@ -0,0 +1,105 @@
+//! Named combinations of options for the most common uses.
+
+use crate::experimental::Engine;
+use crate::{Action, Ansi, Dedent, StripOptions, TrailingNewline};
+
+/// The placeholder replacing blocks with [`Preset::RedactAll`].
+const REDACTED: &str = "[code redacted]";
+
+/// A named combination of options, for [`StripOptions::preset`].
+///
+/// Presets are a starting point: setters chained on the preset options
+/// override its choices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Preset {
+    /// For the answers of language models: fences are found leniently, even
+    /// in the middle of a line, every block is unwrapped, ANSI escape
+    /// sequences are removed, the indentation of fences nested in lists and
+    /// blockquotes is removed from their content and the output ends with a
+    /// line break exactly when the answer does.
+    LlmOutput,
+    /// For flattening documentation: fences are found following CommonMark,
+    /// with tilde fences and longer fences, every block is unwrapped with the
+    /// indentation of its fences removed, `<!-- strip-codeblocks: off -->`
+    /// regions are kept as they are and the output ends with a line break
+    /// exactly when the document does.
+    DocsFlatten,
+    /// For removing code from text shared further: every block, HTML
+    /// `<pre>` elements and math included, is replaced with
+    /// `[code redacted]`, and fences are found through ANSI escape sequences.
+    RedactAll,
+}
+
+impl StripOptions {
+    /// Creates the options of `preset`.
+    ///
+    /// # Arguments
+    ///
+    /// * `preset` - The combination of options
+    ///
+    /// # Returns
+    ///
+    /// The options, which further setters can change.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strip_codeblocks::{Preset, StripOptions, strip_codeblocks_with};
+    ///
+    /// let options = StripOptions::preset(Preset::RedactAll).languages(["sql"]);
+    ///
+    /// let text = "```sql\nDROP TABLE users;\n```\n```sh\nls\n```";
+    /// assert_eq!(strip_codeblocks_with(text, &options), "[code redacted]\n```sh\nls\n```");
+    ///
+    /// let options = StripOptions::preset(Preset::LlmOutput);
+    /// assert_eq!(strip_codeblocks_with("Run ```sh\nls\n```", &options), "Run ls");
+    /// ```
+    pub fn preset(preset: Preset) -> Self {
+        match preset {
+            Preset::LlmOutput => Self::new()
+                .engine(Engine::Regex)
+                .action(Action::Unwrap)
+                .ansi(Ansi::Strip)
+                .dedent(Dedent::new())
+                .trailing_newline(TrailingNewline::MatchInput),
+            Preset::DocsFlatten => Self::new()
+                .engine(Engine::Parser)
+                .action(Action::Unwrap)
+                .dedent(Dedent::new())
+                .region_markers(true)
+                .trailing_newline(TrailingNewline::MatchInput),
+            Preset::RedactAll => Self::new()
+                .action(Action::Placeholder(REDACTED.into()))
+                .math(Action::Placeholder(REDACTED.into()))
+                .html(true)
+                .ansi(Ansi::Transparent),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strip_codeblocks_with;
+
+    #[test]
+    fn test_docs_flatten_follows_commonmark() {
+        let options = StripOptions::preset(Preset::DocsFlatten);
+        let text = "~~~~toml\n[a]\n```\n~~~~\n<!-- strip-codeblocks: off -->\n```sh\nls\n```\n";
+        assert_eq!(
+            strip_codeblocks_with(text, &options),
+            "[a]\n```\n\n<!-- strip-codeblocks: off -->\n```sh\nls\n```\n"
+        );
+    }
+
+    #[test]
+    fn test_redact_all() {
+        let options = StripOptions::preset(Preset::RedactAll);
+        let text = "$$\nx^2\n$$\n<pre>secret</pre>";
+        assert_eq!(
+            strip_codeblocks_with(text, &options),
+            "[code redacted]\n[code redacted]"
+        );
+    }
+}
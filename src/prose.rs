@@ -0,0 +1,108 @@
+//! Stripping the prose around code blocks instead of the blocks.
+
+use crate::strip::for_each_piece;
+use crate::{SegmentKind, StripOptions};
+
+/// Strips the prose from markdown text, keeping only the content of its
+/// fenced code blocks.
+///
+/// This is the inverse of [`strip_codeblocks`](crate::strip_codeblocks), for
+/// pipelines that only care about the code of a document, and the same as
+/// [`strip_prose_with`] with the default options.
+///
+/// # Arguments
+///
+/// * `text` - The markdown text to keep the code blocks of
+///
+/// # Returns
+///
+/// The contents of the code blocks, one after the other.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::strip_prose;
+///
+/// let transcript = "Sure! First:\n```sh\ncd app\n```\nThen `make`:\n```sh\nmake\n```\nDone.";
+///
+/// assert_eq!(strip_prose(transcript), "cd app\nmake\n");
+/// ```
+pub fn strip_prose(text: &str) -> String {
+    strip_prose_with(text, &StripOptions::new())
+}
+
+/// Strips the prose from markdown text, keeping its fenced code blocks as
+/// `options` transform them.
+///
+/// Each block is output as [`strip_codeblocks_with`](crate::strip_codeblocks_with)
+/// would output it, so the [action](StripOptions::action) decides whether a
+/// block stays fenced ([`Action::Keep`](crate::Action::Keep)) or is unwrapped
+/// (the default), and blocks the [languages](StripOptions::languages) filter
+/// doesn't select stay fenced. Everything between blocks is dropped, and a
+/// line break, ending lines like the document does, separates blocks whose
+/// output doesn't end with one.
+///
+/// # Arguments
+///
+/// * `text` - The markdown text to keep the code blocks of
+/// * `options` - How the blocks are output
+///
+/// # Returns
+///
+/// The output of the code blocks, one after the other.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::{Action, StripOptions, strip_prose_with};
+///
+/// let options = StripOptions::new().action(Action::Keep);
+/// let transcript = "Sure:\n```sh\nls\n```\nor `dir` on Windows.";
+///
+/// assert_eq!(strip_prose_with(transcript, &options), "```sh\nls\n```\n");
+/// ```
+pub fn strip_prose_with(text: &str, options: &StripOptions) -> String {
+    let newline = options.newline.resolve(text);
+    let mut output = String::new();
+
+    for_each_piece(text, options, |piece| {
+        if piece.kind != SegmentKind::Code || piece.output.is_empty() {
+            return;
+        }
+        output.push_str(&piece.output);
+        if !output.ends_with('\n') {
+            output.push_str(newline);
+        }
+    });
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Action;
+
+    #[test]
+    fn test_blocks_are_separated_by_line_breaks() {
+        let input = "a\r\n```\r\nx```\r\n```\r\ny\r\n```\r\nc";
+        assert_eq!(strip_prose(input), "x\r\ny\r\n");
+
+        let options = StripOptions::new().action(Action::Keep);
+        assert_eq!(
+            strip_prose_with(input, &options),
+            "```\r\nx```\r\n```\r\ny\r\n```\r\n"
+        );
+    }
+
+    #[test]
+    fn test_languages_and_removed_blocks() {
+        let input = "```sh\nls\n```\ntext\n```py\npass\n```\n";
+
+        let options = StripOptions::new().languages(["sh"]);
+        assert_eq!(strip_prose_with(input, &options), "ls\n```py\npass\n```\n");
+
+        let options = options.action(Action::Remove);
+        assert_eq!(strip_prose_with(input, &options), "```py\npass\n```\n");
+        assert_eq!(strip_prose(""), "");
+    }
+}
@@ -0,0 +1,185 @@
+//! Stripping code blocks from a [`pulldown_cmark`] event stream.
+
+use pulldown_cmark::{CodeBlockKind, Event, Tag, TagEnd};
+
+use crate::block::info_words;
+use crate::content::FirstLineFilter;
+use crate::{Action, StripOptions};
+
+/// An iterator adapter stripping fenced code blocks from a `pulldown-cmark` event stream.
+///
+/// Each fenced code block is handled according to the action the options
+/// select for its language: unwrapped blocks lose their start and end events
+/// but keep their text, removed blocks disappear entirely, kept blocks pass
+/// through unchanged, placeholders replace the block with a single text
+/// event, and blocks cut to their first line keep the text of that line
+/// followed by a marker text event. Indented code blocks are left alone.
+///
+/// Only the language filters and action options are applied; options
+/// transforming the content of unwrapped blocks (such as
+/// [`StripOptions::strip_prompts`]) are not. Neither are
+/// [`select`](StripOptions::select), [`scope`](StripOptions::scope),
+/// [`html`](StripOptions::html),
+/// [`require_blank_line_before_fence`](StripOptions::require_blank_line_before_fence),
+/// since blocks are found by the parser, in its dialect,
+/// [`render_safety`](StripOptions::render_safety),
+/// [`strip_comments`](StripOptions::strip_comments),
+/// [`trailing_newline`](StripOptions::trailing_newline),
+/// [`merge_adjacent`](StripOptions::merge_adjacent),
+/// [`hash_placeholders`](StripOptions::hash_placeholders), since placeholders
+/// are emitted before the content is known,
+/// [`region_markers`](StripOptions::region_markers),
+/// [`math`](StripOptions::math) for display math in prose,
+/// [`shrink`](StripOptions::shrink) and [`dedent`](StripOptions::dedent).
+///
+/// Requires the `pulldown` feature.
+///
+/// # Examples
+///
+/// ```
+/// use pulldown_cmark::{Event, Parser};
+/// use strip_codeblocks::StripCodeBlocks;
+///
+/// let markdown = "Intro\n\n```rust\nfn main() {}\n```\n";
+///
+/// let events: Vec<_> = StripCodeBlocks::new(Parser::new(markdown)).collect();
+/// assert!(events.contains(&Event::Text("fn main() {}\n".into())));
+/// assert!(!events.iter().any(|event| matches!(event, Event::Start(pulldown_cmark::Tag::CodeBlock(_)))));
+/// ```
+#[derive(Debug, Clone)]
+pub struct StripCodeBlocks<I> {
+    events: I,
+    options: StripOptions,
+    /// The action of the code block being traversed, if any.
+    block: Option<Action>,
+    /// The first line of the block being traversed, for [`Action::FirstLine`].
+    first_line: FirstLineFilter,
+}
+
+impl<I> StripCodeBlocks<I> {
+    /// Wraps `events`, unwrapping every fenced code block.
+    pub fn new(events: I) -> Self {
+        Self::with_options(events, StripOptions::new())
+    }
+
+    /// Wraps `events`, handling fenced code blocks according to `options`.
+    pub fn with_options(events: I, options: StripOptions) -> Self {
+        Self {
+            events,
+            options,
+            block: None,
+            first_line: FirstLineFilter::default(),
+        }
+    }
+}
+
+impl<'a, I: Iterator<Item = Event<'a>>> Iterator for StripCodeBlocks<I> {
+    type Item = Event<'a>;
+
+    fn next(&mut self) -> Option<Event<'a>> {
+        loop {
+            let event = self.events.next()?;
+
+            let Some(action) = &self.block else {
+                let Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info))) = &event else {
+                    return Some(event);
+                };
+
+                let language = info_words(info.trim()).next();
+                let action = self.options.action_for_language(language).clone();
+                let replacement = match &action {
+                    Action::Keep => Some(event),
+                    Action::Placeholder(placeholder) => {
+                        Some(Event::Text(placeholder.clone().into()))
+                    }
+                    _ => None,
+                };
+                self.block = Some(action);
+                self.first_line = FirstLineFilter::default();
+
+                match replacement {
+                    Some(replacement) => return Some(replacement),
+                    None => continue,
+                }
+            };
+
+            let passes = matches!(action, Action::Keep | Action::Unwrap);
+            if matches!(event, Event::End(TagEnd::CodeBlock)) {
+                let block = self.block.take();
+                match block {
+                    Some(Action::Keep) => return Some(event),
+                    Some(Action::FirstLine(marker))
+                        if self.first_line.truncated() && !marker.is_empty() =>
+                    {
+                        return Some(Event::Text(format!("{marker}\n").into()));
+                    }
+                    _ => {}
+                }
+            } else if let (Action::FirstLine(_), Event::Text(text)) = (action, &event) {
+                let kept = self.first_line.feed(text);
+                if !kept.is_empty() {
+                    return Some(Event::Text(kept.to_string().into()));
+                }
+            } else if passes {
+                return Some(event);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pulldown_cmark::Parser;
+
+    fn strip(markdown: &str, options: StripOptions) -> Vec<Event<'_>> {
+        StripCodeBlocks::with_options(Parser::new(markdown), options).collect()
+    }
+
+    #[test]
+    fn test_unwrap_keeps_text() {
+        let events = strip("```sh\nls\n```\n", StripOptions::new());
+        assert_eq!(events, [Event::Text("ls\n".into())]);
+    }
+
+    #[test]
+    fn test_remove_and_placeholder() {
+        let markdown = "```sh\nls\n```\n";
+        assert!(strip(markdown, StripOptions::new().action(Action::Remove)).is_empty());
+        assert_eq!(
+            strip(
+                markdown,
+                StripOptions::new().action(Action::Placeholder("[code]".into()))
+            ),
+            [Event::Text("[code]".into())]
+        );
+    }
+
+    #[test]
+    fn test_first_line() {
+        let events = strip(
+            "```sh\n$ make\ncc foo.c\n```\n",
+            StripOptions::new().action(Action::FirstLine("…".into())),
+        );
+        let text: String = events
+            .iter()
+            .map(|event| match event {
+                Event::Text(text) => text.as_ref(),
+                _ => "",
+            })
+            .collect();
+        assert_eq!(text, "$ make\n…\n");
+    }
+
+    #[test]
+    fn test_unselected_and_indented_blocks_pass_through() {
+        let markdown = "```toml\n[a]\n```\n\n    indented\n";
+        let options = StripOptions::new()
+            .languages(["rust"])
+            .action(Action::Remove);
+        assert_eq!(
+            strip(markdown, options),
+            Parser::new(markdown).collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,124 @@
+//! Regions of a document where stripping is turned off by markers.
+
+use std::ops::Range;
+
+use crate::scan::codeblocks_for;
+use crate::{Action, CodeBlock, StripOptions};
+
+/// The parts of a document between `<!-- strip-codeblocks: off -->` and
+/// `<!-- strip-codeblocks: on -->` markers.
+pub(crate) struct Regions {
+    off: Vec<Range<usize>>,
+}
+
+/// Returns whether `line` is a marker turning stripping on, or off.
+fn marker(line: &str) -> Option<bool> {
+    let inner = line.trim().strip_prefix("<!--")?.strip_suffix("-->")?;
+    let state = inner
+        .trim()
+        .strip_prefix("strip-codeblocks")?
+        .trim_start()
+        .strip_prefix(':')?
+        .trim();
+    match state {
+        "on" => Some(true),
+        "off" => Some(false),
+        _ => None,
+    }
+}
+
+impl Regions {
+    /// Finds the regions of `text` where stripping is off, if `options` ask
+    /// for [region markers](StripOptions::region_markers).
+    ///
+    /// Markers are only recognized on lines of their own outside code blocks,
+    /// so that a block showing one doesn't turn stripping off. A region that
+    /// isn't turned back on lasts until the end of the document.
+    pub(crate) fn new(text: &str, options: &StripOptions) -> Self {
+        let mut off = Vec::new();
+        if !options.region_markers || !text.contains("strip-codeblocks") {
+            return Regions { off };
+        }
+
+        let mut blocks = codeblocks_for(text, options).peekable();
+        let mut start = None;
+        let mut offset = 0;
+        for line in text.split_inclusive('\n') {
+            let line_start = offset;
+            offset += line.len();
+            while blocks
+                .next_if(|block| block.span.end <= line_start)
+                .is_some()
+            {}
+            if blocks.peek().is_some_and(|block| block.span.start < offset) {
+                continue;
+            }
+
+            match (marker(line), start) {
+                (Some(false), None) => start = Some(line_start),
+                (Some(true), Some(off_start)) => {
+                    off.push(off_start..line_start);
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        if let Some(off_start) = start {
+            off.push(off_start..text.len());
+        }
+        Regions { off }
+    }
+
+    /// Returns the action to apply to `block`: the one `options` give it,
+    /// or [`Action::Keep`] when it starts in a region where stripping is off.
+    pub(crate) fn action_for<'o>(
+        &self,
+        block: &CodeBlock<'_>,
+        options: &'o StripOptions,
+    ) -> &'o Action {
+        let start = block.span.start;
+        let index = self.off.partition_point(|region| region.end <= start);
+        match self.off.get(index) {
+            Some(region) if region.start <= start => &Action::Keep,
+            _ => options.action_for(block),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strip_codeblocks_with;
+
+    #[test]
+    fn test_markers() {
+        assert_eq!(marker("<!-- strip-codeblocks: off -->\n"), Some(false));
+        assert_eq!(marker("  <!--strip-codeblocks:on-->"), Some(true));
+        assert_eq!(marker("<!-- strip-codeblocks: maybe -->"), None);
+        assert_eq!(marker("text <!-- strip-codeblocks: off -->"), None);
+    }
+
+    #[test]
+    fn test_regions_toggle_stripping() {
+        let options = StripOptions::new().region_markers(true);
+        let input = "```\na\n```\n<!-- strip-codeblocks: off -->\n```\nb\n```\n<!-- strip-codeblocks: on -->\n```\nc\n```\n";
+        assert_eq!(
+            strip_codeblocks_with(input, &options),
+            "a\n\n<!-- strip-codeblocks: off -->\n```\nb\n```\n<!-- strip-codeblocks: on -->\nc\n\n"
+        );
+        assert_eq!(
+            strip_codeblocks_with(input, &StripOptions::new()),
+            "a\n\n<!-- strip-codeblocks: off -->\nb\n\n<!-- strip-codeblocks: on -->\nc\n\n"
+        );
+    }
+
+    #[test]
+    fn test_markers_inside_blocks_are_ignored() {
+        let options = StripOptions::new().region_markers(true);
+        let input = "```html\n<!-- strip-codeblocks: off -->\n```\n```\nb\n```\n<!-- strip-codeblocks: off -->\n```\nc\n```";
+        assert_eq!(
+            strip_codeblocks_with(input, &options),
+            "<!-- strip-codeblocks: off -->\n\nb\n\n<!-- strip-codeblocks: off -->\n```\nc\n```"
+        );
+    }
+}
@@ -0,0 +1,116 @@
+//! Keeping unwrapped code from rendering as markdown structure.
+
+use std::borrow::Cow;
+
+/// How lines of unwrapped code that would render as markdown structure are
+/// handled.
+///
+/// Once unwrapped, a `# comment` renders as a heading, `> prompt` as a
+/// quote, `- item` or `1. item` as a list, and a line of `---` turns the line
+/// above it into a heading. Escaping or indenting such lines keeps stripped
+/// text render-safe in markdown viewers. Prose is never touched.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+#[non_exhaustive]
+pub enum RenderSafety {
+    /// Leave lines as they are.
+    #[default]
+    Off,
+    /// Escape the character opening the structure with a backslash, as in
+    /// `\# comment` or `1\. item`, which renders as the original line.
+    Escape,
+    /// Indent such lines by four spaces, which renders them as code after a
+    /// blank line, and as part of the paragraph otherwise.
+    Indent,
+}
+
+/// Returns the offset at which a backslash escapes the markdown structure
+/// `line` opens, if it opens any.
+fn structure_marker(line: &str) -> Option<usize> {
+    let rest = line.trim_start_matches(' ');
+    let indent = line.len() - rest.len();
+    // Four spaces or a tab already make an indented code block
+    if indent >= 4 || rest.starts_with('\t') {
+        return None;
+    }
+
+    let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if (1..=9).contains(&digits) {
+        return rest[digits..]
+            .starts_with(['.', ')'])
+            .then_some(indent + digits);
+    }
+    rest.starts_with(['#', '>', '-', '+', '*', '=', '_', '|', '`', '~', '<'])
+        .then_some(indent)
+}
+
+/// Returns `content` with its lines opening markdown structure handled
+/// according to `policy`.
+pub(crate) fn render_safe(content: &str, policy: RenderSafety) -> Cow<'_, str> {
+    if policy == RenderSafety::Off
+        || !content
+            .split_inclusive('\n')
+            .any(|line| structure_marker(line).is_some())
+    {
+        return Cow::Borrowed(content);
+    }
+
+    let mut output = String::with_capacity(content.len() + 16);
+    for line in content.split_inclusive('\n') {
+        match (structure_marker(line), policy) {
+            (Some(marker), RenderSafety::Escape) => {
+                output.push_str(&line[..marker]);
+                output.push('\\');
+                output.push_str(&line[marker..]);
+            }
+            (Some(_), _) => {
+                output.push_str("    ");
+                output.push_str(line);
+            }
+            (None, _) => output.push_str(line),
+        }
+    }
+    Cow::Owned(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{StripOptions, strip_codeblocks_with};
+
+    #[test]
+    fn test_structure_markers() {
+        assert_eq!(structure_marker("# comment"), Some(0));
+        assert_eq!(structure_marker("  > quote"), Some(2));
+        assert_eq!(structure_marker("12. item"), Some(2));
+        assert_eq!(structure_marker("3) item"), Some(1));
+        assert_eq!(structure_marker("2024 was"), None);
+        assert_eq!(structure_marker("    - indented"), None);
+        assert_eq!(structure_marker("\t# tabbed"), None);
+        assert_eq!(structure_marker("let x = 1;"), None);
+    }
+
+    #[test]
+    fn test_escape_and_indent() {
+        let input = "```sh\n# install\nmake\n---\r\n```";
+
+        let options = StripOptions::new().render_safety(RenderSafety::Escape);
+        assert_eq!(
+            strip_codeblocks_with(input, &options),
+            "\\# install\nmake\n\\---\r\n"
+        );
+        let options = StripOptions::new().render_safety(RenderSafety::Indent);
+        assert_eq!(
+            strip_codeblocks_with(input, &options),
+            "    # install\nmake\n    ---\r\n"
+        );
+        assert!(matches!(
+            render_safe("make\n", RenderSafety::Escape),
+            Cow::Borrowed(_)
+        ));
+    }
+}
@@ -0,0 +1,192 @@
+//! Declarative per-language actions with [`ReplacementMap`].
+
+use std::collections::BTreeMap;
+
+use crate::strip::strip;
+use crate::{Action, StripOptions};
+
+/// The per-language actions of a [`ReplacementMap`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct Replacements {
+    by_language: BTreeMap<String, Action>,
+    fallback: Action,
+}
+
+impl Default for Replacements {
+    fn default() -> Self {
+        Self {
+            by_language: BTreeMap::new(),
+            fallback: Action::Keep,
+        }
+    }
+}
+
+impl Replacements {
+    /// Creates the table applying `action` to blocks in `languages`, and keeping the others.
+    pub(crate) fn for_languages(languages: &[String], action: &Action) -> Self {
+        Self {
+            by_language: languages
+                .iter()
+                .map(|language| (language.clone(), action.clone()))
+                .collect(),
+            fallback: Action::Keep,
+        }
+    }
+
+    /// Returns every action of the table, for in-place adjustment.
+    pub(crate) fn actions_mut(&mut self) -> impl Iterator<Item = &mut Action> {
+        self.by_language
+            .values_mut()
+            .chain(std::iter::once(&mut self.fallback))
+    }
+
+    /// Returns the action for blocks in `language`.
+    pub(crate) fn action_for(&self, language: Option<&str>) -> &Action {
+        language
+            .and_then(|language| self.by_language.get(language))
+            .unwrap_or(&self.fallback)
+    }
+}
+
+/// A table of what happens to code blocks, by language, applied in a single pass.
+///
+/// Each language gets its own [`Action`], and every other block, untagged
+/// blocks included, gets the [fallback](Self::fallback) action, which is to
+/// keep them unless set otherwise.
+///
+/// The table takes the place of [`StripOptions::action`] and
+/// [`StripOptions::languages`]; the other options are set with
+/// [`options`](Self::options). A map converts into [`StripOptions`] for use
+/// with the rest of the API.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::{Action, ReplacementMap};
+///
+/// let map = ReplacementMap::new()
+///     .language("json", Action::Unwrap)
+///     .language("mermaid", Action::Keep)
+///     .language("secret", Action::Remove)
+///     .fallback(Action::Placeholder("…".into()));
+///
+/// let input = "```json\n{}\n```\n```secret\nhunter2\n```\n```py\npass\n```";
+/// assert_eq!(map.apply(input), "{}\n\n\n…");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ReplacementMap {
+    /// Options whose replacements are always set.
+    options: StripOptions,
+}
+
+impl ReplacementMap {
+    /// Creates an empty table, keeping every block.
+    pub fn new() -> Self {
+        Self {
+            options: StripOptions {
+                replacements: Some(Replacements::default()),
+                ..StripOptions::default()
+            },
+        }
+    }
+
+    /// Applies `action` to blocks in `language`, replacing any previous action for it.
+    pub fn language(mut self, language: impl Into<String>, action: Action) -> Self {
+        self.replacements_mut()
+            .by_language
+            .insert(language.into(), action);
+        self
+    }
+
+    /// Applies `action` to blocks in languages without an action of their own,
+    /// and to blocks without a language. Defaults to [`Action::Keep`].
+    pub fn fallback(mut self, action: Action) -> Self {
+        self.replacements_mut().fallback = action;
+        self
+    }
+
+    /// Uses `options` for everything but the choice of actions.
+    pub fn options(self, options: StripOptions) -> Self {
+        let replacements = self.options.replacements;
+        Self {
+            options: StripOptions {
+                replacements,
+                ..options
+            },
+        }
+    }
+
+    /// Strips the code blocks of `text` according to the table.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The markdown text containing code blocks to strip
+    ///
+    /// # Returns
+    ///
+    /// A new string with each code block handled as its language dictates.
+    pub fn apply(&self, text: &str) -> String {
+        strip(text, &self.options, None)
+    }
+
+    fn replacements_mut(&mut self) -> &mut Replacements {
+        self.options
+            .replacements
+            .as_mut()
+            .expect("a map always has replacements")
+    }
+}
+
+impl Default for ReplacementMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<ReplacementMap> for StripOptions {
+    fn from(map: ReplacementMap) -> Self {
+        map.options
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{strip_codeblocks_with, strip_codeblocks_with_report};
+
+    #[test]
+    fn test_empty_map_keeps_everything() {
+        let input = "```rust\nfn a() {}\n```\n```\nplain\n```";
+        assert_eq!(ReplacementMap::new().apply(input), input);
+    }
+
+    #[test]
+    fn test_map_overrides_action_and_filter() {
+        let base = StripOptions::new()
+            .action(Action::Remove)
+            .languages(["rust"])
+            .strip_prompts(true);
+        let map = ReplacementMap::new()
+            .language("sh", Action::Unwrap)
+            .options(base);
+        let input = "```rust\nfn a() {}\n```\n```sh\n$ ls\n```";
+
+        assert_eq!(map.apply(input), "```rust\nfn a() {}\n```\nls\n");
+        assert_eq!(
+            strip_codeblocks_with(input, &map.clone().into()),
+            map.apply(input)
+        );
+    }
+
+    #[test]
+    fn test_map_in_report() {
+        let options: StripOptions = ReplacementMap::new()
+            .fallback(Action::Remove)
+            .language("toml", Action::Keep)
+            .into();
+        let (_, report) = strip_codeblocks_with_report("```toml\n[a]\n```\n```\nx\n```", &options);
+
+        assert_eq!(report.blocks[0].action, Action::Keep);
+        assert_eq!(report.blocks[1].action, Action::Remove);
+    }
+}
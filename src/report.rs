@@ -0,0 +1,166 @@
+//! The [`Report`] describing what stripping did to a document.
+
+use std::ops::{Range, RangeInclusive};
+use std::{fmt, iter};
+
+use crate::{Action, Annotations};
+
+/// A summary of the code blocks found while stripping a document and what was done to them.
+///
+/// Reports own their data, so they can outlive the document they describe.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct Report {
+    /// One entry per code block found, in document order.
+    pub blocks: Vec<BlockReport>,
+}
+
+/// What happened to a single code block.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct BlockReport {
+    /// The language of the block, if it had one.
+    pub language: Option<String>,
+    /// Byte range of the whole block, fences included, in the original document.
+    pub span: Range<usize>,
+    /// The lines of the whole block, fences included, starting at 1.
+    pub lines: RangeInclusive<usize>,
+    /// The action applied to the block.
+    pub action: Action,
+    /// The data a [`Pipeline`](crate::Pipeline) [annotated](crate::Pipeline::annotate)
+    /// the block with, empty when stripping outside a pipeline.
+    pub annotations: Annotations,
+}
+
+impl Report {
+    /// Returns the blocks that were changed, i.e. not [kept](Action::Keep) as is.
+    pub fn changed(&self) -> impl Iterator<Item = &BlockReport> {
+        self.blocks
+            .iter()
+            .filter(|block| block.action != Action::Keep)
+    }
+}
+
+/// Displays the report as a table of its blocks, with their lines, language
+/// and action, followed by a summary line, for logs and verbose output.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::{Action, StripOptions, strip_codeblocks_with_report};
+///
+/// let options = StripOptions::new().deny_languages(["toml"]);
+/// let input = "```rust\nfn a() {}\n```\n```toml\n[a]\n```\n\n```\nls\n```";
+/// let (_, report) = strip_codeblocks_with_report(input, &options);
+///
+/// assert_eq!(
+///     report.to_string(),
+///     "\
+/// block  lines  language  action
+///     1  1-3    rust      unwrapped
+///     2  4-6    toml      kept
+///     3  8-10   -         unwrapped
+/// 3 blocks, 2 changed"
+/// );
+/// ```
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rows: Vec<[String; 4]> = self
+            .blocks
+            .iter()
+            .enumerate()
+            .map(|(index, block)| {
+                let (start, end) = (block.lines.start(), block.lines.end());
+                [
+                    (index + 1).to_string(),
+                    if start == end {
+                        start.to_string()
+                    } else {
+                        format!("{start}-{end}")
+                    },
+                    block.language.clone().unwrap_or_else(|| "-".into()),
+                    describe_action(&block.action),
+                ]
+            })
+            .collect();
+
+        if !rows.is_empty() {
+            let header = ["block", "lines", "language", "action"].map(String::from);
+            let width = |column: usize| {
+                (rows.iter().chain([&header]))
+                    .map(|row| row[column].chars().count())
+                    .max()
+                    .unwrap_or(0)
+            };
+            let widths = [width(0), width(1), width(2)];
+            for [number, lines, language, action] in iter::once(&header).chain(&rows) {
+                writeln!(
+                    f,
+                    "{number:>0$}  {lines:<1$}  {language:<2$}  {action}",
+                    widths[0], widths[1], widths[2]
+                )?;
+            }
+        }
+
+        match self.blocks.len() {
+            0 => f.write_str("no blocks"),
+            1 => write!(f, "1 block, {} changed", self.changed().count()),
+            blocks => write!(f, "{blocks} blocks, {} changed", self.changed().count()),
+        }
+    }
+}
+
+/// Describes what `action` did to a block, e.g. `unwrapped`.
+pub(crate) fn describe_action(action: &Action) -> String {
+    match action {
+        Action::Unwrap => "unwrapped".into(),
+        Action::Keep => "kept".into(),
+        Action::Remove => "removed".into(),
+        Action::Placeholder(text) => format!("replaced with {text:?}"),
+        Action::FirstLine(_) => "cut to its first line".into(),
+    }
+}
+
+/// Counts the lines of blocks found in document order, without counting
+/// those of the text before each again.
+#[derive(Debug, Clone)]
+pub(crate) struct LineCounter<'a> {
+    text: &'a str,
+    /// Where lines were counted up to.
+    offset: usize,
+    /// The line `offset` is on.
+    line: usize,
+}
+
+impl<'a> LineCounter<'a> {
+    pub(crate) fn new(text: &'a str) -> Self {
+        Self {
+            text,
+            offset: 0,
+            line: 1,
+        }
+    }
+
+    /// Returns the line of the byte at `offset`, starting at 1.
+    ///
+    /// Offsets are expected in increasing order; an earlier one makes the
+    /// lines be counted again from the start.
+    pub(crate) fn line(&mut self, offset: usize) -> usize {
+        if offset < self.offset {
+            (self.offset, self.line) = (0, 1);
+        }
+        self.line += self.text[self.offset..offset].matches('\n').count();
+        self.offset = offset;
+        self.line
+    }
+
+    /// Returns the lines of the block at `span`, which starts no earlier than
+    /// the blocks given before.
+    pub(crate) fn lines(&mut self, span: &Range<usize>) -> RangeInclusive<usize> {
+        self.line(span.start);
+        let block = &self.text[span.clone()];
+        // A block running to the end of the text can end with its last line break
+        let block = block.strip_suffix('\n').unwrap_or(block);
+        self.line..=self.line + block.matches('\n').count()
+    }
+}
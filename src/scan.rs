@@ -0,0 +1,365 @@
+//! Locating fenced code blocks in markdown text.
+
+use std::ops::Range;
+
+use crate::block::{info_words, trim_info};
+use crate::experimental::Engine;
+use crate::experimental::parser::ParserBlocks;
+use crate::table::TableRows;
+use crate::{Ansi, Attributes, CodeBlock, StripOptions, ansi, html};
+
+/// Returns the span of the first fenced code block of `text` starting at
+/// `from` or after.
+///
+/// This matches what the regular expression ``(?s)```([^\n`]*)\n(.*?)``` ``
+/// would, the definition of the [regex engine](Engine::Regex):
+/// - Three backticks (```)
+/// - Optional info string (any characters except newline and backtick)
+/// - Newline
+/// - Content (non-greedy, including newlines)
+/// - Three backticks (```)
+///
+/// Searching for the backticks directly is several times faster than running
+/// the expression, which matters for short texts such as chat messages.
+fn find_fenced_block(text: &str, from: usize) -> Option<Range<usize>> {
+    let mut start = from;
+    loop {
+        let open = start + text[start..].find("```")?;
+        let info_start = open + 3;
+        let info_end = info_start + text[info_start..].find(['\n', '`'])?;
+        if text.as_bytes()[info_end] == b'\n' {
+            let content_start = info_end + 1;
+            let close = content_start + text[content_start..].find("```")?;
+            return Some(open..close + 3);
+        }
+        // A backtick in the info string: the fence may start further on
+        start = open + 1;
+    }
+}
+
+/// Returns whether a fence at `offset` in `text`, after any earlier block
+/// ending at `previous_end`, starts its line and follows a blank line, as
+/// strict dialects require.
+///
+/// The start of the document and a previous block count as blank lines, and
+/// so do lines only holding blockquote markers before a quoted fence.
+pub(crate) fn follows_blank_line(text: &str, offset: usize, previous_end: usize) -> bool {
+    let line_start = text[..offset].rfind('\n').map_or(0, |newline| newline + 1);
+    if !text[line_start..offset]
+        .chars()
+        .all(|c| matches!(c, ' ' | '\t' | '>'))
+    {
+        return false;
+    }
+    if text[previous_end.min(line_start)..line_start]
+        .trim()
+        .is_empty()
+    {
+        return true;
+    }
+
+    let before = &text[..line_start - 1];
+    let previous = &before[before.rfind('\n').map_or(0, |newline| newline + 1)..];
+    previous
+        .trim_matches(|c: char| c.is_whitespace() || c == '>')
+        .is_empty()
+}
+
+/// Why [`RegexBlocks`] left fence-like backticks alone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Skip {
+    /// They are in a GFM table row.
+    TableRow,
+    /// They don't follow a blank line, as
+    /// [required](StripOptions::require_blank_line_before_fence).
+    NoBlankLine,
+}
+
+/// Iterator over the fenced code blocks of a document found by one of the engines.
+#[derive(Debug)]
+pub(crate) enum Blocks<'a> {
+    Regex(RegexBlocks<'a>),
+    Parser(ParserBlocks<'a>),
+    /// Blocks found ahead of time, e.g. in a copy of the text without escape sequences.
+    Found(std::vec::IntoIter<CodeBlock<'a>>),
+}
+
+impl<'a> Iterator for Blocks<'a> {
+    type Item = CodeBlock<'a>;
+
+    fn next(&mut self) -> Option<CodeBlock<'a>> {
+        match self {
+            Blocks::Regex(blocks) => blocks.next(),
+            Blocks::Parser(blocks) => blocks.next(),
+            Blocks::Found(blocks) => blocks.next(),
+        }
+    }
+}
+
+/// Iterator over the blocks found by [`find_fenced_block`].
+///
+/// Fences in the rows of GFM tables are prose, like the inline code spans of
+/// the cells they are in, and so are fences not following a blank line if
+/// [required](Self::require_blank_line).
+#[derive(Debug)]
+pub(crate) struct RegexBlocks<'a> {
+    text: &'a str,
+    /// Where the next match is looked for.
+    pos: usize,
+    /// The starts of the matches skipped so far, and why, if they are
+    /// [recorded](Self::record_skips).
+    skipped: Option<Vec<(usize, Skip)>>,
+    /// The index of the next block.
+    index: usize,
+    /// Whether opening fences must follow a blank line.
+    blank_line: bool,
+    /// Where the last block ended.
+    previous_end: usize,
+    /// The table rows found so far.
+    tables: TableRows,
+}
+
+impl<'a> RegexBlocks<'a> {
+    pub(crate) fn new(text: &'a str) -> Self {
+        Self {
+            text,
+            pos: 0,
+            skipped: None,
+            index: 0,
+            blank_line: false,
+            previous_end: 0,
+            tables: TableRows::default(),
+        }
+    }
+
+    /// Only opens blocks at fences [following a blank line](follows_blank_line) if `required`.
+    pub(crate) fn require_blank_line(mut self, required: bool) -> Self {
+        self.blank_line = required;
+        self
+    }
+
+    /// Records the matches skipped, for [`skipped`](Self::skipped). They
+    /// aren't by default, so that finding blocks doesn't allocate.
+    pub(crate) fn record_skips(mut self) -> Self {
+        self.skipped = Some(Vec::new());
+        self
+    }
+
+    /// Returns the starts of the matches skipped so far, and why, if they are
+    /// recorded.
+    pub(crate) fn skipped(&self) -> &[(usize, Skip)] {
+        self.skipped.as_deref().unwrap_or_default()
+    }
+}
+
+impl<'a> Iterator for RegexBlocks<'a> {
+    type Item = CodeBlock<'a>;
+
+    fn next(&mut self) -> Option<CodeBlock<'a>> {
+        loop {
+            let whole = find_fenced_block(self.text, self.pos)?;
+            let start = whole.start;
+            let skip = if self.tables.contains(self.text, start) {
+                Some(Skip::TableRow)
+            } else if self.blank_line && !follows_blank_line(self.text, start, self.previous_end) {
+                Some(Skip::NoBlankLine)
+            } else {
+                None
+            };
+            if let Some(skip) = skip {
+                // Look again right after the fence-like backticks
+                if let Some(skipped) = &mut self.skipped {
+                    skipped.push((start, skip));
+                }
+                self.pos = start + 3;
+                continue;
+            }
+
+            self.pos = whole.end;
+            self.previous_end = whole.end;
+            self.index += 1;
+            return Some(regex_block(self.text, whole, self.index - 1));
+        }
+    }
+}
+
+/// Returns an iterator over the fenced code blocks of `text` found by the
+/// default engine, in document order.
+pub(crate) fn codeblocks(text: &str) -> Blocks<'_> {
+    codeblocks_with(text, Engine::default())
+}
+
+/// Returns an iterator over the fenced code blocks of `text` found by `engine`, in document order.
+pub(crate) fn codeblocks_with(text: &str, engine: Engine) -> Blocks<'_> {
+    blocks_with(text, engine, false)
+}
+
+/// Returns an iterator over the fenced code blocks of `text` found by
+/// `engine`, only opened at fences following a blank line if `blank_line`.
+pub(crate) fn blocks_with(text: &str, engine: Engine, blank_line: bool) -> Blocks<'_> {
+    match engine {
+        Engine::Regex => Blocks::Regex(RegexBlocks::new(text).require_blank_line(blank_line)),
+        Engine::Parser => Blocks::Parser(ParserBlocks::new(text).require_blank_line(blank_line)),
+    }
+}
+
+/// Returns an iterator over the fenced code blocks of `text` found as
+/// `options` ask for, in document order.
+pub(crate) fn codeblocks_for<'a>(text: &'a str, options: &StripOptions) -> Blocks<'a> {
+    let blank_line = options.require_blank_line_before_fence;
+    let blocks = if options.ansi == Ansi::Keep || !ansi::has_escapes(text) {
+        blocks_with(text, options.engine, blank_line)
+    } else {
+        Blocks::Found(ansi::transparent_codeblocks(text, options.engine, blank_line).into_iter())
+    };
+    if options.html {
+        return Blocks::Found(
+            html::with_pre_blocks(text, blocks, options.html_scripts).into_iter(),
+        );
+    }
+    blocks
+}
+
+/// Builds the block of `text` at `span` found by [`find_fenced_block`], the
+/// `index`th of the document.
+///
+/// The groups are found from the match itself rather than by capturing them,
+/// which would allocate for every block: the info string runs from the
+/// opening backticks to the first line break, which it can't contain, and the
+/// content from there to the closing backticks.
+fn regex_block(text: &str, span: Range<usize>, index: usize) -> CodeBlock<'_> {
+    let info_start = span.start + 3;
+    let info_end = info_start + text[info_start..].find('\n').unwrap();
+    let content_span = info_end + 1..span.end - 3;
+    let info_raw = &text[info_start..info_end];
+    let info_raw = info_raw.strip_suffix('\r').unwrap_or(info_raw);
+    let info = trim_info(info_raw);
+
+    CodeBlock {
+        info,
+        info_raw,
+        fence: &text[span.start..info_start],
+        language: info_words(info).next(),
+        content: &text[content_span.clone()],
+        span,
+        content_span,
+        attributes: Attributes::parse(info),
+        index,
+    }
+}
+
+/// Rebuilds `text`, replacing every fenced code block with the output of `replace`.
+pub(crate) fn replace_codeblocks<E>(
+    text: &str,
+    mut replace: impl FnMut(&CodeBlock<'_>, &mut String) -> Result<(), E>,
+) -> Result<String, E> {
+    let mut output = String::with_capacity(text.len());
+    let mut last = 0;
+
+    for block in codeblocks(text) {
+        output.push_str(&text[last..block.span.start]);
+        replace(&block, &mut output)?;
+        last = block.span.end;
+    }
+
+    output.push_str(&text[last..]);
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use regex::Regex;
+
+    use super::*;
+
+    #[test]
+    fn test_find_fenced_block_matches_regex() {
+        let regex = Regex::new(r"(?s)```([^\n`]*)\n(.*?)```").unwrap();
+        let inputs = [
+            "a\n```rust\nfn main() {}\n```\nb",
+            "````\nx\n````",
+            "```a`b\n```\nc\n```",
+            "``` é\n```",
+            "``\n```",
+            "```\n",
+            "```x",
+            "```\n``````\n```",
+            "no fences",
+        ];
+        for input in inputs {
+            for from in (0..=input.len()).filter(|&from| input.is_char_boundary(from)) {
+                assert_eq!(
+                    find_fenced_block(input, from),
+                    regex.find_at(input, from).map(|found| found.range()),
+                    "{input:?} from {from}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_follows_blank_line() {
+        let text = "a\n```\n\n> ```\n> b\n> ```\nc ```";
+        assert!(!follows_blank_line(text, 2, 0));
+        assert!(follows_blank_line(text, 9, 0));
+        assert!(!follows_blank_line(text, 19, 0));
+        assert!(follows_blank_line(text, 19, 16));
+        assert!(!follows_blank_line(text, 25, 0));
+        assert!(follows_blank_line("  ```", 2, 0));
+    }
+
+    #[test]
+    fn test_blank_line_required_by_both_engines() {
+        let text = "Tight\n```\nnot code\n\n```\nb\n```\n```\nc\n```";
+        for engine in [Engine::Regex, Engine::Parser] {
+            let blocks: Vec<_> = blocks_with(text, engine, true)
+                .map(|block| block.content)
+                .collect();
+            assert_eq!(blocks, ["b\n", "c\n"], "{engine:?}");
+        }
+    }
+
+    #[test]
+    fn test_codeblock_fields() {
+        let input = "Intro\n```rust ignore\nfn main() {}\n```\nOutro";
+        let blocks: Vec<_> = codeblocks(input).collect();
+
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].info, "rust ignore");
+        assert_eq!(blocks[0].info_raw, "rust ignore");
+        assert_eq!(blocks[0].language, Some("rust"));
+        assert_eq!(blocks[0].content, "fn main() {}\n");
+        assert_eq!(
+            &input[blocks[0].span.clone()],
+            "```rust ignore\nfn main() {}\n```"
+        );
+        assert_eq!(&input[blocks[0].content_span.clone()], blocks[0].content);
+    }
+
+    #[test]
+    fn test_codeblock_without_info() {
+        let input = "```\ncode\n```";
+        let block = codeblocks(input).next().unwrap();
+
+        assert_eq!(block.info, "");
+        assert_eq!(block.language, None);
+    }
+
+    #[test]
+    fn test_info_raw_keeps_whitespace() {
+        let input = "```  python  \nprint(1)\n```";
+        let block = codeblocks(input).next().unwrap();
+
+        assert_eq!(block.info, "python");
+        assert_eq!(block.info_raw, "  python  ");
+    }
+
+    #[test]
+    fn test_language_before_comma() {
+        let input = "```rust,no_run\nloop {}\n```";
+        let block = codeblocks(input).next().unwrap();
+
+        assert_eq!(block.info, "rust,no_run");
+        assert_eq!(block.language, Some("rust"));
+    }
+}
@@ -0,0 +1,289 @@
+//! Turning the code blocks of one language into a runnable script.
+
+use std::error::Error;
+use std::fmt::{self, Write};
+
+use crate::console::prompt_command;
+use crate::newline::with_newlines;
+use crate::scan::codeblocks;
+use crate::strict::{self, line_number};
+use crate::{Ambiguity, StripError, StripOptions};
+
+/// An error returned by [`extract_script`] instead of a script that might not
+/// run as the document intends.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ScriptError {
+    /// No interpreter is known for the language, so no shebang can be written.
+    UnsupportedLanguage(String),
+    /// The document has no block in the language.
+    NoBlocks,
+    /// The document has an unclosed or mismatched fence, or a fence opened in
+    /// the middle of a line, so which lines belong to the script is unclear.
+    Ambiguous {
+        /// The line of the fence, starting at 1.
+        line: usize,
+        /// What makes it ambiguous.
+        reason: Ambiguity,
+    },
+    /// A block in the language has content that shouldn't be run as is.
+    Suspicious {
+        /// The line of the content, starting at 1.
+        line: usize,
+        /// What is suspicious about it.
+        reason: Suspicion,
+    },
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScriptError::UnsupportedLanguage(language) => {
+                write!(f, "no interpreter is known for `{language}`")
+            }
+            ScriptError::NoBlocks => f.write_str("no block in the language"),
+            ScriptError::Ambiguous { line, reason } => write!(f, "line {line}: {reason}"),
+            ScriptError::Suspicious { line, reason } => write!(f, "line {line}: {reason}"),
+        }
+    }
+}
+
+impl Error for ScriptError {}
+
+impl From<StripError> for ScriptError {
+    fn from(error: StripError) -> Self {
+        match error {
+            StripError::Ambiguous { line, reason } => ScriptError::Ambiguous { line, reason },
+        }
+    }
+}
+
+/// What makes the content of a block unsafe to run, for [`ScriptError::Suspicious`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Suspicion {
+    /// A line starts with a `$ ` prompt: the block is a console session, whose
+    /// output lines would be run as commands.
+    Prompt,
+    /// The content has a control character, such as an ANSI escape sequence.
+    ControlCharacter,
+    /// The content has a Unicode bidirectional control character, which can
+    /// make code display differently from how it runs.
+    BidiControl,
+}
+
+impl fmt::Display for Suspicion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Suspicion::Prompt => "line starts with a console prompt",
+            Suspicion::ControlCharacter => "content has a control character",
+            Suspicion::BidiControl => "content has a bidirectional control character",
+        })
+    }
+}
+
+/// The shebang, first lines and line comment prefix of the scripts in a language.
+struct Interpreter {
+    shebang: &'static str,
+    prelude: &'static str,
+    comment: &'static str,
+}
+
+/// Returns the interpreter of the scripts written in `language`, if known.
+fn interpreter(language: &str) -> Option<Interpreter> {
+    let shell = |shebang| Interpreter {
+        shebang,
+        prelude: "set -e\n",
+        comment: "#",
+    };
+    let hash = |shebang| Interpreter {
+        shebang,
+        prelude: "",
+        comment: "#",
+    };
+
+    Some(match language {
+        "sh" | "shell" => shell("#!/bin/sh"),
+        "bash" => shell("#!/usr/bin/env bash"),
+        "zsh" => shell("#!/usr/bin/env zsh"),
+        "python" | "py" | "python3" => hash("#!/usr/bin/env python3"),
+        "ruby" | "rb" => hash("#!/usr/bin/env ruby"),
+        "perl" | "pl" => hash("#!/usr/bin/env perl"),
+        "javascript" | "js" | "node" => Interpreter {
+            shebang: "#!/usr/bin/env node",
+            prelude: "",
+            comment: "//",
+        },
+        _ => return None,
+    })
+}
+
+/// Returns the first suspicious line of `content` and what is suspicious about it.
+fn suspicion(content: &str) -> Option<(usize, Suspicion)> {
+    content.lines().enumerate().find_map(|(index, line)| {
+        let reason = if prompt_command(line).is_some() {
+            Suspicion::Prompt
+        } else if line
+            .chars()
+            .any(|c| matches!(c, '\u{202a}'..='\u{202e}' | '\u{2066}'..='\u{2069}'))
+        {
+            Suspicion::BidiControl
+        } else if line
+            .chars()
+            .any(|c| c.is_control() && !matches!(c, '\t' | '\r'))
+        {
+            Suspicion::ControlCharacter
+        } else {
+            return None;
+        };
+        Some((index, reason))
+    })
+}
+
+/// Concatenates every code block in `lang` into a script that can be run.
+///
+/// The script starts with a shebang for the language's interpreter (and
+/// `set -e` for shells, so that it stops at the first failing command),
+/// followed by the content of each block behind a comment giving its line in
+/// the document. Line breaks are normalized to `\n`.
+///
+/// Since running a script is harder to take back than reading a document, the
+/// script is refused when it might not be what the document shows: when a
+/// fence is unclosed, closed by a fence of a different length, or opened in
+/// the middle of a line, or when a block in `lang` is a console session or
+/// contains control characters.
+///
+/// # Arguments
+///
+/// * `text` - The markdown text to extract the script from
+/// * `lang` - The language of the blocks making up the script, such as `bash` or `python`
+///
+/// # Returns
+///
+/// The script, or the reason it was refused.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::{ScriptError, Suspicion, extract_script};
+///
+/// let readme = "Build:\n```bash\ncargo build\n```\nTest:\n```bash\ncargo test\n```";
+/// assert_eq!(
+///     extract_script(readme, "bash").unwrap(),
+///     "#!/usr/bin/env bash\nset -e\n\n# line 2\ncargo build\n\n# line 6\ncargo test\n"
+/// );
+///
+/// let transcript = "```bash\n$ cargo build\n   Compiling app\n```";
+/// assert_eq!(
+///     extract_script(transcript, "bash"),
+///     Err(ScriptError::Suspicious { line: 2, reason: Suspicion::Prompt })
+/// );
+/// ```
+pub fn extract_script(text: &str, lang: &str) -> Result<String, ScriptError> {
+    let interpreter =
+        interpreter(lang).ok_or_else(|| ScriptError::UnsupportedLanguage(lang.to_string()))?;
+    strict::check(text, &StripOptions::new())?;
+
+    let mut script = String::new();
+    for block in codeblocks(text).filter(|block| block.language == Some(lang)) {
+        let first_line = line_number(text, block.content_span.start);
+        if let Some((index, reason)) = suspicion(block.content) {
+            return Err(ScriptError::Suspicious {
+                line: first_line + index,
+                reason,
+            });
+        }
+
+        if script.is_empty() {
+            script.push_str(interpreter.shebang);
+            script.push('\n');
+            script.push_str(interpreter.prelude);
+        }
+        let content = with_newlines(block.content, "\n");
+        writeln!(
+            script,
+            "\n{} line {}",
+            interpreter.comment,
+            line_number(text, block.span.start)
+        )
+        .expect("writing to a String never fails");
+        script.push_str(&content);
+        if !content.ends_with('\n') {
+            script.push('\n');
+        }
+    }
+
+    if script.is_empty() {
+        return Err(ScriptError::NoBlocks);
+    }
+    Ok(script)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_blocks_in_the_language_are_extracted() {
+        let input =
+            "```python\nimport os\n```\n```sh\nls\n```\r\n```python\r\nprint(os.getcwd())\r\n```";
+        assert_eq!(
+            extract_script(input, "python").unwrap(),
+            "#!/usr/bin/env python3\n\n# line 1\nimport os\n\n# line 7\nprint(os.getcwd())\n"
+        );
+    }
+
+    #[test]
+    fn test_comment_syntax_follows_the_language() {
+        let script = extract_script("```js\nconsole.log(1)\n```", "js").unwrap();
+        assert_eq!(script, "#!/usr/bin/env node\n\n// line 1\nconsole.log(1)\n");
+    }
+
+    #[test]
+    fn test_ambiguous_fences_are_refused() {
+        assert_eq!(
+            extract_script("```sh\nls\n```\n```sh\nrm -rf build\n", "sh"),
+            Err(ScriptError::Ambiguous {
+                line: 4,
+                reason: Ambiguity::UnclosedFence
+            })
+        );
+        assert_eq!(
+            extract_script("~~~sh\nls\n~~~~\n", "sh"),
+            Err(ScriptError::Ambiguous {
+                line: 3,
+                reason: Ambiguity::MismatchedFence
+            })
+        );
+    }
+
+    #[test]
+    fn test_suspicious_content_is_refused() {
+        assert_eq!(
+            extract_script("```sh\nls\necho \u{1b}[31mred\n```", "sh"),
+            Err(ScriptError::Suspicious {
+                line: 3,
+                reason: Suspicion::ControlCharacter
+            })
+        );
+        assert_eq!(
+            extract_script("# Run\n```sh\nls # \u{202e}safe\n```", "sh"),
+            Err(ScriptError::Suspicious {
+                line: 3,
+                reason: Suspicion::BidiControl
+            })
+        );
+    }
+
+    #[test]
+    fn test_missing_blocks_and_languages() {
+        assert_eq!(
+            extract_script("```sh\nls\n```", "bash"),
+            Err(ScriptError::NoBlocks)
+        );
+        assert_eq!(
+            extract_script("```ml\nlet x = 1\n```", "ml"),
+            Err(ScriptError::UnsupportedLanguage("ml".into()))
+        );
+    }
+}
@@ -0,0 +1,187 @@
+//! Splitting a document into prose and code [`Segment`]s.
+
+use std::fmt;
+use std::iter;
+
+use crate::CodeBlock;
+use crate::scan::codeblocks;
+
+/// A run of a markdown document: either prose or a fenced code block.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Segment<'a> {
+    /// Text outside of fenced code blocks, inline code included.
+    Prose(&'a str),
+    /// A fenced code block.
+    Code(CodeBlock<'a>),
+}
+
+impl<'a> From<&'a str> for Segment<'a> {
+    fn from(prose: &'a str) -> Self {
+        Segment::Prose(prose)
+    }
+}
+
+impl<'a> From<CodeBlock<'a>> for Segment<'a> {
+    fn from(block: CodeBlock<'a>) -> Self {
+        Segment::Code(block)
+    }
+}
+
+/// Displays prose as is, and code blocks as fenced blocks like [`CodeBlock`] does.
+impl fmt::Display for Segment<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Segment::Prose(prose) => f.write_str(prose),
+            Segment::Code(block) => block.fmt(f),
+        }
+    }
+}
+
+/// Splits markdown text into alternating prose and code segments.
+///
+/// Concatenating the prose segments and the full text of the code blocks
+/// (their [`span`](CodeBlock::span)) reproduces `text` exactly. Empty prose
+/// segments, e.g. between two adjacent blocks, are omitted.
+///
+/// # Arguments
+///
+/// * `text` - The markdown text to split
+///
+/// # Returns
+///
+/// The segments of `text`, in document order.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::{Segment, segments};
+///
+/// let markdown = "Intro\n```rust\nfn main() {}\n```\nOutro";
+///
+/// let segments = segments(markdown);
+/// assert_eq!(segments.len(), 3);
+/// assert_eq!(segments[0], Segment::Prose("Intro\n"));
+/// assert!(matches!(&segments[1], Segment::Code(block) if block.language == Some("rust")));
+/// assert_eq!(segments[2], Segment::Prose("\nOutro"));
+/// ```
+pub fn segments(text: &str) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+    let mut last = 0;
+
+    for block in codeblocks(text) {
+        if block.span.start > last {
+            segments.push(Segment::Prose(&text[last..block.span.start]));
+        }
+        last = block.span.end;
+        segments.push(Segment::Code(block));
+    }
+
+    if last < text.len() {
+        segments.push(Segment::Prose(&text[last..]));
+    }
+    segments
+}
+
+/// Returns an iterator over the prose of markdown text, the runs of text
+/// outside of fenced code blocks.
+///
+/// These are the [`Segment::Prose`] segments of [`segments`], found lazily
+/// without collecting the segments or building the stripped text, so NLP
+/// tooling can tokenize the prose of a document directly. Inline code is
+/// part of the prose, and empty runs, e.g. between two adjacent blocks, are
+/// skipped.
+///
+/// # Arguments
+///
+/// * `text` - The markdown text to find the prose of
+///
+/// # Returns
+///
+/// An iterator over the runs of prose of `text`, borrowed from it, in document order.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::prose_spans;
+///
+/// let markdown = "Intro\n```rust\nfn main() {}\n```\nRun `main`.";
+///
+/// let words: Vec<_> = prose_spans(markdown).flat_map(str::split_whitespace).collect();
+/// assert_eq!(words, ["Intro", "Run", "`main`."]);
+/// ```
+pub fn prose_spans(text: &str) -> impl Iterator<Item = &str> {
+    let mut blocks = codeblocks(text);
+    // Where the next run of prose starts, until the end of the text
+    let mut start = Some(0);
+
+    iter::from_fn(move || {
+        while let Some(from) = start {
+            let (end, next) = match blocks.next() {
+                Some(block) => (block.span.start, Some(block.span.end)),
+                None => (text.len(), None),
+            };
+            start = next;
+            if end > from {
+                return Some(&text[from..end]);
+            }
+        }
+        None
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_segments_adjacent_blocks() {
+        let input = "```\na\n``````\nb\n```";
+        let segments = segments(input);
+
+        assert_eq!(segments.len(), 2);
+        assert!(
+            segments
+                .iter()
+                .all(|segment| matches!(segment, Segment::Code(_)))
+        );
+    }
+
+    #[test]
+    fn test_segments_reproduce_text() {
+        let input = "# Title\n\n```sh\nls\n```\n\nText with `inline`.\n```\nx\n```";
+        let rebuilt: String = segments(input)
+            .iter()
+            .map(|segment| match segment {
+                Segment::Prose(prose) => *prose,
+                Segment::Code(block) => &input[block.span.clone()],
+            })
+            .collect();
+        assert_eq!(rebuilt, input);
+    }
+
+    #[test]
+    fn test_segments_without_blocks() {
+        assert_eq!(segments("just text"), [Segment::Prose("just text")]);
+        assert!(segments("").is_empty());
+    }
+
+    #[test]
+    fn test_prose_spans_match_segments() {
+        for input in [
+            "a\n```\nb\n```\nc",
+            "```\na\n``````\nb\n```",
+            "just text",
+            "",
+        ] {
+            let prose: Vec<_> = segments(input)
+                .into_iter()
+                .filter_map(|segment| match segment {
+                    Segment::Prose(prose) => Some(prose),
+                    Segment::Code(_) => None,
+                })
+                .collect();
+            assert_eq!(prose_spans(input).collect::<Vec<_>>(), prose);
+        }
+    }
+}
@@ -0,0 +1,186 @@
+//! Shortening long blocks to their first and last lines.
+
+use std::borrow::Cow;
+
+use crate::StripOptions;
+use crate::newline::with_newlines;
+use crate::scan::replace_codeblocks;
+
+/// The marker replacing omitted lines when none is set.
+const DEFAULT_MARKER: &str = "… ({omitted} lines omitted)";
+
+/// How [`shrink_codeblocks`] and [`StripOptions::shrink`](crate::StripOptions::shrink)
+/// shorten long blocks.
+///
+/// A block of more than `max_lines` lines keeps its first and last `keep`
+/// lines, and the lines in between are replaced by a marker line, in which
+/// `{omitted}` stands for how many lines were omitted.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::{Shrink, StripOptions, strip_codeblocks_with};
+///
+/// let options = StripOptions::new().shrink(Shrink::new(2, 1).marker("[{omitted} more]"));
+///
+/// let dump = "```\n0x00\n0x01\n0x02\n0x03\n```";
+/// assert_eq!(strip_codeblocks_with(dump, &options), "0x00\n[2 more]\n0x03\n");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(deny_unknown_fields)
+)]
+#[non_exhaustive]
+pub struct Shrink {
+    max_lines: usize,
+    keep: usize,
+    #[cfg_attr(feature = "serde", serde(default = "default_marker"))]
+    marker: String,
+}
+
+/// Returns the marker of shrinks deserialized without one.
+#[cfg(feature = "serde")]
+fn default_marker() -> String {
+    DEFAULT_MARKER.to_string()
+}
+
+impl Shrink {
+    /// Shrinks blocks of more than `max_lines` lines to their first and
+    /// last `keep` lines, with the marker `… ({omitted} lines omitted)`.
+    pub fn new(max_lines: usize, keep: usize) -> Self {
+        Self {
+            max_lines,
+            keep,
+            marker: DEFAULT_MARKER.to_string(),
+        }
+    }
+
+    /// Sets the line replacing the omitted lines, where `{omitted}` stands
+    /// for their number.
+    pub fn marker(mut self, marker: impl Into<String>) -> Self {
+        self.marker = marker.into();
+        self
+    }
+
+    /// Returns `content` shrunk, ending the marker line with `newline`.
+    pub(crate) fn apply<'a>(&self, content: Cow<'a, str>, newline: &str) -> Cow<'a, str> {
+        let lines = content.split_inclusive('\n').count();
+        if lines <= self.max_lines || lines <= 2 * self.keep + 1 {
+            return content;
+        }
+
+        let omitted = lines - 2 * self.keep;
+        let head_len: usize = content
+            .split_inclusive('\n')
+            .take(self.keep)
+            .map(str::len)
+            .sum();
+        let tail_len: usize = content
+            .split_inclusive('\n')
+            .skip(lines - self.keep)
+            .map(str::len)
+            .sum();
+
+        let marker = self.marker.replace("{omitted}", &omitted.to_string());
+        let mut output = String::with_capacity(head_len + marker.len() + tail_len + 2);
+        output.push_str(&content[..head_len]);
+        output.push_str(&with_newlines(&marker, newline));
+        output.push_str(newline);
+        output.push_str(&content[content.len() - tail_len..]);
+        Cow::Owned(output)
+    }
+}
+
+/// Returns `content` shrunk as set by [`StripOptions::shrink`], if it is.
+pub(crate) fn shrunk<'a>(
+    content: Cow<'a, str>,
+    options: &StripOptions,
+    newline: &str,
+) -> Cow<'a, str> {
+    match &options.shrink {
+        Some(shrink) => shrink.apply(content, newline),
+        None => content,
+    }
+}
+
+/// Shortens the code blocks of markdown text longer than `max_lines` lines
+/// to their first and last `keep` lines, keeping them fenced.
+///
+/// The omitted lines are replaced by a `… (N lines omitted)` line inside the
+/// block, so that long stack traces and dumps don't dominate a transcript.
+/// To shrink blocks while stripping them, use [`Shrink`] with
+/// [`StripOptions::shrink`](crate::StripOptions::shrink).
+///
+/// # Arguments
+///
+/// * `text` - The markdown text containing code blocks to shrink
+/// * `max_lines` - The number of lines above which a block is shrunk
+/// * `keep` - The number of lines kept at each end of a shrunk block
+///
+/// # Returns
+///
+/// The text with its long code blocks shrunk.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::shrink_codeblocks;
+///
+/// let dump = "```\n1\n2\n3\n4\n5\n```\n```\nshort\n```";
+/// assert_eq!(
+///     shrink_codeblocks(dump, 4, 1),
+///     "```\n1\n… (3 lines omitted)\n5\n```\n```\nshort\n```"
+/// );
+/// ```
+pub fn shrink_codeblocks(text: &str, max_lines: usize, keep: usize) -> String {
+    let shrink = Shrink::new(max_lines, keep);
+    let newline = crate::Newline::Auto.resolve(text);
+
+    let Ok(output) = replace_codeblocks(text, |block, output| {
+        output.push_str(&text[block.span.start..block.content_span.start]);
+        output.push_str(&shrink.apply(Cow::Borrowed(block.content), newline));
+        output.push_str(&text[block.content_span.end..block.span.end]);
+        Ok::<_, std::convert::Infallible>(())
+    });
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Action, strip_codeblocks_with};
+
+    #[test]
+    fn test_short_blocks_are_untouched() {
+        let shrink = Shrink::new(3, 2);
+        assert!(matches!(
+            shrink.apply(Cow::Borrowed("1\n2\n3\n"), "\n"),
+            Cow::Borrowed(_)
+        ));
+        // Shrinking 5 lines to 2 + marker + 2 would save nothing
+        assert_eq!(
+            shrink.apply(Cow::Borrowed("1\n2\n3\n4\n5"), "\n"),
+            "1\n2\n3\n4\n5"
+        );
+        assert_eq!(
+            shrink.apply(Cow::Borrowed("1\n2\n3\n4\n5\n6"), "\n"),
+            "1\n2\n… (2 lines omitted)\n5\n6"
+        );
+    }
+
+    #[test]
+    fn test_line_endings_follow_the_document() {
+        let input = "```\r\na\r\nb\r\nc\r\n```\r\n";
+        assert_eq!(
+            shrink_codeblocks(input, 2, 0),
+            "```\r\n… (3 lines omitted)\r\n```\r\n"
+        );
+
+        let options = StripOptions::new()
+            .action(Action::Keep)
+            .shrink(Shrink::new(2, 1));
+        assert_eq!(strip_codeblocks_with(input, &options), input);
+    }
+}
@@ -0,0 +1,286 @@
+//! A table of stripping cases pinning down the behavior of this crate.
+//!
+//! Each [`SpecCase`] is an input, the options it is stripped with and the
+//! exact output expected. The cases run as tests of the crate itself, and
+//! services depending on it can run them too with [`run_spec`], so that a
+//! change of behavior between versions fails their CI instead of changing
+//! their output silently.
+//!
+//! ```
+//! use strip_codeblocks::spec::{CASES, run_spec};
+//!
+//! assert!(CASES.iter().any(|case| case.name == "unwrap/default"));
+//! run_spec()?;
+//! # Ok::<(), strip_codeblocks::spec::SpecDrift>(())
+//! ```
+
+use std::error::Error;
+use std::fmt;
+
+use crate::experimental::Engine;
+use crate::{
+    Action, Ansi, Dedent, HtmlEntities, Newline, RenderSafety, Shrink, StripOptions,
+    TrailingNewline, strip_codeblocks_with,
+};
+
+/// A stripping case: what `input` is stripped to with some options.
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub struct SpecCase {
+    /// The unique name of the case, grouped by feature, such as `unwrap/default`.
+    pub name: &'static str,
+    /// The markdown text stripped.
+    pub input: &'static str,
+    /// Returns the options the input is stripped with.
+    pub options: fn() -> StripOptions,
+    /// The text the input is stripped to.
+    pub expected: &'static str,
+}
+
+impl SpecCase {
+    /// Strips the input of the case with its options.
+    pub fn run(&self) -> String {
+        strip_codeblocks_with(self.input, &(self.options)())
+    }
+}
+
+/// Declares a [`SpecCase`], with default options when none are given.
+macro_rules! case {
+    ($name:literal, $input:literal => $expected:literal) => {
+        case!($name, StripOptions::new, $input => $expected)
+    };
+    ($name:literal, $options:expr, $input:literal => $expected:literal) => {
+        SpecCase {
+            name: $name,
+            input: $input,
+            options: $options,
+            expected: $expected,
+        }
+    };
+}
+
+/// The cases of the spec, in no particular order.
+pub static CASES: &[SpecCase] = &[
+    case!("unwrap/default", "Run:\n```sh\nls\n```\nDone" => "Run:\nls\n\nDone"),
+    case!("unwrap/no_language", "```\nplain\n```" => "plain\n"),
+    case!("unwrap/several", "```a\n1\n```\n```b\n2\n```" => "1\n\n2\n"),
+    case!("tildes/not_a_fence", "~~~py\npass\n~~~" => "~~~py\npass\n~~~"),
+    case!("inline/untouched", "Call `ls` or ``a`b``." => "Call `ls` or ``a`b``."),
+    case!("prose/untouched", "No code here.\n" => "No code here.\n"),
+    case!("unclosed/kept", "```sh\nls\n" => "```sh\nls\n"),
+    case!(
+        "engine/parser_nested_fences",
+        || StripOptions::new().engine(Engine::Parser),
+        "````md\n```\nx\n```\n````" => "```\nx\n```\n"
+    ),
+    case!(
+        "languages/filter",
+        || StripOptions::new().languages(["sh"]),
+        "```sh\nls\n```\n```py\npass\n```" => "ls\n\n```py\npass\n```"
+    ),
+    case!(
+        "languages/deny",
+        || StripOptions::new().deny_languages(["sql"]),
+        "```sql\nSELECT 1\n```\n```sh\nls\n```" => "```sql\nSELECT 1\n```\nls\n"
+    ),
+    case!(
+        "action/keep",
+        || StripOptions::new().action(Action::Keep),
+        "```sh\nls\n```" => "```sh\nls\n```"
+    ),
+    case!(
+        "action/remove",
+        || StripOptions::new().action(Action::Remove),
+        "a\n```\nb\n```\nc" => "a\n\nc"
+    ),
+    case!(
+        "action/placeholder",
+        || StripOptions::new().action(Action::Placeholder("[code]".into())),
+        "a\n```\nb\n```\nc" => "a\n[code]\nc"
+    ),
+    case!(
+        "action/first_line",
+        || StripOptions::new().action(Action::FirstLine("…".into())),
+        "```sh\nmake\nmake test\n```" => "make\n…\n"
+    ),
+    case!(
+        "console/strip_prompts",
+        || StripOptions::new().strip_prompts(true),
+        "```console\n$ ls\nfile\n```" => "ls\nfile\n"
+    ),
+    case!(
+        "console/drop_output",
+        || StripOptions::new().strip_prompts(true).drop_output(true),
+        "```console\n$ ls\nfile\n```" => "ls\n"
+    ),
+    case!(
+        "entities/decode",
+        || StripOptions::new().html_entities(HtmlEntities::Decode),
+        "a &lt; b\n```\na &lt; b\n```" => "a &lt; b\na < b\n"
+    ),
+    case!(
+        "ansi/strip",
+        || StripOptions::new().ansi(Ansi::Strip),
+        "\x1b[1mBold\x1b[0m\n```\nls\n```" => "Bold\nls\n"
+    ),
+    case!(
+        "whitespace/preserve",
+        || StripOptions::new().preserve_whitespace(true),
+        "a\n```sh\n  ls\n```\nb" => "a\n  ls\nb"
+    ),
+    case!(
+        "newline/crlf",
+        || StripOptions::new()
+            .newline(Newline::CrLf)
+            .action(Action::Placeholder("x\ny".into())),
+        "```\ncode\n```" => "x\r\ny"
+    ),
+    case!(
+        "trailing_newline/never",
+        || StripOptions::new().trailing_newline(TrailingNewline::Never),
+        "Run:\n```sh\nls\n```\n" => "Run:\nls"
+    ),
+    case!(
+        "merge/adjacent",
+        || StripOptions::new().merge_adjacent(true),
+        "```sh\ncd app\n```\n\n```sh\nmake\n```" => "cd app\nmake\n"
+    ),
+    case!(
+        "regions/off",
+        || StripOptions::new().region_markers(true),
+        "<!-- strip-codeblocks: off -->\n```sh\nls\n```" => "<!-- strip-codeblocks: off -->\n```sh\nls\n```"
+    ),
+    case!("math/kept", "```math\nx^2\n```" => "```math\nx^2\n```"),
+    case!(
+        "math/remove",
+        || StripOptions::new().math(Action::Remove),
+        "a\n$$\nx^2\n$$\nb" => "a\n\nb"
+    ),
+    case!(
+        "hash/placeholder",
+        || StripOptions::new()
+            .action(Action::Placeholder("{sha256:8}".into()))
+            .hash_placeholders(true),
+        "```\nabc```" => "ba7816bf"
+    ),
+    case!(
+        "render_safety/escape",
+        || StripOptions::new().render_safety(RenderSafety::Escape),
+        "```sh\n# comment\n```" => "\\# comment\n"
+    ),
+    case!(
+        "shrink/long",
+        || StripOptions::new().shrink(Shrink::new(2, 1)),
+        "```\n1\n2\n3\n4\n```" => "1\n… (2 lines omitted)\n4\n"
+    ),
+    case!(
+        "dedent/list_item",
+        || StripOptions::new().dedent(Dedent::new()),
+        "- a\n  ```\n  x\n    y\n  ```" => "- a\n  x\n    y\n"
+    ),
+];
+
+/// A case of the spec whose output changed.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct SpecFailure {
+    /// The name of the case.
+    pub name: &'static str,
+    /// The output the case expects.
+    pub expected: &'static str,
+    /// The output of this version of the crate.
+    pub actual: String,
+}
+
+/// The error returned by [`run_spec`] when cases fail.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct SpecDrift {
+    /// The failing cases, in the order of [`CASES`].
+    pub failures: Vec<SpecFailure>,
+}
+
+impl fmt::Display for SpecDrift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} spec cases failed", self.failures.len())?;
+        for failure in &self.failures {
+            write!(
+                f,
+                "\n{}: expected {:?}, got {:?}",
+                failure.name, failure.expected, failure.actual
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for SpecDrift {}
+
+/// Runs every case of the spec.
+///
+/// # Returns
+///
+/// `Ok` if every case gives its expected output, or the cases that don't.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::spec::run_spec;
+///
+/// // In a test of a service depending on the crate
+/// assert!(run_spec().is_ok());
+/// ```
+pub fn run_spec() -> Result<(), SpecDrift> {
+    let failures: Vec<_> = CASES
+        .iter()
+        .filter_map(|case| {
+            let actual = case.run();
+            (actual != case.expected).then_some(SpecFailure {
+                name: case.name,
+                expected: case.expected,
+                actual,
+            })
+        })
+        .collect();
+
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(SpecDrift { failures })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spec_passes() {
+        if let Err(drift) = run_spec() {
+            panic!("{drift}");
+        }
+    }
+
+    #[test]
+    fn test_names_are_unique() {
+        let mut names: Vec<_> = CASES.iter().map(|case| case.name).collect();
+        names.sort_unstable();
+        names.dedup();
+        assert_eq!(names.len(), CASES.len());
+    }
+
+    #[test]
+    fn test_drift_is_reported() {
+        let drift = SpecDrift {
+            failures: vec![SpecFailure {
+                name: "unwrap/default",
+                expected: "ls\n",
+                actual: "```ls```".into(),
+            }],
+        };
+        assert_eq!(
+            drift.to_string(),
+            "1 spec cases failed\nunwrap/default: expected \"ls\\n\", got \"```ls```\""
+        );
+    }
+}
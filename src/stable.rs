@@ -0,0 +1,106 @@
+//! Stripping nested code blocks one level per pass until nothing changes.
+
+use std::borrow::Cow;
+
+use crate::{StripOptions, strip_codeblocks_cow};
+
+/// Strips fenced code blocks again and again, like [`strip_codeblocks`](crate::strip_codeblocks),
+/// until the text stops changing or `max_passes` passes were made.
+///
+/// This is [`strip_until_stable_with`] with the default options.
+///
+/// # Arguments
+///
+/// * `text` - The markdown text containing code blocks to strip
+/// * `max_passes` - The most passes made, guarding against options whose
+///   output never settles
+///
+/// # Returns
+///
+/// The text once stable, or after `max_passes` passes.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::strip_until_stable;
+///
+/// // Single-level input is stable after one pass
+/// let text = "Run:\n```sh\nls\n```\n";
+/// assert_eq!(strip_until_stable(text, 10), "Run:\nls\n\n");
+/// assert_eq!(strip_until_stable(text, 0), text);
+/// ```
+pub fn strip_until_stable(text: &str, max_passes: usize) -> String {
+    strip_until_stable_with(text, &StripOptions::new(), max_passes)
+}
+
+/// Strips fenced code blocks according to `options` again and again, until
+/// the text stops changing or `max_passes` passes were made.
+///
+/// Each pass strips one level of nesting of markdown-in-markdown, such as a
+/// markdown example whose blocks show up once the example is unwrapped.
+/// Options replacing blocks with fences of their own, such as a placeholder
+/// holding a fence, may never settle, which `max_passes` bounds.
+///
+/// # Arguments
+///
+/// * `text` - The markdown text containing code blocks to strip
+/// * `options` - Which blocks are stripped and how, at every pass
+/// * `max_passes` - The most passes made
+///
+/// # Returns
+///
+/// The text once stable, or after `max_passes` passes.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::experimental::Engine;
+/// use strip_codeblocks::{StripOptions, strip_until_stable_with};
+///
+/// let options = StripOptions::new().engine(Engine::Parser);
+///
+/// let text = "Example:\n\n`````md\n````md\n```sh\nls\n```\n````\n`````\n";
+/// assert_eq!(strip_until_stable_with(text, &options, 10), "Example:\n\nls\n\n\n\n");
+/// ```
+pub fn strip_until_stable_with(text: &str, options: &StripOptions, max_passes: usize) -> String {
+    let mut current = Cow::Borrowed(text);
+    for _ in 0..max_passes {
+        let stripped = match strip_codeblocks_cow(&current, options) {
+            Cow::Borrowed(_) => break,
+            Cow::Owned(stripped) if stripped == *current => break,
+            Cow::Owned(stripped) => stripped,
+        };
+        current = Cow::Owned(stripped);
+    }
+    current.into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Action, strip_codeblocks};
+
+    #[test]
+    fn test_single_level_is_idempotent() {
+        for input in [
+            "a\n```sh\nls\n```\nb `c`",
+            "```\nx\n```\n```py\ny\n```",
+            "no fences",
+            "```unclosed\n",
+        ] {
+            let once = strip_codeblocks(input);
+            assert_eq!(strip_codeblocks(&once), once, "{input:?}");
+            assert_eq!(strip_until_stable(input, 10), once);
+        }
+    }
+
+    #[test]
+    fn test_passes_are_bounded() {
+        // Every placeholder is a new block to replace
+        let options = StripOptions::new().action(Action::Placeholder("```\n[code]\n```".into()));
+        assert_eq!(
+            strip_until_stable_with("```\nx\n```", &options, 3),
+            "```\n[code]\n```"
+        );
+    }
+}
@@ -0,0 +1,134 @@
+//! Corpus-level statistics about code blocks.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::scan::codeblocks;
+
+/// Statistics about the code blocks of one language.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct LangStats {
+    /// Number of blocks.
+    pub blocks: usize,
+    /// Total size of the blocks' content, in bytes.
+    pub total_bytes: usize,
+}
+
+impl LangStats {
+    /// Returns the average content size of the blocks, in bytes (`0.0` without blocks).
+    pub fn average_bytes(&self) -> f64 {
+        if self.blocks == 0 {
+            0.0
+        } else {
+            self.total_bytes as f64 / self.blocks as f64
+        }
+    }
+}
+
+/// Displays the statistics as a line, such as `2 blocks, 24 bytes (12.0 on average)`.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::language_histogram;
+///
+/// let histogram = language_histogram(["```sh\nls\n```\n```\n```"]);
+///
+/// for (language, stats) in &histogram {
+///     println!("{language:>8}: {stats}");
+/// }
+/// assert_eq!(histogram["sh"].to_string(), "1 block, 3 bytes (3.0 on average)");
+/// assert_eq!(histogram[""].to_string(), "1 block, 0 bytes (0.0 on average)");
+/// ```
+impl fmt::Display for LangStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let plural = |count| if count == 1 { "" } else { "s" };
+        write!(
+            f,
+            "{} block{}, {} byte{} ({:.1} on average)",
+            self.blocks,
+            plural(self.blocks),
+            self.total_bytes,
+            plural(self.total_bytes),
+            self.average_bytes()
+        )
+    }
+}
+
+/// Counts the code blocks of each language across a set of documents.
+///
+/// Blocks without a language are counted under the empty string. The result is
+/// sorted by language, so it can be printed or compared deterministically.
+///
+/// # Arguments
+///
+/// * `texts` - The markdown documents to analyze
+///
+/// # Returns
+///
+/// The statistics of every language found, keyed by language.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::language_histogram;
+///
+/// let transcripts = [
+///     "```rust\nfn main() {}\n```",
+///     "```python\nprint(1)\n```\nand\n```rust\nlet x = 1;\n```",
+/// ];
+///
+/// let histogram = language_histogram(transcripts);
+/// assert_eq!(histogram["rust"].blocks, 2);
+/// assert_eq!(histogram["rust"].total_bytes, 24);
+/// assert_eq!(histogram["rust"].average_bytes(), 12.0);
+/// assert_eq!(histogram["python"].blocks, 1);
+/// ```
+pub fn language_histogram<'a>(
+    texts: impl IntoIterator<Item = &'a str>,
+) -> BTreeMap<String, LangStats> {
+    let mut histogram: BTreeMap<String, LangStats> = BTreeMap::new();
+
+    for text in texts {
+        for block in codeblocks(text) {
+            let language = block.language.unwrap_or_default();
+            // Avoid allocating the key for languages already seen
+            let stats = match histogram.get_mut(language) {
+                Some(stats) => stats,
+                None => histogram.entry(language.to_string()).or_default(),
+            };
+            stats.blocks += 1;
+            stats.total_bytes += block.content.len();
+        }
+    }
+
+    histogram
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_histogram_counts_untagged_blocks() {
+        let histogram = language_histogram(["```\nab\n```\n```\nc\n```", "no code"]);
+
+        assert_eq!(histogram.len(), 1);
+        assert_eq!(histogram[""].blocks, 2);
+        assert_eq!(histogram[""].total_bytes, 5);
+        assert_eq!(histogram[""].average_bytes(), 2.5);
+    }
+
+    #[test]
+    fn test_histogram_is_sorted_by_language() {
+        let histogram = language_histogram(["```zsh\na\n```\n```c\nb\n```\n```json\nc\n```"]);
+        let languages: Vec<_> = histogram.keys().map(String::as_str).collect();
+        assert_eq!(languages, ["c", "json", "zsh"]);
+    }
+
+    #[test]
+    fn test_empty_stats_average() {
+        assert_eq!(LangStats::default().average_bytes(), 0.0);
+    }
+}
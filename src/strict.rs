@@ -0,0 +1,225 @@
+//! Detection of structures stripping can only handle best-effort, for strict mode.
+
+use std::error::Error;
+use std::fmt;
+
+use crate::StripOptions;
+use crate::block::trim_info;
+use crate::experimental::parser::{Fence, footnote_label, opening_fence};
+use crate::scan::codeblocks_for;
+
+/// An error returned by [`try_strip_codeblocks_with`](crate::try_strip_codeblocks_with).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum StripError {
+    /// The document has a structure whose blocks aren't clear-cut, in
+    /// [strict](StripOptions::strict) mode.
+    Ambiguous {
+        /// The line of the structure, starting at 1.
+        line: usize,
+        /// What makes it ambiguous.
+        reason: Ambiguity,
+    },
+}
+
+impl fmt::Display for StripError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StripError::Ambiguous { line, reason } => write!(f, "line {line}: {reason}"),
+        }
+    }
+}
+
+impl Error for StripError {}
+
+/// What makes a structure ambiguous, for [`StripError::Ambiguous`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Ambiguity {
+    /// An opening fence is never closed.
+    UnclosedFence,
+    /// A fence line made of the same character as the opening fence, but of
+    /// a different length, which engines disagree on closing the block.
+    MismatchedFence,
+    /// A block is opened in the middle of a line, e.g. inside an inline code span.
+    InlineFence,
+}
+
+impl fmt::Display for Ambiguity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Ambiguity::UnclosedFence => "fence is never closed",
+            Ambiguity::MismatchedFence => "fence length does not match the opening fence",
+            Ambiguity::InlineFence => "fence opens in the middle of a line",
+        })
+    }
+}
+
+/// Checks that the blocks of `text` are clear-cut, returning the first
+/// ambiguous structure otherwise.
+pub(crate) fn check(text: &str, options: &StripOptions) -> Result<(), StripError> {
+    let fence_lines = check_fence_lines(text).err();
+    let inline = codeblocks_for(text, options)
+        .find(|block| !starts_line(&text[..block.span.start]))
+        .map(|block| StripError::Ambiguous {
+            line: line_number(text, block.span.start),
+            reason: Ambiguity::InlineFence,
+        });
+
+    // Report whichever comes first in the document
+    match (fence_lines, inline) {
+        (Some(a), Some(b)) => Err(if line_of(&a) <= line_of(&b) { a } else { b }),
+        (Some(error), None) | (None, Some(error)) => Err(error),
+        (None, None) => Ok(()),
+    }
+}
+
+/// Checks that every fence starting a line, or a blockquote or list item, is
+/// closed by a fence of the same length.
+fn check_fence_lines(text: &str) -> Result<(), StripError> {
+    let mut open: Option<(Fence, usize)> = None;
+
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+        let fence = opening_fence(strip_containers(line));
+        match (open, fence) {
+            (None, Some((fence, _))) => open = Some((fence, line_number)),
+            (Some((opening, _)), Some((fence, info)))
+                if fence.char == opening.char && trim_info(info).is_empty() =>
+            {
+                if fence.len != opening.len {
+                    return Err(StripError::Ambiguous {
+                        line: line_number,
+                        reason: Ambiguity::MismatchedFence,
+                    });
+                }
+                open = None;
+            }
+            _ => {}
+        }
+    }
+
+    match open {
+        Some((_, line)) => Err(StripError::Ambiguous {
+            line,
+            reason: Ambiguity::UnclosedFence,
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Whether a fence preceded on its line by `before` starts the line, allowing
+/// for indentation, blockquote markers and list markers.
+fn starts_line(before: &str) -> bool {
+    let line = &before[before.rfind('\n').map_or(0, |newline| newline + 1)..];
+    strip_containers(line).is_empty()
+}
+
+/// Removes the indentation, blockquote markers and list marker or footnote
+/// label starting `line`.
+pub(crate) fn strip_containers(line: &str) -> &str {
+    let line = line.trim_start_matches([' ', '\t', '>']);
+    if let Some(label) = footnote_label(line) {
+        return line[label..].trim_start_matches([' ', '\t']);
+    }
+    let marker = match line.bytes().next() {
+        Some(b'-' | b'*' | b'+') => 1,
+        _ => {
+            let digits = line.bytes().take_while(u8::is_ascii_digit).count();
+            match line.as_bytes().get(digits) {
+                Some(b'.' | b')') if digits > 0 => digits + 1,
+                _ => 0,
+            }
+        }
+    };
+
+    match line[marker..].strip_prefix([' ', '\t']) {
+        Some(rest) if marker > 0 => rest.trim_start_matches([' ', '\t']),
+        _ => line,
+    }
+}
+
+/// Returns the line of `text` containing the byte at `offset`, starting at 1.
+pub(crate) fn line_number(text: &str, offset: usize) -> usize {
+    text[..offset].matches('\n').count() + 1
+}
+
+fn line_of(error: &StripError) -> usize {
+    match error {
+        StripError::Ambiguous { line, .. } => *line,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::experimental::Engine;
+
+    fn ambiguity(text: &str) -> Option<(usize, Ambiguity)> {
+        match check(text, &StripOptions::new()) {
+            Ok(()) => None,
+            Err(StripError::Ambiguous { line, reason }) => Some((line, reason)),
+        }
+    }
+
+    #[test]
+    fn test_clear_cut_documents() {
+        for text in [
+            "",
+            "Prose with `inline` code\n```rust\nfn main() {}\n```\n",
+            "> ```sh\n> ls\n> ```",
+            "- ```sh\n  ls\n  ```",
+            "Note[^1]\n\n[^1]: ```sh\n    ls\n    ```",
+            "~~~\n```\n~~~\n",
+        ] {
+            assert_eq!(ambiguity(text), None, "{text:?}");
+        }
+    }
+
+    #[test]
+    fn test_unclosed_fence() {
+        assert_eq!(
+            ambiguity("Intro\n```rust\nfn main() {}\n"),
+            Some((2, Ambiguity::UnclosedFence))
+        );
+    }
+
+    #[test]
+    fn test_mismatched_fence() {
+        assert_eq!(
+            ambiguity("~~~~\ncode\n~~~\nmore\n~~~~"),
+            Some((3, Ambiguity::MismatchedFence))
+        );
+        assert_eq!(
+            ambiguity("```\ncode\n`````"),
+            Some((3, Ambiguity::MismatchedFence))
+        );
+    }
+
+    #[test]
+    fn test_inline_fence() {
+        let text = "Open a block with ```js\nx\n```\n";
+        assert_eq!(ambiguity(text), Some((1, Ambiguity::InlineFence)));
+
+        // Nested fences confuse the regex engine, but not the parser
+        let nested = "````md\n```rust\n````\n";
+        assert_eq!(ambiguity(nested), Some((1, Ambiguity::InlineFence)));
+        let parser = StripOptions::new().engine(Engine::Parser);
+        assert!(check(nested, &parser).is_ok());
+    }
+
+    #[test]
+    fn test_first_ambiguity_is_reported() {
+        let text = "Text ```js\nx\n```\n````\nunclosed";
+        assert_eq!(ambiguity(text), Some((1, Ambiguity::InlineFence)));
+    }
+
+    #[test]
+    fn test_display() {
+        let error = StripError::Ambiguous {
+            line: 4,
+            reason: Ambiguity::UnclosedFence,
+        };
+        assert_eq!(error.to_string(), "line 4: fence is never closed");
+    }
+}
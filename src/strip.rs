@@ -0,0 +1,549 @@
+//! The stripping pass shared by the `strip_codeblocks*` functions.
+
+use std::borrow::Cow;
+use std::convert::Infallible;
+use std::ops::Range;
+use std::{fmt, iter};
+
+use crate::content::{first_line, unwrapped_content};
+use crate::hash::placeholder_output;
+use crate::math::math_output;
+use crate::merge::{mergeable, merged_output};
+use crate::newline::without_trailing_newlines;
+use crate::region::Regions;
+use crate::report::{BlockReport, LineCounter};
+use crate::scan::codeblocks_for;
+use crate::shrink::shrunk;
+use crate::stripper::Hooks;
+use crate::{
+    Action, Annotations, Ansi, CodeBlock, Report, SegmentKind, StripOptions, TrailingNewline, ansi,
+    trace,
+};
+
+/// Strips `text` according to `options`, recording each block in `report` if given.
+pub(crate) fn strip(text: &str, options: &StripOptions, report: Option<&mut Report>) -> String {
+    let mut output = String::with_capacity(text.len());
+    strip_to(text, options, report, &mut output).expect("writing to a String never fails");
+    output
+}
+
+/// Strips `text` according to `options`, borrowing it when no block is changed.
+pub(crate) fn strip_cow<'a>(text: &'a str, options: &StripOptions) -> Cow<'a, str> {
+    let math_changed = *options.math_action() != Action::Keep && text.contains("$$");
+    if math_changed || prose_output(text, options) != text {
+        return Cow::Owned(strip(text, options, None));
+    }
+    if options.trailing_newline != TrailingNewline::Keep {
+        let output = strip(text, options, None);
+        return if output == text {
+            Cow::Borrowed(text)
+        } else {
+            Cow::Owned(output)
+        };
+    }
+
+    let regions = Regions::new(text, options);
+    let mut blocks = codeblocks_for(text, options);
+    let Some(first) = blocks
+        .by_ref()
+        .find(|block| *regions.action_for(block, options) != Action::Keep)
+    else {
+        return Cow::Borrowed(text);
+    };
+
+    // Kept blocks are copied with the prose, so stripping can start at the first changed one
+    let mut output = String::with_capacity(text.len());
+    strip_blocks(
+        text,
+        iter::once(first).chain(blocks),
+        options,
+        None,
+        &Hooks::default(),
+        &mut output,
+    )
+    .expect("writing to a String never fails");
+    Cow::Owned(output)
+}
+
+/// Strips `text` according to `options` into `output`, recording each block in `report` if given.
+pub(crate) fn strip_to<W: fmt::Write + ?Sized>(
+    text: &str,
+    options: &StripOptions,
+    report: Option<&mut Report>,
+    output: &mut W,
+) -> fmt::Result {
+    strip_hooked_to(text, options, report, &Hooks::default(), output)
+}
+
+/// Strips `text` according to `options` into `output`, recording each block
+/// in `report` if given and calling `hooks` around each.
+pub(crate) fn strip_hooked_to<W: fmt::Write + ?Sized>(
+    text: &str,
+    options: &StripOptions,
+    report: Option<&mut Report>,
+    hooks: &Hooks,
+    output: &mut W,
+) -> fmt::Result {
+    let blocks = codeblocks_for(text, options);
+    strip_blocks(text, blocks, options, report, hooks, output)
+}
+
+/// Strips `blocks` of `text` according to `options` into `output`, recording
+/// each in `report` if given and calling `hooks` around each.
+fn strip_blocks<'a, W: fmt::Write + ?Sized>(
+    text: &'a str,
+    blocks: impl Iterator<Item = CodeBlock<'a>>,
+    options: &'a StripOptions,
+    mut report: Option<&mut Report>,
+    hooks: &Hooks,
+    output: &mut W,
+) -> fmt::Result {
+    let _span = trace::debug_span!("strip", len = text.len(), engine = ?options.engine);
+    let mut lines = LineCounter::new(text);
+
+    try_for_each_piece(text, blocks, options, |piece| {
+        if let (Some(block), Some(action)) = (&piece.block, piece.action) {
+            for block in iter::once(block).chain(&piece.merged) {
+                trace::debug!(
+                    language = block.language,
+                    span = ?block.span,
+                    action = ?action,
+                    "code block found"
+                );
+                if let Some(report) = report.as_deref_mut() {
+                    report.blocks.push(BlockReport {
+                        language: block.language.map(str::to_string),
+                        span: block.span.clone(),
+                        lines: lines.lines(&block.span),
+                        action: action.clone(),
+                        annotations: Annotations::default(),
+                    });
+                }
+                hooks.enter(block);
+            }
+        }
+        output.write_str(&piece.output)?;
+        if let (Some(block), Some(action)) = (&piece.block, piece.action) {
+            for block in iter::once(block).chain(&piece.merged) {
+                hooks.exit(block, action, &piece.output);
+            }
+        }
+        Ok(())
+    })?;
+
+    trace::debug!("strip finished");
+    Ok(())
+}
+
+/// A piece of the stripped text: a run of prose, or what a block was stripped to.
+pub(crate) struct Piece<'a> {
+    pub(crate) kind: SegmentKind,
+    /// Byte range of the piece in the original text.
+    pub(crate) span: Range<usize>,
+    pub(crate) output: Cow<'a, str>,
+    /// The action applied to the block, for code pieces.
+    pub(crate) action: Option<&'a Action>,
+    /// The block, for code pieces.
+    pub(crate) block: Option<CodeBlock<'a>>,
+    /// The blocks [merged](StripOptions::merge_adjacent) into `block`, if any.
+    pub(crate) merged: Vec<CodeBlock<'a>>,
+}
+
+/// Strips `text` according to `options`, calling `f` with each piece of the
+/// output in order. Empty prose is skipped.
+pub(crate) fn for_each_piece<'a>(
+    text: &'a str,
+    options: &'a StripOptions,
+    mut f: impl FnMut(Piece<'a>),
+) {
+    let blocks = codeblocks_for(text, options);
+    let Ok(()) = try_for_each_piece(text, blocks, options, |piece| {
+        f(piece);
+        Ok::<_, Infallible>(())
+    });
+}
+
+/// Strips `blocks` of `text` according to `options`, calling `f` with each
+/// piece of the output in order until it fails. Empty prose is skipped.
+pub(crate) fn try_for_each_piece<'a, E>(
+    text: &'a str,
+    blocks: impl Iterator<Item = CodeBlock<'a>>,
+    options: &'a StripOptions,
+    mut f: impl FnMut(Piece<'a>) -> Result<(), E>,
+) -> Result<(), E> {
+    if options.trailing_newline == TrailingNewline::Keep {
+        return try_for_each_raw_piece(text, blocks, options, f);
+    }
+
+    // The pieces since the last one with more than line breaks, held back
+    // until the end of the output is known to be in them
+    let mut held: Vec<Piece<'a>> = Vec::new();
+    try_for_each_raw_piece(text, blocks, options, |piece| {
+        if !piece.output.trim_end_matches(['\r', '\n']).is_empty() {
+            held.drain(..).try_for_each(&mut f)?;
+        }
+        held.push(piece);
+        Ok(())
+    })?;
+
+    match options.trailing_newline.wanted(text) {
+        Some(true) => {
+            let last = held.iter_mut().rev().find(|piece| !piece.output.is_empty());
+            if let Some(last) = last.filter(|last| !last.output.ends_with('\n')) {
+                let newline = options.newline.resolve(text);
+                last.output.to_mut().push_str(newline);
+            }
+        }
+        Some(false) => {
+            for piece in held.iter_mut().rev() {
+                piece.output = without_trailing_newlines(std::mem::take(&mut piece.output));
+                if !piece.output.is_empty() {
+                    break;
+                }
+            }
+        }
+        None => {}
+    }
+    held.into_iter().try_for_each(f)
+}
+
+/// Strips `blocks` of `text` like [`try_for_each_piece`], leaving the end
+/// of the output as it comes.
+fn try_for_each_raw_piece<'a, E>(
+    text: &'a str,
+    blocks: impl Iterator<Item = CodeBlock<'a>>,
+    options: &'a StripOptions,
+    f: impl FnMut(Piece<'a>) -> Result<(), E>,
+) -> Result<(), E> {
+    let newline = options.newline.resolve(text);
+    let regions = Regions::new(text, options);
+
+    #[cfg(feature = "parallel")]
+    if text.len() >= crate::parallel::MIN_LEN {
+        let blocks: Vec<_> = blocks.collect();
+        return crate::parallel::try_for_each_raw_piece(
+            text, &blocks, &regions, newline, options, f,
+        );
+    }
+    try_for_each_piece_in(text, 0..text.len(), blocks, &regions, newline, options, f)
+}
+
+/// Strips `blocks` of `text` like [`try_for_each_raw_piece`], calling `f`
+/// with the pieces of the output for the part of `text` in `bounds` only.
+///
+/// The part must start at the start of the text or of a block, and the
+/// blocks must be those starting in it.
+pub(crate) fn try_for_each_piece_in<'a, E>(
+    text: &'a str,
+    bounds: Range<usize>,
+    blocks: impl Iterator<Item = CodeBlock<'a>>,
+    regions: &Regions,
+    newline: &str,
+    options: &'a StripOptions,
+    mut f: impl FnMut(Piece<'a>) -> Result<(), E>,
+) -> Result<(), E> {
+    let mut blocks = blocks.peekable();
+    let mut last = bounds.start;
+
+    while let Some(block) = blocks.next() {
+        if block.span.start > last {
+            f(Piece {
+                kind: SegmentKind::Prose,
+                span: last..block.span.start,
+                output: math_output(
+                    prose_output(&text[last..block.span.start], options),
+                    options,
+                    newline,
+                ),
+                action: None,
+                block: None,
+                merged: Vec::new(),
+            })?;
+        }
+
+        let action = regions.action_for(&block, options);
+        let mut merged = Vec::new();
+        if options.merge_adjacent && *action != Action::Keep {
+            while let Some(next) =
+                blocks.next_if(|next| mergeable(text, merged.last().unwrap_or(&block), next))
+            {
+                merged.push(next);
+            }
+        }
+
+        let end = block_end(text, merged.last().unwrap_or(&block), action, options);
+        let output = if merged.is_empty() {
+            block_output(text, &block, action, options, newline)
+        } else {
+            merged_output(text, &block, &merged, action, options, newline)
+        };
+        f(Piece {
+            kind: SegmentKind::Code,
+            span: block.span.start..end,
+            output,
+            action: Some(action),
+            block: Some(block),
+            merged,
+        })?;
+        last = end;
+    }
+
+    if last < bounds.end {
+        f(Piece {
+            kind: SegmentKind::Prose,
+            span: last..bounds.end,
+            output: math_output(
+                prose_output(&text[last..bounds.end], options),
+                options,
+                newline,
+            ),
+            action: None,
+            block: None,
+            merged: Vec::new(),
+        })?;
+    }
+    Ok(())
+}
+
+/// Returns the text `prose` is copied as, which is only changed by [`Ansi::Strip`].
+pub(crate) fn prose_output<'a>(prose: &'a str, options: &StripOptions) -> Cow<'a, str> {
+    if options.ansi == Ansi::Strip && !options.preserve_whitespace {
+        ansi::strip_ansi(prose)
+    } else {
+        Cow::Borrowed(prose)
+    }
+}
+
+/// Returns where the text following `block` resumes once `action` is applied to it.
+///
+/// This is the end of the block, or in
+/// [whitespace-preserving](StripOptions::preserve_whitespace) mode the end of
+/// its closing fence line when the block's lines are dropped.
+pub(crate) fn block_end(
+    text: &str,
+    block: &CodeBlock<'_>,
+    action: &Action,
+    options: &StripOptions,
+) -> usize {
+    let dropped = matches!(
+        action,
+        Action::Unwrap | Action::Remove | Action::FirstLine(_)
+    );
+    if options.preserve_whitespace && dropped {
+        block.span.end + closing_line_break(text, block)
+    } else {
+        block.span.end
+    }
+}
+
+/// Returns the length of the line break ending the closing fence line of
+/// `block`, or 0 if the closing fence doesn't sit on a line of its own.
+pub(crate) fn closing_line_break(text: &str, block: &CodeBlock<'_>) -> usize {
+    let closed = block.content_span.end < block.span.end;
+    let own_line = text[..block.content_span.end].ends_with('\n');
+    if !closed || !own_line {
+        return 0;
+    }
+
+    let rest = &text[block.span.end..];
+    if rest.starts_with("\r\n") {
+        2
+    } else if rest.starts_with('\n') {
+        1
+    } else {
+        0
+    }
+}
+
+/// Returns the text `block` of `text` is replaced with when `action` is
+/// applied to it, inserting line breaks as `newline`.
+pub(crate) fn block_output<'a>(
+    text: &'a str,
+    block: &CodeBlock<'a>,
+    action: &'a Action,
+    options: &StripOptions,
+    newline: &str,
+) -> Cow<'a, str> {
+    match action {
+        Action::Unwrap => shrunk(
+            unwrapped_content(text, block, options, false),
+            options,
+            newline,
+        ),
+        Action::Keep => prose_output(&text[block.span.clone()], options),
+        Action::Remove => Cow::Borrowed(""),
+        Action::Placeholder(placeholder) => {
+            placeholder_output(placeholder, block.content, options, newline)
+        }
+        Action::FirstLine(marker) => first_line(
+            unwrapped_content(text, block, options, false),
+            marker,
+            newline,
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use crate::{Action, StripOptions, strip_codeblocks_with, strip_codeblocks_with_report};
+
+    #[test]
+    fn test_remove_action() {
+        let options = StripOptions::new().action(Action::Remove);
+        assert_eq!(
+            strip_codeblocks_with("a\n```\nb\n```\nc", &options),
+            "a\n\nc"
+        );
+    }
+
+    #[test]
+    fn test_first_line_action() {
+        let options = StripOptions::new()
+            .action(Action::FirstLine("[...]".into()))
+            .strip_prompts(true);
+        let input = "```sh\n$ make\ncc -o foo foo.c\n```\nDone";
+        assert_eq!(
+            strip_codeblocks_with(input, &options),
+            "make\n[...]\n\nDone"
+        );
+        assert_eq!(
+            strip_codeblocks_with(input, &options.preserve_whitespace(true)),
+            "$ make\n[...]\nDone"
+        );
+    }
+
+    #[test]
+    fn test_language_filter_skips_untagged_blocks() {
+        let options = StripOptions::new().languages(["python"]);
+        let input = "```\nplain\n```\n```python\nprint(1)\n```";
+        assert_eq!(
+            strip_codeblocks_with(input, &options),
+            "```\nplain\n```\nprint(1)\n"
+        );
+    }
+
+    #[test]
+    fn test_scope_goes_by_opening_fence() {
+        let input = "```\na\n```\n```\nb\n```\n```\nc\n```";
+        let options = StripOptions::new().scope(4..=12);
+        assert_eq!(
+            strip_codeblocks_with(input, &options),
+            "```\na\n```\nb\n\n```\nc\n```"
+        );
+        let options = StripOptions::new().scope(..4);
+        assert_eq!(
+            strip_codeblocks_with(input, &options),
+            "a\n\n```\nb\n```\n```\nc\n```"
+        );
+        assert!(matches!(
+            crate::strip_codeblocks_cow(input, &StripOptions::new().scope(input.len()..)),
+            Cow::Borrowed(_)
+        ));
+    }
+
+    #[test]
+    fn test_cow_borrows_when_nothing_changes() {
+        let options = StripOptions::new().languages(["python"]);
+        for input in ["", "no blocks", "```rust\na\n```\n```\nb\n```"] {
+            assert!(matches!(
+                crate::strip_codeblocks_cow(input, &options),
+                Cow::Borrowed(text) if text == input
+            ));
+        }
+    }
+
+    #[test]
+    fn test_cow_matches_strip() {
+        let options = StripOptions::new().languages(["python"]);
+        let input = "```rust\na\n```\n```python\nb\n```\n```rust\nc\n```";
+        assert_eq!(
+            crate::strip_codeblocks_cow(input, &options),
+            strip_codeblocks_with(input, &options)
+        );
+    }
+
+    #[test]
+    fn test_writer_errors_are_propagated() {
+        struct Full;
+        impl std::fmt::Write for Full {
+            fn write_str(&mut self, _: &str) -> std::fmt::Result {
+                Err(std::fmt::Error)
+            }
+        }
+
+        let result =
+            crate::strip_codeblocks_to_writer("```\na\n```", &StripOptions::new(), &mut Full);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_report_records_actions() {
+        let options = StripOptions::new().languages(["rust"]);
+        let input = "```rust\nfn a() {}\n```\n```toml\n[a]\n```";
+
+        let (output, report) = strip_codeblocks_with_report(input, &options);
+
+        assert_eq!(output, "fn a() {}\n\n```toml\n[a]\n```");
+        assert_eq!(report.blocks.len(), 2);
+        assert_eq!(report.blocks[0].language.as_deref(), Some("rust"));
+        assert_eq!(report.blocks[0].action, Action::Unwrap);
+        assert_eq!(report.blocks[1].span, 22..37);
+        assert_eq!(report.blocks[1].lines, 4..=6);
+        assert_eq!(report.blocks[1].action, Action::Keep);
+        assert_eq!(report.changed().count(), 1);
+    }
+
+    #[test]
+    fn test_fences_at_document_edges() {
+        use crate::TrailingNewline;
+        use crate::experimental::Engine;
+
+        // (input, default output, output ending with a line break, output without one)
+        let cases = [
+            ("```\nx\n```", "x\n", "x\n", "x"),
+            ("```\nx\n```\n", "x\n\n", "x\n\n", "x"),
+            ("```sh\r\nx\r\n```\r\n", "x\r\n\r\n", "x\r\n\r\n", "x"),
+            ("a\n```\nx```", "a\nx", "a\nx\n", "a\nx"),
+            ("```\n```", "", "", ""),
+        ];
+        for engine in [Engine::Regex, Engine::Parser] {
+            let options = StripOptions::new().engine(engine);
+            for (input, default, always, never) in cases {
+                if engine == Engine::Parser && input.ends_with("x```") {
+                    // The parser only closes fences on lines of their own
+                    continue;
+                }
+                let with = |trailing| {
+                    strip_codeblocks_with(input, &options.clone().trailing_newline(trailing))
+                };
+                assert_eq!(strip_codeblocks_with(input, &options), default, "{input:?}");
+                assert_eq!(with(TrailingNewline::Always), always, "{input:?}");
+                assert_eq!(with(TrailingNewline::Never), never, "{input:?}");
+                let matching = if input.ends_with('\n') { always } else { never };
+                assert_eq!(with(TrailingNewline::MatchInput), matching, "{input:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_trailing_newline_after_removed_blocks() {
+        use crate::TrailingNewline;
+
+        let options = StripOptions::new()
+            .action(Action::Remove)
+            .trailing_newline(TrailingNewline::MatchInput);
+        let (output, report) = strip_codeblocks_with_report("a\r\n```\nx\n```", &options);
+        assert_eq!(output, "a");
+        assert_eq!(report.blocks.len(), 1);
+        assert_eq!(
+            crate::strip_codeblocks_cow("a\n", &options),
+            Cow::Borrowed("a\n")
+        );
+        assert_eq!(
+            crate::strip_codeblocks_cow("a", &options.trailing_newline(TrailingNewline::Always)),
+            "a\n"
+        );
+    }
+}
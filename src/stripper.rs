@@ -0,0 +1,406 @@
+//! Stripping many documents with the same options through a [`Stripper`].
+
+use std::borrow::Cow;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+use crate::newline::with_newlines;
+use crate::replacements::Replacements;
+use crate::{
+    Action, CodeBlock, LanguageMatch, Newline, Report, StripError, StripOptions, strict, strip,
+};
+
+type EnterHook = Arc<dyn Fn(&CodeBlock<'_>) + Send + Sync>;
+
+type ExitHook = Arc<dyn Fn(&CodeBlock<'_>, &Action, &str) + Send + Sync>;
+
+/// The hooks of a [`Stripper`], called around each block it strips.
+#[derive(Clone, Default)]
+pub(crate) struct Hooks {
+    enter: Vec<EnterHook>,
+    exit: Vec<ExitHook>,
+}
+
+impl Hooks {
+    /// Calls the enter hooks with `block`, before its output is written.
+    pub(crate) fn enter(&self, block: &CodeBlock<'_>) {
+        self.enter.iter().for_each(|hook| hook(block));
+    }
+
+    /// Calls the exit hooks with `block`, its action and what it was
+    /// stripped to, once that is written.
+    pub(crate) fn exit(&self, block: &CodeBlock<'_>, action: &Action, output: &str) {
+        self.exit
+            .iter()
+            .for_each(|hook| hook(block, action, output));
+    }
+
+    fn is_empty(&self) -> bool {
+        self.enter.is_empty() && self.exit.is_empty()
+    }
+}
+
+/// Hooks are equal when they are the same closures, shared by clones.
+impl PartialEq for Hooks {
+    fn eq(&self, other: &Self) -> bool {
+        self.enter.len() == other.enter.len()
+            && self.exit.len() == other.exit.len()
+            && same_closures(&self.enter, &other.enter)
+            && same_closures(&self.exit, &other.exit)
+    }
+}
+
+/// Returns whether `a` and `b` hold the same closures.
+fn same_closures<T: ?Sized>(a: &[Arc<T>], b: &[Arc<T>]) -> bool {
+    a.iter().zip(b).all(|(a, b)| Arc::ptr_eq(a, b))
+}
+
+impl Eq for Hooks {}
+
+impl Hash for Hooks {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.enter.len().hash(state);
+        self.exit.len().hash(state);
+    }
+}
+
+impl fmt::Debug for Hooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Hooks")
+            .field("enter", &self.enter.len())
+            .field("exit", &self.exit.len())
+            .finish()
+    }
+}
+
+/// Compiled [`StripOptions`], created with [`StripOptions::build`].
+///
+/// Everything that doesn't depend on the document is resolved once, when the
+/// stripper is built: the [language filter](StripOptions::languages)
+/// becomes a lookup table, or has its [regexes](crate::LanguageMatch::regex)
+/// compiled, and
+/// the line breaks of placeholders are converted to a fixed
+/// [`newline`](StripOptions::newline). A stripper built at startup then
+/// strips any number of documents, from any number of threads, without
+/// per-call setup.
+///
+/// Each method behaves like the function taking the same options, except
+/// that [reports](Self::strip_with_report) show placeholders with their line
+/// breaks converted.
+///
+/// Strippers are `Send + Sync`, and cheap to clone: clones share the
+/// compiled options, so a server can build one at startup and hand a clone
+/// to each worker or request handler, or keep it in an `Arc` or a `static`.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::{Action, Newline, StripOptions};
+///
+/// let stripper = StripOptions::new()
+///     .action(Action::Placeholder("[code]\n".into()))
+///     .newline(Newline::CrLf)
+///     .build();
+///
+/// for document in ["```sh\nls\n```", "Intro\n```py\npass\n```\n"] {
+///     assert!(stripper.strip(document).contains("[code]\r\n"));
+/// }
+/// ```
+///
+/// Sharing a stripper between threads:
+///
+/// ```
+/// use std::thread;
+///
+/// use strip_codeblocks::{StripOptions, Stripper};
+///
+/// let stripper = Stripper::from(StripOptions::new().languages(["sh"]));
+///
+/// let handles: Vec<_> = ["```sh\nls\n```", "```sh\npwd\n```"]
+///     .into_iter()
+///     .map(|document| {
+///         let stripper = stripper.clone();
+///         thread::spawn(move || stripper.strip(document))
+///     })
+///     .collect();
+/// let outputs: Vec<_> = handles.into_iter().map(|handle| handle.join().unwrap()).collect();
+/// assert_eq!(outputs, ["ls\n", "pwd\n"]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[must_use]
+pub struct Stripper {
+    options: Arc<StripOptions>,
+    hooks: Hooks,
+}
+
+impl Stripper {
+    /// Compiles `options`.
+    pub(crate) fn new(mut options: StripOptions) -> Self {
+        // Only equal languages can be looked up; regexes are compiled now rather than for the first block
+        if options.replacements.is_none()
+            && options.language_match == LanguageMatch::new()
+            && let Some(languages) = options.languages.take()
+        {
+            options.replacements = Some(Replacements::for_languages(&languages, &options.action));
+        }
+        options.compiled_regexes();
+
+        if options.newline != Newline::Auto {
+            let newline = options.newline.resolve("");
+            let actions = std::iter::once(&mut options.action).chain(
+                options
+                    .replacements
+                    .iter_mut()
+                    .flat_map(Replacements::actions_mut),
+            );
+            for action in actions {
+                if let Action::Placeholder(text) | Action::FirstLine(text) = action
+                    && let Cow::Owned(converted) = with_newlines(text, newline)
+                {
+                    *text = converted;
+                }
+            }
+        }
+
+        Self {
+            options: Arc::new(options),
+            hooks: Hooks::default(),
+        }
+    }
+
+    /// Calls `hook` with each block found, before what it is stripped to is
+    /// written, for side effects such as logging or counting blocks.
+    ///
+    /// Hooks observe stripping without changing its output. They are called
+    /// by every method, in document order, with every block found, whatever
+    /// its action, and blocks [merged](StripOptions::merge_adjacent) into
+    /// one are entered together. Several hooks are called in the order they
+    /// were added. Clones of the stripper share its hooks, so hooks keeping
+    /// state use atomics or locks, as the stripper can be used from several
+    /// threads at once.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    ///
+    /// use strip_codeblocks::StripOptions;
+    ///
+    /// let blocks = Arc::new(AtomicUsize::new(0));
+    /// let counter = Arc::clone(&blocks);
+    /// let stripper = StripOptions::new()
+    ///     .languages(["sh"])
+    ///     .build()
+    ///     .on_block_enter(move |_| {
+    ///         counter.fetch_add(1, Ordering::Relaxed);
+    ///     });
+    ///
+    /// assert_eq!(stripper.strip("```sh\nls\n```\n```py\npass\n```"), "ls\n\n```py\npass\n```");
+    /// assert_eq!(blocks.load(Ordering::Relaxed), 2);
+    /// ```
+    pub fn on_block_enter<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&CodeBlock<'_>) + Send + Sync + 'static,
+    {
+        self.hooks.enter.push(Arc::new(hook));
+        self
+    }
+
+    /// Calls `hook` with each block found, its action and what it was
+    /// stripped to, once that is written, for side effects such as writing
+    /// extracted blocks to files.
+    ///
+    /// Exit hooks are called like [enter hooks](Self::on_block_enter), after
+    /// them. Blocks merged into one are each given the output of the merged
+    /// block. Hooks needing more than this, or changing the output, are
+    /// better written as a [`Pipeline`](crate::Pipeline).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// use strip_codeblocks::{Action, StripOptions};
+    ///
+    /// let log = Arc::new(Mutex::new(Vec::new()));
+    /// let sink = Arc::clone(&log);
+    /// let stripper = StripOptions::new()
+    ///     .action(Action::Placeholder("[code]".into()))
+    ///     .build()
+    ///     .on_block_exit(move |block, _, output| {
+    ///         let language = block.language.unwrap_or("text");
+    ///         sink.lock().unwrap().push(format!("{language}: {output}"));
+    ///     });
+    ///
+    /// assert_eq!(stripper.strip("Run:\n```sh\nls\n```"), "Run:\n[code]");
+    /// assert_eq!(*log.lock().unwrap(), ["sh: [code]"]);
+    /// ```
+    pub fn on_block_exit<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&CodeBlock<'_>, &Action, &str) + Send + Sync + 'static,
+    {
+        self.hooks.exit.push(Arc::new(hook));
+        self
+    }
+
+    /// Strips `text` like [`strip_codeblocks_with`](crate::strip_codeblocks_with).
+    #[must_use]
+    pub fn strip(&self, text: &str) -> String {
+        let mut output = String::with_capacity(text.len());
+        self.strip_to_writer(text, &mut output)
+            .expect("writing to a String never fails");
+        output
+    }
+
+    /// Strips `text` like [`try_strip_codeblocks_with`](crate::try_strip_codeblocks_with).
+    pub fn try_strip(&self, text: &str) -> Result<String, StripError> {
+        if self.options.strict {
+            strict::check(text, &self.options)?;
+        }
+        Ok(self.strip(text))
+    }
+
+    /// Strips `text` like [`strip_codeblocks_cow`](crate::strip_codeblocks_cow).
+    #[must_use]
+    pub fn strip_cow<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        if self.hooks.is_empty() {
+            return strip::strip_cow(text, &self.options);
+        }
+        // Blocks that are kept are still found, for the hooks to be called
+        let output = self.strip(text);
+        if output == text {
+            Cow::Borrowed(text)
+        } else {
+            Cow::Owned(output)
+        }
+    }
+
+    /// Strips `text` into `writer` like
+    /// [`strip_codeblocks_to_writer`](crate::strip_codeblocks_to_writer).
+    pub fn strip_to_writer<W: fmt::Write + ?Sized>(
+        &self,
+        text: &str,
+        writer: &mut W,
+    ) -> fmt::Result {
+        strip::strip_hooked_to(text, &self.options, None, &self.hooks, writer)
+    }
+
+    /// Strips `text` like [`strip_codeblocks_with_report`](crate::strip_codeblocks_with_report).
+    #[must_use]
+    pub fn strip_with_report(&self, text: &str) -> (String, Report) {
+        let mut report = Report::default();
+        let mut output = String::with_capacity(text.len());
+        strip::strip_hooked_to(
+            text,
+            &self.options,
+            Some(&mut report),
+            &self.hooks,
+            &mut output,
+        )
+        .expect("writing to a String never fails");
+        (output, report)
+    }
+}
+
+/// Compiles options like [`StripOptions::build`].
+impl From<StripOptions> for Stripper {
+    fn from(options: StripOptions) -> Self {
+        Self::new(options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ReplacementMap, strip_codeblocks_with, strip_codeblocks_with_report};
+
+    const INPUT: &str = "a\n```rust\nfn a() {}\n```\n```sh\nls\n```\n```\nplain\n```\nb";
+
+    #[test]
+    fn test_stripper_matches_options() {
+        for options in [
+            StripOptions::new(),
+            StripOptions::new().languages(["rust", "sh"]),
+            StripOptions::new()
+                .languages(["sh"])
+                .action(Action::Placeholder("x\ny".into())),
+            StripOptions::new()
+                .action(Action::FirstLine("…\n".into()))
+                .newline(Newline::CrLf),
+            ReplacementMap::new().language("sh", Action::Remove).into(),
+        ] {
+            let stripper = options.clone().build();
+            assert_eq!(
+                stripper.strip(INPUT),
+                strip_codeblocks_with(INPUT, &options)
+            );
+            let (output, report) = stripper.strip_with_report(INPUT);
+            let (expected_output, expected_report) = strip_codeblocks_with_report(INPUT, &options);
+            assert_eq!(output, expected_output);
+            assert_eq!(report.blocks.len(), expected_report.blocks.len());
+        }
+    }
+
+    #[test]
+    fn test_placeholders_are_converted_once() {
+        let stripper = StripOptions::new()
+            .action(Action::Placeholder("[code]\r\n".into()))
+            .newline(Newline::Lf)
+            .build();
+        assert_eq!(
+            stripper.options.action,
+            Action::Placeholder("[code]\n".into())
+        );
+        assert!(matches!(
+            stripper.strip_cow("no code"),
+            Cow::Borrowed("no code")
+        ));
+    }
+
+    #[test]
+    fn test_hooks_observe_every_method() {
+        use std::sync::Mutex;
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let (enter, exit) = (Arc::clone(&events), Arc::clone(&events));
+        let stripper = StripOptions::new()
+            .languages(["sh"])
+            .build()
+            .on_block_enter(move |block| {
+                enter
+                    .lock()
+                    .unwrap()
+                    .push(format!("enter {:?}", block.language));
+            })
+            .on_block_exit(move |block, action, output| {
+                let event = format!("exit {:?} {action:?} {output:?}", block.language);
+                exit.lock().unwrap().push(event);
+            });
+        assert_ne!(stripper, StripOptions::new().languages(["sh"]).build());
+        assert_eq!(stripper, stripper.clone());
+
+        let text = "```py\npass\n```\n```sh\nls\n```";
+        assert_eq!(stripper.strip_cow("```py\npass\n```"), "```py\npass\n```");
+        assert_eq!(stripper.strip_with_report(text).0, "```py\npass\n```\nls\n");
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                "enter Some(\"py\")",
+                "exit Some(\"py\") Keep \"```py\\npass\\n```\"",
+                "enter Some(\"py\")",
+                "exit Some(\"py\") Keep \"```py\\npass\\n```\"",
+                "enter Some(\"sh\")",
+                "exit Some(\"sh\") Unwrap \"ls\\n\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_strict_stripper() {
+        let stripper = StripOptions::new().strict(true).build();
+        assert!(stripper.try_strip("```\nunclosed").is_err());
+        assert_eq!(stripper.try_strip("```\nls\n```").unwrap(), "ls\n");
+    }
+}
@@ -0,0 +1,127 @@
+//! Detection of GFM table rows, whose cells may contain fence-like backtick runs.
+
+/// Finds the rows of GitHub Flavored Markdown tables, for fences at
+/// increasing offsets of a text.
+///
+/// A table is a header row, a delimiter row like `| --- | :-: |`, and the rows
+/// following them, all of them lines containing a pipe. The delimiter row
+/// itself counts as a row. The lines before the last fence looked at are
+/// only read once, so that finding every fence of a text stays linear.
+#[derive(Debug, Default)]
+pub(crate) struct TableRows {
+    /// Where the lines not read yet start.
+    scanned: usize,
+    /// How many rows the run of lines containing a pipe ending at `scanned` has.
+    run: usize,
+    /// Whether that run has a delimiter row following a header row.
+    delimited: bool,
+}
+
+impl TableRows {
+    /// Returns whether the line containing the byte at `offset` in `text` is
+    /// a table row.
+    ///
+    /// Offsets are expected in increasing order; an earlier one makes the
+    /// text be read again from the start.
+    pub(crate) fn contains(&mut self, text: &str, offset: usize) -> bool {
+        let start = text[..offset].rfind('\n').map_or(0, |newline| newline + 1);
+        let end = text[offset..]
+            .find('\n')
+            .map_or(text.len(), |newline| offset + newline);
+        let line = &text[start..end];
+        if !is_row_candidate(line) {
+            return false;
+        }
+
+        // A header row is directly followed by the delimiter row
+        let next = text[end..]
+            .strip_prefix('\n')
+            .and_then(|rest| rest.lines().next());
+        if is_delimiter_row(line)
+            || next.is_some_and(|next| is_row_candidate(next) && is_delimiter_row(next))
+        {
+            return true;
+        }
+
+        // Any other row follows a delimiter row and its header, without interruption
+        if start < self.scanned {
+            *self = Self::default();
+        }
+        for line in text[self.scanned..start].lines() {
+            if is_row_candidate(line) {
+                self.delimited |= self.run > 0 && is_delimiter_row(line);
+                self.run += 1;
+            } else {
+                (self.run, self.delimited) = (0, false);
+            }
+        }
+        self.scanned = start;
+        self.delimited
+    }
+}
+
+/// Returns whether `line` could be a table row: a non-blank line containing a pipe.
+fn is_row_candidate(line: &str) -> bool {
+    line.contains('|')
+}
+
+/// Returns whether `line` is a delimiter row, e.g. `|:---|---:|`.
+fn is_delimiter_row(line: &str) -> bool {
+    let line = line.trim();
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let line = line.strip_suffix('|').unwrap_or(line);
+
+    !line.trim().is_empty()
+        && line.split('|').all(|cell| {
+            let cell = cell.trim();
+            let cell = cell.strip_prefix(':').unwrap_or(cell);
+            let cell = cell.strip_suffix(':').unwrap_or(cell);
+            !cell.is_empty() && cell.bytes().all(|byte| byte == b'-')
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strip_codeblocks;
+
+    const TABLE: &str =
+        "| Fence | Opens with |\n|---|:-:|\n| backtick | ```js |\n| tilde | ~~~ |\n\nAfter";
+
+    #[test]
+    fn test_table_rows() {
+        let mut rows = TableRows::default();
+        for row in ["| Fence", "|---|", "| backtick", "| tilde"] {
+            assert!(rows.contains(TABLE, TABLE.find(row).unwrap()), "{row}");
+        }
+        assert!(!rows.contains(TABLE, TABLE.find("After").unwrap()));
+        // Looking back, the text is read again
+        assert!(rows.contains(TABLE, TABLE.find("| tilde").unwrap()));
+
+        let text = format!("{TABLE}\n| a | ```sh |\n");
+        assert!(!TableRows::default().contains(&text, text.find("| a").unwrap()));
+    }
+
+    #[test]
+    fn test_pipes_without_delimiter_row() {
+        let text = "a | b\nc | d ```js\n";
+        assert!(!TableRows::default().contains(text, text.find("```").unwrap()));
+    }
+
+    #[test]
+    fn test_delimiter_rows() {
+        assert!(is_delimiter_row("| --- | :-: | --: |"));
+        assert!(is_delimiter_row("---|---"));
+        assert!(!is_delimiter_row("| - x - |"));
+        assert!(!is_delimiter_row("||"));
+    }
+
+    #[test]
+    fn test_fences_in_table_cells_are_prose() {
+        let input = format!("{TABLE}\n```rust\nfn main() {{}}\n```\n");
+        assert_eq!(
+            strip_codeblocks(&input),
+            format!("{TABLE}\nfn main() {{}}\n\n")
+        );
+    }
+}
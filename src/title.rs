@@ -0,0 +1,228 @@
+//! Finding the heading or caption a code block is labelled with.
+
+use std::ops::Range;
+
+use crate::scan::codeblocks;
+
+/// Returns the text of `line` if it is an ATX heading, such as `## Install`.
+pub(crate) fn atx_heading(line: &str) -> Option<&str> {
+    let line = line.trim();
+    let rest = line.trim_start_matches('#');
+    let level = line.len() - rest.len();
+    if !(1..=6).contains(&level) || !(rest.is_empty() || rest.starts_with([' ', '\t'])) {
+        return None;
+    }
+
+    // A closing sequence of `#` must be preceded by a space
+    let rest = rest.trim();
+    let unclosed = rest.trim_end_matches('#');
+    let title = if unclosed.is_empty() || unclosed.ends_with([' ', '\t']) {
+        unclosed.trim_end()
+    } else {
+        rest
+    };
+    Some(title).filter(|title| !title.is_empty())
+}
+
+/// Returns whether `line` underlines the line before it into a setext heading.
+pub(crate) fn setext_underline(line: &str) -> bool {
+    let line = line.trim();
+    !line.is_empty() && (line.bytes().all(|b| b == b'=') || line.bytes().all(|b| b == b'-'))
+}
+
+/// Returns the text of `line` if it is a caption written in bold, such as
+/// `**Example:**` or `__app.py__`.
+fn bold_caption(line: &str) -> Option<&str> {
+    let line = line.trim();
+    let line = line.strip_suffix(':').unwrap_or(line);
+    let inner = ["**", "__"].into_iter().find_map(|marker| {
+        line.strip_prefix(marker)?
+            .strip_suffix(marker)
+            .filter(|inner| !inner.contains(marker))
+    })?;
+    let inner = inner.trim();
+    let inner = inner.strip_suffix(':').unwrap_or(inner).trim_end();
+    Some(inner).filter(|inner| !inner.is_empty())
+}
+
+/// Returns the lines of `text` before `end`, last first, with their ranges.
+pub(crate) fn lines_before(text: &str, end: usize) -> impl Iterator<Item = (Range<usize>, &str)> {
+    let mut end = text[..end].rfind('\n').map_or(0, |newline| newline + 1);
+    std::iter::from_fn(move || {
+        if end == 0 {
+            return None;
+        }
+        let line_end = end - 1;
+        let start = text[..line_end]
+            .rfind('\n')
+            .map_or(0, |newline| newline + 1);
+        end = start;
+        Some((start..line_end, &text[start..line_end]))
+    })
+}
+
+/// The spans of the code blocks starting before an offset of a text, to skip
+/// the lines of [`lines_before`] that are in one.
+pub(crate) struct BlocksBefore {
+    /// The spans not behind the lines looked at yet, in document order.
+    spans: Vec<Range<usize>>,
+}
+
+impl BlocksBefore {
+    pub(crate) fn new(text: &str, end: usize) -> Self {
+        Self {
+            spans: codeblocks(text)
+                .take_while(|block| block.span.start < end)
+                .map(|block| block.span)
+                .collect(),
+        }
+    }
+
+    /// Returns whether the line starting at `start`, before those given
+    /// earlier, is in a block.
+    pub(crate) fn contain(&mut self, start: usize) -> bool {
+        while self.spans.last().is_some_and(|span| span.start > start) {
+            self.spans.pop();
+        }
+        self.spans.last().is_some_and(|span| start < span.end)
+    }
+}
+
+/// Returns the title of the block of `text` starting at `start`: a bold
+/// caption line right before it, or else the nearest heading before it.
+///
+/// Lines within other code blocks are skipped, so that a `# comment` in a
+/// shell block isn't taken for a heading. This reads `text` up to `start`;
+/// use [`Titles`] for the titles of many blocks.
+pub(crate) fn preceding_title(text: &str, start: usize) -> Option<&str> {
+    Titles::new(text).before(start)
+}
+
+/// Finds the titles of blocks like [`preceding_title`], in one pass over the
+/// text for blocks given in document order.
+pub(crate) struct Titles<'a> {
+    text: &'a str,
+    /// The spans of the code blocks of the text, in document order.
+    spans: Vec<Range<usize>>,
+    /// The first span not ending before the lines looked at yet.
+    span: usize,
+    /// The start of the first line not looked at yet.
+    offset: usize,
+    /// The last line looked at, unless it was in a block.
+    previous: Option<&'a str>,
+    /// The nearest heading so far.
+    heading: Option<&'a str>,
+    /// The bold caption of the last line that wasn't blank, if it is one and
+    /// only blank lines outside blocks followed.
+    caption: Option<&'a str>,
+}
+
+impl<'a> Titles<'a> {
+    pub(crate) fn new(text: &'a str) -> Self {
+        Self {
+            text,
+            spans: codeblocks(text).map(|block| block.span).collect(),
+            span: 0,
+            offset: 0,
+            previous: None,
+            heading: None,
+            caption: None,
+        }
+    }
+
+    /// Returns the title of the block starting at `start`, from the lines
+    /// before the one it starts on.
+    ///
+    /// Blocks are meant to be given in document order; going back starts
+    /// over from the start of the text.
+    pub(crate) fn before(&mut self, start: usize) -> Option<&'a str> {
+        let text = self.text;
+        let line_start = text[..start].rfind('\n').map_or(0, |newline| newline + 1);
+        if line_start < self.offset {
+            self.span = 0;
+            self.offset = 0;
+            self.previous = None;
+            self.heading = None;
+            self.caption = None;
+        }
+        while self.offset < line_start {
+            // Lines before the start of another end with a line break
+            let end = text[self.offset..line_start]
+                .find('\n')
+                .map_or(line_start, |newline| self.offset + newline);
+            self.push_line(self.offset, &text[self.offset..end]);
+            self.offset = end + 1;
+        }
+        self.caption.or(self.heading)
+    }
+
+    /// Looks at the line starting at `start`.
+    fn push_line(&mut self, start: usize, line: &'a str) {
+        while self
+            .spans
+            .get(self.span)
+            .is_some_and(|span| span.end <= start)
+        {
+            self.span += 1;
+        }
+        if self
+            .spans
+            .get(self.span)
+            .is_some_and(|span| span.start <= start)
+        {
+            self.previous = None;
+            self.caption = None;
+            return;
+        }
+
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        if let Some(heading) = atx_heading(line) {
+            self.heading = Some(heading);
+            self.caption = None;
+        } else if !line.trim().is_empty() {
+            self.caption = bold_caption(line);
+        }
+        if setext_underline(line)
+            && let Some(previous) = self.previous.filter(|previous| !previous.trim().is_empty())
+        {
+            self.heading = Some(previous.trim());
+        }
+        self.previous = Some(line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_headings() {
+        assert_eq!(atx_heading("## Install ##"), Some("Install"));
+        assert_eq!(atx_heading("# C#"), Some("C#"));
+        assert_eq!(atx_heading("#hashtag"), None);
+        assert_eq!(atx_heading("####### Too deep"), None);
+        assert_eq!(bold_caption("**Example:**"), Some("Example"));
+        assert_eq!(bold_caption("__app.py__:"), Some("app.py"));
+        assert_eq!(bold_caption("**a** and **b**"), None);
+    }
+
+    #[test]
+    fn test_nearest_title() {
+        let text = "# Guide\n\n**Setup:**\n\n```sh\nmake\n```\nThen:\n```sh\n# comment\n```\n```sh\nls\n```";
+        let starts: Vec<_> = codeblocks(text).map(|block| block.span.start).collect();
+
+        assert_eq!(preceding_title(text, starts[0]), Some("Setup"));
+        assert_eq!(preceding_title(text, starts[1]), Some("Guide"));
+        assert_eq!(preceding_title(text, starts[2]), Some("Guide"));
+        assert_eq!(preceding_title("```\nx\n```", 0), None);
+    }
+
+    #[test]
+    fn test_setext_heading() {
+        let text = "Usage\r\n=====\r\n\r\nRun it: ```sh\nrun\n```";
+        assert_eq!(
+            preceding_title(text, text.find("```").unwrap()),
+            Some("Usage")
+        );
+    }
+}
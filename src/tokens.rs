@@ -0,0 +1,189 @@
+//! Counting the tokens of stripped text, for prompt budgeting.
+
+use std::fmt;
+use std::ops::Range;
+
+use crate::StripOptions;
+use crate::strip::for_each_piece;
+
+/// Whether a [`SegmentTokens`] counts prose or a code block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum SegmentKind {
+    /// Text outside of fenced code blocks.
+    Prose,
+    /// A fenced code block.
+    Code,
+}
+
+impl fmt::Display for SegmentKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SegmentKind::Prose => "prose",
+            SegmentKind::Code => "code",
+        })
+    }
+}
+
+/// The number of tokens a single segment of a document contributes to the stripped text.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct SegmentTokens {
+    /// Whether the segment is prose or a code block.
+    pub kind: SegmentKind,
+    /// Byte range of the segment, fences included, in the original document.
+    ///
+    /// In [whitespace-preserving](StripOptions::preserve_whitespace) mode, the
+    /// range of a dropped block also covers the line break after its closing fence.
+    pub span: Range<usize>,
+    /// Tokens in the text the segment was stripped to.
+    pub tokens: usize,
+}
+
+/// Token counts of a stripped document, per segment.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct TokenCounts {
+    /// One entry per segment, in document order. Empty prose segments are omitted.
+    pub segments: Vec<SegmentTokens>,
+}
+
+impl TokenCounts {
+    /// Returns the tokens of the whole stripped text.
+    pub fn total(&self) -> usize {
+        self.segments.iter().map(|segment| segment.tokens).sum()
+    }
+
+    /// Returns the tokens of the prose segments.
+    pub fn prose(&self) -> usize {
+        self.sum(SegmentKind::Prose)
+    }
+
+    /// Returns the tokens of the code blocks, as stripped.
+    pub fn code(&self) -> usize {
+        self.sum(SegmentKind::Code)
+    }
+
+    fn sum(&self, kind: SegmentKind) -> usize {
+        self.segments
+            .iter()
+            .filter(|segment| segment.kind == kind)
+            .map(|segment| segment.tokens)
+            .sum()
+    }
+}
+
+/// Strips fenced code blocks like [`strip_codeblocks_with`](crate::strip_codeblocks_with),
+/// counting the tokens of each segment of the output on the way.
+///
+/// `tokenizer` is called once per non-empty piece of the stripped text: each
+/// run of prose, and the text each code block was stripped to (its content,
+/// placeholder, or the block itself when kept). Blocks that were removed count
+/// for zero tokens without calling it. Any tokenizer can be plugged in, such as
+/// a BPE encoder's `encode(..).len()`.
+///
+/// Tokenizers usually don't count a text split in two as the sum of its
+/// halves, so [`TokenCounts::total`] can differ slightly from counting the
+/// whole output at once.
+///
+/// # Arguments
+///
+/// * `text` - The markdown text containing code blocks to strip
+/// * `options` - Which blocks are stripped and how
+/// * `tokenizer` - Returns the number of tokens in a piece of text
+///
+/// # Returns
+///
+/// The stripped text, and the [`TokenCounts`] of its segments.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::{StripOptions, strip_and_count_tokens};
+///
+/// let words = |text: &str| text.split_whitespace().count();
+/// let input = "Run this:\n```sh\ncargo build --release\n```\nThen wait.";
+///
+/// let (output, counts) = strip_and_count_tokens(input, &StripOptions::new(), words);
+/// assert_eq!(output, "Run this:\ncargo build --release\n\nThen wait.");
+/// assert_eq!(counts.prose(), 4);
+/// assert_eq!(counts.code(), 3);
+/// assert_eq!(counts.segments.len(), 3);
+/// ```
+pub fn strip_and_count_tokens(
+    text: &str,
+    options: &StripOptions,
+    mut tokenizer: impl FnMut(&str) -> usize,
+) -> (String, TokenCounts) {
+    let mut output = String::with_capacity(text.len());
+    let mut counts = TokenCounts::default();
+    let mut count = |piece: &str| {
+        if piece.is_empty() {
+            0
+        } else {
+            tokenizer(piece)
+        }
+    };
+    for_each_piece(text, options, |piece| {
+        output.push_str(&piece.output);
+        counts.segments.push(SegmentTokens {
+            kind: piece.kind,
+            span: piece.span,
+            tokens: count(&piece.output),
+        });
+    });
+    (output, counts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Action, strip_codeblocks_with};
+
+    fn chars(text: &str) -> usize {
+        text.chars().count()
+    }
+
+    #[test]
+    fn test_output_matches_strip() {
+        let input = "a\n```rust\nfn a() {}\n```\n```toml\n[a]\n```\nb";
+        for options in [
+            StripOptions::new(),
+            StripOptions::new().languages(["rust"]),
+            StripOptions::new().action(Action::Placeholder("[code]".into())),
+        ] {
+            let (output, counts) = strip_and_count_tokens(input, &options, chars);
+            assert_eq!(output, strip_codeblocks_with(input, &options));
+            assert_eq!(counts.total(), chars(&output));
+        }
+    }
+
+    #[test]
+    fn test_removed_blocks_are_not_tokenized() {
+        let options = StripOptions::new().action(Action::Remove);
+        let mut calls = Vec::new();
+        let (_, counts) = strip_and_count_tokens("x\n```\ncode\n```", &options, |piece| {
+            calls.push(piece.to_string());
+            1
+        });
+
+        assert_eq!(calls, ["x\n"]);
+        assert_eq!(counts.segments[1].kind, SegmentKind::Code);
+        assert_eq!(counts.segments[1].span, 2..14);
+        assert_eq!(counts.segments[1].tokens, 0);
+    }
+
+    #[test]
+    fn test_adjacent_blocks_have_no_prose() {
+        let (_, counts) =
+            strip_and_count_tokens("```\na\n``````\nb\n```", &StripOptions::new(), chars);
+        assert!(
+            counts
+                .segments
+                .iter()
+                .all(|segment| segment.kind == SegmentKind::Code)
+        );
+        assert_eq!(counts.prose(), 0);
+        assert_eq!(counts.code(), 4);
+    }
+}
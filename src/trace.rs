@@ -0,0 +1,30 @@
+//! Opt-in [`tracing`](https://docs.rs/tracing) instrumentation.
+//!
+//! The macros forward to `tracing` with the `tracing` feature, and expand to
+//! nothing without it, so instrumented code needs no `cfg` attributes.
+
+/// Emits a `DEBUG` event, like `tracing::debug!`.
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        ::tracing::debug!($($arg)*);
+    };
+}
+
+/// Enters a `DEBUG` span, like `tracing::debug_span!(..).entered()`, until
+/// the returned guard is dropped.
+macro_rules! debug_span {
+    ($($arg:tt)*) => {{
+        #[cfg(feature = "tracing")]
+        let guard = ::tracing::debug_span!($($arg)*).entered();
+        #[cfg(not(feature = "tracing"))]
+        let guard = $crate::trace::NoSpan;
+        guard
+    }};
+}
+
+pub(crate) use {debug, debug_span};
+
+/// The guard of a span when the `tracing` feature is disabled.
+#[cfg(not(feature = "tracing"))]
+pub(crate) struct NoSpan;
@@ -0,0 +1,113 @@
+//! Stripping UTF-16 text, as JavaScript strings are, without converting the output.
+
+use crate::StripOptions;
+use crate::strip::for_each_piece;
+
+/// Strips fenced code blocks from UTF-16 text like
+/// [`strip_codeblocks`](crate::strip_codeblocks).
+///
+/// This is [`strip_codeblocks_lossy_utf16_with`] with [`StripOptions::new`].
+///
+/// # Arguments
+///
+/// * `text` - The UTF-16 code units of the markdown text
+///
+/// # Returns
+///
+/// The UTF-16 code units of the stripped text.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::strip_codeblocks_lossy_utf16;
+///
+/// let text: Vec<u16> = "Run:\n```sh\nls\n```".encode_utf16().collect();
+/// let stripped = strip_codeblocks_lossy_utf16(&text);
+///
+/// assert_eq!(String::from_utf16(&stripped).unwrap(), "Run:\nls\n");
+/// ```
+pub fn strip_codeblocks_lossy_utf16(text: &[u16]) -> Vec<u16> {
+    strip_codeblocks_lossy_utf16_with(text, &StripOptions::new())
+}
+
+/// Strips fenced code blocks from UTF-16 text according to `options`, for
+/// JavaScript strings handed over as code units, such as from WebAssembly.
+///
+/// The input is decoded once, and the output is written in UTF-16 directly:
+/// prose and blocks left as they were are copied from `text`, and only the
+/// text stripping produced is encoded, so the output isn't built in UTF-8
+/// and converted again. Unpaired surrogates, which JavaScript strings can
+/// hold but Unicode text can't, are read as U+FFFD, the replacement
+/// character. They are kept in what is copied, and replaced in what is
+/// produced, such as unwrapped content.
+///
+/// # Arguments
+///
+/// * `text` - The UTF-16 code units of the markdown text
+/// * `options` - Which blocks are stripped and how
+///
+/// # Returns
+///
+/// The UTF-16 code units of the stripped text.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::{Action, StripOptions, strip_codeblocks_lossy_utf16_with};
+///
+/// let options = StripOptions::new().action(Action::Placeholder("[code]".into()));
+///
+/// let mut text: Vec<u16> = "Café\n```\nls\n```".encode_utf16().collect();
+/// text.push(0xd800);
+/// let stripped = strip_codeblocks_lossy_utf16_with(&text, &options);
+///
+/// assert_eq!(String::from_utf16_lossy(&stripped), "Café\n[code]\u{fffd}");
+/// assert_eq!(stripped.last(), Some(&0xd800));
+/// ```
+pub fn strip_codeblocks_lossy_utf16_with(text: &[u16], options: &StripOptions) -> Vec<u16> {
+    let decoded: String = char::decode_utf16(text.iter().copied())
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect();
+
+    let mut output = Vec::with_capacity(text.len());
+    // The same position in `decoded`, in bytes, and in `text`, in code units
+    let (mut byte, mut unit) = (0, 0);
+    let mut advance = |to: usize| {
+        unit += decoded[byte..to]
+            .chars()
+            .map(char::len_utf16)
+            .sum::<usize>();
+        byte = to;
+        unit
+    };
+    for_each_piece(&decoded, options, |piece| {
+        let start = advance(piece.span.start);
+        let end = advance(piece.span.end);
+        if piece.output == decoded[piece.span.clone()] {
+            output.extend_from_slice(&text[start..end]);
+        } else {
+            output.extend(piece.output.encode_utf16());
+        }
+    });
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{TrailingNewline, strip_codeblocks_with};
+
+    #[test]
+    fn test_matches_strip() {
+        let options = StripOptions::new()
+            .languages(["sh"])
+            .trailing_newline(TrailingNewline::Never);
+        let text = "😀 ```sh\nécho\n```\n```py\n𝔵 = 1\n```\n\n";
+        let utf16: Vec<u16> = text.encode_utf16().collect();
+        assert_eq!(
+            String::from_utf16(&strip_codeblocks_lossy_utf16_with(&utf16, &options)).unwrap(),
+            strip_codeblocks_with(text, &options)
+        );
+        assert!(strip_codeblocks_lossy_utf16(&[]).is_empty());
+    }
+}
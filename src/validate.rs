@@ -0,0 +1,439 @@
+//! Checking the content of code blocks against per-language validators.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::CodeBlock;
+use crate::scan::codeblocks;
+
+/// Why a validator rejected the content of a block.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct ValidationError {
+    /// What is wrong with the content.
+    pub message: String,
+    /// Byte offset in the content where the problem was found, if known.
+    pub offset: Option<usize>,
+}
+
+impl ValidationError {
+    /// Creates an error with `message` and no position.
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            offset: None,
+        }
+    }
+
+    /// Sets the byte offset in the content where the problem was found.
+    pub fn at(mut self, offset: usize) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.offset {
+            Some(offset) => write!(f, "{} at byte {offset}", self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+impl Error for ValidationError {}
+
+/// A code block whose content was rejected by its validator.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct InvalidBlock<'a> {
+    /// The rejected block.
+    pub block: CodeBlock<'a>,
+    /// Why it was rejected.
+    pub error: ValidationError,
+}
+
+impl InvalidBlock<'_> {
+    /// Returns the byte offset of the problem in the document, if known.
+    pub fn position(&self) -> Option<usize> {
+        self.error
+            .offset
+            .map(|offset| self.block.content_span.start + offset)
+    }
+}
+
+type Validator = Arc<dyn Fn(&str) -> Result<(), ValidationError> + Send + Sync>;
+
+/// The validators [`validate_codeblocks`] runs, by block language.
+///
+/// [`Validators::new`] comes with a JSON syntax check for `json` blocks;
+/// validators for other languages are added with [`with`](Self::with).
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::{ValidationError, Validators};
+///
+/// let validators = Validators::new().with("yaml", |content| {
+///     if content.contains('\t') {
+///         return Err(ValidationError::new("tabs are not allowed"));
+///     }
+///     Ok(())
+/// });
+/// ```
+#[derive(Clone)]
+pub struct Validators {
+    by_language: HashMap<String, Validator>,
+}
+
+impl Validators {
+    /// Creates the built-in validators: a JSON syntax check for `json` blocks.
+    pub fn new() -> Self {
+        Self::empty().with("json", validate_json)
+    }
+
+    /// Creates a set without any validator.
+    pub fn empty() -> Self {
+        Self {
+            by_language: HashMap::new(),
+        }
+    }
+
+    /// Validates blocks in `language` with `validator`, replacing any previous one.
+    pub fn with<F>(mut self, language: impl Into<String>, validator: F) -> Self
+    where
+        F: Fn(&str) -> Result<(), ValidationError> + Send + Sync + 'static,
+    {
+        self.by_language
+            .insert(language.into(), Arc::new(validator));
+        self
+    }
+
+    /// Returns the validator for blocks in `language`, if any.
+    fn get(&self, language: &str) -> Option<&Validator> {
+        self.by_language.get(language)
+    }
+}
+
+impl Default for Validators {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Validators {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut languages: Vec<_> = self.by_language.keys().collect();
+        languages.sort();
+        f.debug_struct("Validators")
+            .field("languages", &languages)
+            .finish()
+    }
+}
+
+/// Checks the content of the code blocks of `text` with the validator for their language.
+///
+/// Blocks are found exactly as [`strip_codeblocks`](crate::strip_codeblocks)
+/// finds them. Blocks without a language, or in a language without a
+/// validator, are not checked.
+///
+/// # Arguments
+///
+/// * `text` - The markdown text containing code blocks to check
+/// * `validators` - The validators to run, by language
+///
+/// # Returns
+///
+/// The blocks that were rejected, in document order, with the reason.
+///
+/// # Examples
+///
+/// ```
+/// use strip_codeblocks::{Validators, validate_codeblocks};
+///
+/// let answer = "Here you go:\n```json\n{\"name\": \"demo\",}\n```";
+///
+/// let invalid = validate_codeblocks(answer, &Validators::new());
+/// assert_eq!(invalid.len(), 1);
+/// assert_eq!(invalid[0].error.message, "expected a string");
+/// assert_eq!(invalid[0].position(), Some(37));
+/// ```
+pub fn validate_codeblocks<'a>(text: &'a str, validators: &Validators) -> Vec<InvalidBlock<'a>> {
+    codeblocks(text)
+        .filter_map(|block| {
+            let validator = validators.get(block.language?)?;
+            let error = validator(block.content).err()?;
+            Some(InvalidBlock { block, error })
+        })
+        .collect()
+}
+
+/// Nesting depth of arrays and objects beyond which JSON is rejected.
+const MAX_JSON_DEPTH: usize = 128;
+
+/// Checks that `content` is a single, syntactically valid JSON value (RFC 8259).
+fn validate_json(content: &str) -> Result<(), ValidationError> {
+    let mut parser = JsonParser {
+        bytes: content.as_bytes(),
+        pos: 0,
+    };
+    parser.skip_whitespace();
+    parser.value(0)?;
+    parser.skip_whitespace();
+    if parser.pos < parser.bytes.len() {
+        return Err(parser.error("unexpected characters after the value"));
+    }
+    Ok(())
+}
+
+/// A JSON syntax checker, going through the input without building values.
+struct JsonParser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl JsonParser<'_> {
+    fn error(&self, message: &str) -> ValidationError {
+        ValidationError::new(message).at(self.pos)
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8, message: &str) -> Result<(), ValidationError> {
+        if self.peek() != Some(byte) {
+            return Err(self.error(message));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn value(&mut self, depth: usize) -> Result<(), ValidationError> {
+        match self.peek() {
+            Some(b'{') => self.object(depth + 1),
+            Some(b'[') => self.array(depth + 1),
+            Some(b'"') => self.string(),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            Some(b't') => self.literal("true"),
+            Some(b'f') => self.literal("false"),
+            Some(b'n') => self.literal("null"),
+            Some(_) => Err(self.error("expected a value")),
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+
+    fn object(&mut self, depth: usize) -> Result<(), ValidationError> {
+        if depth > MAX_JSON_DEPTH {
+            return Err(self.error("nesting too deep"));
+        }
+        self.pos += 1;
+        self.skip_whitespace();
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+            return Ok(());
+        }
+
+        loop {
+            if self.peek() != Some(b'"') {
+                return Err(self.error("expected a string"));
+            }
+            self.string()?;
+            self.skip_whitespace();
+            self.expect(b':', "expected `:`")?;
+            self.skip_whitespace();
+            self.value(depth)?;
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => {
+                    self.pos += 1;
+                    self.skip_whitespace();
+                }
+                Some(b'}') => {
+                    self.pos += 1;
+                    return Ok(());
+                }
+                _ => return Err(self.error("expected `,` or `}`")),
+            }
+        }
+    }
+
+    fn array(&mut self, depth: usize) -> Result<(), ValidationError> {
+        if depth > MAX_JSON_DEPTH {
+            return Err(self.error("nesting too deep"));
+        }
+        self.pos += 1;
+        self.skip_whitespace();
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            return Ok(());
+        }
+
+        loop {
+            self.value(depth)?;
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => {
+                    self.pos += 1;
+                    self.skip_whitespace();
+                }
+                Some(b']') => {
+                    self.pos += 1;
+                    return Ok(());
+                }
+                _ => return Err(self.error("expected `,` or `]`")),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<(), ValidationError> {
+        self.pos += 1;
+        loop {
+            match self.peek() {
+                Some(b'"') => {
+                    self.pos += 1;
+                    return Ok(());
+                }
+                Some(b'\\') => {
+                    self.pos += 1;
+                    match self.peek() {
+                        Some(b'"' | b'\\' | b'/' | b'b' | b'f' | b'n' | b'r' | b't') => {
+                            self.pos += 1
+                        }
+                        Some(b'u') => {
+                            self.pos += 1;
+                            for _ in 0..4 {
+                                if !self.peek().is_some_and(|byte| byte.is_ascii_hexdigit()) {
+                                    return Err(self.error("invalid unicode escape"));
+                                }
+                                self.pos += 1;
+                            }
+                        }
+                        _ => return Err(self.error("invalid escape")),
+                    }
+                }
+                Some(0x00..0x20) => return Err(self.error("control character in string")),
+                Some(_) => self.pos += 1,
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<(), ValidationError> {
+        if self.peek() == Some(b'-') {
+            self.pos += 1;
+        }
+        match self.peek() {
+            Some(b'0') => self.pos += 1,
+            Some(b'1'..=b'9') => self.digits(),
+            _ => return Err(self.error("invalid number")),
+        }
+        if self.peek() == Some(b'.') {
+            self.pos += 1;
+            if !self.peek().is_some_and(|byte| byte.is_ascii_digit()) {
+                return Err(self.error("invalid number"));
+            }
+            self.digits();
+        }
+        if matches!(self.peek(), Some(b'e' | b'E')) {
+            self.pos += 1;
+            if matches!(self.peek(), Some(b'+' | b'-')) {
+                self.pos += 1;
+            }
+            if !self.peek().is_some_and(|byte| byte.is_ascii_digit()) {
+                return Err(self.error("invalid number"));
+            }
+            self.digits();
+        }
+        Ok(())
+    }
+
+    fn digits(&mut self) {
+        while self.peek().is_some_and(|byte| byte.is_ascii_digit()) {
+            self.pos += 1;
+        }
+    }
+
+    fn literal(&mut self, literal: &str) -> Result<(), ValidationError> {
+        if !self.bytes[self.pos..].starts_with(literal.as_bytes()) {
+            return Err(self.error("expected a value"));
+        }
+        self.pos += literal.len();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_json() {
+        for json in [
+            "{}",
+            " [1, -2.5e+3, 0, true, false, null]\n",
+            r#"{"a": {"b": ["\u00e9\n", "\"quoted\""]}, "c": "ünï"}"#,
+            "\"just a string\"",
+        ] {
+            assert_eq!(validate_json(json), Ok(()), "{json}");
+        }
+    }
+
+    #[test]
+    fn test_invalid_json() {
+        for (json, message, offset) in [
+            ("", "unexpected end of input", 0),
+            ("{\"a\" 1}", "expected `:`", 5),
+            ("[1, 2", "expected `,` or `]`", 5),
+            ("[01]", "expected `,` or `]`", 2),
+            ("{} {}", "unexpected characters after the value", 3),
+            ("\"tab\there\"", "control character in string", 4),
+            ("\"\\x\"", "invalid escape", 2),
+            ("[1.]", "invalid number", 3),
+            ("nul", "expected a value", 0),
+            ("{'a': 1}", "expected a string", 1),
+        ] {
+            assert_eq!(
+                validate_json(json),
+                Err(ValidationError::new(message).at(offset)),
+                "{json}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_json_nesting_limit() {
+        let deep = "[".repeat(MAX_JSON_DEPTH + 1) + &"]".repeat(MAX_JSON_DEPTH + 1);
+        assert_eq!(
+            validate_json(&deep).unwrap_err().message,
+            "nesting too deep"
+        );
+        let ok = "[".repeat(MAX_JSON_DEPTH) + &"]".repeat(MAX_JSON_DEPTH);
+        assert_eq!(validate_json(&ok), Ok(()));
+    }
+
+    #[test]
+    fn test_custom_validators() {
+        let validators = Validators::empty().with("toml", |content| {
+            if !content.contains('=') {
+                return Err(ValidationError::new("no key"));
+            }
+            Ok(())
+        });
+        let input = "```toml\na = 1\n```\n```toml\n[table]\n```\n```json\n{\n```";
+
+        let invalid = validate_codeblocks(input, &validators);
+        assert_eq!(invalid.len(), 1);
+        assert_eq!(invalid[0].block.content, "[table]\n");
+        assert_eq!(invalid[0].position(), None);
+        assert_eq!(invalid[0].error.to_string(), "no key");
+    }
+}